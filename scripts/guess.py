@@ -20,7 +20,7 @@ from pprint import pformat
 from textwrap import dedent
 
 
-SPECIAL_TABLES = ["table", "column", "datatype", "rule", "history", "message"]
+SPECIAL_TABLES = ["table", "column", "datatype", "rule", "history", "message", "load_state"]
 VERBOSE = False
 
 
@@ -14,6 +14,7 @@ pub enum Expression {
     RegexMatch(String, String),
     RegexSub(String, String, String),
     Function(String, Vec<Box<Expression>>),
+    Tuple(Vec<Box<Expression>>),
 }
 
 // We use Debug instead of Display because it is not possible to display a Vec.
@@ -45,6 +46,34 @@ impl std::fmt::Debug for Expression {
                     name, args
                 )
             }
+            Expression::Tuple(items) => write!(f, "{{\"tuple\": {:?}}}", items),
+        }
+    }
+}
+
+// Display renders the expression back into the syntax of Valve's grammar, e.g., as it would
+// appear in the condition column of the datatype table.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::None => write!(f, ""),
+            Expression::Null => write!(f, "null"),
+            Expression::NotNull => write!(f, "not null"),
+            Expression::Label(l) => write!(f, "{}", l),
+            Expression::Field(a, b) => write!(f, "{}.{}", a, b),
+            Expression::NamedArg(a, b) => write!(f, "{}={}", a, b),
+            Expression::RegexMatch(pattern, flags) => write!(f, "/{}/{}", pattern, flags),
+            Expression::RegexSub(pattern, replace, flags) => {
+                write!(f, "s/{}/{}/{}", pattern, replace, flags)
+            }
+            Expression::Function(name, args) => {
+                let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            Expression::Tuple(items) => {
+                let items = items.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+                write!(f, "({})", items.join(", "))
+            }
         }
     }
 }
@@ -668,12 +668,17 @@ pub fn annotate(
                         foreign.table, foreign.column, SQL_PARAM
                     ),
                 );
-                let param = get_query_param(&json!(value), &foreign.sql_type);
+                let param = match get_query_param(&json!(value), &foreign.sql_type) {
+                    Ok(param) => param,
+                    Err(_) => continue,
+                };
                 let mut query = sqlx_query(&sql);
                 match param {
                     QueryParam::Integer(p) => query = query.bind(p),
                     QueryParam::Numeric(p) => query = query.bind(p),
                     QueryParam::Real(p) => query = query.bind(p),
+                    QueryParam::Date(p) => query = query.bind(p),
+                    QueryParam::Timestamp(p) => query = query.bind(p),
                     QueryParam::String(p) => query = query.bind(p),
                 }
                 num_matches += block_on(query.fetch_all(&valve.pool))
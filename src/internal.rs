@@ -7,7 +7,7 @@ use sqlx::any::AnyKind;
 use std::collections::{HashMap, HashSet};
 
 lazy_static! {
-    pub static ref INTERNAL_TABLES: Vec<&'static str> = vec!["message", "history"];
+    pub static ref INTERNAL_TABLES: Vec<&'static str> = vec!["message", "history", "load_state"];
 }
 
 pub fn generate_internal_table_config(table_name: &str) -> ValveTableConfig {
@@ -28,6 +28,7 @@ pub fn generate_internal_table_config(table_name: &str) -> ValveTableConfig {
                 "level".to_string(),
                 "rule".to_string(),
                 "message".to_string(),
+                "suggestions".to_string(),
             ],
             column: {
                 let mut column_configs = HashMap::new();
@@ -103,6 +104,17 @@ pub fn generate_internal_table_config(table_name: &str) -> ValveTableConfig {
                         ..Default::default()
                     },
                 );
+                column_configs.insert(
+                    "suggestions".to_string(),
+                    ValveColumnConfig {
+                        table: "message".to_string(),
+                        column: "suggestions".to_string(),
+                        description: "A JSON array of suggested replacements for the value"
+                            .to_string(),
+                        datatype: "text".to_string(),
+                        ..Default::default()
+                    },
+                );
                 column_configs
             },
             ..Default::default()
@@ -211,6 +223,88 @@ pub fn generate_internal_table_config(table_name: &str) -> ValveTableConfig {
             },
             ..Default::default()
         },
+        "load_state" => ValveTableConfig {
+            table: "load_state".to_string(),
+            table_type: "load_state".to_string(),
+            options: HashSet::from(["internal".to_string()]),
+            description: "The state of the sources of the tables when they were last loaded"
+                .to_string(),
+            column_order: vec![
+                "table".to_string(),
+                "path".to_string(),
+                "checksum".to_string(),
+                "size".to_string(),
+                "config_hash".to_string(),
+                "load_time".to_string(),
+            ],
+            column: {
+                let mut column_configs = HashMap::new();
+                column_configs.insert(
+                    "table".to_string(),
+                    ValveColumnConfig {
+                        table: "load_state".to_string(),
+                        column: "table".to_string(),
+                        description: "The table that was loaded".to_string(),
+                        datatype: "table_name".to_string(),
+                        ..Default::default()
+                    },
+                );
+                column_configs.insert(
+                    "path".to_string(),
+                    ValveColumnConfig {
+                        table: "load_state".to_string(),
+                        column: "path".to_string(),
+                        description: "The path of the file that the table was loaded from"
+                            .to_string(),
+                        datatype: "text".to_string(),
+                        ..Default::default()
+                    },
+                );
+                column_configs.insert(
+                    "checksum".to_string(),
+                    ValveColumnConfig {
+                        table: "load_state".to_string(),
+                        column: "checksum".to_string(),
+                        description: "The SHA-256 checksum of the file".to_string(),
+                        datatype: "text".to_string(),
+                        ..Default::default()
+                    },
+                );
+                column_configs.insert(
+                    "size".to_string(),
+                    ValveColumnConfig {
+                        table: "load_state".to_string(),
+                        column: "size".to_string(),
+                        description: "The size of the file in bytes".to_string(),
+                        datatype: "natural_number".to_string(),
+                        ..Default::default()
+                    },
+                );
+                column_configs.insert(
+                    "config_hash".to_string(),
+                    ValveColumnConfig {
+                        table: "load_state".to_string(),
+                        column: "config_hash".to_string(),
+                        description: "A hash of the configuration that the table was loaded with"
+                            .to_string(),
+                        datatype: "text".to_string(),
+                        ..Default::default()
+                    },
+                );
+                column_configs.insert(
+                    "load_time".to_string(),
+                    ValveColumnConfig {
+                        table: "load_state".to_string(),
+                        column: "load_time".to_string(),
+                        description: "The time at which the table was loaded".to_string(),
+                        datatype: "line".to_string(),
+                        ..Default::default()
+                    },
+                );
+                column_configs
+            },
+            ..Default::default()
+        },
         _ => todo!(
             "Table configuration for table '{}' is not implemented.",
             table_name
@@ -275,7 +369,8 @@ pub fn generate_internal_table_ddl(
                       "value" {text_type},
                       "level" {text_type},
                       "rule" {text_type},
-                      "message" {text_type}
+                      "message" {text_type},
+                      "suggestions" {text_type}
                     );
                   "#},
                 message_id = {
@@ -293,6 +388,29 @@ pub fn generate_internal_table_ddl(
             );
             statements
         }
+        "load_state" => {
+            statements.push(format!(
+                indoc! {r#"
+                    CREATE TABLE "load_state" (
+                      "table" {text_type},
+                      "path" {text_type},
+                      "checksum" {text_type},
+                      "size" BIGINT,
+                      "config_hash" {text_type},
+                      {load_time}
+                    );
+                  "#},
+                text_type = text_type,
+                load_time = {
+                    if *db_kind == AnyKind::Sqlite {
+                        "\"load_time\" TIMESTAMP DEFAULT(STRFTIME('%Y-%m-%d %H:%M:%f', 'NOW'))"
+                    } else {
+                        "\"load_time\" TIMESTAMP DEFAULT CURRENT_TIMESTAMP"
+                    }
+                },
+            ));
+            statements
+        }
         _ => todo!("Table DDL for table '{}' is not implemented.", table_name),
     }
 }
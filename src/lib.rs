@@ -40,6 +40,9 @@ pub static PRINTF_RE: &str = r#"^%.*([\w%])$"#;
 /// The size of the datatype validation cache.
 pub static DT_CACHE_SIZE: usize = 10000;
 
+/// The maximum number of suggested replacements to attach to a validation message.
+pub static MAX_SUGGESTIONS: usize = 3;
+
 // Note that SQL_PARAM must be a 'word' (from the point of view of regular expressions) since in the
 // local_sql_syntax() function below we are matchng against it using '\b' which represents a word
 // boundary. If you want to use a non-word placeholder then you must also change '\b' in the regex
@@ -47,7 +50,22 @@ pub static DT_CACHE_SIZE: usize = 10000;
 /// The word (in the regex sense) placeholder to use for query parameters when binding using sqlx.
 pub static SQL_PARAM: &str = "VALVEPARAM";
 
+/// The format (see <https://docs.rs/chrono/latest/chrono/format/strftime/>) in which values of the
+/// SQL type DATE are represented.
+pub static SQL_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The format (see <https://docs.rs/chrono/latest/chrono/format/strftime/>) in which values of the
+/// SQL type TIMESTAMP are represented.
+pub static SQL_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
 lazy_static! {
-    pub static ref SQL_TYPES: Vec<&'static str> =
-        vec!["text", "varchar", "numeric", "integer", "real"];
+    pub static ref SQL_TYPES: Vec<&'static str> = vec![
+        "text",
+        "varchar",
+        "numeric",
+        "integer",
+        "real",
+        "date",
+        "timestamp"
+    ];
 }
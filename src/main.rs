@@ -4,7 +4,12 @@ use crate::tests::{run_api_tests, run_dt_hierarchy_tests};
 use anyhow::Result;
 use clap::{ArgAction, Parser, Subcommand};
 use futures::executor::block_on;
-use ontodev_valve::{guess::guess, valve::Valve};
+use ontodev_valve::{
+    guess::guess,
+    internal::INTERNAL_TABLES,
+    valve::{Valve, ValveMessageFilter},
+};
+use serde_json::{json, Value as SerdeValue};
 
 // Help strings that are used in more than one subcommand:
 static SOURCE_HELP: &str = "The location of a TSV file, representing the 'table' table, \
@@ -16,6 +21,9 @@ static DATABASE_HELP: &str = "Can be one of (A) A URL of the form `postgresql://
 
 static SAVE_DIR_HELP: &str = "Save tables to DIR instead of to their configured paths";
 
+static CONTRACT_IRIS_HELP: &str = "Contract IRIs to CURIEs, using the prefix table, in the \
+                                   columns that are meant to contain CURIEs";
+
 #[derive(Parser)]
 #[command(version,
           about = "Valve: A lightweight validation engine -- command line interface",
@@ -53,7 +61,23 @@ enum Commands {
                       initial loading only, as data integrity will not be \
                       guaranteed in the case of an interrupted transaction.")]
         initial_load: bool,
-        // TODO: Add a --dry-run flag.
+
+        #[arg(long,
+              action = ArgAction::SetTrue,
+              help = "Read and validate the data tables without accessing DATABASE, and \
+                      print a JSON report of the number of rows, conflict rows, errors, \
+                      warnings, and infos for each table, followed by the messages that would \
+                      have been generated. The tables are loaded into a temporary SQLite \
+                      database, whatever the type of DATABASE.")]
+        dry_run: bool,
+
+        #[arg(long,
+              action = ArgAction::SetTrue,
+              conflicts_with = "dry_run",
+              help = "Only reload the tables whose source files or configuration have changed \
+                      since they were last loaded, along with the tables that depend on them. \
+                      The messages and history of the reloaded tables are deleted.")]
+        changed_only: bool,
     },
 
     /// Creates a database in a given location but does not load any of the tables.
@@ -84,6 +108,9 @@ enum Commands {
 
         #[arg(long, value_name = "DIR", action = ArgAction::Set, help = SAVE_DIR_HELP)]
         save_dir: Option<String>,
+
+        #[arg(long, action = ArgAction::SetTrue, help = CONTRACT_IRIS_HELP)]
+        contract_iris: bool,
     },
 
     /// Saves the configured data tables from the given list as TSV files.
@@ -103,6 +130,9 @@ enum Commands {
 
         #[arg(long, value_name = "DIR", action = ArgAction::Set, help = SAVE_DIR_HELP)]
         save_dir: Option<String>,
+
+        #[arg(long, action = ArgAction::SetTrue, help = CONTRACT_IRIS_HELP)]
+        contract_iris: bool,
     },
 
     /// Prints the Valve configuration as a JSON-formatted string to the terminal.
@@ -165,6 +195,152 @@ enum Commands {
         table_tsv: String,
     },
 
+    /// Prints the fixes that are proposed for the values of the given table that have validation
+    /// messages, as a JSON array, to the terminal, and optionally applies them.
+    ProposeFixes {
+        #[arg(long, value_name = "COLUMN", action = ArgAction::Set,
+              help = "Only propose fixes for values of COLUMN")]
+        column: Option<String>,
+
+        #[arg(long, value_name = "ROW", action = ArgAction::Set,
+              help = "Only propose fixes for values in the row whose row number is ROW")]
+        row: Option<u32>,
+
+        #[arg(long, value_name = "RULE", action = ArgAction::Set,
+              help = "Only propose fixes for values that violate RULE, which may contain the \
+                      wildcard '%' (e.g., 'datatype:%')")]
+        rule: Option<String>,
+
+        #[arg(long, action = ArgAction::SetTrue,
+              help = "Apply the proposed fixes to the database. The changes are recorded in the \
+                      history table and may be undone.")]
+        apply: bool,
+
+        #[arg(value_name = "SOURCE", action = ArgAction::Set, help = SOURCE_HELP)]
+        source: String,
+
+        #[arg(value_name = "DATABASE", action = ArgAction::Set, help = DATABASE_HELP)]
+        database: String,
+
+        #[arg(value_name = "TABLE", action = ArgAction::Set,
+              help = "The table for whose values fixes will be proposed")]
+        table: String,
+    },
+
+    /// Validates the given tables, or all of the tables if none are given, without using a
+    /// database, and prints the resulting messages to the terminal. Exits with a non-zero status
+    /// if any error-level messages were generated.
+    Validate {
+        #[arg(long, value_name = "FORMAT", action = ArgAction::Set,
+              value_parser = ["tsv", "json"], default_value = "tsv",
+              help = "Print the messages as TSV (the default) or as a JSON array")]
+        format: String,
+
+        #[arg(value_name = "SOURCE", action = ArgAction::Set, help = SOURCE_HELP)]
+        source: String,
+
+        #[arg(value_name = "TABLE", action = ArgAction::Append,
+              help = "A table to validate. The tables that the given tables depend on are \
+                      validated as well, but their messages are not printed.")]
+        tables: Vec<String>,
+    },
+
+    /// Checks the Valve configuration and prints every problem found with it, rather than only the
+    /// first, to the terminal. Exits with a non-zero status if any error-level problems were found.
+    CheckConfig {
+        #[arg(long, value_name = "FORMAT", action = ArgAction::Set,
+              value_parser = ["tsv", "json"], default_value = "tsv",
+              help = "Print the problems as TSV (the default) or as a JSON array")]
+        format: String,
+
+        #[arg(value_name = "SOURCE", action = ArgAction::Set, help = SOURCE_HELP)]
+        source: String,
+    },
+
+    /// Compares two versions of the Valve configuration and prints the differences between them,
+    /// along with the tables that will be migrated, and those whose validation results may change,
+    /// as a result of each difference.
+    ConfigDiff {
+        #[arg(long, value_name = "FORMAT", action = ArgAction::Set,
+              value_parser = ["tsv", "json"], default_value = "tsv",
+              help = "Print the differences as TSV (the default) or as a JSON array")]
+        format: String,
+
+        #[arg(value_name = "OLD_SOURCE", action = ArgAction::Set,
+              help = "The location of a TSV file, representing the old version of the 'table' \
+                      table, from which to read the old Valve configuration.")]
+        old: String,
+
+        #[arg(value_name = "NEW_SOURCE", action = ArgAction::Set,
+              help = "The location of a TSV file, representing the new version of the 'table' \
+                      table, from which to read the new Valve configuration.")]
+        new: String,
+    },
+
+    /// Synchronizes the given tables, or all of the editable tables if none are given, in a
+    /// given database with the TSV files that they are loaded from, by inserting, updating, and
+    /// deleting only those rows that have changed in the files.
+    Sync {
+        #[arg(long, value_name = "USER", action = ArgAction::Set,
+              default_value = Valve::DEFAULT_SYNC_USER,
+              help = "The user to whom the changes are attributed in the history table")]
+        user: String,
+
+        #[arg(value_name = "SOURCE", action = ArgAction::Set, help = SOURCE_HELP)]
+        source: String,
+
+        #[arg(value_name = "DATABASE", action = ArgAction::Set, help = DATABASE_HELP)]
+        database: String,
+
+        #[arg(value_name = "TABLE", action = ArgAction::Append,
+              help = "A table to synchronize with its TSV file")]
+        tables: Vec<String>,
+    },
+
+    /// Migrates the tables in a given database to their current configuration without losing the
+    /// data in them, using ALTER TABLE statements wherever possible. The SQL statements that will
+    /// be executed are printed first.
+    Migrate {
+        #[arg(long,
+              action = ArgAction::SetTrue,
+              help = "Only print the SQL statements that would be executed, without \
+                      executing them.")]
+        plan: bool,
+
+        #[arg(value_name = "SOURCE", action = ArgAction::Set, help = SOURCE_HELP)]
+        source: String,
+
+        #[arg(value_name = "DATABASE", action = ArgAction::Set, help = DATABASE_HELP)]
+        database: String,
+    },
+
+    /// Validates the data in a given database under a candidate configuration, without changing
+    /// the database, and prints the number of messages, for each column and rule of each table,
+    /// that would be new or would be resolved in comparison with the message table, as well as
+    /// the number of messages in the message table that could not be compared.
+    WhatIf {
+        #[arg(long, value_name = "FORMAT", action = ArgAction::Set,
+              value_parser = ["tsv", "json"], default_value = "tsv",
+              help = "Print the number of new, resolved, and unreproduced messages as TSV (the \
+                      default), or the messages themselves as a JSON array")]
+        format: String,
+
+        #[arg(value_name = "SOURCE", action = ArgAction::Set, help = SOURCE_HELP)]
+        source: String,
+
+        #[arg(value_name = "DATABASE", action = ArgAction::Set, help = DATABASE_HELP)]
+        database: String,
+
+        #[arg(value_name = "CANDIDATE", action = ArgAction::Set,
+              help = "The location of a TSV file, representing the 'table' table, from which to \
+                      read the candidate Valve configuration.")]
+        candidate: String,
+
+        #[arg(value_name = "TABLE", action = ArgAction::Append,
+              help = "A table to validate under the candidate configuration")]
+        tables: Vec<String>,
+    },
+
     /// Runs a set of predefined tests, on a specified pre-loaded database, that will test Valve's
     /// Application Programmer Interface.
     TestApi {
@@ -235,15 +411,36 @@ async fn main() -> Result<()> {
     match &cli.command {
         Commands::Load {
             initial_load,
+            dry_run,
+            changed_only,
             source,
             database,
         } => {
             exit_unless_tsv(source);
+            if *dry_run {
+                let mut valve = Valve::build_in_memory(source).unwrap();
+                valve.set_verbose(cli.verbose);
+                let tables = valve
+                    .get_sorted_table_list(false)
+                    .into_iter()
+                    .filter(|table| !INTERNAL_TABLES.contains(table))
+                    .collect::<Vec<_>>();
+                let report = valve.load_tables_dry_run(&tables).await.unwrap();
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+                if report.tables.values().any(|stats| stats.errors > 0) {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
             let mut valve = build_valve(source, database).unwrap();
             if *initial_load {
                 block_on(valve.configure_for_initial_load()).unwrap();
             }
-            valve.load_all_tables(true).await.unwrap();
+            if *changed_only {
+                valve.load_changed_tables(true).await.unwrap();
+            } else {
+                valve.load_all_tables(true).await.unwrap();
+            }
         }
         Commands::Create {
             source,
@@ -292,9 +489,11 @@ async fn main() -> Result<()> {
             save_dir,
             source,
             database,
+            contract_iris,
         } => {
             exit_unless_tsv(source);
-            let valve = build_valve(source, database).unwrap();
+            let mut valve = build_valve(source, database).unwrap();
+            valve.set_contract_iris(*contract_iris);
             valve.save_all_tables(&save_dir).await.unwrap();
         }
         Commands::Save {
@@ -302,9 +501,11 @@ async fn main() -> Result<()> {
             source,
             database,
             tables,
+            contract_iris,
         } => {
             exit_unless_tsv(source);
-            let valve = build_valve(source, database).unwrap();
+            let mut valve = build_valve(source, database).unwrap();
+            valve.set_contract_iris(*contract_iris);
             let tables = tables
                 .iter()
                 .filter(|s| *s != "")
@@ -332,6 +533,225 @@ async fn main() -> Result<()> {
                 cli.assume_yes,
             );
         }
+        Commands::ProposeFixes {
+            column,
+            row,
+            rule,
+            apply,
+            source,
+            database,
+            table,
+        } => {
+            exit_unless_tsv(source);
+            let valve = build_valve(source, database).unwrap();
+            let filter = ValveMessageFilter {
+                column: column.clone(),
+                row: *row,
+                rule: rule.clone(),
+                ..Default::default()
+            };
+            let fixes = valve.propose_fixes(table, &filter).await.unwrap();
+            println!("{}", serde_json::to_string_pretty(&fixes).unwrap());
+            if *apply && !fixes.is_empty() {
+                if !cli.assume_yes {
+                    print!("Apply {} fix(es) to '{}'? [y/N] ", fixes.len(), table);
+                    if !proceed::proceed() {
+                        std::process::exit(1);
+                    }
+                }
+                let updated_rows = valve.apply_fixes(&fixes).await.unwrap();
+                println!("Updated {} row(s) of '{}'", updated_rows.len(), table);
+            }
+        }
+        Commands::Validate {
+            format,
+            source,
+            tables,
+        } => {
+            exit_unless_tsv(source);
+            let mut valve = Valve::build_in_memory(source).unwrap();
+            valve.set_verbose(cli.verbose);
+            let tables = tables.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let validated_tables = valve.validate_tables_in_memory(&tables).unwrap();
+            let mut messages = vec![];
+            for (table, rows) in &validated_tables {
+                for row in rows {
+                    for (column, cell) in &row.contents {
+                        for message in &cell.messages {
+                            messages.push(json!({
+                                "table": table,
+                                "row": row.row_number,
+                                "column": column,
+                                "value": cell.strvalue(),
+                                "level": message.level,
+                                "rule": message.rule,
+                                "message": message.message,
+                            }));
+                        }
+                    }
+                }
+            }
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&messages).unwrap());
+            } else {
+                println!("table\trow\tcolumn\tvalue\tlevel\trule\tmessage");
+                for message in &messages {
+                    let fields = ["table", "row", "column", "value", "level", "rule", "message"]
+                        .iter()
+                        .map(|field| match &message[field] {
+                            SerdeValue::String(s) => s.to_string(),
+                            value => value.to_string(),
+                        })
+                        .collect::<Vec<_>>();
+                    println!("{}", fields.join("\t"));
+                }
+            }
+            if messages.iter().any(|message| message["level"] == "error") {
+                std::process::exit(1);
+            }
+        }
+        Commands::CheckConfig { format, source } => {
+            exit_unless_tsv(source);
+            let issues = Valve::lint_config(source).unwrap();
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&issues).unwrap());
+            } else {
+                println!("file\trow\tcolumn\tseverity\tmessage");
+                for issue in &issues {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}",
+                        issue.file, issue.row, issue.column, issue.severity, issue.message
+                    );
+                }
+            }
+            if issues.iter().any(|issue| issue.severity == "error") {
+                std::process::exit(1);
+            }
+        }
+        Commands::ConfigDiff { format, old, new } => {
+            exit_unless_tsv(old);
+            exit_unless_tsv(new);
+            let old_valve = Valve::build_in_memory(old).unwrap();
+            let new_valve = Valve::build_in_memory(new).unwrap();
+            let changes = old_valve.diff_config(&new_valve.config);
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&changes).unwrap());
+            } else {
+                println!("kind\titem\tchange\tfield\told\tnew\trebuilds\trevalidates");
+                for change in &changes {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        change.kind,
+                        change.item,
+                        change.change,
+                        change.field,
+                        change.old,
+                        change.new,
+                        change.rebuilds.join(","),
+                        change.revalidates.join(",")
+                    );
+                }
+            }
+        }
+        Commands::Sync {
+            user,
+            source,
+            database,
+            tables,
+        } => {
+            exit_unless_tsv(source);
+            let mut valve = build_valve(source, database).unwrap();
+            valve.set_sync_user(user).unwrap();
+            let stats = match tables.is_empty() {
+                true => valve.sync_all_tables().await.unwrap(),
+                false => {
+                    let tables = tables.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                    valve.sync_tables(&tables).await.unwrap()
+                }
+            };
+            println!("table\tinserted\tupdated\tdeleted\tunchanged");
+            for (table, stats) in &stats {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    table, stats.inserted, stats.updated, stats.deleted, stats.unchanged
+                );
+            }
+        }
+        Commands::Migrate {
+            plan,
+            source,
+            database,
+        } => {
+            exit_unless_tsv(source);
+            let valve = build_valve(source, database).unwrap();
+            let steps = valve.plan_migration().await.unwrap();
+            for step in &steps {
+                println!("-- {}: {}", step.table, step.description);
+                if !step.params.is_empty() {
+                    println!(
+                        "-- parameters: {}",
+                        step.params
+                            .iter()
+                            .map(|p| format!("'{}'", p))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                for stmt in &step.statements {
+                    println!("{};", stmt.trim().trim_end_matches(';'));
+                }
+            }
+            if !*plan && !steps.is_empty() {
+                if let Some(step) = steps.iter().find(|step| step.recreates_table) {
+                    println!(
+                        "Unable to migrate the table '{}' without losing its data: {}",
+                        step.table, step.description
+                    );
+                    std::process::exit(1);
+                }
+                if !cli.assume_yes {
+                    print!("Execute the above statements? [y/N] ");
+                    if !proceed::proceed() {
+                        std::process::exit(1);
+                    }
+                }
+                if let Err(e) = valve.migrate().await {
+                    println!("Migration failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::WhatIf {
+            format,
+            source,
+            database,
+            candidate,
+            tables,
+        } => {
+            exit_unless_tsv(source);
+            exit_unless_tsv(candidate);
+            let valve = build_valve(source, database).unwrap();
+            let mut candidate = Valve::build_in_memory(candidate).unwrap();
+            candidate.set_verbose(cli.verbose);
+            let tables = tables.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let deltas = valve.validate_with_config(&candidate, &tables).await.unwrap();
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&deltas).unwrap());
+            } else {
+                println!("table\tcolumn\trule\tnew\tresolved\tunreproduced");
+                for delta in &deltas {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}\t{}",
+                        delta.table,
+                        delta.column,
+                        delta.rule,
+                        delta.new.len(),
+                        delta.resolved.len(),
+                        delta.unreproduced.len()
+                    );
+                }
+            }
+        }
         Commands::TestApi {
             source,
             database,
@@ -2,13 +2,22 @@
 
 use anyhow::Result;
 use futures::executor::block_on;
+use indexmap::IndexMap;
 use indoc::indoc;
 use ontodev_valve::{
     ast::Expression,
-    toolkit::SerdeMap,
+    internal::INTERNAL_TABLES,
+    toolkit::{
+        compile_condition, compile_transform, get_query_param, get_suggestions, local_sql_syntax,
+        CompiledCondition, SerdeMap, ValueType,
+    },
     validate::validate_cell_datatype,
-    valve::{Valve, ValveCell, ValveDatatypeConfig, ValveError},
-    PRINTF_RE,
+    valve::{
+        Valve, ValveCell, ValveDatatypeConfig, ValveError, ValveForeignConstraint,
+        ValveMessageFilter, ValveRow, ValveRuleKind,
+    },
+    valve_grammar::StartParser,
+    PRINTF_RE, SQL_PARAM,
 };
 use rand::{
     distributions::{Alphanumeric, DistString, Distribution, Uniform},
@@ -20,7 +29,10 @@ use rand_regex::Regex as RandRegex;
 use regex::Regex;
 use serde_json::{json, Value as SerdeValue};
 use sprintf::sprintf;
-use sqlx::{any::AnyPool, query as sqlx_query, Row, ValueRef};
+use sqlx::{
+    any::{AnyKind, AnyPool},
+    query as sqlx_query, Column, Row, ValueRef,
+};
 use std::sync::Arc;
 
 async fn test_matching(valve: &Valve) -> Result<()> {
@@ -976,21 +988,1723 @@ async fn test_move(valve: &Valve) -> Result<()> {
     Ok(())
 }
 
+fn test_condition_functions(valve: &Valve) -> Result<()> {
+    eprint!("Running test_condition_functions() ... ");
+
+    let parser = StartParser::new();
+    let check = |condition: &str, sql_type: &str, value: &str| -> bool {
+        let compiled = compile_condition(
+            condition,
+            &parser,
+            &valve.datatype_conditions,
+            sql_type,
+            None,
+            &valve.config.prefix,
+        )
+        .unwrap();
+        compiled.compiled.clone()(value)
+    };
+
+    // Numeric comparisons:
+    assert!(check("range(0, 150)", "INTEGER", "0"));
+    assert!(check("range(0, 150)", "INTEGER", "150"));
+    assert!(!check("range(0, 150)", "INTEGER", "151"));
+    assert!(!check("range(0, 150)", "INTEGER", "12.5"));
+    assert!(!check("range(0, 150)", "INTEGER", "abc"));
+    assert!(check("range(-1.5, 1.5)", "REAL", "-1.25"));
+    assert!(!check("range(-1.5, 1.5)", "REAL", "1.75"));
+    assert!(!check("gt(0)", "REAL", "inf"));
+    assert!(!check("lt(0)", "REAL", "-infinity"));
+    assert!(!check("ge(0)", "REAL", "NaN"));
+    assert!(check("gt(3000000000)", "BIGINT", "9007199254740993"));
+    assert!(!check(
+        "gt(9007199254740993)",
+        "INTEGER",
+        "9007199254740992"
+    ));
+    assert!(check("gt(10)", "NUMERIC", "10.01"));
+    assert!(!check("gt(10)", "NUMERIC", "10"));
+    assert!(check("ge(10)", "NUMERIC", "10"));
+    assert!(check("lt(10)", "TEXT", "9"));
+    assert!(!check("lt(10)", "TEXT", "10"));
+    assert!(check("le(10)", "TEXT", "10"));
+
+    // Lengths:
+    assert!(check("length(0, 3)", "TEXT", ""));
+    assert!(check("length(1, 3)", "TEXT", "abc"));
+    assert!(!check("length(1, 3)", "TEXT", "abcd"));
+
+    // Dates and timestamps:
+    assert!(check("date('%Y-%m-%d')", "TEXT", "2024-02-29"));
+    assert!(!check("date('%Y-%m-%d')", "TEXT", "2023-02-29"));
+    assert!(!check("date('%Y-%m-%d')", "TEXT", "2023-02-30"));
+    assert!(!check("date('%Y-%m-%d')", "TEXT", "2023-2-3"));
+    assert!(check("date('%m/%d/%Y')", "TEXT", "12/31/1999"));
+    assert!(!check("date('%m/%d/%Y')", "TEXT", "1999-12-31"));
+    assert!(check(
+        "datetime('%Y-%m-%d %H:%M')",
+        "TEXT",
+        "2024-01-01 23:59"
+    ));
+    assert!(!check(
+        "datetime('%Y-%m-%d %H:%M')",
+        "TEXT",
+        "2024-01-01 24:00"
+    ));
+    assert!(check("gt(2020-01-01)", "DATE", "2020-01-02"));
+    assert!(!check("gt(2020-01-01)", "DATE", "2020-01-01"));
+    assert!(!check("gt(2020-01-01)", "DATE", "2020-02-30"));
+
+    // Combinations of conditions and datatypes:
+    assert!(check("any(CURIE, equals(foo))", "TEXT", "ex:123"));
+    assert!(check("any(CURIE, equals(foo))", "TEXT", "foo"));
+    assert!(!check("any(CURIE, equals(foo))", "TEXT", "bar"));
+    assert!(check("all(word, not(equals('TODO')))", "TEXT", "done"));
+    assert!(!check("all(word, not(equals('TODO')))", "TEXT", "TODO"));
+    assert!(!check("all(word, not(equals('TODO')))", "TEXT", "not done"));
+    assert!(check("not(in(a, b))", "TEXT", "c"));
+    assert!(!check("not(in(a, b))", "TEXT", "a"));
+    assert!(check(
+        "all(integer, any(lt(0), range(10, 20)))",
+        "INTEGER",
+        "-5"
+    ));
+    assert!(!check(
+        "all(integer, any(lt(0), range(10, 20)))",
+        "INTEGER",
+        "5"
+    ));
+
+    let compiled = compile_condition(
+        "all(word, not(equals('TODO')))",
+        &parser,
+        &valve.datatype_conditions,
+        "TEXT",
+        None,
+        &valve.config.prefix,
+    )?;
+    let subconditions = compiled
+        .subconditions
+        .iter()
+        .map(|c| c.original.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(subconditions, vec!["word", "not(equals('TODO'))"]);
+
+    // Invalid conditions:
+    for (condition, sql_type) in [
+        ("range(0, 1.5)", "INTEGER"),
+        ("range(1, 0)", "INTEGER"),
+        ("range(1)", "INTEGER"),
+        ("range(0, inf)", "REAL"),
+        ("gt(NaN)", "NUMERIC"),
+        ("lt(infinity)", "TEXT"),
+        ("gt(1, 2)", "INTEGER"),
+        ("length(-1, 2)", "TEXT"),
+        ("not(word, line)", "TEXT"),
+        ("any(word, integer_list)", "TEXT"),
+        ("all(word, undefined_datatype)", "TEXT"),
+        ("date()", "TEXT"),
+        ("date('%Y-%m-%d', '%d')", "TEXT"),
+        ("datetime('%Q')", "TEXT"),
+        ("gt(2020-02-30)", "DATE"),
+        ("equals()", "TEXT"),
+        ("list()", "TEXT"),
+        ("curie(/foo/)", "TEXT"),
+    ] {
+        let result = compile_condition(
+            condition,
+            &parser,
+            &valve.datatype_conditions,
+            sql_type,
+            None,
+            &valve.config.prefix,
+        );
+        assert!(result.is_err(), "Expected '{}' to be invalid", condition);
+    }
+
+    // Conditions that refer to other columns in the same row:
+    let table_config = valve.config.table.get("table5").unwrap();
+    let row = ValveRow::from_simple_json(
+        json!({"text": "5", "int": "5", "real": "", "num": "5.5"})
+            .as_object()
+            .unwrap(),
+        None,
+    )?;
+    let check_in_row = |condition: &str, sql_type: &str, value: &str| -> bool {
+        let compiled = compile_condition(
+            condition,
+            &parser,
+            &valve.datatype_conditions,
+            sql_type,
+            Some(table_config),
+            &valve.config.prefix,
+        )
+        .unwrap();
+        compiled.check(value, &row)
+    };
+    assert!(check_in_row("ge(table5.int)", "INTEGER", "5"));
+    assert!(!check_in_row("gt(table5.int)", "INTEGER", "5"));
+    assert!(check_in_row("lt(table5.num)", "REAL", "5.25"));
+    assert!(check_in_row(
+        "range(table5.int, table5.num)",
+        "REAL",
+        "5.25"
+    ));
+    assert!(!check_in_row("range(table5.int, table5.num)", "REAL", "6"));
+    assert!(check_in_row("range(5, table5.num)", "REAL", "5.25"));
+    assert!(!check_in_row("le(table5.real)", "REAL", "5"));
+    assert!(check_in_row("equals(table5.text)", "TEXT", "5"));
+    assert!(!check_in_row("equals('text')", "TEXT", "5"));
+    assert!(check_in_row("equals('text')", "TEXT", "text"));
+    assert!(check_in_row("not(equals(table5.int))", "TEXT", "6"));
+    assert!(check_in_row(
+        "any(equals(foo), equals(table5.int))",
+        "TEXT",
+        "5"
+    ));
+    assert!(!check_in_row(
+        "all(integer, gt(table5.num))",
+        "INTEGER",
+        "5"
+    ));
+
+    // Labels that coincide with the names of columns are nevertheless literal values:
+    assert!(check_in_row("equals(text)", "TEXT", "text"));
+    assert!(!check_in_row("equals(text)", "TEXT", "5"));
+    assert!(check_in_row("in(int, num)", "TEXT", "num"));
+    assert!(!check_in_row("in(int, num)", "TEXT", "5"));
+    assert!(compile_condition(
+        "range(int, num)",
+        &parser,
+        &valve.datatype_conditions,
+        "REAL",
+        Some(table_config),
+        &valve.config.prefix,
+    )
+    .is_err());
+
+    eprintln!("done.");
+    Ok(())
+}
+
+fn test_registered_condition_functions(valve: &Valve) -> Result<()> {
+    eprint!("Running test_registered_condition_functions() ... ");
+
+    // An ORCID identifier is valid if its last character is the ISO 7064 11,2 checksum of its
+    // other digits:
+    fn orcid(
+        condition: &str,
+        parsed_condition: &Expression,
+        _sql_type: &str,
+    ) -> Result<CompiledCondition> {
+        match parsed_condition {
+            Expression::Function(_, args) if args.len() == 1 => match &*args[0] {
+                Expression::Label(label) if label == "checksum" => (),
+                _ => return Err(ValveError::InputError("Invalid argument".to_string()).into()),
+            },
+            _ => return Err(ValveError::InputError("Invalid arguments".to_string()).into()),
+        };
+        let re = Regex::new(r"^\d{4}-\d{4}-\d{4}-\d{3}[\dX]$")?;
+        Ok(CompiledCondition {
+            value_type: ValueType::Single,
+            original: condition.to_string(),
+            parsed: parsed_condition.clone(),
+            compiled: Arc::new(move |x| {
+                if !re.is_match(x) {
+                    return false;
+                }
+                let digits = x.replace("-", "");
+                let mut total = 0;
+                for c in digits[..15].chars() {
+                    total = (total + c.to_digit(10).unwrap()) * 2;
+                }
+                let checksum = match (12 - total % 11) % 11 {
+                    10 => 'X',
+                    n => std::char::from_digit(n, 10).unwrap(),
+                };
+                digits.ends_with(checksum)
+            }),
+            compiled_with_row: None,
+            subconditions: vec![],
+        })
+    }
+    Valve::register_condition_function("orcid", orcid)?;
+
+    // Builtin functions cannot be overridden, and a function cannot be registered twice:
+    assert!(Valve::register_condition_function("match", orcid).is_err());
+    assert!(Valve::register_condition_function("orcid", orcid).is_err());
+    assert!(Valve::register_condition_function("not a name", orcid).is_err());
+
+    let parser = StartParser::new();
+    let compile = |condition: &str| -> Result<CompiledCondition> {
+        compile_condition(
+            condition,
+            &parser,
+            &valve.datatype_conditions,
+            "TEXT",
+            None,
+            &valve.config.prefix,
+        )
+    };
+    let compiled = compile("orcid(checksum)")?;
+    assert!((compiled.compiled)("0000-0002-1825-0097"));
+    assert!(!(compiled.compiled)("0000-0002-1825-0098"));
+    assert!((compiled.compiled)("0000-0002-1694-233X"));
+    assert!(!(compiled.compiled)("0000-0002-1694-2330"));
+    assert!(!(compiled.compiled)("orcid"));
+
+    // Registered functions may be combined with builtin functions:
+    let compiled = compile("any(orcid(checksum), equals(none))")?;
+    assert!((compiled.compiled)("none"));
+    assert!((compiled.compiled)("0000-0002-1825-0097"));
+
+    // Invalid arguments are reported by the registered function:
+    assert!(compile("orcid(foo)").is_err());
+
+    // Unknown functions result in a configuration error:
+    match compile("undefined_function(foo)") {
+        Err(e) => match e.downcast_ref::<ValveError>() {
+            Some(ValveError::ConfigError(_)) => (),
+            _ => panic!("Expected a configuration error but got: {}", e),
+        },
+        Ok(_) => panic!("Expected 'undefined_function(foo)' to be invalid"),
+    };
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_script_rules(valve: &Valve) -> Result<()> {
+    eprint!("Running test_script_rules() ... ");
+
+    // Both xyzzy and bar are present although foo is 'e', and xyzzy is less than child:
+    let row = json!({"child": 5, "parent": 6, "xyzzy": 2, "foo": "e", "bar": 25});
+    let vrow = valve
+        .validate_row("table6", row.as_object().unwrap(), None)
+        .await?;
+    let bar = vrow.contents.get("bar").unwrap();
+    assert!(!bar.valid);
+    assert_eq!(bar.messages.len(), 1);
+    assert_eq!(bar.messages[0].rule, "rule:script-1");
+    assert_eq!(bar.messages[0].level, "error");
+    assert_eq!(
+        bar.messages[0].message,
+        "exactly one of xyzzy or bar must be present when foo is 'e'"
+    );
+    // The second script does not specify a level, so the level of the rule is used. It also has
+    // an id in the rule table, which is used in place of its position:
+    let xyzzy = vrow.contents.get("xyzzy").unwrap();
+    assert!(!xyzzy.valid);
+    assert_eq!(xyzzy.messages.len(), 1);
+    assert_eq!(xyzzy.messages[0].rule, "rule:xyzzy_not_less_than_child");
+    assert_eq!(xyzzy.messages[0].level, "warning");
+
+    // A row that satisfies both scripts:
+    let row = json!({"child": 5, "parent": 6, "xyzzy": 8, "foo": "e", "bar": ""});
+    let vrow = valve
+        .validate_row("table6", row.as_object().unwrap(), None)
+        .await?;
+    assert!(vrow.contents.values().all(|cell| cell
+        .messages
+        .iter()
+        .all(|m| !m.rule.starts_with("rule:script"))));
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_composite_keys(valve: &Valve) -> Result<()> {
+    eprint!("Running test_composite_keys() ... ");
+
+    // Validate a row whose (subject, visit) combination is already in table17:
+    let row = json!({"subject": "b", "visit": 1, "note": "new"});
+    let vrow = valve
+        .validate_row("table17", row.as_object().unwrap(), None)
+        .await?;
+    let visit = vrow.contents.get("visit").unwrap();
+    assert!(!visit.valid);
+    assert_eq!(visit.messages.len(), 1);
+    assert_eq!(visit.messages[0].rule, "key:primary");
+    assert_eq!(
+        visit.messages[0].message,
+        "Values of subject, visit must be unique"
+    );
+    // Neither the subject nor the visit on its own is unique, so these columns should be valid:
+    assert!(vrow.contents.get("subject").unwrap().valid);
+    assert!(vrow.contents.get("note").unwrap().valid);
+
+    // Validate a row whose (subject, note) combination is already in table17:
+    let row = json!({"subject": "a", "visit": 3, "note": "first"});
+    let vrow = valve
+        .validate_row("table17", row.as_object().unwrap(), None)
+        .await?;
+    assert!(vrow.contents.get("visit").unwrap().valid);
+    let note = vrow.contents.get("note").unwrap();
+    assert!(!note.valid);
+    assert_eq!(note.messages[0].rule, "key:unique");
+
+    // The same row should not conflict with itself:
+    let vrow = valve
+        .validate_row("table17", row.as_object().unwrap(), Some(1))
+        .await?;
+    assert!(vrow.contents.get("note").unwrap().valid);
+
+    // An empty note cannot violate the unique key on (subject, note):
+    let row = json!({"subject": "c", "visit": 3, "note": ""});
+    let vrow = valve
+        .validate_row("table17", row.as_object().unwrap(), None)
+        .await?;
+    assert!(vrow.contents.values().all(|cell| cell.valid));
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_composite_foreign_keys(valve: &Valve) -> Result<()> {
+    eprint!("Running test_composite_foreign_keys() ... ");
+
+    // Both (a, 2) and (b, 1) are in table17, but (a, 1) and (b, 2) are checked jointly:
+    let row = json!({"subject": "a", "visit": 2, "result": "positive"});
+    let vrow = valve
+        .validate_row("table18", row.as_object().unwrap(), None)
+        .await?;
+    assert!(vrow.contents.values().all(|cell| cell.valid));
+
+    let row = json!({"subject": "c", "visit": 3, "result": "positive"});
+    let vrow = valve
+        .validate_row("table18", row.as_object().unwrap(), None)
+        .await?;
+    // The violation is reported on the last column of the key:
+    assert!(vrow.contents.get("subject").unwrap().valid);
+    let visit = vrow.contents.get("visit").unwrap();
+    assert!(!visit.valid);
+    assert_eq!(visit.messages.len(), 1);
+    assert_eq!(visit.messages[0].rule, "key:foreign");
+    assert_eq!(
+        visit.messages[0].message,
+        "Values ('c', '3') of columns subject, visit are not in (table17.subject, table17.visit)"
+    );
+
+    // If one of the key's columns is empty the key is not checked:
+    let row = json!({"subject": "", "visit": 3, "result": "positive"});
+    let vrow = valve
+        .validate_row("table18", row.as_object().unwrap(), None)
+        .await?;
+    assert!(vrow.contents.get("visit").unwrap().valid);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+fn test_transforms(valve: &Valve) -> Result<()> {
+    eprint!("Running test_transforms() ... ");
+
+    let parser = StartParser::new();
+    let substitutions = compile_transform(r"s/^([a-z]+):/\U$1\E:/ s/  +/ /g", &parser)?;
+    let value = substitutions
+        .iter()
+        .fold("obi:0000001  extra".to_string(), |value, substitution| {
+            substitution.apply(&value)
+        });
+    assert_eq!(value, "OBI:0000001 extra");
+
+    // Apart from regex substitutions, only the 'lowercase' and 'uppercase' shorthands are allowed:
+    let substitutions = compile_transform("uppercase", &parser)?;
+    assert_eq!(substitutions[0].apply("obi:0000001"), "OBI:0000001");
+    assert!(compile_transform("match(/foo/)", &parser).is_err());
+
+    // Transforms are configured using the optional transform column of the column table, which
+    // we add to a copy of the column table in order to trim and lowercase the values of the result
+    // column of table18:
+    let tmp_dir = std::env::temp_dir();
+    let column_path = tmp_dir.join(format!("valve_transform_column_{}.tsv", std::process::id()));
+    let column_path = column_path.to_string_lossy().to_string();
+    let table_path = tmp_dir.join(format!("valve_transform_table_{}.tsv", std::process::id()));
+    let table_path = table_path.to_string_lossy().to_string();
+    let source_column_path = &valve.config.table.get("column").unwrap().path;
+    let source_table_path = &valve.config.table.get("table").unwrap().path;
+    let mut columns = String::new();
+    for (i, line) in std::fs::read_to_string(source_column_path)?
+        .lines()
+        .enumerate()
+    {
+        let transform = match line {
+            _ if i == 0 => "transform",
+            _ if line.starts_with("table18\tresult\t") => r"s/^\s+|\s+$//g s/.+/\L$0\E/",
+            _ => "",
+        };
+        columns.push_str(&format!("{}\t{}\n", line, transform));
+    }
+    columns.push_str("column\ttransform\t\tempty\t\tline\t\t\t\n");
+    std::fs::write(&column_path, columns)?;
+    let tables = std::fs::read_to_string(source_table_path)?
+        .replace(
+            &format!("\t{}\t", source_column_path),
+            &format!("\t{}\t", column_path),
+        )
+        .replace(
+            &format!("\t{}\t", source_table_path),
+            &format!("\t{}\t", table_path),
+        );
+    std::fs::write(&table_path, tables)?;
+    let transforming_valve = Valve::build_in_memory(&table_path);
+    std::fs::remove_file(&column_path)?;
+    std::fs::remove_file(&table_path)?;
+    let transforming_valve = transforming_valve?;
+
+    let rows = IndexMap::from([(
+        "table18".to_string(),
+        vec![
+            json!({"subject": "a", "visit": 1, "result": "  Positive "}),
+            json!({"subject": "a", "visit": 3, "result": "negative"}),
+        ]
+        .into_iter()
+        .map(|row| row.as_object().unwrap().clone())
+        .collect::<Vec<_>>(),
+    )]);
+    let validated_rows = transforming_valve.validate_rows_in_memory(&rows)?;
+    let result = validated_rows["table18"][0].contents.get("result").unwrap();
+    assert_eq!(result.value, json!("positive"));
+    assert!(result.valid);
+    assert_eq!(result.messages.len(), 1);
+    assert_eq!(result.messages[0].rule, "transform:rewrite");
+    assert_eq!(result.messages[0].level, "info");
+
+    // Values that are already normalized are not rewritten:
+    let result = validated_rows["table18"][1].contents.get("result").unwrap();
+    assert!(result.messages.is_empty());
+
+    // Without the transform column, values are not rewritten:
+    let validated_rows = valve.validate_rows_in_memory(&rows)?;
+    let result = validated_rows["table18"][0].contents.get("result").unwrap();
+    assert_eq!(result.value, json!("  Positive "));
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_suggestions(valve: &Valve) -> Result<()> {
+    eprint!("Running test_suggestions() ... ");
+
+    // Values that differ only in case are ranked first, and ties are broken alphabetically:
+    let allowed = ["appel", "banana", "apple", "aple"]
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        get_suggestions("Apple", &allowed),
+        vec!["apple", "aple", "appel"]
+    );
+    assert!(get_suggestions("cherry", &allowed).is_empty());
+
+    // Violations of in() conditions:
+    let mut cell = ValveCell::new(&json!("Colum"));
+    validate_cell_datatype(
+        &valve.config,
+        &valve.datatype_conditions,
+        &"table".to_string(),
+        &"type".to_string(),
+        &mut cell,
+    );
+    assert!(!cell.valid);
+    let message = cell
+        .messages
+        .iter()
+        .find(|m| m.rule == "datatype:table_type")
+        .unwrap();
+    assert_eq!(message.suggestions, vec!["column"]);
+
+    // Violations of foreign keys:
+    let row = json!({"foo": "_beta_", "bar": "plorgo"});
+    let vrow = valve
+        .validate_row("table12", row.as_object().unwrap(), None)
+        .await?;
+    let bar = vrow.contents.get("bar").unwrap();
+    assert_eq!(bar.messages.len(), 1);
+    assert_eq!(bar.messages[0].rule, "key:foreign");
+    assert_eq!(
+        bar.messages[0].suggestions,
+        vec!["plorg", "slorgo", "dorgo"]
+    );
+
+    // The suggestions generated when the data was loaded are stored in the message table:
+    let sql = r#"SELECT "suggestions" FROM "message"
+                 WHERE "table" = 'table3' AND "row" = 1 AND "column" = 'source'"#;
+    let row = sqlx_query(sql).fetch_one(&valve.pool).await?;
+    let suggestions: String = row.get("suggestions");
+    assert_eq!(suggestions, r#"["COB"]"#);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_tree_cycles(valve: &Valve) -> Result<()> {
+    eprint!("Running test_tree_cycles() ... ");
+
+    // The tree in table2 is a chain from 'a' to 'h'. Giving 'h' the parent 'a' closes the chain:
+    let row = json!({"child": "h", "parent": "a", "xyzzy": "", "foo": "", "bar": ""});
+    let vrow = valve
+        .validate_row("table2", row.as_object().unwrap(), Some(8))
+        .await?;
+    let parent = vrow.contents.get("parent").unwrap();
+    assert!(!parent.valid);
+    let message = parent
+        .messages
+        .iter()
+        .find(|m| m.rule == "tree:cycle")
+        .unwrap();
+    assert_eq!(
+        message.message,
+        "Value 'a' of column parent results in a cycle: h -> a -> b -> c -> d -> e -> f -> g -> h"
+    );
+
+    // A value cannot be its own parent either:
+    let row = json!({"child": "i", "parent": "i", "xyzzy": "", "foo": "", "bar": ""});
+    let vrow = valve
+        .validate_row("table2", row.as_object().unwrap(), Some(9))
+        .await?;
+    let parent = vrow.contents.get("parent").unwrap();
+    assert!(parent.messages.iter().any(|m| m.rule == "tree:cycle"
+        && m.message == "Value 'i' of column parent results in a cycle: i -> i"));
+
+    // Without a cycle there is no tree:cycle message:
+    let row = json!({"child": "i", "parent": "a", "xyzzy": "", "foo": "", "bar": ""});
+    let vrow = valve
+        .validate_row("table2", row.as_object().unwrap(), Some(9))
+        .await?;
+    assert!(vrow.contents.get("parent").unwrap().messages.is_empty());
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_tree_queries(valve: &Valve) -> Result<()> {
+    eprint!("Running test_tree_queries() ... ");
+
+    fn row_numbers(rows: &[ValveRow]) -> Vec<u32> {
+        rows.iter().map(|r| r.row_number.unwrap()).collect()
+    }
+
+    // After test_update_1(), the value 'b' in table2 has two parents: 'f' (row 1) and 'c' (row 2).
+    // The tree in table2 is not materialized, so these queries traverse the tree in memory:
+    let ancestors = valve.get_ancestors("table2", "parent", "b").await?;
+    assert_eq!(row_numbers(&ancestors), vec![3, 6, 4, 7, 5, 8]);
+    let descendants = valve.get_descendants("table2", "parent", "e").await?;
+    assert_eq!(row_numbers(&descendants), vec![4, 3, 1, 2]);
+    let children = valve.get_children("table2", "parent", "f").await?;
+    assert_eq!(row_numbers(&children), vec![1, 5]);
+    let roots = valve.get_roots("table2", "parent").await?;
+    assert_eq!(row_numbers(&roots), vec![8, 9]);
+    assert_eq!(valve.get_depth("table2", "parent", "b").await?, 3);
+    assert_eq!(valve.get_depth("table2", "parent", "h").await?, 0);
+    assert!(valve.get_depth("table2", "parent", "a").await.is_err());
+    assert!(valve.get_ancestors("table2", "child", "b").await.is_err());
+
+    // The tree in table6 is materialized in a closure table, which should have been kept up to
+    // date by test_update_2() and test_insert_2():
+    let descendants = valve.get_descendants("table6", "parent", "6").await?;
+    assert_eq!(row_numbers(&descendants), vec![1, 2, 5, 10, 4, 3]);
+    let ancestors = valve.get_ancestors("table6", "parent", "3").await?;
+    assert_eq!(row_numbers(&ancestors), vec![4, 5, 6, 7, 8]);
+    assert!(valve
+        .get_ancestors("table6", "parent", "1")
+        .await?
+        .is_empty());
+    assert_eq!(valve.get_depth("table6", "parent", "2").await?, 3);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_polyhierarchy(valve: &Valve) -> Result<()> {
+    eprint!("Running test_polyhierarchy() ... ");
+
+    fn row_numbers(rows: &[ValveRow]) -> Vec<u32> {
+        rows.iter().map(|r| r.row_number.unwrap()).collect()
+    }
+
+    // The parents column of table19 is a list of CURIEs, each of which must be in the id column:
+    let row = json!({"id": "ZOO:8", "label": "hamster", "parents": "ZOO:2|ZOO:8|ZOO:10"});
+    let vrow = valve
+        .validate_row("table19", row.as_object().unwrap(), None)
+        .await?;
+    let parents = vrow.contents.get("parents").unwrap();
+    assert!(!parents.valid);
+    assert_eq!(
+        parents
+            .messages
+            .iter()
+            .filter(|m| m.rule == "tree:foreign")
+            .map(|m| m.message.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Value 'ZOO:10' of column parents is not in column id"]
+    );
+
+    // Acyclicity is enforced over all of the parents of a term:
+    let row = json!({"id": "ZOO:1", "label": "animal", "parents": "ZOO:4"});
+    let vrow = valve
+        .validate_row("table19", row.as_object().unwrap(), Some(1))
+        .await?;
+    let parents = vrow.contents.get("parents").unwrap();
+    assert!(parents.messages.iter().any(|m| m.rule == "tree:cycle"
+        && m.message
+            == "Value 'ZOO:4' of column parents results in a cycle: \
+                ZOO:1 -> ZOO:4 -> ZOO:2 -> ZOO:1"));
+
+    // Hierarchy queries follow every parent of a term:
+    let ancestors = valve.get_ancestors("table19", "parents", "ZOO:4").await?;
+    assert_eq!(row_numbers(&ancestors), vec![2, 3, 1]);
+    let descendants = valve.get_descendants("table19", "parents", "ZOO:3").await?;
+    assert_eq!(row_numbers(&descendants), vec![4, 5]);
+    let children = valve.get_children("table19", "parents", "ZOO:2").await?;
+    assert_eq!(row_numbers(&children), vec![4, 5]);
+    assert_eq!(valve.get_depth("table19", "parents", "ZOO:5").await?, 2);
+
+    // The possible values of the parents column are the values of the id column:
+    let matching_values = valve
+        .get_matching_values("table19", "parents", Some("ZOO:"))
+        .await?;
+    assert_eq!(matching_values.as_array().unwrap().len(), 7);
+
+    // Inserting the missing parent of row 5 resolves the tree:foreign violation in that row:
+    let row = json!({"id": "ZOO:9", "label": "parrot", "parents": "ZOO:3"});
+    valve
+        .insert_row("table19", row.as_object().unwrap())
+        .await?;
+    let descendants = valve.get_descendants("table19", "parents", "ZOO:9").await?;
+    assert_eq!(row_numbers(&descendants), vec![5]);
+    assert!(descendants[0].contents.get("parents").unwrap().valid);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+fn test_prefixes(valve: &Valve) -> Result<()> {
+    eprint!("Running test_prefixes() ... ");
+
+    // The prefixes are defined in the prefix table:
+    assert_eq!(
+        valve.expand_curie("owl:Class")?,
+        "http://www.w3.org/2002/07/owl#Class"
+    );
+    assert_eq!(
+        valve.expand_curie("VO:0000001")?,
+        "http://purl.obolibrary.org/obo/VO_0000001"
+    );
+    assert!(valve.expand_curie("FOO:0000001").is_err());
+    assert!(valve.expand_curie("owl Class").is_err());
+    assert_eq!(
+        valve.contract_iri("http://purl.obolibrary.org/obo/COB_0000013")?,
+        "COB:0000013"
+    );
+    assert!(valve.contract_iri("http://example.com/foo").is_err());
+
+    // The type column of table3 must contain CURIEs with registered prefixes. An unknown prefix
+    // results in a validation message:
+    let validate = |value: &str| -> ValveCell {
+        let mut cell = ValveCell::new(&json!(value));
+        validate_cell_datatype(
+            &valve.config,
+            &valve.datatype_conditions,
+            &"table3".to_string(),
+            &"type".to_string(),
+            &mut cell,
+        );
+        cell
+    };
+    assert!(validate("rdfs:Class").valid);
+    let cell = validate("foaf:Person");
+    assert!(!cell.valid);
+    assert_eq!(cell.messages.len(), 1);
+    assert_eq!(cell.messages[0].rule, "datatype:known_CURIE");
+
+    // The arguments of curie(), if any, restrict the allowed prefixes:
+    let parser = StartParser::new();
+    let compiled = compile_condition(
+        "curie(owl, rdfs)",
+        &parser,
+        &valve.datatype_conditions,
+        "TEXT",
+        None,
+        &valve.config.prefix,
+    )?;
+    assert!((compiled.compiled)("owl:Class"));
+    assert!(!(compiled.compiled)("rdf:type"));
+    assert!(!(compiled.compiled)("http://www.w3.org/2002/07/owl#Class"));
+
+    eprintln!("done.");
+    Ok(())
+}
+
+fn test_validate_in_memory(valve: &Valve) -> Result<()> {
+    eprint!("Running test_validate_in_memory() ... ");
+
+    // Returns the rules of the messages about the given column of the given row:
+    fn get_rules(rows: &[ValveRow], row_number: u32, column: &str) -> Vec<String> {
+        let row = rows
+            .iter()
+            .find(|row| row.row_number == Some(row_number))
+            .unwrap();
+        row.contents
+            .get(column)
+            .unwrap()
+            .messages
+            .iter()
+            .map(|m| m.rule.to_string())
+            .collect()
+    }
+
+    assert!(Valve::build_in_memory("test/src/table.txt").is_err());
+    let in_memory = Valve::build_in_memory(&valve.get_path()?)?;
+
+    // Only the requested tables are returned, though the tables they depend on are validated too:
+    let tables = in_memory.validate_tables_in_memory(&vec!["table3", "table6"])?;
+    assert_eq!(tables.keys().collect::<Vec<_>>(), vec!["table3", "table6"]);
+    let table3 = tables.get("table3").unwrap();
+    assert_eq!(table3.len(), 10);
+    assert_eq!(get_rules(table3, 1, "source"), vec!["key:foreign"]);
+    assert_eq!(get_rules(table3, 2, "parent"), vec!["tree:cycle"]);
+    assert_eq!(get_rules(table3, 5, "parent"), vec!["tree:foreign"]);
+    assert_eq!(get_rules(table3, 8, "id"), vec!["key:unique"]);
+    assert_eq!(get_rules(table3, 10, "label"), vec!["key:primary"]);
+    assert!(get_rules(table3, 9, "label").is_empty());
+    let table6 = tables.get("table6").unwrap();
+    assert_eq!(
+        get_rules(table6, 1, "foo"),
+        vec!["rule:foo-2", "rule:foo-4"]
+    );
+    assert_eq!(
+        get_rules(table6, 6, "xyzzy"),
+        vec!["rule:xyzzy_not_less_than_child"]
+    );
+    let child = table6[8].contents.get("child").unwrap();
+    assert!(!child.valid);
+    assert_eq!(
+        child.messages[0].message,
+        "Value '9' of column child exists only in table4_conflict.numeric_foreign_column"
+    );
+
+    // Rows that are given in memory are validated as if they were the only rows in their table:
+    let rows = json!([
+        {"id": "ZOO:1", "label": "animal", "parents": ""},
+        {"id": "ZOO:2", "label": "mammal", "parents": "ZOO:1|ZOO:8"},
+        {"id": "ZOO:2", "label": "marsupial", "parents": "ZOO:1"},
+    ]);
+    let rows = rows
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row.as_object().unwrap().clone())
+        .collect::<Vec<_>>();
+    let tables =
+        in_memory.validate_rows_in_memory(&IndexMap::from([("table19".to_string(), rows)]))?;
+    assert_eq!(tables.len(), 1);
+    let table19 = tables.get("table19").unwrap();
+    assert!(get_rules(table19, 1, "parents").is_empty());
+    assert_eq!(get_rules(table19, 2, "parents"), vec!["tree:foreign"]);
+    assert_eq!(get_rules(table19, 3, "id"), vec!["key:primary"]);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_load_dry_run(valve: &Valve) -> Result<()> {
+    eprint!("Running test_load_dry_run() ... ");
+
+    let count_messages = || async {
+        let sql = r#"SELECT COUNT(1) AS "num_messages" FROM "message""#;
+        let row = sqlx_query(sql).fetch_one(&valve.pool).await?;
+        let num_messages: i64 = row.get("num_messages");
+        Ok::<i64, anyhow::Error>(num_messages)
+    };
+    let num_messages = count_messages().await?;
+
+    // Only the requested tables are reported on, though the tables they depend on are loaded too:
+    let report = valve.load_tables_dry_run(&vec!["table3"]).await?;
+    assert_eq!(report.tables.keys().collect::<Vec<_>>(), vec!["table3"]);
+    let stats = report.tables.get("table3").unwrap();
+    assert_eq!(stats.rows, 2);
+    assert_eq!(stats.conflict_rows, 8);
+    assert_eq!(stats.errors, 20);
+    assert_eq!(stats.warnings, 0);
+    assert_eq!(report.messages.len(), 20);
+    assert!(report.messages.iter().all(|m| m.table == "table3"));
+    assert!(report
+        .messages
+        .iter()
+        .any(|m| m.row == 2 && m.column == "parent" && m.rule == "tree:cycle"));
+
+    // The database itself has not been touched:
+    assert_eq!(count_messages().await?, num_messages);
+
+    // A dry run does not require a database:
+    let in_memory = Valve::build_in_memory(&valve.get_path()?)?;
+    let report = in_memory.load_tables_dry_run(&vec!["table6"]).await?;
+    let stats = report.tables.get("table6").unwrap();
+    assert_eq!(stats.rows, 8);
+    assert_eq!(stats.conflict_rows, 1);
+    assert_eq!(stats.warnings, 1);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+fn test_explain_rule(valve: &Valve) -> Result<()> {
+    eprint!("Running test_explain_rule() ... ");
+
+    // Every kind of rule can be recovered from the identifiers that are used in messages:
+    for kind in ValveRuleKind::ALL {
+        let rule_id = kind.rule_id("1");
+        let (recovered, _) = ValveRuleKind::from_rule_id(&rule_id).unwrap();
+        assert_eq!(recovered, kind);
+    }
+    assert!(ValveRuleKind::from_rule_id("bogus:rule").is_none());
+
+    let explanation = valve.explain_rule("key:foreign")?;
+    assert_eq!(explanation.kind, ValveRuleKind::KeyForeign);
+    assert_eq!(explanation.level, "error");
+    assert!(explanation.details.is_empty());
+
+    let explanation = valve.explain_rule("option:redundant")?;
+    assert_eq!(explanation.level, "warning");
+
+    let explanation = valve.explain_rule("datatype:word")?;
+    assert_eq!(explanation.kind, ValveRuleKind::Datatype);
+    assert_eq!(explanation.details.len(), 1);
+    assert!(explanation.details[0].contains("exclude(/\\W/)"));
+
+    // A rule in the rule table that has been given an id:
+    let explanation = valve.explain_rule("rule:bar_y_or_z")?;
+    assert_eq!(explanation.kind, ValveRuleKind::Rule);
+    assert_eq!(explanation.details.len(), 1);
+    assert!(explanation.details[0].starts_with("table2: when foo is 'equals(5)'"));
+
+    // A positional rule identifier can refer to rules in more than one table:
+    let explanation = valve.explain_rule("rule:foo-1")?;
+    assert_eq!(explanation.details.len(), 2);
+
+    // A script rule that has been given an id, and one that has not:
+    let explanation = valve.explain_rule("rule:xyzzy_not_less_than_child")?;
+    assert_eq!(explanation.kind, ValveRuleKind::Rule);
+    assert_eq!(explanation.level, "warning");
+    let explanation = valve.explain_rule("rule:script-1")?;
+    assert_eq!(explanation.kind, ValveRuleKind::ScriptRule);
+    assert_eq!(explanation.details.len(), 1);
+
+    // The fourth rule for table2.foo has been given an id, so only the fourth rule for
+    // table6.foo, which has not, is identified by its position:
+    let explanation = valve.explain_rule("rule:foo-4")?;
+    assert_eq!(explanation.details.len(), 1);
+    assert!(explanation.details[0].starts_with("table6:"));
+    assert!(valve.explain_rule("rule:script-2").is_err());
+    assert!(valve.explain_rule("datatype:nonexistent").is_err());
+    assert!(valve.explain_rule("bogus").is_err());
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_fixes(valve: &Valve) -> Result<()> {
+    eprint!("Running test_fixes() ... ");
+
+    let fixes = valve
+        .propose_fixes("table3", &ValveMessageFilter::default())
+        .await?;
+    assert_eq!(fixes.len(), 2);
+    assert_eq!(fixes[0].row, 1);
+    assert_eq!(fixes[0].column, "label");
+    assert_eq!(fixes[0].value, "   mobecular entity");
+    assert_eq!(fixes[0].fixed_value, "mobecular entity");
+    assert_eq!(fixes[1].row, 7);
+    assert_eq!(fixes[1].column, "source");
+    assert_eq!(fixes[1].fixed_value, "COB");
+    assert!(fixes[1].rules.contains(&"key:foreign".to_string()));
+
+    // Only propose fixes for the values that satisfy the filter:
+    let filter = ValveMessageFilter {
+        column: Some("label".to_string()),
+        rule: Some("datatype:%".to_string()),
+        ..Default::default()
+    };
+    let fixes = valve.propose_fixes("table3", &filter).await?;
+    assert_eq!(fixes.len(), 1);
+    assert_eq!(fixes[0].row, 1);
+
+    // Apply the fix, verify that the messages about the value are gone, then undo it:
+    let updated_rows = valve.apply_fixes(&fixes).await?;
+    assert_eq!(updated_rows.len(), 1);
+    let label = updated_rows[0].contents.get("label").unwrap();
+    assert_eq!(label.strvalue(), "mobecular entity");
+    assert!(label.valid);
+    assert!(valve.propose_fixes("table3", &filter).await?.is_empty());
+    valve.undo().await?;
+    assert_eq!(valve.propose_fixes("table3", &filter).await?.len(), 1);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_sync(valve: &Valve) -> Result<()> {
+    eprint!("Running test_sync() ... ");
+
+    // Synchronizes table7 with a TSV file having the given contents, and returns the statistics:
+    async fn sync_table7(valve: &Valve, contents: &str) -> Result<(usize, usize, usize, usize)> {
+        let path = std::env::temp_dir().join(format!("valve_sync_{}.tsv", std::process::id()));
+        std::fs::write(&path, contents)?;
+        let mut sync_valve = valve.clone();
+        sync_valve.config.table.get_mut("table7").unwrap().path =
+            path.to_string_lossy().to_string();
+        let stats = sync_valve.sync_tables(&vec!["table7"]).await;
+        std::fs::remove_file(&path)?;
+        let stats = stats?.get("table7").unwrap().clone();
+        Ok((
+            stats.inserted,
+            stats.updated,
+            stats.deleted,
+            stats.unchanged,
+        ))
+    }
+
+    // Rows are matched by primary key, which for table7 is zork:
+    let stats = sync_table7(valve, "zork\tplanetfall\nb\t3\nc\te\nd\t4\n").await?;
+    assert_eq!(stats, (1, 1, 1, 1));
+    let sql = r#"SELECT "user" FROM "history" ORDER BY "history_id" DESC LIMIT 1"#;
+    let row = sqlx_query(sql).fetch_one(&valve.pool).await?;
+    let user: &str = row.get("user");
+    assert_eq!(user, Valve::DEFAULT_SYNC_USER);
+
+    // Synchronizing again with the same file does nothing:
+    let stats = sync_table7(valve, "zork\tplanetfall\nb\t3\nc\te\nd\t4\n").await?;
+    assert_eq!(stats, (0, 0, 0, 3));
+
+    // Undo the insert, update, and delete:
+    for _ in 0..3 {
+        valve.undo().await?;
+    }
+
+    // Values in the file are compared with the values in the table after they are transformed:
+    let mut valve = valve.clone();
+    let column_config = valve
+        .config
+        .table
+        .get_mut("table7")
+        .unwrap()
+        .column
+        .get_mut("zork")
+        .unwrap();
+    column_config.transform = "lowercase".to_string();
+    let stats = sync_table7(&valve, "zork\tplanetfall\nA\t1\nB\t2\nC\te\n").await?;
+    assert_eq!(stats, (0, 0, 0, 3));
+    let column_config = valve
+        .config
+        .table
+        .get_mut("table7")
+        .unwrap()
+        .column
+        .get_mut("zork")
+        .unwrap();
+    column_config.transform = "".to_string();
+
+    // Rows are matched by row number when the table has no primary key, i.e., the nth row of the
+    // file is matched with row n of the table:
+    valve
+        .config
+        .constraint
+        .primary
+        .insert("table7".to_string(), vec![]);
+    valve.set_sync_user("test-sync")?;
+    let stats = sync_table7(&valve, "zork\tplanetfall\na\t1\nb\t2\n").await?;
+    assert_eq!(stats, (0, 0, 1, 2));
+    let row = sqlx_query(sql).fetch_one(&valve.pool).await?;
+    let user: &str = row.get("user");
+    assert_eq!(user, "test-sync");
+
+    // The changes are made in a single transaction, so that when one of them fails (here because
+    // zork is still the primary key of table7 in the database) none of them is made:
+    let sql = r#"SELECT "zork", "planetfall" FROM "table7_text_view" ORDER BY "row_order""#;
+    let get_rows = || async {
+        sqlx_query(sql)
+            .fetch_all(&valve.pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<String, _>("zork"),
+                    row.get::<String, _>("planetfall"),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let rows = get_rows().await;
+    assert!(sync_table7(&valve, "zork\tplanetfall\na\t9\na\t1\n")
+        .await
+        .is_err());
+    assert_eq!(get_rows().await, rows);
+
+    let stats = sync_table7(&valve, "zork\tplanetfall\na\t1\nb\t5\n").await?;
+    assert_eq!(stats, (0, 1, 0, 1));
+    assert_eq!(
+        get_rows().await,
+        vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "5".to_string())
+        ]
+    );
+    let stats = sync_table7(&valve, "zork\tplanetfall\na\t1\nb\t5\n").await?;
+    assert_eq!(stats, (0, 0, 0, 2));
+    for _ in 0..2 {
+        valve.undo().await?;
+    }
+    assert!(valve.set_sync_user(" test-sync").is_err());
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_load_changed_tables(valve: &Valve) -> Result<()> {
+    eprint!("Running test_load_changed_tables() ... ");
+
+    // Nothing has changed since the database was loaded:
+    assert!(valve.get_changed_tables().await?.is_empty());
+
+    // A table is considered to have changed when its configuration changes, e.g., when it is
+    // configured to be loaded from a different file, even if the file has the same contents:
+    let path = std::env::temp_dir().join(format!("valve_changed_{}.tsv", std::process::id()));
+    let contents = std::fs::read_to_string("test/src/ontology/table7.tsv")?;
+    std::fs::write(&path, &contents)?;
+    let mut changed_valve = valve.clone();
+    changed_valve.config.table.get_mut("table7").unwrap().path = path.to_string_lossy().to_string();
+    let changed_tables = changed_valve.get_changed_tables().await;
+    if changed_tables.is_err() {
+        std::fs::remove_file(&path)?;
+    }
+    assert_eq!(changed_tables?, vec!["table7"]);
+
+    // Reloading the changed tables deletes their history, but not the history of other tables:
+    let count_history = |condition: &str| -> Result<i64> {
+        let sql = format!(
+            r#"SELECT COUNT(1) AS "count" FROM "history" WHERE {}"#,
+            condition
+        );
+        let row = block_on(sqlx_query(&sql).fetch_one(&valve.pool))?;
+        Ok(row.get::<i64, _>("count"))
+    };
+    let row = json!({"zork": "a", "planetfall": "7"});
+    valve
+        .update_row("table7", &1, row.as_object().unwrap())
+        .await?;
+    assert!(count_history(r#""table" = 'table7'"#)? > 0);
+    let num_other_records = count_history(r#""table" <> 'table7'"#)?;
+    let loaded_tables = changed_valve.load_changed_tables(true).await;
+    std::fs::remove_file(&path)?;
+    assert_eq!(loaded_tables?, vec!["table7"]);
+    assert_eq!(count_history(r#""table" = 'table7'"#)?, 0);
+    assert_eq!(count_history(r#""table" <> 'table7'"#)?, num_other_records);
+
+    // Reloading the table under the original configuration brings the database up to date:
+    assert_eq!(valve.load_changed_tables(true).await?, vec!["table7"]);
+    assert!(valve.get_changed_tables().await?.is_empty());
+
+    // A table is also considered to have changed when a datatype that one of its column's
+    // datatypes refers to in its condition changes (table12.foo is of type custom_b, which is
+    // defined as list(custom_a, ' ')):
+    let mut changed_valve = valve.clone();
+    changed_valve
+        .config
+        .datatype
+        .get_mut("custom_a")
+        .unwrap()
+        .condition = "match(/_\\w+/)".to_string();
+    assert!(changed_valve
+        .get_changed_tables()
+        .await?
+        .contains(&"table12".to_string()));
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_composite_primary_key_nulls(valve: &Valve) -> Result<()> {
+    eprint!("Running test_composite_primary_key_nulls() ... ");
+
+    // A row whose values for a multi-column primary key would be stored as NULL, which the
+    // database (in particular PostgreSQL) does not allow, is stored in the conflict table, both
+    // when the table is loaded and when the row is inserted:
+    let path = std::env::temp_dir().join(format!("valve_null_keys_{}.tsv", std::process::id()));
+    let contents = std::fs::read_to_string("test/src/ontology/table17.tsv")?;
+    std::fs::write(&path, format!("{}d\t\tnone\n", contents))?;
+    let mut nullable_valve = valve.clone();
+    let table_config = nullable_valve.config.table.get_mut("table17").unwrap();
+    table_config.path = path.to_string_lossy().to_string();
+    table_config.column.get_mut("visit").unwrap().nulltype = "empty".to_string();
+    let loaded_tables = nullable_valve.load_changed_tables(true).await;
+    std::fs::remove_file(&path)?;
+    assert_eq!(loaded_tables?, vec!["table17", "table18"]);
+    let count_conflicts = |subject: &str| -> Result<i64> {
+        let sql = local_sql_syntax(
+            &valve.pool,
+            &format!(
+                r#"SELECT COUNT(1) AS "count" FROM "table17_conflict" WHERE "subject" = {}"#,
+                SQL_PARAM
+            ),
+        );
+        let row = block_on(sqlx_query(&sql).bind(subject).fetch_one(&valve.pool))?;
+        Ok(row.get::<i64, _>("count"))
+    };
+    assert_eq!(count_conflicts("d")?, 1);
+    let row = json!({"subject": "e", "visit": "", "note": "none"});
+    let (row_number, vrow) = nullable_valve
+        .insert_row("table17", row.as_object().unwrap())
+        .await?;
+    assert!(vrow.contents.values().all(|cell| cell.valid));
+    assert_eq!(count_conflicts("e")?, 1);
+    nullable_valve.delete_row("table17", &row_number).await?;
+
+    // Reloading the tables under the original configuration brings the database up to date:
+    assert_eq!(
+        valve.load_changed_tables(true).await?,
+        vec!["table17", "table18"]
+    );
+    assert_eq!(count_conflicts("d")?, 0);
+
+    // A table without a conflict table declares its multi-column primary key as a unique key
+    // instead, so that such rows can be stored in the table itself:
+    let mut no_conflict_valve = valve.clone();
+    let table_config = no_conflict_valve.config.table.get_mut("table17").unwrap();
+    table_config.options.remove("conflict");
+    let ddl = no_conflict_valve
+        .get_table_ddl(&"table17".to_string(), &valve.pool)?
+        .join("\n");
+    assert!(ddl.contains(r#"UNIQUE ("subject", "visit")"#));
+    assert!(!ddl.contains("PRIMARY KEY"));
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_migrate(valve: &Valve) -> Result<()> {
+    eprint!("Running test_migrate() ... ");
+
+    // The database already matches the configuration:
+    assert!(valve.plan_migration().await?.is_empty());
+
+    let count_rows = |table: &str| -> Result<i64> {
+        let sql = format!(r#"SELECT COUNT(1) AS "count" FROM "{}_view""#, table);
+        let row = block_on(sqlx_query(&sql).fetch_one(&valve.pool))?;
+        Ok(row.get::<i64, _>("count"))
+    };
+    let num_rows = count_rows("table7")?;
+
+    // Add a column to table7 in a copy of the configuration. The column is added to the existing
+    // table, which keeps its data:
+    let mut migrated_valve = valve.clone();
+    let table_config = migrated_valve.config.table.get_mut("table7").unwrap();
+    let mut column_config = table_config.column.get("planetfall").unwrap().clone();
+    column_config.column = "extra".to_string();
+    column_config.datatype = "text".to_string();
+    table_config
+        .column
+        .insert("extra".to_string(), column_config);
+    table_config.column_order.insert(1, "extra".to_string());
+    let steps = migrated_valve.migrate().await?;
+    let descriptions = steps
+        .iter()
+        .map(|s| s.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        descriptions,
+        vec![
+            "Drop the table's views",
+            "Add the column 'extra'",
+            "Recreate the table's views"
+        ]
+    );
+    assert!(steps.iter().all(|s| s.table == "table7"));
+    assert!(migrated_valve.plan_migration().await?.is_empty());
+    // Columns are not physically reordered, so the stored columns are still not in their
+    // configured order:
+    assert!(migrated_valve.table_has_changed("table7").await?);
+    assert_eq!(count_rows("table7")?, num_rows);
+
+    // The views present the new column in its configured position:
+    let sql = r#"SELECT * FROM "table7_text_view" LIMIT 1"#;
+    let row = sqlx_query(sql).fetch_one(&valve.pool).await?;
+    let columns = row.columns().iter().map(|c| c.name()).collect::<Vec<_>>();
+    assert_eq!(
+        columns,
+        vec![
+            "row_number",
+            "row_order",
+            "message",
+            "history",
+            "zork",
+            "extra",
+            "planetfall"
+        ]
+    );
+
+    // Migrating back to the original configuration drops the column again, along with any
+    // messages about it:
+    let steps = valve.migrate().await?;
+    assert!(steps
+        .iter()
+        .any(|s| s.description == "Drop the column 'extra'"));
+    let delete_step = steps
+        .iter()
+        .find(|s| s.description == "Delete the messages about the column 'extra'")
+        .unwrap();
+    assert_eq!(delete_step.params, vec!["table7", "extra"]);
+    assert!(valve.plan_migration().await?.is_empty());
+    assert_eq!(count_rows("table7")?, num_rows);
+
+    // Creating the tables under a changed configuration migrates them rather than recreating
+    // them, so their data is kept:
+    migrated_valve.create_all_tables().await?;
+    assert!(migrated_valve.plan_migration().await?.is_empty());
+    assert_eq!(count_rows("table7")?, num_rows);
+    valve.create_all_tables().await?;
+    assert!(valve.plan_migration().await?.is_empty());
+    assert_eq!(count_rows("table7")?, num_rows);
+
+    // A column whose values can all be converted to its new SQL type is migrated in place:
+    let mut retyped_valve = valve.clone();
+    let table_config = retyped_valve.config.table.get_mut("table7").unwrap();
+    let column_config = table_config.column.get_mut("planetfall").unwrap();
+    column_config.datatype = "text".to_string();
+    let steps = retyped_valve.plan_migration().await?;
+    assert!(!steps.is_empty());
+    assert!(steps
+        .iter()
+        .all(|s| s.table == "table7" && !s.recreates_table));
+    if valve.pool.any_kind() == AnyKind::Postgres {
+        assert!(steps.iter().any(|s| s
+            .description
+            .starts_with("Change the SQL type of the column 'planetfall'")));
+    } else {
+        assert!(steps
+            .iter()
+            .any(|s| s.description.starts_with("Copy the table's data aside")));
+    }
+
+    // A column whose values cannot all be converted to its new SQL type (here, the primary key
+    // of table7) cannot be migrated, and the plan says so instead of altering the column:
+    let mut retyped_valve = valve.clone();
+    let table_config = retyped_valve.config.table.get_mut("table7").unwrap();
+    let column_config = table_config.column.get_mut("zork").unwrap();
+    column_config.datatype = "integer".to_string();
+    let steps = retyped_valve.plan_migration().await?;
+    assert_eq!(steps.len(), 1);
+    assert!(steps[0].recreates_table);
+    assert!(steps[0].statements.is_empty());
+    assert!(steps[0]
+        .description
+        .contains("the values of the column 'zork' cannot all be converted"));
+    assert!(retyped_valve.migrate().await.is_err());
+    assert_eq!(count_rows("table7")?, num_rows);
+    assert!(valve.plan_migration().await?.is_empty());
+
+    eprintln!("done.");
+    Ok(())
+}
+
+fn test_lint_config(valve: &Valve) -> Result<()> {
+    eprint!("Running test_lint_config() ... ");
+
+    // The only errors in the test configuration are the unrecognized and reserved options that
+    // are deliberately configured in the table table:
+    let table_path = &valve.config.table.get("table").unwrap().path;
+    let issues = Valve::lint_config(table_path)?;
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == "error")
+        .map(|i| {
+            (
+                i.file.as_str(),
+                i.row,
+                i.column.as_str(),
+                i.message.as_str(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            (
+                "test/src/table.tsv",
+                12,
+                "options",
+                "unrecognized option: 'foo'"
+            ),
+            (
+                "test/src/table.tsv",
+                23,
+                "options",
+                "reserved for internal use: 'internal'"
+            ),
+        ]
+    );
+    assert!(Valve::lint_config("test/src/table.csv").is_err());
+
+    // Problems in more than one row of the column table are all reported:
+    let tmp_dir = std::env::temp_dir();
+    let column_path = tmp_dir.join(format!("valve_lint_column_{}.tsv", std::process::id()));
+    let column_path = column_path.to_string_lossy().to_string();
+    let table_path = tmp_dir.join(format!("valve_lint_table_{}.tsv", std::process::id()));
+    let table_path = table_path.to_string_lossy().to_string();
+    let mut columns = std::fs::read_to_string("test/src/column.tsv")?;
+    let num_rows = columns.lines().count() as u32 - 1;
+    columns.push_str("table7\textra_1\t\t\t\tno_such_datatype\t\t\n");
+    columns.push_str("table7\textra_2\t\t\t\ttext\tfrom(table7.no_such_column)\t\n");
+    std::fs::write(&column_path, columns)?;
+    let tables = std::fs::read_to_string("test/src/table.tsv")?
+        .replace("\ttest/src/column.tsv\t", &format!("\t{}\t", column_path))
+        .replace("\ttest/src/table.tsv\t", &format!("\t{}\t", table_path));
+    std::fs::write(&table_path, tables)?;
+    let issues = Valve::lint_config(&table_path);
+    // Building Valve from the same configuration fails with an error that describes every
+    // problem found with it:
+    let build_error = match Valve::build_in_memory(&table_path) {
+        Ok(_) => String::from(""),
+        Err(e) => e.to_string(),
+    };
+    std::fs::remove_file(&column_path)?;
+    std::fs::remove_file(&table_path)?;
+    let errors = issues?
+        .into_iter()
+        .filter(|i| i.file == column_path)
+        .map(|i| (i.row, i.column, i.severity, i.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            (
+                num_rows + 1,
+                "datatype".to_string(),
+                "error".to_string(),
+                "Undefined datatype 'no_such_datatype'".to_string()
+            ),
+            (
+                num_rows + 2,
+                "structure".to_string(),
+                "error".to_string(),
+                "Undefined column 'table7.no_such_column' in foreign key: \
+                 from(table7.no_such_column)"
+                    .to_string()
+            ),
+        ]
+    );
+    assert!(build_error.contains("Undefined datatype 'no_such_datatype'"));
+    assert!(build_error.contains("Undefined column 'table7.no_such_column' in foreign key"));
+
+    // When the column table cannot be read, only that problem is reported, and not the problems
+    // with everything that depends on it, such as the rules:
+    let mut columns = std::fs::read_to_string("test/src/column.tsv")?;
+    columns.push_str(
+        "table7	extra
+",
+    );
+    std::fs::write(&column_path, columns)?;
+    let tables = std::fs::read_to_string("test/src/table.tsv")?
+        .replace("\ttest/src/column.tsv\t", &format!("\t{}\t", column_path))
+        .replace("\ttest/src/table.tsv\t", &format!("\t{}\t", table_path));
+    std::fs::write(&table_path, tables)?;
+    let issues = Valve::lint_config(&table_path);
+    std::fs::remove_file(&column_path)?;
+    std::fs::remove_file(&table_path)?;
+    let errors = issues?
+        .into_iter()
+        .filter(|i| i.severity == "error" && i.file != table_path)
+        .map(|i| (i.file, i.row))
+        .collect::<Vec<_>>();
+    assert_eq!(errors, vec![(column_path.to_string(), 0)]);
+
+    eprintln!("done.");
+    Ok(())
+}
+
+fn test_diff_config(valve: &Valve) -> Result<()> {
+    eprint!("Running test_diff_config() ... ");
+
+    // There are no differences between a configuration and itself:
+    assert!(valve.diff_config(&valve.config).is_empty());
+
+    let mut config = valve.config.clone();
+    let summarize = |config| {
+        valve
+            .diff_config(config)
+            .into_iter()
+            .map(|c| (c.kind, c.item, c.change, c.field, c.rebuilds, c.revalidates))
+            .collect::<Vec<_>>()
+    };
+
+    // Changing the datatype of a column to one with a different SQL type requires the table to be
+    // recreated, while changing its description has no effect:
+    let bar = config
+        .table
+        .get_mut("table2")
+        .and_then(|t| t.column.get_mut("bar"))
+        .unwrap();
+    bar.datatype = "integer".to_string();
+    bar.description = "A bar".to_string();
+    // Adding a foreign key also requires the table to be recreated:
+    config
+        .constraint
+        .foreign
+        .entry("table2".to_string())
+        .or_default()
+        .push(ValveForeignConstraint {
+            table: "table2".to_string(),
+            column: "bar".to_string(),
+            ftable: "table4".to_string(),
+            fcolumn: "numeric_foreign_column".to_string(),
+        });
+    let table2 = vec!["table2".to_string()];
+    assert_eq!(
+        summarize(&config),
+        vec![
+            (
+                "column".to_string(),
+                "table2.bar".to_string(),
+                "changed".to_string(),
+                "datatype".to_string(),
+                table2.clone(),
+                table2.clone(),
+            ),
+            (
+                "column".to_string(),
+                "table2.bar".to_string(),
+                "changed".to_string(),
+                "description".to_string(),
+                vec![],
+                vec![],
+            ),
+            (
+                "constraint".to_string(),
+                "table2".to_string(),
+                "added".to_string(),
+                "".to_string(),
+                table2.clone(),
+                table2.clone(),
+            ),
+        ]
+    );
+
+    // Changing the condition of a datatype affects the validation of every (non-internal) table
+    // with a column of that datatype, but does not require any tables to be recreated:
+    let mut config = valve.config.clone();
+    config.datatype.get_mut("word").unwrap().condition = "exclude(/[^A-Za-z]/)".to_string();
+    let changes = valve.diff_config(&config);
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].rebuilds.is_empty());
+    for (table, table_config) in &valve.config.table {
+        if !INTERNAL_TABLES.contains(&table.as_str())
+            && table_config.column.values().any(|c| c.datatype == "word")
+        {
+            assert!(changes[0].revalidates.contains(table));
+        }
+    }
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_validate_with_config(valve: &Valve) -> Result<()> {
+    eprint!("Running test_validate_with_config() ... ");
+
+    // Validating the data in the database under the current configuration does not change any of
+    // the messages in the message table:
+    let current = Valve::build_in_memory(&valve.get_path()?)?;
+    assert!(valve
+        .validate_with_config(&current, &vec![])
+        .await?
+        .iter()
+        .all(|delta| delta.new.is_empty() && delta.resolved.is_empty()));
+
+    // A message in the message table that validating the rows does not reproduce is reported as
+    // such:
+    let sql = r#"INSERT INTO "message"
+                   ("table", "row", "column", "value", "level", "rule", "message")
+                 VALUES ('table16', 1, 'bar', 'x', 'error', 'custom:test', 'A test message')"#;
+    sqlx_query(sql).execute(&valve.pool).await?;
+    let deltas = valve.validate_with_config(&current, &vec!["table16"]).await;
+    let sql = r#"DELETE FROM "message" WHERE "rule" = 'custom:test'"#;
+    sqlx_query(sql).execute(&valve.pool).await?;
+    let deltas = deltas?;
+    assert_eq!(deltas.len(), 1);
+    assert_eq!(
+        (
+            deltas[0].rule.as_str(),
+            deltas[0].new.len(),
+            deltas[0].resolved.len(),
+            deltas[0].unreproduced.len()
+        ),
+        ("custom:test", 0, 0, 1)
+    );
+    assert!(valve
+        .validate_with_config(&current, &vec!["column"])
+        .await
+        .is_err());
+
+    // Removing the condition of the 'exclude_pipe' datatype resolves every message about it:
+    let tmp_dir = std::env::temp_dir();
+    let datatype_path = tmp_dir.join(format!("valve_what_if_datatype_{}.tsv", std::process::id()));
+    let datatype_path = datatype_path.to_string_lossy().to_string();
+    let table_path = tmp_dir.join(format!("valve_what_if_table_{}.tsv", std::process::id()));
+    let table_path = table_path.to_string_lossy().to_string();
+    let datatypes = std::fs::read_to_string("test/src/datatype.tsv")?
+        .replace("\texclude(/[a-z]+|[0-9]+/)\t", "\t\t");
+    std::fs::write(&datatype_path, datatypes)?;
+    let tables = std::fs::read_to_string("test/src/table.tsv")?
+        .replace(
+            "\ttest/src/datatype.tsv\t",
+            &format!("\t{}\t", datatype_path),
+        )
+        .replace("\ttest/src/table.tsv\t", &format!("\t{}\t", table_path));
+    std::fs::write(&table_path, tables)?;
+    let candidate = Valve::build_in_memory(&table_path);
+    std::fs::remove_file(&datatype_path)?;
+    std::fs::remove_file(&table_path)?;
+    let deltas = valve
+        .validate_with_config(&candidate?, &vec!["table16"])
+        .await?;
+
+    let sql = r#"SELECT COUNT(1) AS "count" FROM "message"
+                  WHERE "table" = 'table16' AND "rule" = 'datatype:exclude_pipe'"#;
+    let row = sqlx_query(sql).fetch_one(&valve.pool).await?;
+    let num_messages = row.get::<i64, _>("count") as usize;
+    assert!(num_messages > 0);
+    assert_eq!(deltas.len(), 1);
+    assert_eq!(
+        (
+            deltas[0].table.as_str(),
+            deltas[0].column.as_str(),
+            deltas[0].rule.as_str(),
+            deltas[0].new.len(),
+            deltas[0].resolved.len()
+        ),
+        ("table16", "bar", "datatype:exclude_pipe", 0, num_messages)
+    );
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_dates(valve: &Valve) -> Result<()> {
+    eprint!("Running test_dates() ... ");
+
+    // Values of DATE and TIMESTAMP columns are read back from the database as they were given:
+    let row = json!({"observed": "2024-03-31", "recorded": "2024-04-01 08:00:15"});
+    let (row_number, _) = valve
+        .insert_row("table20", row.as_object().unwrap())
+        .await?;
+    let sql = format!(
+        r#"SELECT CAST("observed" AS TEXT) AS "observed", CAST("recorded" AS TEXT) AS "recorded"
+             FROM "table20" WHERE "row_number" = {}"#,
+        row_number
+    );
+    let db_row = sqlx_query(&sql).fetch_one(&valve.pool).await?;
+    assert_eq!(db_row.get::<&str, &str>("observed"), "2024-03-31");
+    assert_eq!(db_row.get::<&str, &str>("recorded"), "2024-04-01 08:00:15");
+
+    // Values that are not valid dates or timestamps cannot be bound to query parameters of those
+    // SQL types:
+    assert!(get_query_param(&json!("2024-02-30"), "date").is_err());
+    assert!(get_query_param(&json!("2024-02-29 24:00:00"), "timestamp").is_err());
+    assert!(get_query_param(&json!("2024-02-29"), "date").is_ok());
+
+    valve.delete_row("table20", &row_number).await?;
+
+    eprintln!("done.");
+    Ok(())
+}
+
+async fn test_save_contracted_iris(valve: &Valve) -> Result<()> {
+    eprint!("Running test_save_contracted_iris() ... ");
+
+    // The type column of table3 is meant to contain CURIEs, while the id column may contain any
+    // value that looks like a CURIE. Only the former is contracted when saving:
+    let row = json!({
+        "source": "COB",
+        "id": "http://purl.obolibrary.org/obo/COB_0000099",
+        "label": "contracted entity",
+        "type": "http://www.w3.org/2002/07/owl#Class",
+        "parent": "",
+        "related": "",
+    });
+    let (row_number, _) = valve.insert_row("table3", row.as_object().unwrap()).await?;
+    let save_path = std::env::temp_dir().join(format!("valve_table3_{}.tsv", std::process::id()));
+    let save_path = save_path.to_string_lossy().to_string();
+    let get_saved_row = || -> Result<String> {
+        let saved = std::fs::read_to_string(&save_path)?;
+        Ok(saved
+            .lines()
+            .find(|line| line.contains("contracted entity"))
+            .unwrap_or_default()
+            .to_string())
+    };
+
+    valve.save_table("table3", &save_path).await?;
+    assert_eq!(
+        get_saved_row()?,
+        "COB\thttp://purl.obolibrary.org/obo/COB_0000099\tcontracted entity\t\
+         http://www.w3.org/2002/07/owl#Class\t\t"
+    );
+
+    let mut contracting_valve = valve.clone();
+    contracting_valve.set_contract_iris(true);
+    contracting_valve.save_table("table3", &save_path).await?;
+    assert_eq!(
+        get_saved_row()?,
+        "COB\thttp://purl.obolibrary.org/obo/COB_0000099\tcontracted entity\towl:Class\t\t"
+    );
+
+    std::fs::remove_file(&save_path)?;
+    valve.delete_row("table3", &row_number).await?;
+
+    eprintln!("done.");
+    Ok(())
+}
+
 pub async fn run_api_tests(valve: &Valve) -> Result<()> {
     // NOTE that you must use an external script to fetch the data from the database and run a diff
     // against a known good sample to verify that these tests yield the expected results:
-    test_matching(&valve).await?;
-    test_update_1(&valve).await?;
-    test_insert_1(&valve).await?;
-    test_update_2(&valve).await?;
-    test_insert_2(&valve).await?;
-    test_insert_3(&valve).await?;
-    test_dependencies(&valve).await?;
-    test_undo_redo(&valve).await?;
-    test_randomized_api_test_with_undo_redo(&valve).await?;
-    test_modes(&valve).await?;
-    test_default(&valve).await?;
-    test_move(&valve).await?;
+    test_condition_functions(valve)?;
+    test_registered_condition_functions(valve)?;
+    test_matching(valve).await?;
+    test_composite_keys(valve).await?;
+    test_composite_foreign_keys(valve).await?;
+    test_script_rules(valve).await?;
+    test_transforms(valve)?;
+    test_suggestions(valve).await?;
+    test_prefixes(valve)?;
+    test_explain_rule(valve)?;
+    test_validate_in_memory(valve)?;
+    test_load_dry_run(valve).await?;
+    test_tree_cycles(valve).await?;
+    test_update_1(valve).await?;
+    test_insert_1(valve).await?;
+    test_update_2(valve).await?;
+    test_insert_2(valve).await?;
+    test_tree_queries(valve).await?;
+    test_insert_3(valve).await?;
+    test_dependencies(valve).await?;
+    test_undo_redo(valve).await?;
+    test_randomized_api_test_with_undo_redo(valve).await?;
+    test_modes(valve).await?;
+    test_default(valve).await?;
+    test_move(valve).await?;
+    test_fixes(valve).await?;
+    test_polyhierarchy(valve).await?;
+    test_sync(valve).await?;
+    test_load_changed_tables(valve).await?;
+    test_composite_primary_key_nulls(valve).await?;
+    test_migrate(valve).await?;
+    test_lint_config(valve)?;
+    test_diff_config(valve)?;
+    test_validate_with_config(valve).await?;
+    test_dates(valve).await?;
+    test_save_contracted_iris(valve).await?;
 
     // When the first argument to Valve::build() is not a string ending in .tsv, the table table
     // should be read from the database string (given by the second argument) instead, i.e., valve
@@ -3,45 +3,55 @@
 use crate::{
     ast::Expression,
     internal::{generate_internal_table_config, INTERNAL_TABLES},
-    validate::{validate_row_tx, validate_rows_constraints, validate_rows_intra},
+    validate::{
+        compile_rule_script, validate_cell_datatype, validate_cell_nulltype, validate_row_tx,
+        validate_rows_constraints, validate_rows_intra,
+    },
     valve::{
-        ValveCell, ValveCellMessage, ValveChange, ValveColumnConfig, ValveConfig,
+        ValveCell, ValveCellMessage, ValveChange, ValveColumnConfig, ValveCompositeConstraint,
+        ValveCompositeForeignConstraint, ValveConfig, ValveConfigChange, ValveConfigIssue,
         ValveConstraintConfig, ValveDatatypeConfig, ValveError, ValveForeignConstraint,
-        ValveMessage, ValveRow, ValveRowChange, ValveRuleConfig, ValveSpecialConfig,
-        ValveTableConfig, ValveTreeConstraint,
+        ValveMessage, ValveRow, ValveRowChange, ValveRuleConfig, ValveRuleKind,
+        ValveScriptRuleConfig, ValveSpecialConfig, ValveTableConfig, ValveTreeConstraint,
     },
     valve_grammar::StartParser,
-    CHUNK_SIZE, MAX_DB_CONNECTIONS, MOVE_INTERVAL, MULTI_THREADED, SQL_PARAM,
+    CHUNK_SIZE, MAX_DB_CONNECTIONS, MAX_SUGGESTIONS, MOVE_INTERVAL, MULTI_THREADED,
+    SQL_DATE_FORMAT, SQL_PARAM, SQL_TIMESTAMP_FORMAT, SQL_TYPES,
 };
 use anyhow::Result;
 use async_recursion::async_recursion;
+use chrono::{
+    format::{Item, StrftimeItems},
+    NaiveDate, NaiveDateTime,
+};
 use crossbeam;
 use csv::{ReaderBuilder, StringRecordsIter};
 use futures::executor::block_on;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use indoc::indoc;
 use is_executable::IsExecutable;
 use itertools::{IntoChunks, Itertools};
 use lazy_static::lazy_static;
 use petgraph::{
-    algo::{all_simple_paths, toposort},
+    algo::{all_simple_paths, tarjan_scc, toposort},
     graphmap::DiGraphMap,
     Direction,
 };
-use regex::Regex;
+use regex::{Captures, Regex};
 use serde_json::{json, Value as SerdeValue};
 use sqlx::{
     any::{AnyConnectOptions, AnyKind, AnyPool, AnyPoolOptions, AnyRow},
     query as sqlx_query, Acquire, Column, Row, Transaction, ValueRef,
 };
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::Write,
     fs::File,
-    iter::FromIterator,
     path::Path,
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
+use strsim::damerau_levenshtein;
 
 lazy_static! {
     static ref ALLOWED_OPTIONS: HashSet<&'static str> = HashSet::from([
@@ -58,12 +68,31 @@ lazy_static! {
         "no-validate_on_load",
         "no-edit",
         "no-save",
+        "closure",
     ]);
+    static ref BUILTIN_CONDITION_FUNCTIONS: HashSet<&'static str> = HashSet::from([
+        "equals", "exclude", "match", "search", "in", "range", "gt", "lt", "ge", "le", "date",
+        "datetime", "length", "any", "all", "not", "list",
+    ]);
+    static ref CONDITION_FUNCTIONS: RwLock<HashMap<String, Arc<dyn ConditionFunction>>> =
+        RwLock::new(HashMap::new());
+    // Matches a line break along with any whitespace surrounding it:
+    static ref LINE_BREAK_RE: Regex = Regex::new(r"\s*[\r\n]+\s*").unwrap();
+    // Matches the escape sequences used to change the case of the replacement in a substitution:
+    static ref CASE_ESCAPE_RE: Regex = Regex::new(r"\\[ULE]").unwrap();
+    // Matches the whole of a value, which is what the lowercase and uppercase transforms replace:
+    static ref WHOLE_VALUE_RE: Regex = Regex::new(r"(?s).+").unwrap();
+    // Compiled transforms, indexed by the text of the transform:
+    static ref COMPILED_TRANSFORMS: RwLock<HashMap<String, Arc<Vec<Substitution>>>> =
+        RwLock::new(HashMap::new());
 }
 
 /// Alias for [Map](serde_json::map)<[String], [Value](serde_json::value)>.
 pub type SerdeMap = serde_json::Map<String, SerdeValue>;
 
+/// Alias for a compiled condition that also has access to the other values in a value's row.
+pub type RowConditionFn = Arc<dyn Fn(&str, &ValveRow) -> bool + Sync + Send>;
+
 /// Represents a structure such as those found in the `structure` column of the `column` table in
 /// both its parsed format (i.e., as an [Expression](ast/enum.Expression.html)) as well as in its
 /// original format (i.e., as a plain [String]).
@@ -93,13 +122,30 @@ pub enum ValueType {
 }
 
 /// Represents a condition in three different ways: (i) in String format, (ii) as a parsed
-/// [Expression](ast/enum.Expression.html), and (iii) as a pre-compiled regular expression.
+/// [Expression](ast/enum.Expression.html), and (iii) as a pre-compiled regular expression. When
+/// the condition combines other conditions (i.e., using `any()`, `all()`, or `not()`), the
+/// compiled versions of the combined conditions are stored in `subconditions`. When the condition
+/// refers to the values of other columns in the same row (which is possible for rule conditions),
+/// it must be checked using `compiled_with_row`, since `compiled` has no access to those values.
 #[derive(Clone)]
 pub struct CompiledCondition {
     pub value_type: ValueType,
     pub original: String,
     pub parsed: Expression,
     pub compiled: Arc<dyn Fn(&str) -> bool + Sync + Send>,
+    pub compiled_with_row: Option<RowConditionFn>,
+    pub subconditions: Vec<CompiledCondition>,
+}
+
+impl CompiledCondition {
+    /// Given a value and the row that it belongs to, return true if the value satisfies this
+    /// condition and false otherwise.
+    pub fn check(&self, value: &str, row: &ValveRow) -> bool {
+        match &self.compiled_with_row {
+            Some(compiled_with_row) => compiled_with_row(value, row),
+            None => (self.compiled)(value),
+        }
+    }
 }
 
 // We use Debug here instead of Display because we have only implemented Debug for Expressions.
@@ -114,12 +160,514 @@ impl std::fmt::Debug for CompiledCondition {
     }
 }
 
+/// A function, implemented outside of Valve, that may be called in the conditions of datatypes and
+/// rules in the same way as Valve's builtin condition functions (e.g., `match()` or `in()`).
+/// Condition functions must be registered, using
+/// [register_condition_function()](fn.register_condition_function.html) (or, equivalently,
+/// [Valve::register_condition_function()](../valve/struct.Valve.html#method.register_condition_function)),
+/// before the Valve instance whose configuration calls them is built. This trait is implemented
+/// for any function or closure with the same signature as [compile()](#tymethod.compile).
+pub trait ConditionFunction: Send + Sync {
+    /// Given a condition in its original String format, its parsed version (which will be of the
+    /// form [Expression::Function](../ast/enum.Expression.html)), and the SQL type of the values
+    /// that the condition will be applied to, return a CompiledCondition, or an error if the
+    /// function's arguments are not valid.
+    fn compile(
+        &self,
+        condition: &str,
+        parsed_condition: &Expression,
+        sql_type: &str,
+    ) -> Result<CompiledCondition>;
+}
+
+impl<F> ConditionFunction for F
+where
+    F: Fn(&str, &Expression, &str) -> Result<CompiledCondition> + Send + Sync,
+{
+    fn compile(
+        &self,
+        condition: &str,
+        parsed_condition: &Expression,
+        sql_type: &str,
+    ) -> Result<CompiledCondition> {
+        self(condition, parsed_condition, sql_type)
+    }
+}
+
+/// Given a function name and a [ConditionFunction], register the condition function under the
+/// given name so that it may be called in the conditions of datatypes and rules. Note that
+/// conditions are compiled when a Valve instance is built, so that registering a condition
+/// function has no effect on Valve instances that have already been built. It is an error to
+/// register a condition function under the name of one of Valve's builtin condition functions, or
+/// under a name that is not a valid function name, or under a name that has already been used.
+pub fn register_condition_function(name: &str, function: Arc<dyn ConditionFunction>) -> Result<()> {
+    if BUILTIN_CONDITION_FUNCTIONS.contains(name) {
+        return Err(ValveError::InputError(format!(
+            "Cannot register condition function '{}' since it is a builtin function",
+            name
+        ))
+        .into());
+    }
+    let name_regex = Regex::new(r"^[a-zA-Z0-9-_]+$")?;
+    if !name_regex.is_match(name) {
+        return Err(ValveError::InputError(format!(
+            "'{}' is not a valid condition function name",
+            name
+        ))
+        .into());
+    }
+    let mut condition_functions = CONDITION_FUNCTIONS
+        .write()
+        .expect("Could not acquire a lock on the condition function registry");
+    if condition_functions.contains_key(name) {
+        return Err(ValveError::InputError(format!(
+            "A condition function named '{}' has already been registered",
+            name
+        ))
+        .into());
+    }
+    condition_functions.insert(name.to_string(), function);
+    Ok(())
+}
+
+/// Given a function name, return the [ConditionFunction] that has been registered under that name,
+/// or None if there is no such function.
+pub fn get_condition_function(name: &str) -> Option<Arc<dyn ConditionFunction>> {
+    CONDITION_FUNCTIONS
+        .read()
+        .expect("Could not acquire a lock on the condition function registry")
+        .get(name)
+        .cloned()
+}
+
+/// Represents one of the substitutions, of the form `s/PATTERN/REPLACEMENT/FLAGS`, in a transform,
+/// as found in the optional `transform` column of the `column` and `datatype` tables. When
+/// `global` is true (i.e., when the `g` flag has been given), every match of the pattern is
+/// replaced, and otherwise only the first match is.
+#[derive(Clone, Debug)]
+pub struct Substitution {
+    pub original: String,
+    pub pattern: Regex,
+    pub replacement: String,
+    pub global: bool,
+}
+
+impl Substitution {
+    /// Given a value, return the result of applying this substitution to it. In addition to the
+    /// usual syntax for referring to capture groups (e.g., `$1` or `${name}`), the replacement may
+    /// contain the escape sequences `\U` and `\L`, which convert the text that follows them to
+    /// upper or lower case, respectively, until the end of the replacement or until `\E`.
+    pub fn apply(&self, value: &str) -> String {
+        let replace = |caps: &Captures| -> String {
+            let mut replaced = String::from("");
+            let mut case = "E";
+            let mut start = 0;
+            let mut push_segment = |segment: &str, case: &str| {
+                let mut expanded = String::from("");
+                caps.expand(segment, &mut expanded);
+                match case {
+                    "U" => replaced.push_str(&expanded.to_uppercase()),
+                    "L" => replaced.push_str(&expanded.to_lowercase()),
+                    _ => replaced.push_str(&expanded),
+                };
+            };
+            for escape in CASE_ESCAPE_RE.find_iter(&self.replacement) {
+                push_segment(&self.replacement[start..escape.start()], case);
+                case = &escape.as_str()[1..];
+                start = escape.end();
+            }
+            push_segment(&self.replacement[start..], case);
+            replaced
+        };
+        if self.global {
+            self.pattern.replace_all(value, replace).to_string()
+        } else {
+            self.pattern.replace(value, replace).to_string()
+        }
+    }
+}
+
+/// Given the text of a transform, i.e., a space-separated list of substitutions of the form
+/// `s/PATTERN/REPLACEMENT/FLAGS` (or of the shorthands `lowercase` and `uppercase`), and a parser, compile the transform (or retrieve the previously
+/// compiled version of the transform) and return the compiled substitutions, or a configuration
+/// error if the transform could not be compiled.
+pub fn compile_transform(transform: &str, parser: &StartParser) -> Result<Arc<Vec<Substitution>>> {
+    if let Some(substitutions) = COMPILED_TRANSFORMS
+        .read()
+        .expect("Could not acquire a lock on the compiled transforms")
+        .get(transform)
+    {
+        return Ok(substitutions.clone());
+    }
+
+    let parsed_transform = match parser.parse(transform) {
+        Ok(parsed_transform) => parsed_transform,
+        Err(_) => {
+            return Err(ValveError::ConfigError(format!(
+                "Could not parse transform: '{}'",
+                transform
+            ))
+            .into())
+        }
+    };
+    let mut substitutions = vec![];
+    for expression in parsed_transform {
+        match &*expression {
+            Expression::RegexSub(pattern, replacement, flags) => {
+                let inline_flags = flags.replace("g", "");
+                let pattern = {
+                    if inline_flags.is_empty() {
+                        pattern.to_string()
+                    } else {
+                        format!("(?{}){}", inline_flags, pattern)
+                    }
+                };
+                let pattern = Regex::new(&pattern).map_err(|e| {
+                    ValveError::ConfigError(format!(
+                        "Invalid substitution '{}' in transform: '{}': {}",
+                        expression, transform, e
+                    ))
+                })?;
+                substitutions.push(Substitution {
+                    original: expression.to_string(),
+                    pattern,
+                    replacement: replacement.to_string(),
+                    global: flags.contains("g"),
+                });
+            }
+            // The shorthands 'lowercase' and 'uppercase' are also supported for compatibility
+            // with configurations written for earlier versions of VALVE:
+            Expression::Label(label) if label == "lowercase" || label == "uppercase" => {
+                let replacement = match label.as_str() {
+                    "lowercase" => r"\L$0",
+                    _ => r"\U$0",
+                };
+                substitutions.push(Substitution {
+                    original: label.to_string(),
+                    pattern: WHOLE_VALUE_RE.clone(),
+                    replacement: replacement.to_string(),
+                    global: false,
+                });
+            }
+            _ => {
+                return Err(ValveError::ConfigError(format!(
+                    "'{}' in transform: '{}' is neither 'lowercase', 'uppercase', nor a \
+                     substitution of the form s/PATTERN/REPLACEMENT/FLAGS",
+                    expression, transform
+                ))
+                .into())
+            }
+        };
+    }
+
+    let substitutions = Arc::new(substitutions);
+    COMPILED_TRANSFORMS
+        .write()
+        .expect("Could not acquire a lock on the compiled transforms")
+        .insert(transform.to_string(), substitutions.clone());
+    Ok(substitutions)
+}
+
+/// Given the global config struct, a table name, and a column name, return the transform that
+/// applies to the column, i.e., the transform configured for the column if there is one, or
+/// otherwise the transform configured for the column's datatype or for the nearest of its
+/// ancestors that has one. If no transform applies to the column, return an empty string.
+pub fn get_column_transform(config: &ValveConfig, table: &str, column: &str) -> String {
+    let column_config = match config.table.get(table).and_then(|t| t.column.get(column)) {
+        Some(column_config) => column_config,
+        None => return String::from(""),
+    };
+    if !column_config.transform.is_empty() {
+        return column_config.transform.to_string();
+    }
+    let mut datatype = column_config.datatype.to_string();
+    while let Some(dt_config) = config.datatype.get(&datatype) {
+        if !dt_config.transform.is_empty() {
+            return dt_config.transform.to_string();
+        }
+        datatype = dt_config.parent.to_string();
+    }
+    String::from("")
+}
+
+/// Given the global config struct, a table name, and a row, apply the transforms that apply to the
+/// row's columns (see [get_column_transform()]) to the row's values, and return a list of
+/// (column, original value, transform) triples indicating which of the row's values have been
+/// rewritten. Empty values are never transformed.
+pub fn transform_row(
+    config: &ValveConfig,
+    table_name: &str,
+    row: &mut ValveRow,
+) -> Vec<(String, String, String)> {
+    let mut rewritten = vec![];
+    for (column, cell) in row.contents.iter_mut() {
+        let transform = get_column_transform(config, table_name, column);
+        let original = cell.strvalue();
+        if transform.is_empty() || original.is_empty() {
+            continue;
+        }
+        let substitutions = match compile_transform(&transform, &StartParser::new()) {
+            Ok(substitutions) => substitutions,
+            Err(e) => {
+                log::error!("Could not apply transform '{}': {}", transform, e);
+                continue;
+            }
+        };
+        let mut value = original.to_string();
+        for substitution in substitutions.iter() {
+            value = substitution.apply(&value);
+        }
+        if value != original {
+            cell.value = json!(value);
+            rewritten.push((column.to_string(), original, transform));
+        }
+    }
+    rewritten
+}
+
+/// Given a row, and a list of (column, original value, transform) triples such as is returned by
+/// [transform_row()], add an informational message to each of the corresponding cells of the row
+/// recording the original value of the cell.
+pub fn add_transform_messages(row: &mut ValveRow, rewritten: &Vec<(String, String, String)>) {
+    for (column, original, transform) in rewritten {
+        if let Some(cell) = row.contents.get_mut(column) {
+            cell.messages.push(ValveCellMessage {
+                rule: ValveRuleKind::TransformRewrite.id().to_string(),
+                level: ValveRuleKind::TransformRewrite.default_level().to_string(),
+                message: format!(
+                    "Value '{}' was rewritten to '{}' by the transform '{}'",
+                    original,
+                    cell.strvalue(),
+                    transform
+                ),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// Given a string, return the prefix and local part of the CURIE that it represents, or None if it
+/// does not have the form of a CURIE, i.e., a non-empty prefix followed by a colon and a local
+/// part, without any whitespace.
+pub fn split_curie(curie: &str) -> Option<(&str, &str)> {
+    if curie.chars().any(|c| c.is_whitespace()) {
+        return None;
+    }
+    match curie.split_once(':') {
+        Some((prefix, local)) if !prefix.is_empty() && !local.starts_with("//") => {
+            Some((prefix, local))
+        }
+        _ => None,
+    }
+}
+
+/// Given a map from CURIE prefixes to the base IRIs that they stand for, and a CURIE, return the
+/// IRI that the CURIE expands to, or None if the CURIE is malformed or its prefix is unknown.
+pub fn expand_curie(prefixes: &HashMap<String, String>, curie: &str) -> Option<String> {
+    let (prefix, local) = split_curie(curie)?;
+    prefixes
+        .get(prefix)
+        .map(|base| format!("{}{}", base, local))
+}
+
+/// Given a map from CURIE prefixes to the base IRIs that they stand for, and an IRI, return the
+/// CURIE that the IRI contracts to, or None if the IRI does not begin with any of the base IRIs.
+/// When more than one base IRI matches, the longest one is used.
+pub fn contract_iri(prefixes: &HashMap<String, String>, iri: &str) -> Option<String> {
+    prefixes
+        .iter()
+        .filter(|(_, base)| !base.is_empty() && iri.starts_with(*base))
+        .max_by(|(p1, b1), (p2, b2)| b1.len().cmp(&b2.len()).then(p2.cmp(p1)))
+        .map(|(prefix, base)| format!("{}:{}", prefix, &iri[base.len()..]))
+}
+
+/// Given a global configuration struct, compiled datatype conditions, and a table name, return the
+/// columns of the table that are meant to contain CURIEs, i.e., those whose datatype, or one of
+/// whose datatype's ancestors, has a condition that requires a `curie()`.
+pub fn get_curie_columns(
+    config: &ValveConfig,
+    datatype_conditions: &HashMap<String, CompiledCondition>,
+    table: &str,
+) -> HashSet<String> {
+    fn requires_curie(expression: &Expression) -> bool {
+        match expression {
+            Expression::Function(name, _) if name == "curie" => true,
+            Expression::Function(name, args) if name == "any" || name == "all" => {
+                args.iter().any(|arg| requires_curie(arg))
+            }
+            _ => false,
+        }
+    }
+
+    let mut curie_columns = HashSet::new();
+    let table_config = match config.table.get(table) {
+        Some(table_config) => table_config,
+        None => return curie_columns,
+    };
+    for (column, column_config) in &table_config.column {
+        if column_config.datatype.is_empty() {
+            continue;
+        }
+        let mut datatypes = vec![column_config.datatype.to_string()];
+        datatypes.append(
+            &mut get_datatype_ancestors(config, datatype_conditions, &column_config.datatype, true)
+                .into_iter()
+                .map(|dt| dt.datatype)
+                .collect(),
+        );
+        if datatypes.iter().any(|dt| {
+            datatype_conditions
+                .get(dt)
+                .is_some_and(|condition| requires_curie(&condition.parsed))
+        }) {
+            curie_columns.insert(column.to_string());
+        }
+    }
+    curie_columns
+}
+
+/// Given a value that is not one of the given allowed values, return (at most MAX_SUGGESTIONS of)
+/// the allowed values that the given value may have been intended to be, ranked from most to
+/// least similar. Allowed values that differ from the given value only in case are ranked first,
+/// followed by those whose edit distance from the given value (ignoring case) is no more than a
+/// third of the length of the longer of the two values.
+pub fn get_suggestions(value: &str, allowed_values: &Vec<String>) -> Vec<String> {
+    let lowercase_value = value.to_lowercase();
+    let mut ranked_values = vec![];
+    for allowed_value in allowed_values {
+        if allowed_value == value {
+            continue;
+        }
+        let lowercase_allowed_value = allowed_value.to_lowercase();
+        let rank = {
+            if lowercase_allowed_value == lowercase_value {
+                0
+            } else {
+                let distance = damerau_levenshtein(&lowercase_value, &lowercase_allowed_value);
+                let max_distance = std::cmp::max(
+                    lowercase_value.chars().count(),
+                    lowercase_allowed_value.chars().count(),
+                ) / 3;
+                if distance > max_distance {
+                    continue;
+                }
+                distance
+            }
+        };
+        ranked_values.push((rank, allowed_value));
+    }
+    ranked_values.sort();
+    ranked_values.dedup();
+    ranked_values
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, allowed_value)| allowed_value.to_string())
+        .collect()
+}
+
+/// Given the global config struct, compiled datatype conditions, a table name, a column name, a
+/// value of the column, and the validation messages associated with the value, return a proposed
+/// replacement for the value, if one can be found, along with a description of the fix and the
+/// list of the rules, violated by the value, that the replacement satisfies. The fixes that are
+/// tried are, in order: removing surrounding whitespace, replacing line breaks with spaces,
+/// collapsing runs of whitespace, and removing all whitespace (which are only tried when the
+/// value violates the column's datatype), and finally replacing the value with one of the
+/// suggestions associated with the messages that differs from the value only in case or in
+/// surrounding whitespace. A fix is only proposed if the replacement is a valid value of the
+/// column's datatype (or matches the column's nulltype).
+pub fn propose_fix(
+    config: &ValveConfig,
+    datatype_conditions: &HashMap<String, CompiledCondition>,
+    table: &str,
+    column: &str,
+    value: &str,
+    messages: &[ValveCellMessage],
+) -> Option<(String, String, Vec<String>)> {
+    let has_datatype_violation = messages.iter().any(|m| {
+        matches!(
+            ValveRuleKind::from_rule_id(&m.rule),
+            Some((ValveRuleKind::Datatype, _))
+        )
+    });
+    let is_valid = |candidate: &str| -> bool {
+        let mut cell = ValveCell::new(&json!(candidate));
+        validate_cell_nulltype(
+            config,
+            datatype_conditions,
+            &table.to_string(),
+            &column.to_string(),
+            &mut cell,
+        );
+        if cell.nulltype.is_some() {
+            return true;
+        }
+        validate_cell_datatype(
+            config,
+            datatype_conditions,
+            &table.to_string(),
+            &column.to_string(),
+            &mut cell,
+        );
+        cell.messages.is_empty()
+    };
+
+    let mut fix = None;
+    if has_datatype_violation {
+        let candidates = vec![
+            (value.trim().to_string(), "Removed surrounding whitespace"),
+            (
+                LINE_BREAK_RE.replace_all(value, " ").trim().to_string(),
+                "Replaced line breaks with spaces",
+            ),
+            (
+                value.split_whitespace().collect::<Vec<_>>().join(" "),
+                "Collapsed runs of whitespace",
+            ),
+            (
+                value.split_whitespace().collect::<Vec<_>>().join(""),
+                "Removed whitespace",
+            ),
+        ];
+        fix = candidates
+            .into_iter()
+            .find(|(candidate, _)| candidate != value && is_valid(candidate));
+    }
+    if fix.is_none() {
+        let trimmed_value = value.trim().to_lowercase();
+        fix = messages
+            .iter()
+            .flat_map(|m| m.suggestions.iter())
+            .find(|s| s.to_lowercase() == trimmed_value && is_valid(s))
+            .map(|s| (s.to_string(), "Replaced the value with an allowed value"));
+    }
+
+    fix.map(|(fixed_value, description)| {
+        let datatype_valid = is_valid(&fixed_value);
+        let rules = messages
+            .iter()
+            .filter(|m| {
+                (matches!(
+                    ValveRuleKind::from_rule_id(&m.rule),
+                    Some((ValveRuleKind::Datatype, _))
+                ) && datatype_valid)
+                    || m.suggestions.contains(&fixed_value)
+            })
+            .map(|m| m.rule.to_string())
+            .unique()
+            .collect::<Vec<_>>();
+        (fixed_value, description.to_string(), rules)
+    })
+}
+
 /// Represents a 'when-then' condition, as found in the `rule` table, as two
 /// [CompiledCondition](struct.CompiledCondition.html) structs corresponding to the when and then
-/// parts of the given rule.
+/// parts of the given rule. If the rule has more than one when column, the compiled conditions
+/// corresponding to the when columns after the first are stored in `additional_when`, in the
+/// same order as in the rule's configuration.
 #[derive(Clone)]
 pub struct ColumnRule {
     pub when: CompiledCondition,
+    pub additional_when: Vec<CompiledCondition>,
     pub then: CompiledCondition,
 }
 
@@ -129,8 +677,8 @@ impl std::fmt::Debug for ColumnRule {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "{{\"column_rule\": {{\"when\": {:?}, \"then\": {:?}}}}}",
-            &self.when, &self.then
+            "{{\"column_rule\": {{\"when\": {:?}, \"additional_when\": {:?}, \"then\": {:?}}}}}",
+            &self.when, &self.additional_when, &self.then
         )
     }
 }
@@ -161,9 +709,94 @@ pub enum QueryParam {
     Numeric(f64),
     Real(f64),
     Integer(i32),
+    Date(NaiveDate),
+    Timestamp(NaiveDateTime),
     String(String),
 }
 
+/// The locations, in the configuration files (or, if the configuration has been read from the
+/// database, in the configuration tables), of the configured tables, datatypes, columns, and
+/// rules, used to report the problems found with the configuration. Rows are counted from 1, and
+/// rules are identified by their table, their (first) when column, and their position among the
+/// rules for that column.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigLocations {
+    pub table_file: String,
+    pub datatype_file: String,
+    pub column_file: String,
+    pub rule_file: String,
+    pub table: HashMap<String, u32>,
+    pub datatype: HashMap<String, u32>,
+    pub column: HashMap<(String, String), u32>,
+    pub rule: HashMap<(String, String, usize), u32>,
+}
+
+impl ConfigLocations {
+    /// Given a table, a column of the table table, a severity, and a message, return an issue
+    /// located in the row of the table table that configures the table.
+    pub fn table_issue(
+        &self,
+        table: &str,
+        column: &str,
+        severity: &str,
+        message: &str,
+    ) -> ValveConfigIssue {
+        let row = self.table.get(table).cloned().unwrap_or(0);
+        config_issue(&self.table_file, row, column, severity, message)
+    }
+
+    /// Given a datatype, a column of the datatype table, a severity, and a message, return an
+    /// issue located in the row of the datatype table that configures the datatype.
+    pub fn datatype_issue(
+        &self,
+        datatype: &str,
+        column: &str,
+        severity: &str,
+        message: &str,
+    ) -> ValveConfigIssue {
+        let row = self.datatype.get(datatype).cloned().unwrap_or(0);
+        config_issue(&self.datatype_file, row, column, severity, message)
+    }
+
+    /// Given a table, one of its columns, a column of the column table, a severity, and a message,
+    /// return an issue located in the row of the column table that configures the column.
+    pub fn column_issue(
+        &self,
+        table: &str,
+        table_column: &str,
+        column: &str,
+        severity: &str,
+        message: &str,
+    ) -> ValveConfigIssue {
+        let row = self
+            .column
+            .get(&(table.to_string(), table_column.to_string()))
+            .cloned()
+            .unwrap_or(0);
+        config_issue(&self.column_file, row, column, severity, message)
+    }
+
+    /// Given a table, the (first) when column of a rule for the table, the position of the rule
+    /// among the rules for that column, a column of the rule table, a severity, and a message,
+    /// return an issue located in the row of the rule table that configures the rule.
+    pub fn rule_issue(
+        &self,
+        table: &str,
+        when_column: &str,
+        position: usize,
+        column: &str,
+        severity: &str,
+        message: &str,
+    ) -> ValveConfigIssue {
+        let row = self
+            .rule
+            .get(&(table.to_string(), when_column.to_string(), position))
+            .cloned()
+            .unwrap_or(0);
+        config_issue(&self.rule_file, row, column, severity, message)
+    }
+}
+
 /// Given a string representing the location of a database, return a database connection pool.
 pub async fn get_pool_from_connection_string(database: &str) -> Result<AnyPool> {
     let connection_options;
@@ -267,8 +900,7 @@ pub fn normalize_options(
     fn warn_and_get_message(
         row_number: u32,
         message: &str,
-        violation: &str,
-        level: &str,
+        violation: ValveRuleKind,
         value: &str,
     ) -> ValveMessage {
         log::warn!(
@@ -280,8 +912,8 @@ pub fn normalize_options(
         ValveMessage {
             column: "options".to_string(),
             value: value.to_string(),
-            rule: format!("option:{}", violation),
-            level: level.to_string(),
+            rule: violation.id().to_string(),
+            level: violation.default_level().to_string(),
             message: message.to_string(),
         }
     }
@@ -299,8 +931,7 @@ pub fn normalize_options(
             messages.push(warn_and_get_message(
                 row_number,
                 "unrecognized option",
-                "unrecognized",
-                "error",
+                ValveRuleKind::OptionUnrecognized,
                 &input_option.as_str(),
             ));
             continue;
@@ -309,8 +940,7 @@ pub fn normalize_options(
             messages.push(warn_and_get_message(
                 row_number,
                 "redundant option",
-                "redundant",
-                "warning",
+                ValveRuleKind::OptionRedundant,
                 &input_option.as_str(),
             ));
             continue;
@@ -321,8 +951,7 @@ pub fn normalize_options(
                 messages.push(warn_and_get_message(
                     row_number,
                     "reserved for internal use",
-                    "reserved",
-                    "error",
+                    ValveRuleKind::OptionReserved,
                     &input_option.as_str(),
                 ));
             }
@@ -331,8 +960,7 @@ pub fn normalize_options(
                     messages.push(warn_and_get_message(
                         row_number,
                         "overrides db_view",
-                        "overrides",
-                        "warning",
+                        ValveRuleKind::OptionOverrides,
                         &input_option.as_str(),
                     ));
                     explicit_options.remove("db_view");
@@ -347,13 +975,13 @@ pub fn normalize_options(
                     "save",
                     "edit",
                     "validate_on_load",
+                    "closure",
                 ] {
                     if explicit_options.contains(conflicting_option) {
                         messages.push(warn_and_get_message(
                             row_number,
                             &format!("overrides {}", conflicting_option),
-                            "overrides",
-                            "warning",
+                            ValveRuleKind::OptionOverrides,
                             &input_option.as_str(),
                         ));
                         explicit_options.remove(conflicting_option);
@@ -365,20 +993,18 @@ pub fn normalize_options(
                     messages.push(warn_and_get_message(
                         row_number,
                         "overrides db_view",
-                        "overrides",
-                        "warning",
+                        ValveRuleKind::OptionOverrides,
                         &input_option.as_str(),
                     ));
                     explicit_options.remove("db_view");
                 }
             }
-            "load" => {
+            "load" | "closure" => {
                 if explicit_options.contains("db_view") {
                     messages.push(warn_and_get_message(
                         row_number,
                         "overrides db_view",
-                        "overrides",
-                        "warning",
+                        ValveRuleKind::OptionOverrides,
                         &input_option.as_str(),
                     ));
                     explicit_options.remove("db_view");
@@ -390,8 +1016,7 @@ pub fn normalize_options(
                         messages.push(warn_and_get_message(
                             row_number,
                             &format!("overrides {}", conflicting_option),
-                            "overrides",
-                            "warning",
+                            ValveRuleKind::OptionOverrides,
                             &input_option.as_str(),
                         ));
                         explicit_options.remove(conflicting_option);
@@ -403,8 +1028,7 @@ pub fn normalize_options(
                     messages.push(warn_and_get_message(
                         row_number,
                         "overrides conflict",
-                        "overrides",
-                        "warning",
+                        ValveRuleKind::OptionOverrides,
                         &input_option.as_str(),
                     ));
                     explicit_options.remove("conflict");
@@ -416,8 +1040,7 @@ pub fn normalize_options(
                         messages.push(warn_and_get_message(
                             row_number,
                             &format!("overrides {}", conflicting_option),
-                            "overrides",
-                            "warning",
+                            ValveRuleKind::OptionOverrides,
                             &input_option.as_str(),
                         ));
                         explicit_options.remove(conflicting_option);
@@ -429,8 +1052,7 @@ pub fn normalize_options(
                     messages.push(warn_and_get_message(
                         row_number,
                         "overrides save",
-                        "overrides",
-                        "warning",
+                        ValveRuleKind::OptionOverrides,
                         &input_option.as_str(),
                     ));
                     explicit_options.remove("save");
@@ -442,8 +1064,7 @@ pub fn normalize_options(
                         messages.push(warn_and_get_message(
                             row_number,
                             &format!("overrides {}", conflicting_option),
-                            "overrides",
-                            "warning",
+                            ValveRuleKind::OptionOverrides,
                             &input_option.as_str(),
                         ));
                         explicit_options.remove(conflicting_option);
@@ -455,8 +1076,7 @@ pub fn normalize_options(
                     messages.push(warn_and_get_message(
                         row_number,
                         "overrides edit",
-                        "overrides",
-                        "warning",
+                        ValveRuleKind::OptionOverrides,
                         &input_option.as_str(),
                     ));
                     explicit_options.remove("edit");
@@ -468,8 +1088,7 @@ pub fn normalize_options(
                         messages.push(warn_and_get_message(
                             row_number,
                             &format!("overrides {}", conflicting_option),
-                            "overrides",
-                            "warning",
+                            ValveRuleKind::OptionOverrides,
                             &input_option.as_str(),
                         ));
                         explicit_options.remove(conflicting_option);
@@ -481,8 +1100,7 @@ pub fn normalize_options(
                     messages.push(warn_and_get_message(
                         row_number,
                         "overrides validate_on_load",
-                        "overrides",
-                        "warning",
+                        ValveRuleKind::OptionOverrides,
                         &input_option.as_str(),
                     ));
                     explicit_options.remove("validate_on_load");
@@ -504,8 +1122,11 @@ pub fn normalize_options(
         base_options.insert("load".to_string());
 
         // If no options were specified, the user wants the default, which is a db_table with all
-        // "extra" options enabled:
-        if input_options.is_empty() || *input_options == vec![""] {
+        // "extra" options enabled. Note that the 'closure' option does not affect the defaults:
+        if input_options
+            .iter()
+            .all(|option| ["", "closure"].contains(&option.trim()))
+        {
             explicit_options.insert("save".to_string());
             explicit_options.insert("edit".to_string());
             explicit_options.insert("validate_on_load".to_string());
@@ -536,134 +1157,296 @@ pub fn normalize_options(
     Ok((normalized_options, messages))
 }
 
+/// Given a comma-separated list of conditions, split it into its component conditions. Since a
+/// condition may itself contain commas (e.g., `in(a, b)`), we accumulate the comma-separated
+/// fragments of the list until they form a condition that can be parsed.
+fn split_conditions(conditions: &str, parser: &StartParser) -> Result<Vec<String>> {
+    let mut split = vec![];
+    let mut current = String::from("");
+    for fragment in conditions.split(',') {
+        if !current.is_empty() {
+            current.push(',');
+        }
+        current.push_str(fragment);
+        let condition = current.trim();
+        if condition == "null"
+            || condition == "not null"
+            || parser
+                .parse(condition)
+                .map(|p| p.len() == 1)
+                .unwrap_or(false)
+        {
+            split.push(condition.to_string());
+            current.clear();
+        }
+    }
+    if !current.is_empty() {
+        return Err(ValveError::ConfigError(format!(
+            "Could not parse condition: '{}' in '{}'",
+            current.trim(),
+            conditions
+        ))
+        .into());
+    }
+    Ok(split)
+}
+
+/// Given a file, a row number, a column, a severity, and a message, return the corresponding
+/// [ValveConfigIssue].
+fn config_issue(
+    file: &str,
+    row: u32,
+    column: &str,
+    severity: &str,
+    message: &str,
+) -> ValveConfigIssue {
+    ValveConfigIssue {
+        file: file.to_string(),
+        row,
+        column: column.to_string(),
+        severity: severity.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Given an error, return the description contained in it, without the name of its type.
+fn describe_error(error: &anyhow::Error) -> String {
+    match error.downcast_ref::<ValveError>() {
+        Some(ValveError::ConfigError(message))
+        | Some(ValveError::DataError(message))
+        | Some(ValveError::InputError(message))
+        | Some(ValveError::UserError(message)) => message.to_string(),
+        _ => error.to_string(),
+    }
+}
+
+/// Given a list of the problems found with the configuration, log those that are warnings and, if
+/// any of them are errors, return an error that describes all of them.
+pub fn check_config_issues(issues: &Vec<ValveConfigIssue>) -> Result<()> {
+    let mut errors = vec![];
+    for issue in issues {
+        let location = match (issue.row, issue.column.as_str()) {
+            (0, "") => format!("'{}'", issue.file),
+            (0, column) => format!("'{}', column '{}'", issue.file, column),
+            (row, "") => format!("'{}', row {}", issue.file, row),
+            (row, column) => format!("'{}', row {}, column '{}'", issue.file, row, column),
+        };
+        let description = format!("{}: {}", location, issue.message);
+        if issue.severity == "error" {
+            errors.push(description);
+        } else {
+            log::warn!("{}", description);
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ValveError::ConfigError(format!(
+            "Error while reading the configuration:\n{}",
+            errors.join("\n")
+        ))
+        .into());
+    }
+    Ok(())
+}
+
 /// Given the path to a table table (either a table.tsv file or a database containing a
-/// table named "table"), load and check the 'table', 'column', and 'datatype' tables, and return
-/// the following items:
+/// table named "table"), a parser, a database connection pool, and a list of issues, load and
+/// check the 'table', 'datatype', 'prefix', 'column', and 'rule' tables, adding every problem
+/// found with them to the list of issues rather than stopping at the first one, and return the
+/// following items, from which the parts of the configuration that are in error are left out:
 /// - Special table configuration information
 /// - Table configuration information for all managed tables
 /// - Table configuration information for all managed datatypes
 /// - Rule configuration information for every column of every managed table
+/// - Script rule configuration information for every managed table
 /// - Constraint configuration information
 /// - The list of managed tables in dependency order
 /// - A map from table names to the tables that depend on a given table
 /// - A map from table names to the tables that a given table depends on
+/// - The startup messages about the options of each table, indexed by row of the table table
+/// - The locations of the configured items in the configuration files
 pub fn read_config_files(
     path: &str,
     parser: &StartParser,
     pool: &AnyPool,
-) -> Result<(
+    issues: &mut Vec<ValveConfigIssue>,
+) -> (
     ValveSpecialConfig,
     HashMap<String, ValveTableConfig>,
     Vec<String>,
     HashMap<String, ValveDatatypeConfig>,
     HashMap<String, HashMap<String, Vec<ValveRuleConfig>>>,
+    HashMap<String, Vec<ValveScriptRuleConfig>>,
+    HashMap<String, String>,
     ValveConstraintConfig,
     Vec<String>,
     HashMap<String, Vec<String>>,
     HashMap<String, Vec<String>>,
     IndexMap<u32, Vec<ValveMessage>>,
-)> {
-    // Given a list of columns that are required for some table, and a subset of those columns
-    // that are required to have values, check if both sets of requirements are met by the given
-    // row. Returns a ValveError if both sets of requirements are not met, or if values_are_required
-    // is not a subset of columns_are_required.
-    fn check_table_requirements(
-        columns_are_required: &Vec<&str>,
-        values_are_required: &Vec<&str>,
-        row: &SerdeMap,
-    ) -> Result<()> {
-        let columns_are_required: HashSet<&str> =
-            HashSet::from_iter(columns_are_required.iter().cloned());
-        let values_are_required: HashSet<&str> =
-            HashSet::from_iter(values_are_required.iter().cloned());
-        if !values_are_required.is_subset(&columns_are_required) {
-            return Err(ValveError::InputError(format!(
-                "{:?} is not a subset of {:?}",
-                values_are_required, columns_are_required
-            ))
-            .into());
+    ConfigLocations,
+) {
+    // Reads the rows of a configuration table, either from the given source, which is the path of
+    // a file or, if the configuration is being read from the database, the name of a database
+    // table, and checks that the table has all of the given columns. If the table cannot be read
+    // or does not have all of the columns, an issue is added to the given list of issues and None
+    // is returned.
+    fn read_config_rows(
+        source: &str,
+        from_db: bool,
+        columns: &Vec<&str>,
+        pool: &AnyPool,
+        issues: &mut Vec<ValveConfigIssue>,
+    ) -> Option<Vec<SerdeMap>> {
+        let rows = match from_db {
+            true => read_db_table_into_vector(pool, source),
+            false => read_tsv_into_vector(source),
+        };
+        let rows = match rows {
+            Err(e) => {
+                issues.push(config_issue(source, 0, "", "error", &describe_error(&e)));
+                return None;
+            }
+            Ok(rows) => rows,
+        };
+        let mut missing = false;
+        if let Some(row) = rows.first() {
+            for column in columns {
+                if !row.contains_key(*column) {
+                    issues.push(config_issue(
+                        source,
+                        0,
+                        column,
+                        "error",
+                        &format!("Missing required column '{}'", column),
+                    ));
+                    missing = true;
+                }
+            }
         }
+        match missing {
+            true => None,
+            false => Some(rows),
+        }
+    }
 
-        for &column in columns_are_required.iter() {
-            match row.get(column).and_then(|c| c.as_str()) {
-                None => {
-                    return Err(ValveError::ConfigError(format!(
-                        "Missing required column '{}'",
-                        column
-                    ))
-                    .into());
-                }
-                Some(value) if value == "" && values_are_required.contains(&column) => {
-                    return Err(ValveError::ConfigError(format!(
-                        "Missing required value for '{}'",
-                        column
-                    ))
-                    .into());
-                }
-                _ => (),
+    // Given the name of a special configuration table other than the table table, reads its rows
+    // using read_config_rows(), either from the path configured for it in the table table or, if
+    // the configuration is being read from the database, from the database table of the same
+    // name. Returns the source that the rows were read from along with the rows, or None if the
+    // table has not been configured or could not be read.
+    fn get_special_config(
+        table_name: &str,
+        tables_config: &HashMap<String, ValveTableConfig>,
+        from_db: bool,
+        columns: &Vec<&str>,
+        pool: &AnyPool,
+        issues: &mut Vec<ValveConfigIssue>,
+    ) -> Option<(String, Vec<SerdeMap>)> {
+        let source = match (table_name, from_db) {
+            ("", _) => return None,
+            (_, true) => table_name.to_string(),
+            (_, false) => tables_config.get(table_name)?.path.to_string(),
+        };
+        let rows = read_config_rows(&source, from_db, columns, pool, issues)?;
+        Some((source, rows))
+    }
+
+    // Checks that the given row has values for all of the given columns, adding an issue to the
+    // given list of issues for each one that does not.
+    fn has_values(
+        file: &str,
+        row_number: u32,
+        row: &SerdeMap,
+        columns: &Vec<&str>,
+        issues: &mut Vec<ValveConfigIssue>,
+    ) -> bool {
+        let mut has_values = true;
+        for column in columns {
+            if get_value(row, column).is_empty() {
+                issues.push(config_issue(
+                    file,
+                    row_number,
+                    column,
+                    "error",
+                    &format!("Missing required value for '{}'", column),
+                ));
+                has_values = false;
             }
         }
-        Ok(())
+        has_values
+    }
+
+    // Returns the value of the given column of the given row, or an empty string if there is none.
+    fn get_value<'a>(row: &'a SerdeMap, column: &str) -> &'a str {
+        row.get(column).and_then(|v| v.as_str()).unwrap_or("")
     }
 
     // 1. Load the table config for the 'table' table from the given path, and determine the
-    // table names to use for the other special config types: 'column', 'datatype', and 'rule', then
-    // save those in specials_config. Also begin filling out the more general table configuration
-    // information related to each of those tables, to which further info will be added later.
+    // table names to use for the other special config types: 'column', 'datatype', 'rule', and
+    // 'prefix', then save those in specials_config. Also begin filling out the more general table
+    // configuration information related to each of those tables, to which further info will be
+    // added later.
+    let from_db = !path.to_lowercase().ends_with(".tsv");
+    let mut locations = ConfigLocations {
+        table_file: match from_db {
+            true => "table".to_string(),
+            false => path.to_string(),
+        },
+        ..Default::default()
+    };
+    let table_file = locations.table_file.to_string();
     let mut specials_config = ValveSpecialConfig::default();
     let mut tables_config = HashMap::new();
     let mut table_order: Vec<String> = vec![];
-    let rows = {
-        // Read in the table table from either a file or the database table called "table".
-        if path.to_lowercase().ends_with(".tsv") {
-            read_tsv_into_vector(path)?
-        } else {
-            read_db_table_into_vector(pool, "table")?
-        }
-    };
-
     let mut startup_table_messages = IndexMap::new();
-    for (row_number, row) in rows.iter().enumerate() {
+    // The table-level constraints (if any) that have been declared for each table, which will be
+    // parsed once the column configuration has been read:
+    let mut table_constraints = HashMap::new();
+    let rows = read_config_rows(
+        &table_file,
+        from_db,
+        &vec!["table", "path", "type", "description"],
+        pool,
+        issues,
+    );
+    let tables_read = rows.is_some();
+    for (row_number, row) in rows.unwrap_or_default().iter().enumerate() {
         // enumerate() begins at 0 but we want to count rows from 1:
-        let row_number = row_number as u32;
-        let row_number = row_number + 1;
-        if let Err(e) = check_table_requirements(
-            &vec!["table", "path", "type", "description"],
-            &vec!["table"],
-            &row,
-        ) {
-            return Err(ValveError::ConfigError(format!(
-                "Error while reading '{}': {:?}",
-                path, e
-            ))
-            .into());
+        let row_number = row_number as u32 + 1;
+        if !has_values(&table_file, row_number, row, &vec!["table"], issues) {
+            continue;
         }
-
-        let row_table = row.get("table").and_then(|t| t.as_str()).unwrap();
-        table_order.push(row_table.into());
-        let row_path = row.get("path").and_then(|t| t.as_str()).unwrap();
-        let row_path_info = Path::new(&row_path);
-        let row_type = row.get("type").and_then(|t| t.as_str()).unwrap();
-        let row_type = row_type.to_lowercase();
+        let row_table = get_value(row, "table");
+        if tables_config.contains_key(row_table) {
+            issues.push(config_issue(
+                &table_file,
+                row_number,
+                "table",
+                "error",
+                &format!("Duplicate table '{}'", row_table),
+            ));
+            continue;
+        }
+        let row_path = get_value(row, "path");
+        let row_type = get_value(row, "type").to_lowercase();
         let row_type = row_type.as_str();
-        let row_options = match row.get("options") {
-            Some(o) => o.as_str().unwrap(),
-            None => "",
-        };
-        let row_options = row_options.to_lowercase();
-        let row_options = row_options.as_str().split(" ").collect::<Vec<_>>();
-        let (row_options, messages) = match normalize_options(&row_options, row_number) {
+        let row_options = get_value(row, "options").to_lowercase();
+        let row_options = row_options.split(" ").collect::<Vec<_>>();
+        let row_options = match normalize_options(&row_options, row_number) {
             Err(e) => {
-                return Err(ValveError::ConfigError(format!(
-                    "Error while reading options for '{}' from table table: {}",
-                    row_table, e,
-                ))
-                .into());
+                issues.push(config_issue(
+                    &table_file,
+                    row_number,
+                    "options",
+                    "error",
+                    &describe_error(&e),
+                ));
+                continue;
+            }
+            Ok((options, messages)) => {
+                startup_table_messages.insert(row_number, messages);
+                options
             }
-            Ok((o, m)) => (o, m),
         };
-        startup_table_messages.insert(row_number, messages);
-        let row_desc = row.get("description").and_then(|t| t.as_str()).unwrap();
 
         // Here is a summary of the allowed table configurations for the various table modes:
         // - Views are allowed to have an empty path. If the path is non-empty then it must either
@@ -675,294 +1458,401 @@ pub fn read_config_files(
         let is_view = row_options.contains("db_view");
         let is_readonly = !row_options.contains("edit");
         let is_internal = row_options.contains("internal");
+        let lowercase_path = row_path.to_lowercase();
         if is_view || is_readonly {
             if is_view && row_path.ends_with(".tsv") {
-                return Err(ValveError::ConfigError(format!(
-                    "Invalid path '{}' for view '{}'. '.tsv' files are not supported for views.",
-                    row_path, row_table,
-                ))
-                .into());
-            }
-            if row_path != "" && !row_path.ends_with(".tsv") && !row_path.ends_with(".sql") {
-                if !row_path_info.is_executable() {
-                    return Err(ValveError::ConfigError(format!(
-                        "The generic program '{}' associated with the view or readonly table '{}' \
-                         is not executable (assuming that it even exists at all)",
+                issues.push(config_issue(
+                    &table_file,
+                    row_number,
+                    "path",
+                    "error",
+                    &format!(
+                        "Invalid path '{}' for view '{}'. '.tsv' files are not supported for \
+                         views.",
                         row_path, row_table
-                    ))
-                    .into());
-                }
+                    ),
+                ));
+            } else if !row_path.is_empty()
+                && !row_path.ends_with(".tsv")
+                && !row_path.ends_with(".sql")
+                && !Path::new(row_path).is_executable()
+            {
+                issues.push(config_issue(
+                    &table_file,
+                    row_number,
+                    "path",
+                    "error",
+                    &format!(
+                        "The generic program '{}' associated with the view or readonly table \
+                         '{}' is not executable (assuming that it even exists at all)",
+                        row_path, row_table
+                    ),
+                ));
             }
-        } else if !is_internal && !row_path.to_lowercase().ends_with(".tsv") {
-            return Err(ValveError::ConfigError(format!(
-                "Illegal path for table '{}'. Editable tables require a path that \
-                 ends in '.tsv'",
-                row_table
-            ))
-            .into());
+        } else if !is_internal && !lowercase_path.ends_with(".tsv") {
+            issues.push(config_issue(
+                &table_file,
+                row_number,
+                "path",
+                "error",
+                &format!(
+                    "Illegal path for table '{}'. Editable tables require a path that ends in \
+                     '.tsv'",
+                    row_table
+                ),
+            ));
+        }
+        if (lowercase_path.ends_with(".tsv") || lowercase_path.ends_with(".sql"))
+            && !Path::new(row_path).is_file()
+        {
+            issues.push(config_issue(
+                &table_file,
+                row_number,
+                "path",
+                "warning",
+                &format!(
+                    "Path '{}' of table '{}' does not exist",
+                    row_path, row_table
+                ),
+            ));
         }
 
         // Check that the table table path is the same as the path that was input as an argument to
         // this function:
         if row_type == "table" {
             if row_table != "table" {
-                return Err(ValveError::ConfigError(format!(
-                    "Invalid table table name: '{}'. The table table must be named 'table'",
-                    row_table,
-                ))
-                .into());
+                issues.push(config_issue(
+                    &table_file,
+                    row_number,
+                    "table",
+                    "error",
+                    &format!(
+                        "Invalid table table name: '{}'. The table table must be named 'table'",
+                        row_table
+                    ),
+                ));
             }
-            if path.to_lowercase().ends_with(".tsv") && row_path != path {
-                return Err(ValveError::ConfigError(format!(
-                    "The \"table\" table path '{}' is not the expected '{}'",
-                    row_path, path
-                ))
-                .into());
+            if !from_db && row_path != path {
+                issues.push(config_issue(
+                    &table_file,
+                    row_number,
+                    "path",
+                    "error",
+                    &format!(
+                        "The \"table\" table path '{}' is not the expected '{}'",
+                        row_path, path
+                    ),
+                ));
             }
         }
 
-        let duplicate_err_msg = format!(
-            "Multiple tables with type '{}' declared in '{}'",
-            row_type, path
-        );
-        match row_type {
-            "" => (), // Tables with no type are ignored.
-            "column" => {
-                if specials_config.column != "" {
-                    return Err(ValveError::ConfigError(duplicate_err_msg).into());
-                }
-                specials_config.column = row_table.to_string();
-            }
-            "datatype" => {
-                if specials_config.datatype != "" {
-                    return Err(ValveError::ConfigError(duplicate_err_msg).into());
-                }
-                specials_config.datatype = row_table.to_string();
-            }
-            "rule" => {
-                if specials_config.rule != "" {
-                    return Err(ValveError::ConfigError(duplicate_err_msg).into());
-                }
-                specials_config.rule = row_table.to_string();
-            }
-            "table" => {
-                if specials_config.table != "" {
-                    return Err(ValveError::ConfigError(duplicate_err_msg).into());
-                }
-                specials_config.table = row_table.to_string();
-            }
+        let special_table = match row_type {
+            "" => None, // Tables with no type are ignored.
+            "column" => Some(&mut specials_config.column),
+            "datatype" => Some(&mut specials_config.datatype),
+            "rule" => Some(&mut specials_config.rule),
+            "prefix" => Some(&mut specials_config.prefix),
+            "table" => Some(&mut specials_config.table),
             _ => {
-                return Err(ValveError::ConfigError(format!(
-                    "Unrecognized table type '{}' in '{}'",
-                    row_type, path
-                ))
-                .into())
+                issues.push(config_issue(
+                    &table_file,
+                    row_number,
+                    "type",
+                    "error",
+                    &format!("Unrecognized table type '{}'", row_type),
+                ));
+                None
             }
         };
+        if let Some(special_table) = special_table {
+            if !special_table.is_empty() {
+                issues.push(config_issue(
+                    &table_file,
+                    row_number,
+                    "type",
+                    "error",
+                    &format!(
+                        "Multiple tables with type '{}': '{}' and '{}'",
+                        row_type, special_table, row_table
+                    ),
+                ));
+            } else {
+                *special_table = row_table.to_string();
+            }
+        }
 
+        let constraints = get_value(row, "constraints");
+        if constraints.trim() != "" {
+            table_constraints.insert(row_table.to_string(), constraints.to_string());
+        }
         tables_config.insert(
             row_table.to_string(),
             ValveTableConfig {
                 table: row_table.to_string(),
                 table_type: row_type.to_string(),
                 options: row_options,
-                description: row_desc.to_string(),
+                description: get_value(row, "description").to_string(),
                 path: row_path.to_string(),
                 ..Default::default()
             },
         );
+        table_order.push(row_table.to_string());
+        locations.table.insert(row_table.to_string(), row_number);
     }
 
     // Check that all the required special tables are present
-    if specials_config.column == "" {
-        return Err(ValveError::ConfigError(format!(
-            "Missing required 'column' table in '{}'",
-            path
-        ))
-        .into());
-    }
-    if specials_config.datatype == "" {
-        return Err(ValveError::ConfigError(format!(
-            "Missing required 'datatype' table in '{}'",
-            path
-        ))
-        .into());
-    }
-    if specials_config.table == "" {
-        return Err(ValveError::ConfigError(format!(
-            "Missing required 'table' table in '{}'",
-            path
-        ))
-        .into());
-    }
-
-    // Helper function for extracting special configuration (other than the main 'table'
-    // configuration) from either a file or a table in the database, depending on the value of
-    // `path`. When `path` ends in '.tsv', the path of the config table corresponding to
-    // `table_type` is looked up, the TSV is read, and the rows are returned. When `path` does not
-    // end in '.tsv', the table name corresponding to `table_type` is looked up in the database
-    // indicated by `path`, the table is read, and the rows are returned.
-    fn get_special_config(
-        table_type: &str,
-        specials_config: &ValveSpecialConfig,
-        tables_config: &HashMap<String, ValveTableConfig>,
-        table_table: &str,
-        pool: &AnyPool,
-    ) -> Result<Vec<SerdeMap>> {
-        if table_table.to_lowercase().ends_with(".tsv") {
-            let table_name = match table_type {
-                "column" => &specials_config.column,
-                "datatype" => &specials_config.datatype,
-                "rule" => {
-                    let rule_table = &specials_config.rule;
-                    if rule_table == "" {
-                        return Err(ValveError::ConfigError(format!(
-                            "Tried to get special config for rule table but it is undefined"
-                        ))
-                        .into());
-                    }
-                    rule_table
-                }
-                _ => {
-                    return Err(ValveError::InputError(format!(
-                        "In get_special_config(): Table type '{}' not supported for this function.",
-                        table_type
-                    ))
-                    .into())
-                }
-            };
-            let path = String::from(
-                tables_config
-                    .get(table_name)
-                    .and_then(|t| Some(t.path.to_string()))
-                    .ok_or(ValveError::ConfigError(format!(
-                        "Table '{}', supposedly of type '{}', not found in tables config",
-                        table_name, table_type
-                    )))?,
-            );
-            read_tsv_into_vector(&path)
-        } else {
-            let mut db_table = None;
-            for (table_name, table_config) in tables_config {
-                let this_type = table_config.table_type.as_str();
-                if this_type == table_type {
-                    db_table = Some(table_name);
-                    break;
-                }
+    if tables_read {
+        for (table_type, table) in [
+            ("column", &specials_config.column),
+            ("datatype", &specials_config.datatype),
+            ("table", &specials_config.table),
+        ] {
+            if table.is_empty() {
+                issues.push(config_issue(
+                    &table_file,
+                    0,
+                    "type",
+                    "error",
+                    &format!("Missing required '{}' table", table_type),
+                ));
             }
-            let db_table = match db_table {
-                None => {
-                    return Err(ValveError::ConfigError(format!(
-                        "Could not determine special table name for type '{}'.",
-                        table_type
-                    ))
-                    .into())
-                }
-                Some(table) => table,
-            };
-            read_db_table_into_vector(pool, db_table)
         }
     }
 
     // 2. Load the datatype table.
     let mut datatypes_config = HashMap::new();
-    let rows = get_special_config("datatype", &specials_config, &tables_config, path, pool)?;
-    for row in rows {
-        if let Err(e) = check_table_requirements(
-            &vec!["datatype", "sql_type", "condition", "description", "parent"],
-            &vec!["datatype"],
-            &row,
-        ) {
-            return Err(ValveError::ConfigError(format!(
-                "Error while reading from datatype table: {:?}",
-                e
-            ))
-            .into());
+    let mut datatype_order = vec![];
+    let datatype_rows = get_special_config(
+        &specials_config.datatype,
+        &tables_config,
+        from_db,
+        &vec!["datatype", "sql_type", "condition", "description", "parent"],
+        pool,
+        issues,
+    );
+    let datatypes_read = datatype_rows.is_some();
+    let (datatype_file, rows) = datatype_rows.unwrap_or_default();
+    locations.datatype_file = datatype_file.to_string();
+    for (row_number, row) in rows.iter().enumerate() {
+        let row_number = row_number as u32 + 1;
+        if !has_values(&datatype_file, row_number, row, &vec!["datatype"], issues) {
+            continue;
+        }
+        let dt_name = get_value(row, "datatype");
+        if datatypes_config.contains_key(dt_name) {
+            issues.push(config_issue(
+                &datatype_file,
+                row_number,
+                "datatype",
+                "error",
+                &format!("Duplicate datatype '{}'", dt_name),
+            ));
+            continue;
+        }
+        // The transform column is optional:
+        let transform = get_value(row, "transform");
+        if !transform.is_empty() {
+            if let Err(e) = compile_transform(transform, parser) {
+                issues.push(config_issue(
+                    &datatype_file,
+                    row_number,
+                    "transform",
+                    "error",
+                    &describe_error(&e),
+                ));
+            }
         }
-
-        let dt_name = row.get("datatype").and_then(|d| d.as_str()).unwrap();
-        let sql_type = row.get("sql_type").and_then(|s| s.as_str()).unwrap();
-        let condition = row.get("condition").and_then(|s| s.as_str()).unwrap();
-        let description = row.get("description").and_then(|s| s.as_str()).unwrap();
-        let parent = row.get("parent").and_then(|s| s.as_str()).unwrap();
         datatypes_config.insert(
             dt_name.to_string(),
             ValveDatatypeConfig {
-                sql_type: sql_type.to_string(),
-                condition: condition.to_string(),
+                sql_type: get_value(row, "sql_type").to_string(),
+                condition: get_value(row, "condition").to_string(),
                 datatype: dt_name.to_string(),
-                description: description.to_string(),
-                parent: parent.to_string(),
+                description: get_value(row, "description").to_string(),
+                parent: get_value(row, "parent").to_string(),
+                transform: transform.to_string(),
             },
         );
+        datatype_order.push(dt_name.to_string());
+        locations.datatype.insert(dt_name.to_string(), row_number);
+    }
+
+    // Check that every parent datatype is defined and that no datatype is its own ancestor. The
+    // parent of a datatype that is its own ancestor is left out of the configuration so that the
+    // datatype tree can be climbed safely:
+    for dt_name in &datatype_order {
+        let parent = datatypes_config.get(dt_name).unwrap().parent.to_string();
+        if !parent.is_empty() && !datatypes_config.contains_key(&parent) {
+            issues.push(locations.datatype_issue(
+                dt_name,
+                "parent",
+                "error",
+                &format!("Undefined parent datatype '{}'", parent),
+            ));
+            continue;
+        }
+        let mut ancestors = vec![dt_name.to_string()];
+        let mut current = parent.as_str();
+        while !current.is_empty() && !ancestors.contains(&current.to_string()) {
+            ancestors.push(current.to_string());
+            current = match datatypes_config.get(current) {
+                Some(dt_config) => &dt_config.parent,
+                None => break,
+            };
+        }
+        if current == dt_name {
+            ancestors.push(current.to_string());
+            issues.push(locations.datatype_issue(
+                dt_name,
+                "parent",
+                "error",
+                &format!("Circular parent relationship: {}", ancestors.join(" -> ")),
+            ));
+            datatypes_config.get_mut(dt_name).unwrap().parent = String::from("");
+        }
     }
 
     // Check that all the essential datatypes have been configured:
-    for dt in vec!["text", "empty", "line", "trimmed_line", "nonspace", "word"] {
-        if !datatypes_config.contains_key(dt) {
-            return Err(
-                ValveError::ConfigError(format!("Missing required datatype: '{}'", dt)).into(),
-            );
+    if datatypes_read {
+        for dt in ["text", "empty", "line", "trimmed_line", "nonspace", "word"] {
+            if !datatypes_config.contains_key(dt) {
+                issues.push(config_issue(
+                    &datatype_file,
+                    0,
+                    "datatype",
+                    "error",
+                    &format!("Missing required datatype: '{}'", dt),
+                ));
+            }
         }
     }
 
     // 3. Load the column table.
-    let rows = get_special_config("column", &specials_config, &tables_config, path, pool)?;
     let special_tables = vec![
         specials_config.table.to_string(),
         specials_config.column.to_string(),
         specials_config.datatype.to_string(),
         specials_config.rule.to_string(),
+        specials_config.prefix.to_string(),
     ];
+    let column_rows = get_special_config(
+        &specials_config.column,
+        &tables_config,
+        from_db,
+        &vec![
+            "table",
+            "nulltype",
+            "datatype",
+            "column",
+            "description",
+            "label",
+            "structure",
+        ],
+        pool,
+        issues,
+    );
     // The defined_column_orderings map, which contains the columns of a given table in the order in
     // which they have been defined in the column table, is used as a default in the determination
     // of the [ValveTableConfig::column_order] field, in the case where there no .TSV file
     // representing the table has been configured in valve.
     let mut defined_column_orderings = IndexMap::new();
-    for row in rows {
-        if let Err(e) = check_table_requirements(
-            &vec![
-                "table",
-                "nulltype",
-                "datatype",
-                "column",
-                "description",
-                "label",
-                "structure",
-            ],
+    let columns_read = column_rows.is_some();
+    let (column_file, rows) = column_rows.unwrap_or_default();
+    locations.column_file = column_file.to_string();
+    for (row_number, row) in rows.iter().enumerate() {
+        let row_number = row_number as u32 + 1;
+        if !has_values(
+            &column_file,
+            row_number,
+            row,
             &vec!["table", "column", "datatype"],
-            &row,
+            issues,
         ) {
-            return Err(ValveError::ConfigError(format!(
-                "Error while reading from column table: {:?}",
-                e
-            ))
-            .into());
+            continue;
         }
-
-        let row_table = row.get("table").and_then(|t| t.as_str()).unwrap();
-        if !tables_config.contains_key(row_table) {
-            return Err(ValveError::ConfigError(format!("Undefined table '{}'", row_table)).into());
+        let row_table = get_value(row, "table");
+        let column_name = get_value(row, "column");
+        let table_config = match tables_config.get_mut(row_table) {
+            None => {
+                issues.push(config_issue(
+                    &column_file,
+                    row_number,
+                    "table",
+                    "error",
+                    &format!("Undefined table '{}'", row_table),
+                ));
+                continue;
+            }
+            Some(table_config) => table_config,
+        };
+        if table_config.column.contains_key(column_name) {
+            issues.push(config_issue(
+                &column_file,
+                row_number,
+                "column",
+                "error",
+                &format!("Duplicate column '{}.{}'", row_table, column_name),
+            ));
+            continue;
         }
-        let nulltype = row.get("nulltype").and_then(|t| t.as_str()).unwrap();
-        if nulltype != "" && !datatypes_config.contains_key(nulltype) {
-            return Err(
-                ValveError::ConfigError(format!("Undefined nulltype '{}'", nulltype)).into(),
-            );
+        let nulltype = get_value(row, "nulltype");
+        let datatype = get_value(row, "datatype");
+        if datatypes_read {
+            if !nulltype.is_empty() && !datatypes_config.contains_key(nulltype) {
+                issues.push(config_issue(
+                    &column_file,
+                    row_number,
+                    "nulltype",
+                    "error",
+                    &format!("Undefined nulltype '{}'", nulltype),
+                ));
+            }
+            if !datatypes_config.contains_key(datatype) {
+                issues.push(config_issue(
+                    &column_file,
+                    row_number,
+                    "datatype",
+                    "error",
+                    &format!("Undefined datatype '{}'", datatype),
+                ));
+            } else if !table_config.options.contains("db_view") {
+                // The SQL type of the column is checked again when the table's DDL is generated:
+                let sql_type = resolve_sql_type(&datatypes_config, datatype).to_lowercase();
+                let short_sql_type = match sql_type.starts_with("varchar(") {
+                    true => "varchar",
+                    false => sql_type.as_str(),
+                };
+                if !SQL_TYPES.contains(&short_sql_type) {
+                    issues.push(config_issue(
+                        &column_file,
+                        row_number,
+                        "datatype",
+                        "error",
+                        &format!(
+                            "Unrecognized SQL type '{}' for datatype: '{}'. Accepted SQL types \
+                             are: {}",
+                            sql_type,
+                            datatype,
+                            SQL_TYPES.join(", ")
+                        ),
+                    ));
+                }
+            }
         }
-        let datatype = row.get("datatype").and_then(|d| d.as_str()).unwrap();
-        if !datatypes_config.contains_key(datatype) {
-            return Err(
-                ValveError::ConfigError(format!("Undefined datatype '{}'", datatype)).into(),
-            );
+        // The transform column is optional:
+        let transform = get_value(row, "transform");
+        if !transform.is_empty() {
+            if let Err(e) = compile_transform(transform, parser) {
+                issues.push(config_issue(
+                    &column_file,
+                    row_number,
+                    "transform",
+                    "error",
+                    &describe_error(&e),
+                ));
+            }
         }
-        let column_name = row.get("column").and_then(|c| c.as_str()).unwrap();
-        let description = row.get("description").and_then(|c| c.as_str()).unwrap();
-        let mut label = row
-            .get("label")
-            .and_then(|c| c.as_str())
-            .unwrap()
-            .to_string();
+        let mut label = get_value(row, "label").to_string();
         if !label.is_empty() && special_tables.contains(&row_table.to_string()) {
             log::warn!(
                 "Label '{}' for column '{}' of special table '{}' will be ignored.",
@@ -972,393 +1862,1382 @@ pub fn read_config_files(
             );
             label = String::from("");
         }
-        let structure = row.get("structure").and_then(|c| c.as_str()).unwrap();
-
-        let default = match row.get("default") {
-            None => SerdeValue::String("".to_string()),
-            Some(default) => default.clone(),
-        };
-
-        // If an entry in the defined_column_orderings map for this table doesn't already exist,
-        // create one:
-        if defined_column_orderings
-            .keys()
-            .filter(|k| *k == row_table)
-            .collect::<Vec<_>>()
-            .is_empty()
-        {
-            defined_column_orderings.insert(row_table.to_string(), vec![]);
-        }
-
+        table_config.column.insert(
+            column_name.to_string(),
+            ValveColumnConfig {
+                table: row_table.to_string(),
+                column: column_name.to_string(),
+                datatype: datatype.to_string(),
+                description: get_value(row, "description").to_string(),
+                label,
+                structure: get_value(row, "structure").to_string(),
+                nulltype: nulltype.to_string(),
+                default: row.get("default").cloned().unwrap_or(json!("")),
+                transform: transform.to_string(),
+            },
+        );
         // Add the column name to the ordered list of columns for this table:
-        match defined_column_orderings
-            .get_mut(row_table)
-            .and_then(|t| Some(t.push(column_name.to_string())))
-        {
-            None => {
-                return Err(ValveError::DataError(format!(
-                    "Could not insert column '{}'",
-                    column_name
-                ))
-                .into());
-            }
-            _ => (),
-        };
-
-        tables_config.get_mut(row_table).and_then(|t| {
-            Some(t.column.insert(
-                column_name.to_string(),
-                ValveColumnConfig {
-                    table: row_table.to_string(),
-                    column: column_name.to_string(),
-                    datatype: datatype.to_string(),
-                    description: description.to_string(),
-                    label: label,
-                    structure: structure.to_string(),
-                    nulltype: nulltype.to_string(),
-                    default: default,
-                },
-            ))
-        });
+        defined_column_orderings
+            .entry(row_table.to_string())
+            .or_insert(vec![])
+            .push(column_name.to_string());
+        locations
+            .column
+            .insert((row_table.to_string(), column_name.to_string()), row_number);
     }
 
-    // 4. Load rule table if it exists
+    // 4. Load rule table if it exists. Since rules refer to the columns of tables, and their
+    // conditions to datatypes, the rules are only checked if the column and datatype tables could
+    // be read. Otherwise every rule would be reported as referring to an undefined column or
+    // datatype, even though the only problem is with the column or datatype table itself.
     let mut rules_config = HashMap::new();
-    if specials_config.rule != "" {
-        let table_name = &specials_config.rule;
-        let rows = get_special_config(table_name, &specials_config, &tables_config, path, pool)?;
-        for row in rows {
-            if let Err(e) = check_table_requirements(
-                &vec![
-                    "table",
-                    "when column",
-                    "when condition",
-                    "then column",
-                    "then condition",
-                    "level",
-                    "description",
-                ],
-                &vec![
-                    "table",
-                    "when column",
-                    "when condition",
-                    "then column",
-                    "then condition",
-                    "level",
-                    "description",
-                ],
-                &row,
-            ) {
-                return Err(ValveError::ConfigError(format!(
-                    "Error while reading from rule table: {:?}",
-                    e
-                ))
-                .into());
-            }
-
-            let row_table = row.get("table").and_then(|t| t.as_str()).unwrap();
-            if !tables_config.contains_key(row_table) {
-                return Err(ValveError::ConfigError(format!(
-                    "Undefined table '{}' while reading rule configuration",
-                    row_table
-                ))
-                .into());
-            }
-
-            // Add the rule specified in the given row to the list of rules associated with the
-            // value of the when column:
-            if !rules_config.contains_key(row_table) {
-                rules_config.insert(String::from(row_table), HashMap::new());
-            }
-            let table_rule_config = rules_config.get_mut(row_table).unwrap();
+    let mut script_rules_config = HashMap::new();
+    let when_then_columns = vec![
+        "when column",
+        "when condition",
+        "then column",
+        "then condition",
+    ];
+    let mut columns_are_required = vec!["table", "level", "description"];
+    columns_are_required.append(&mut when_then_columns.clone());
+    let (rule_file, rows) = get_special_config(
+        &specials_config.rule,
+        &tables_config,
+        from_db,
+        &columns_are_required,
+        pool,
+        issues,
+    )
+    .unwrap_or_default();
+    locations.rule_file = rule_file.to_string();
+    let rows = match columns_read && datatypes_read {
+        true => rows,
+        false => vec![],
+    };
+    let id_re = Regex::new(r"^[\w-]+$").unwrap();
+    let script_id_re = Regex::new(r"^script-\d+$").unwrap();
+    let mut rule_ids = HashSet::new();
+    for (row_number, row) in rows.iter().enumerate() {
+        let row_number = row_number as u32 + 1;
+        // The rule table may optionally have a 'script' column. A rule with a script is a script
+        // rule, and its when and then columns and conditions must all be empty:
+        let script = get_value(row, "script");
+        let mut values_are_required = vec!["table", "level", "description"];
+        if script.is_empty() {
+            values_are_required.append(&mut when_then_columns.clone());
+        }
+        if !has_values(&rule_file, row_number, row, &values_are_required, issues) {
+            continue;
+        }
+        let row_table = get_value(row, "table");
+        if !tables_config.contains_key(row_table) {
+            issues.push(config_issue(
+                &rule_file,
+                row_number,
+                "table",
+                "error",
+                &format!("Undefined table '{}'", row_table),
+            ));
+            continue;
+        }
+        let desc = get_value(row, "description");
+        let level = get_value(row, "level");
+
+        // The rule table may also optionally have an 'id' column, giving a stable identifier for
+        // the rule that is used in place of its position in messages about it. Identifiers must be
+        // unique, and must not look like the positional identifier of a script rule:
+        let id = get_value(row, "id").trim();
+        if !id.is_empty() {
+            if !id_re.is_match(id) || script_id_re.is_match(id) {
+                issues.push(config_issue(
+                    &rule_file,
+                    row_number,
+                    "id",
+                    "error",
+                    &format!("Invalid rule id '{}'", id),
+                ));
+            } else if !rule_ids.insert(id.to_string()) {
+                issues.push(config_issue(
+                    &rule_file,
+                    row_number,
+                    "id",
+                    "error",
+                    &format!("Duplicate rule id '{}'", id),
+                ));
+            }
+        }
 
-            let when_col = row.get("when column").and_then(|c| c.as_str()).unwrap();
-            if !table_rule_config.contains_key(when_col) {
-                table_rule_config.insert(String::from(when_col), vec![]);
+        if !script.is_empty() {
+            let mut is_valid = true;
+            for column in &when_then_columns {
+                if !get_value(row, column).is_empty() {
+                    issues.push(config_issue(
+                        &rule_file,
+                        row_number,
+                        column,
+                        "error",
+                        "A rule with a script must not have when or then columns or conditions",
+                    ));
+                    is_valid = false;
+                }
+            }
+            // Compile the script now so that any syntax errors are reported immediately:
+            let compiled = match compile_rule_script(script) {
+                Ok(ast) => Some(ast),
+                Err(e) => {
+                    issues.push(config_issue(
+                        &rule_file,
+                        row_number,
+                        "script",
+                        "error",
+                        &describe_error(&e),
+                    ));
+                    is_valid = false;
+                    None
+                }
+            };
+            if is_valid {
+                script_rules_config
+                    .entry(row_table.to_string())
+                    .or_insert(vec![])
+                    .push(ValveScriptRuleConfig {
+                        description: desc.to_string(),
+                        level: level.to_string(),
+                        table: row_table.to_string(),
+                        script: script.to_string(),
+                        id: id.to_string(),
+                        compiled,
+                    });
             }
+            continue;
+        }
 
-            let column_rule_config = table_rule_config.get_mut(&when_col.to_string()).unwrap();
-            let desc = row.get("description").and_then(|c| c.as_str()).unwrap();
-            let level = row.get("level").and_then(|c| c.as_str()).unwrap();
-            let when_con = row.get("when condition").and_then(|c| c.as_str()).unwrap();
-            let then_col = row.get("then column").and_then(|c| c.as_str()).unwrap();
-            let then_con = row.get("then condition").and_then(|c| c.as_str()).unwrap();
-            column_rule_config.push(ValveRuleConfig {
-                description: desc.to_string(),
-                level: level.to_string(),
-                table: row_table.to_string(),
-                then_column: then_col.to_string(),
-                then_condition: then_con.to_string(),
-                when_column: when_col.to_string(),
-                when_condition: when_con.to_string(),
-            });
+        // A rule may have more than one when column, in which case the when columns, and their
+        // corresponding when conditions, are given as comma-separated lists:
+        let when_cols = get_value(row, "when column")
+            .split(',')
+            .map(|c| c.trim().to_string())
+            .collect::<Vec<_>>();
+        let when_con = get_value(row, "when condition");
+        let when_cons = match when_cols.len() {
+            1 => vec![when_con.to_string()],
+            _ => match split_conditions(when_con, parser) {
+                Err(e) => {
+                    issues.push(config_issue(
+                        &rule_file,
+                        row_number,
+                        "when condition",
+                        "error",
+                        &describe_error(&e),
+                    ));
+                    continue;
+                }
+                Ok(when_cons) => when_cons,
+            },
+        };
+        if when_cols.len() != when_cons.len() {
+            issues.push(config_issue(
+                &rule_file,
+                row_number,
+                "when column",
+                "error",
+                &format!(
+                    "The number of when columns ({}) does not match the number of when \
+                     conditions ({})",
+                    when_cols.len(),
+                    when_cons.len()
+                ),
+            ));
+            continue;
         }
+
+        // Add the rule specified in the given row to the list of rules associated with the value
+        // of the (first) when column:
+        let when_col = &when_cols[0];
+        let column_rule_config = rules_config
+            .entry(row_table.to_string())
+            .or_insert(HashMap::new())
+            .entry(when_col.to_string())
+            .or_insert(vec![]);
+        locations.rule.insert(
+            (
+                row_table.to_string(),
+                when_col.to_string(),
+                column_rule_config.len(),
+            ),
+            row_number,
+        );
+        column_rule_config.push(ValveRuleConfig {
+            description: desc.to_string(),
+            level: level.to_string(),
+            table: row_table.to_string(),
+            then_column: get_value(row, "then column").to_string(),
+            then_condition: get_value(row, "then condition").to_string(),
+            when_column: when_col.to_string(),
+            when_condition: when_cons[0].to_string(),
+            additional_when: when_cols[1..]
+                .iter()
+                .cloned()
+                .zip(when_cons[1..].iter().cloned())
+                .collect::<Vec<_>>(),
+            id: id.to_string(),
+        });
     }
 
-    // 5. Initialize the constraints config:
+    // 5. Load the prefix table if it exists. Since the prefix table is validated like any other
+    // table, an invalid or duplicate prefix is reported as a validation message once the table has
+    // been loaded. Here we simply skip such prefixes with a warning, keeping the first definition
+    // of each one.
+    let mut prefixes_config = HashMap::new();
+    let (prefix_file, rows) = get_special_config(
+        &specials_config.prefix,
+        &tables_config,
+        from_db,
+        &vec!["prefix", "base"],
+        pool,
+        issues,
+    )
+    .unwrap_or_default();
+    for (row_number, row) in rows.iter().enumerate() {
+        let row_number = row_number as u32 + 1;
+        let prefix = get_value(row, "prefix");
+        let base = get_value(row, "base");
+        if prefix.is_empty()
+            || base.is_empty()
+            || prefix.contains(|c: char| c == ':' || c.is_whitespace())
+        {
+            issues.push(config_issue(
+                &prefix_file,
+                row_number,
+                "prefix",
+                "warning",
+                &format!(
+                    "Invalid prefix '{}' with base '{}' will be ignored",
+                    prefix, base
+                ),
+            ));
+        } else if prefixes_config.contains_key(prefix) {
+            issues.push(config_issue(
+                &prefix_file,
+                row_number,
+                "prefix",
+                "warning",
+                &format!(
+                    "Duplicate definition of prefix '{}' with base '{}' will be ignored",
+                    prefix, base
+                ),
+            ));
+        } else {
+            prefixes_config.insert(prefix.to_string(), base.to_string());
+        }
+    }
+
+    // 6. Initialize the constraints config:
     let mut constraints_config = ValveConstraintConfig::default();
     for table_name in &table_order {
-        let table_name = table_name.to_string();
-        let this_table = tables_config
-            .get(&table_name)
-            .ok_or(ValveError::ConfigError(format!(
-                "Table '{}' not found in tables config",
-                table_name
-            )))?;
+        let this_table = tables_config.get(table_name).unwrap();
+        constraints_config
+            .primary
+            .insert(table_name.to_string(), vec![]);
+        constraints_config
+            .unique
+            .insert(table_name.to_string(), vec![]);
+        constraints_config
+            .foreign
+            .insert(table_name.to_string(), vec![]);
+        constraints_config
+            .tree
+            .insert(table_name.to_string(), vec![]);
+        constraints_config
+            .composite
+            .insert(table_name.to_string(), vec![]);
+        constraints_config
+            .composite_foreign
+            .insert(table_name.to_string(), vec![]);
 
-        let mut path = None;
-        if !Path::new(&this_table.path).is_file() {
-            log::warn!(
-                "Path '{}' of table '{}' does not exist",
-                this_table.path,
-                table_name
+        // Constraints on internal tables do not need to be configured explicitly:
+        if !columns_read || this_table.options.contains("internal") {
+            continue;
+        }
+        if this_table.column.is_empty() {
+            issues.push(locations.table_issue(
+                table_name,
+                "table",
+                "error",
+                &format!("No columns configured for table '{}'", table_name),
+            ));
+            continue;
+        }
+
+        let defined_labels = this_table
+            .column
+            .iter()
+            .map(|(k, v)| {
+                if v.label != "" {
+                    v.label.to_string()
+                } else {
+                    k.to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        let defined_column_ordering = defined_column_orderings
+            .get(table_name)
+            .cloned()
+            .unwrap_or_default();
+
+        // We use column_order to explicitly indicate the order in which the columns should appear
+        // in the table, for later reference. The default is to preserve the order from the actual
+        // table file. If that does not exist, we use the ordering in defined_column_ordering.
+        let mut column_order = vec![];
+        let table_path = &this_table.path;
+        if table_path.to_lowercase().ends_with(".tsv") && Path::new(table_path).is_file() {
+            // Get the actual columns from the data itself. Note that we set has_headers to false
+            // (even though the files have header rows) in order to explicitly read the header row.
+            let actual_labels = ReaderBuilder::new()
+                .has_headers(false)
+                .delimiter(b'\t')
+                .from_path(table_path)
+                .map_err(|e| format!("Unable to open '{}': {}", table_path, e))
+                .and_then(|mut rdr| match rdr.records().next() {
+                    None => Err(format!("'{}' is empty", table_path)),
+                    Some(result) => result
+                        .map_err(|e| format!("Unable to read row from '{}': {}", table_path, e)),
+                });
+            match actual_labels {
+                Err(e) => issues.push(config_issue(table_path, 0, "", "error", &e)),
+                Ok(actual_labels) => {
+                    for label_name in actual_labels.iter() {
+                        // Make sure that the actual columns found in the table file are all
+                        // defined in the column configuration:
+                        if !defined_labels.contains(&label_name.to_string()) {
+                            issues.push(config_issue(
+                                table_path,
+                                0,
+                                label_name,
+                                "error",
+                                &format!(
+                                    "Label '{}' of table '{}' not in column config",
+                                    label_name, table_name
+                                ),
+                            ));
+                            continue;
+                        }
+                        // The defined labels are what will be used to create the table's columns.
+                        // Those that are also actual labels come first, in the order in which
+                        // they appear in the table file, followed by the rest, in the order in
+                        // which they have been defined in the column table. Note that the order
+                        // must not depend on the iteration order of the column configuration,
+                        // since otherwise the table would appear to have changed every time that
+                        // Valve is run.
+                        match get_column_for_label(&this_table.column, label_name, table_name) {
+                            Ok(column_name) => column_order.push(column_name),
+                            Err(e) => issues.push(locations.table_issue(
+                                table_name,
+                                "table",
+                                "error",
+                                &describe_error(&e),
+                            )),
+                        };
+                    }
+                    for column_name in &defined_column_ordering {
+                        if !column_order.contains(column_name) {
+                            column_order.push(column_name.to_string());
+                        }
+                    }
+                }
+            };
+        }
+
+        // If for some reason we were unable to determine the column order, then use the order
+        // defined in the column table:
+        if column_order.is_empty() {
+            column_order = defined_column_ordering.clone();
+        }
+        tables_config.get_mut(table_name).unwrap().column_order = column_order;
+
+        // Populate the table constraints for this table:
+        let (primaries, uniques, foreigns, trees) = get_table_constraints(
+            &tables_config,
+            &datatypes_config,
+            parser,
+            table_name,
+            pool,
+            &locations,
+            issues,
+        );
+        constraints_config
+            .primary
+            .insert(table_name.to_string(), primaries);
+        constraints_config
+            .unique
+            .insert(table_name.to_string(), uniques);
+        constraints_config
+            .foreign
+            .insert(table_name.to_string(), foreigns);
+        constraints_config
+            .tree
+            .insert(table_name.to_string(), trees);
+
+        // Populate the table-level constraints for this table:
+        if let Some(constraints) = table_constraints.get(table_name) {
+            let composites = get_composite_constraints(
+                &tables_config,
+                parser,
+                table_name,
+                constraints,
+                &mut constraints_config,
+                &locations,
+                issues,
             );
-        } else if Path::new(&this_table.path).canonicalize().is_err() {
-            log::warn!(
-                "Path '{}' of table '{}' could not be made canonical",
-                this_table.path,
-                table_name
+            constraints_config
+                .composite
+                .insert(table_name.to_string(), composites);
+        }
+
+        // Populate the multi-column foreign constraints for this table:
+        let composite_foreigns = get_composite_foreign_constraints(
+            &tables_config,
+            parser,
+            table_name,
+            &defined_column_ordering,
+            &locations,
+            issues,
+        );
+        constraints_config
+            .composite_foreign
+            .insert(table_name.to_string(), composite_foreigns);
+    }
+
+    // 7. Add implicit unique constraints for trees and foreign keys:
+    for table in &table_order {
+        let table_trees = constraints_config
+            .tree
+            .get(table)
+            .cloned()
+            .unwrap_or_default();
+        let table_primaries = constraints_config
+            .primary
+            .get(table)
+            .cloned()
+            .unwrap_or_default();
+        if let Some(table_uniques) = constraints_config.unique.get_mut(table) {
+            for tree in table_trees {
+                if !table_uniques.contains(&tree.child) && !table_primaries.contains(&tree.child) {
+                    log::warn!(
+                        "Table '{}' has a tree defined on column '{}' which therefore requires \
+                         a UNIQUE constraint. It will be implicitly created.",
+                        table,
+                        tree.child
+                    );
+                    table_uniques.push(tree.child.to_string());
+                }
+            }
+        }
+
+        let table_foreigns = constraints_config
+            .foreign
+            .get(table)
+            .cloned()
+            .unwrap_or_default();
+        for foreign in table_foreigns {
+            let ftable = &foreign.ftable;
+            let fprimaries = constraints_config
+                .primary
+                .get(ftable)
+                .cloned()
+                .unwrap_or_default();
+            let funiques = match constraints_config.unique.get_mut(ftable) {
+                None => continue,
+                Some(funiques) => funiques,
+            };
+            let fcolumn = &foreign.fcolumn;
+            if !funiques.contains(fcolumn) && !fprimaries.contains(fcolumn) {
+                log::warn!(
+                    "Column '{}.{}' is a foreign key for table '{}' and therefore requires \
+                     a UNIQUE constraint. It will be implicitly created.",
+                    ftable,
+                    fcolumn,
+                    table,
+                );
+                funiques.push(fcolumn.to_string());
+            }
+        }
+
+        let table_composite_foreigns = constraints_config
+            .composite_foreign
+            .get(table)
+            .cloned()
+            .unwrap_or_default();
+        for foreign in &table_composite_foreigns {
+            let ftable = &foreign.ftable;
+            let fcomposites = match constraints_config.composite.get_mut(ftable) {
+                None => continue,
+                Some(fcomposites) => fcomposites,
+            };
+            let mut fcolumns = foreign.fcolumns.clone();
+            fcolumns.sort();
+            let has_key = fcomposites.iter().any(|c| {
+                let mut columns = c.columns.clone();
+                columns.sort();
+                columns == fcolumns
+            });
+            if !has_key {
+                log::warn!(
+                    "Columns ({}) of table '{}' are a foreign key for table '{}' and therefore \
+                     require a UNIQUE constraint. It will be implicitly created.",
+                    foreign.fcolumns.join(", "),
+                    ftable,
+                    table,
+                );
+                fcomposites.push(ValveCompositeConstraint {
+                    table: ftable.to_string(),
+                    key_type: "unique".to_string(),
+                    columns: foreign.fcolumns.clone(),
+                });
+            }
+        }
+    }
+
+    // 8. Add internal table configuration to the table config:
+    for table in INTERNAL_TABLES.iter() {
+        tables_config.insert(table.to_string(), generate_internal_table_config(table));
+        table_order.push(table.to_string());
+    }
+
+    // 9. Sort the tables (other than internal tables) according to their foreign key
+    // dependencies so that tables are always loaded after the tables they depend on.
+    let (sorted_tables, table_dependencies_in, table_dependencies_out) = verify_table_deps_and_sort(
+        &table_order
+            .iter()
+            // Internal tables will be taken account of within verify_table_deps_and_sort() and
+            // manually added to the sorted table list that is returned there.
+            .filter(|&m| !INTERNAL_TABLES.contains(&m.to_string().as_str()))
+            .cloned()
+            .collect::<Vec<_>>(),
+        &constraints_config,
+        &locations,
+        issues,
+    );
+
+    // 10. Finally, return all the configs:
+    (
+        specials_config,
+        tables_config,
+        table_order,
+        datatypes_config,
+        rules_config,
+        script_rules_config,
+        prefixes_config,
+        constraints_config,
+        sorted_tables,
+        table_dependencies_in,
+        table_dependencies_out,
+        startup_table_messages,
+        locations,
+    )
+}
+
+/// Given a datatype configuration map and the name of a datatype, climb the datatype tree, starting
+/// from the given datatype, and return the first SQL type found, or TEXT if there is none. Unlike
+/// [get_sql_type()], this function tolerates undefined datatypes and cycles in the datatype tree.
+fn resolve_sql_type(
+    datatypes_config: &HashMap<String, ValveDatatypeConfig>,
+    datatype: &str,
+) -> String {
+    let mut visited = HashSet::new();
+    let mut current = datatype;
+    while let Some(dt_config) = datatypes_config.get(current) {
+        if !visited.insert(current) {
+            break;
+        }
+        if !dt_config.sql_type.is_empty() {
+            return dt_config.sql_type.to_string();
+        }
+        current = &dt_config.parent;
+    }
+    "TEXT".to_string()
+}
+
+/// Given the path to a table table (either a table.tsv file or a database containing a table
+/// named "table"), a parser, and a database connection pool, check the 'table', 'datatype',
+/// 'prefix', 'column', and 'rule' tables in the same way as
+/// [Valve::build()](crate::valve::Valve::build()) does, but instead of stopping if there are
+/// problems with the configuration, return every problem found, in the order in which it was
+/// found, as a list of [ValveConfigIssue]s. Besides the problems that would cause Valve to fail to
+/// build, the list includes the problems with table options that are reported as startup messages
+/// once Valve has been built, and warnings about configured paths that do not exist and prefixes
+/// that will be ignored.
+pub fn lint_config_files(
+    path: &str,
+    parser: &StartParser,
+    pool: &AnyPool,
+) -> Vec<ValveConfigIssue> {
+    let mut issues = vec![];
+    let (
+        specials_config,
+        tables_config,
+        table_order,
+        datatypes_config,
+        rules_config,
+        script_rules_config,
+        prefixes_config,
+        constraints_config,
+        _,
+        _,
+        _,
+        startup_table_messages,
+        locations,
+    ) = read_config_files(path, parser, pool, &mut issues);
+    for (row_number, messages) in startup_table_messages {
+        for message in messages {
+            issues.push(config_issue(
+                &locations.table_file,
+                row_number,
+                "options",
+                &message.level,
+                &format!("{}: '{}'", message.message, message.value),
+            ));
+        }
+    }
+
+    let config = ValveConfig {
+        special: specials_config,
+        table: tables_config,
+        table_order,
+        datatype: datatypes_config,
+        rule: rules_config,
+        script_rule: script_rules_config,
+        prefix: prefixes_config,
+        constraint: constraints_config,
+    };
+    let datatype_conditions =
+        generate_datatype_conditions(&config, parser, pool, &locations, &mut issues);
+    generate_rule_conditions(
+        &config,
+        &datatype_conditions,
+        parser,
+        pool,
+        &locations,
+        &mut issues,
+    );
+    issues
+}
+
+/// Given an old and a new configuration, and a parser, compare the two configurations and return
+/// the tables, columns, constraints, datatypes, rules, and prefixes that have been added, removed,
+/// or changed in the new configuration, as a list of [ValveConfigChange]s. Along with each change
+/// are listed the tables whose database structure will need to be migrated as a result of it,
+/// i.e., the tables for which, given a database that matches the old configuration,
+/// [Valve::table_has_changed()] will return true under the new configuration (see
+/// [Valve::migrate()] for when migrating a table loses its history), and the tables whose
+/// validation results may be affected by it, either directly or through the datatypes, and the
+/// datatypes' ancestors and conditions, that their columns and rules refer to.
+///
+/// [Valve::table_has_changed()]: crate::valve::Valve::table_has_changed()
+/// [Valve::migrate()]: crate::valve::Valve::migrate()
+pub fn diff_configs(
+    old: &ValveConfig,
+    new: &ValveConfig,
+    parser: &StartParser,
+) -> Vec<ValveConfigChange> {
+    // Given the kind, name, and type of change, along with the changed field and its old and new
+    // values, return the corresponding change, which will initially not affect any tables.
+    fn describe(
+        kind: &str,
+        item: &str,
+        change: &str,
+        field: &str,
+        old: &str,
+        new: &str,
+    ) -> ValveConfigChange {
+        ValveConfigChange {
+            kind: kind.to_string(),
+            item: item.to_string(),
+            change: change.to_string(),
+            field: field.to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // Returns the names of the items in the given old and new lists, in the order in which they
+    // appear in the new list, followed by those that appear only in the old list.
+    fn merge_names(old_names: &[String], new_names: &[String]) -> Vec<String> {
+        new_names
+            .iter()
+            .chain(old_names.iter())
+            .unique()
+            .cloned()
+            .collect()
+    }
+
+    // Returns the given datatype along with the datatypes that depend on it in the given
+    // configuration, i.e., its descendants, and, if include_references is true, the datatypes
+    // whose conditions refer to any of these (e.g., in a list() condition), and so on.
+    fn get_dependent_datatypes(
+        config: &ValveConfig,
+        parser: &StartParser,
+        datatype: &str,
+        include_references: bool,
+    ) -> HashSet<String> {
+        let mut dependents = HashSet::from([datatype.to_string()]);
+        loop {
+            let mut found = vec![];
+            for (dt_name, dt_config) in &config.datatype {
+                if dependents.contains(dt_name) {
+                    continue;
+                }
+                let references = match include_references {
+                    false => vec![],
+                    true => parser
+                        .parse(&dt_config.condition)
+                        .map(|parsed| {
+                            parsed
+                                .iter()
+                                .flat_map(|p| get_referenced_datatypes(p))
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default(),
+                };
+                if dependents.contains(&dt_config.parent)
+                    || references.iter().any(|dt| dependents.contains(dt))
+                {
+                    found.push(dt_name.to_string());
+                }
+            }
+            if found.is_empty() {
+                return dependents;
+            }
+            dependents.extend(found);
+        }
+    }
+
+    // Returns the tables in the given configuration whose columns have one of the given datatypes
+    // as their datatype or nulltype, or whose rules have conditions that refer to one of them.
+    fn get_affected_tables(
+        config: &ValveConfig,
+        parser: &StartParser,
+        datatypes: &HashSet<String>,
+    ) -> Vec<String> {
+        let refers_to_datatypes = |condition: &str| -> bool {
+            parser
+                .parse(condition)
+                .map(|parsed| {
+                    parsed
+                        .iter()
+                        .flat_map(|p| get_referenced_datatypes(p))
+                        .any(|dt| datatypes.contains(&dt))
+                })
+                .unwrap_or(false)
+        };
+        config
+            .table_order
+            .iter()
+            .filter(|table| !INTERNAL_TABLES.contains(&table.as_str()))
+            .filter(|table| {
+                let columns_affected = config.table.get(*table).is_some_and(|t| {
+                    t.column
+                        .values()
+                        .any(|c| datatypes.contains(&c.datatype) || datatypes.contains(&c.nulltype))
+                });
+                let rules_affected = config.rule.get(*table).is_some_and(|rules| {
+                    rules.values().flatten().any(|rule| {
+                        refers_to_datatypes(&rule.when_condition)
+                            || refers_to_datatypes(&rule.then_condition)
+                            || rule
+                                .additional_when
+                                .iter()
+                                .any(|(_, condition)| refers_to_datatypes(condition))
+                    })
+                });
+                columns_affected || rules_affected
+            })
+            .cloned()
+            .collect()
+    }
+
+    // Returns the constraints of the given table in the given configuration, as a map from
+    // descriptions of the constraints to flags indicating whether they are database constraints,
+    // as opposed to tree constraints, which are only enforced by Valve.
+    fn get_constraints(config: &ValveConfig, table: &str) -> IndexMap<String, bool> {
+        let constraints = &config.constraint;
+        let mut descriptions = IndexMap::new();
+        for column in constraints.primary.get(table).unwrap_or(&vec![]) {
+            descriptions.insert(format!("PRIMARY KEY ({})", column), true);
+        }
+        for column in constraints.unique.get(table).unwrap_or(&vec![]) {
+            descriptions.insert(format!("UNIQUE ({})", column), true);
+        }
+        for composite in constraints.composite.get(table).unwrap_or(&vec![]) {
+            descriptions.insert(
+                format!(
+                    "{} ({})",
+                    match composite.key_type.as_str() {
+                        "primary" => "PRIMARY KEY",
+                        _ => "UNIQUE",
+                    },
+                    composite.columns.join(", ")
+                ),
+                true,
+            );
+        }
+        for fkey in constraints.foreign.get(table).unwrap_or(&vec![]) {
+            descriptions.insert(
+                format!(
+                    "FOREIGN KEY ({}) REFERENCES {}({})",
+                    fkey.column, fkey.ftable, fkey.fcolumn
+                ),
+                true,
+            );
+        }
+        for fkey in constraints.composite_foreign.get(table).unwrap_or(&vec![]) {
+            descriptions.insert(
+                format!(
+                    "FOREIGN KEY ({}) REFERENCES {}({})",
+                    fkey.columns.join(", "),
+                    fkey.ftable,
+                    fkey.fcolumns.join(", ")
+                ),
+                true,
             );
-        } else {
-            path = Some(this_table.path.to_string())
         }
+        for tree in constraints.tree.get(table).unwrap_or(&vec![]) {
+            descriptions.insert(
+                format!("TREE ({}) CHILD ({})", tree.parent, tree.child),
+                false,
+            );
+        }
+        descriptions
+    }
 
-        // Constraints on internal tables do not need to be configured explicitly:
-        if this_table.options.contains("internal") {
-            continue;
+    // Returns the rules of the given table in the given configuration, including its script rules,
+    // as a map from the rules' identifiers to maps from the names of their fields to the values of
+    // those fields. A rule without an explicit id is identified by its contents.
+    fn get_rules(config: &ValveConfig, table: &str) -> IndexMap<String, IndexMap<String, String>> {
+        let mut rules = vec![];
+        for rule in config
+            .rule
+            .get(table)
+            .map(|r| r.values().flatten())
+            .into_iter()
+            .flatten()
+        {
+            let mut when = vec![(
+                rule.when_column.to_string(),
+                rule.when_condition.to_string(),
+            )];
+            when.append(&mut rule.additional_when.clone());
+            let when = when
+                .iter()
+                .map(|(column, condition)| format!("{} {}", column, condition))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let then = format!("{} {}", rule.then_column, rule.then_condition);
+            let fields = IndexMap::from([
+                ("when".to_string(), when.to_string()),
+                ("then".to_string(), then.to_string()),
+                ("level".to_string(), rule.level.to_string()),
+                ("description".to_string(), rule.description.to_string()),
+            ]);
+            let id = match rule.id.as_str() {
+                "" => format!("when {} then {} ({})", when, then, rule.level),
+                id => id.to_string(),
+            };
+            rules.push((id, fields));
+        }
+        for rule in config.script_rule.get(table).unwrap_or(&vec![]) {
+            let fields = IndexMap::from([
+                ("script".to_string(), rule.script.to_string()),
+                ("level".to_string(), rule.level.to_string()),
+                ("description".to_string(), rule.description.to_string()),
+            ]);
+            let id = match rule.id.as_str() {
+                "" => format!("script {} ({})", rule.script, rule.level),
+                id => id.to_string(),
+            };
+            rules.push((id, fields));
+        }
+        rules.sort_by(|a, b| a.0.cmp(&b.0));
+        rules.into_iter().collect()
+    }
+
+    let mut changes = vec![];
+
+    // 1. Compare the tables, their columns, and their constraints:
+    let tables = merge_names(&old.table_order, &new.table_order)
+        .into_iter()
+        .filter(|table| !INTERNAL_TABLES.contains(&table.as_str()))
+        .collect::<Vec<_>>();
+    for table in &tables {
+        let (old_table, new_table) = match (old.table.get(table), new.table.get(table)) {
+            (Some(old_table), Some(new_table)) => (old_table, new_table),
+            (Some(_), None) => {
+                changes.push(describe("table", table, "removed", "", "", ""));
+                continue;
+            }
+            (None, Some(_)) => {
+                let mut change = describe("table", table, "added", "", "", "");
+                change.rebuilds = vec![table.to_string()];
+                change.revalidates = vec![table.to_string()];
+                changes.push(change);
+                continue;
+            }
+            (None, None) => continue,
+        };
+
+        let old_options = old_table.options.iter().sorted().join(" ");
+        let new_options = new_table.options.iter().sorted().join(" ");
+        for (field, old_value, new_value) in [
+            (
+                "type",
+                old_table.table_type.to_string(),
+                new_table.table_type.to_string(),
+            ),
+            (
+                "path",
+                old_table.path.to_string(),
+                new_table.path.to_string(),
+            ),
+            (
+                "description",
+                old_table.description.to_string(),
+                new_table.description.to_string(),
+            ),
+            ("options", old_options, new_options),
+            (
+                "column_order",
+                old_table.column_order.join(", "),
+                new_table.column_order.join(", "),
+            ),
+        ] {
+            if old_value != new_value {
+                let mut change = describe("table", table, "changed", field, &old_value, &new_value);
+                // A table that becomes a view, or vice versa, must be recreated:
+                if field == "options"
+                    && old_table.options.contains("db_view")
+                        != new_table.options.contains("db_view")
+                {
+                    change.rebuilds = vec![table.to_string()];
+                }
+                changes.push(change);
+            }
         }
 
-        let this_column_config = &this_table.column;
-        let defined_labels = this_column_config
-            .iter()
-            .map(|(k, v)| {
-                if v.label != "" {
-                    v.label.to_string()
-                } else {
-                    k.to_string()
+        for column in merge_names(&old_table.column_order, &new_table.column_order) {
+            let item = format!("{}.{}", table, column);
+            let (old_column, new_column) =
+                match (old_table.column.get(&column), new_table.column.get(&column)) {
+                    (Some(old_column), Some(new_column)) => (old_column, new_column),
+                    (old_column, new_column) => {
+                        let mut change = describe(
+                            "column",
+                            &item,
+                            match new_column {
+                                Some(_) => "added",
+                                None => "removed",
+                            },
+                            "",
+                            "",
+                            "",
+                        );
+                        if old_column.is_some() || new_column.is_some() {
+                            change.rebuilds = vec![table.to_string()];
+                            change.revalidates = vec![table.to_string()];
+                            changes.push(change);
+                        }
+                        continue;
+                    }
+                };
+            for (field, old_value, new_value) in [
+                ("datatype", &old_column.datatype, &new_column.datatype),
+                ("nulltype", &old_column.nulltype, &new_column.nulltype),
+                ("structure", &old_column.structure, &new_column.structure),
+                ("transform", &old_column.transform, &new_column.transform),
+                (
+                    "default",
+                    &old_column.default.to_string(),
+                    &new_column.default.to_string(),
+                ),
+                ("label", &old_column.label, &new_column.label),
+                (
+                    "description",
+                    &old_column.description,
+                    &new_column.description,
+                ),
+            ] {
+                if old_value == new_value {
+                    continue;
+                }
+                let mut change = describe("column", &item, "changed", field, old_value, new_value);
+                if ["datatype", "nulltype", "structure", "transform"].contains(&field) {
+                    change.revalidates = vec![table.to_string()];
+                }
+                if field == "datatype"
+                    && resolve_sql_type(&old.datatype, old_value).to_lowercase()
+                        != resolve_sql_type(&new.datatype, new_value).to_lowercase()
+                {
+                    change.rebuilds = vec![table.to_string()];
+                }
+                changes.push(change);
+            }
+        }
+
+        let old_constraints = get_constraints(old, table);
+        let new_constraints = get_constraints(new, table);
+        for (constraint, is_db_constraint) in &old_constraints {
+            if !new_constraints.contains_key(constraint) {
+                let mut change = describe("constraint", table, "removed", "", constraint, "");
+                if *is_db_constraint {
+                    change.rebuilds = vec![table.to_string()];
+                }
+                change.revalidates = vec![table.to_string()];
+                changes.push(change);
+            }
+        }
+        for (constraint, is_db_constraint) in &new_constraints {
+            if !old_constraints.contains_key(constraint) {
+                let mut change = describe("constraint", table, "added", "", "", constraint);
+                if *is_db_constraint {
+                    change.rebuilds = vec![table.to_string()];
+                }
+                change.revalidates = vec![table.to_string()];
+                changes.push(change);
+            }
+        }
+    }
+
+    // 2. Compare the datatypes:
+    let datatypes = old
+        .datatype
+        .keys()
+        .chain(new.datatype.keys())
+        .unique()
+        .sorted()
+        .collect::<Vec<_>>();
+    for datatype in datatypes {
+        // The tables affected by a change to a datatype are those whose columns or rules depend on
+        // the datatype in either the old or the new configuration:
+        let get_revalidated_tables = || -> Vec<String> {
+            let old_tables = get_affected_tables(
+                old,
+                parser,
+                &get_dependent_datatypes(old, parser, datatype, true),
+            );
+            let new_tables = get_affected_tables(
+                new,
+                parser,
+                &get_dependent_datatypes(new, parser, datatype, true),
+            );
+            merge_names(&old_tables, &new_tables)
+        };
+        // The tables that are rebuilt as a result of a change to a datatype are those which have
+        // a column whose SQL type, which may be inherited from the datatype, has changed:
+        let get_rebuilt_tables = || -> Vec<String> {
+            let descendants = get_dependent_datatypes(old, parser, datatype, false)
+                .union(&get_dependent_datatypes(new, parser, datatype, false))
+                .cloned()
+                .collect::<HashSet<_>>();
+            tables
+                .iter()
+                .filter(|table| {
+                    let old_columns = old.table.get(*table).map(|t| &t.column);
+                    let new_columns = new.table.get(*table).map(|t| &t.column);
+                    match (old_columns, new_columns) {
+                        (Some(old_columns), Some(new_columns)) => {
+                            old_columns.iter().any(|(column, old_column)| {
+                                match new_columns.get(column) {
+                                    Some(new_column)
+                                        if descendants.contains(&old_column.datatype)
+                                            || descendants.contains(&new_column.datatype) =>
+                                    {
+                                        resolve_sql_type(&old.datatype, &old_column.datatype)
+                                            .to_lowercase()
+                                            != resolve_sql_type(&new.datatype, &new_column.datatype)
+                                                .to_lowercase()
+                                    }
+                                    _ => false,
+                                }
+                            })
+                        }
+                        _ => false,
+                    }
+                })
+                .cloned()
+                .collect()
+        };
+
+        let (old_datatype, new_datatype) =
+            match (old.datatype.get(datatype), new.datatype.get(datatype)) {
+                (Some(old_datatype), Some(new_datatype)) => (old_datatype, new_datatype),
+                (_, new_datatype) => {
+                    let mut change = describe(
+                        "datatype",
+                        datatype,
+                        match new_datatype {
+                            Some(_) => "added",
+                            None => "removed",
+                        },
+                        "",
+                        "",
+                        "",
+                    );
+                    change.revalidates = get_revalidated_tables();
+                    changes.push(change);
+                    continue;
                 }
-            })
-            .collect::<Vec<_>>();
+            };
+        for (field, old_value, new_value) in [
+            ("parent", &old_datatype.parent, &new_datatype.parent),
+            (
+                "condition",
+                &old_datatype.condition,
+                &new_datatype.condition,
+            ),
+            ("sql_type", &old_datatype.sql_type, &new_datatype.sql_type),
+            (
+                "transform",
+                &old_datatype.transform,
+                &new_datatype.transform,
+            ),
+            (
+                "description",
+                &old_datatype.description,
+                &new_datatype.description,
+            ),
+        ] {
+            if old_value == new_value {
+                continue;
+            }
+            let mut change = describe("datatype", datatype, "changed", field, old_value, new_value);
+            if field != "description" {
+                change.revalidates = get_revalidated_tables();
+            }
+            if field == "parent" || field == "sql_type" {
+                change.rebuilds = get_rebuilt_tables();
+            }
+            changes.push(change);
+        }
+    }
 
-        // We use column_order to explicitly indicate the order in which the columns should appear
-        // in the table, for later reference. The default is to preserve the order from the actual
-        // table file. If that does not exist, we use the ordering in defined_labels.
-        let mut column_order = vec![];
-        match path {
-            Some(path) if path.to_lowercase().ends_with(".tsv") => {
-                // Get the actual columns from the data itself. Note that we set has_headers to
-                // false (even though the files have header rows) in order to explicitly read the
-                // header row.
-                let mut rdr = ReaderBuilder::new()
-                    .has_headers(false)
-                    .delimiter(b'\t')
-                    .from_reader(File::open(path.clone()).map_err(|err| {
-                        ValveError::ConfigError(format!(
-                            "Unable to open '{}': {}",
-                            path.clone(),
-                            err
-                        ))
-                    })?);
-                let mut iter = rdr.records();
-                if let Some(result) = iter.next() {
-                    let actual_labels = result
-                        .map_err(|e| {
-                            ValveError::ConfigError(format!(
-                                "Unable to read row from '{}': {}",
-                                path, e
-                            ))
-                        })?
-                        .iter()
-                        .map(|c| c.to_string())
-                        .collect::<Vec<_>>();
-                    // Make sure that the actual columns found in the table file are all defined
-                    // in the column configuration:
-                    for label_name in &actual_labels {
-                        if !defined_labels.contains(&&label_name.to_string()) {
-                            return Err(ValveError::ConfigError(format!(
-                                "Label '{}' of table '{}' not in column config",
-                                label_name, table_name
-                            ))
-                            .into());
+    // 3. Compare the rules of each table:
+    for table in &tables {
+        let old_rules = get_rules(old, table);
+        let new_rules = get_rules(new, table);
+        for id in merge_names(
+            &old_rules.keys().cloned().collect::<Vec<_>>(),
+            &new_rules.keys().cloned().collect::<Vec<_>>(),
+        ) {
+            let item = format!("{}: {}", table, id);
+            match (old_rules.get(&id), new_rules.get(&id)) {
+                (Some(old_fields), Some(new_fields)) => {
+                    for (field, new_value) in new_fields {
+                        let old_value = old_fields.get(field).cloned().unwrap_or_default();
+                        if old_value != *new_value {
+                            let mut change =
+                                describe("rule", &item, "changed", field, &old_value, new_value);
+                            if field != "description" {
+                                change.revalidates = vec![table.to_string()];
+                            }
+                            changes.push(change);
                         }
                     }
-                    // The defined_labels are what will be used to create the table's columns. In
-                    // the case where a defined_label is not also an actual_label.
-                    for label_name in &defined_labels {
-                        let column_name =
-                            get_column_for_label(&this_column_config, label_name, &table_name)?;
-                        column_order.push(column_name);
-                    }
-                } else {
-                    return Err(ValveError::ConfigError(format!("'{}' is empty", path)).into());
                 }
-            }
-            _ => (),
-        };
-
-        // If for some reason we were unable to determine the column order, then use the order
-        // defined in the column table:
-        if column_order.is_empty() {
-            match defined_column_orderings.get(&table_name) {
-                Some(order) => column_order = order.to_vec(),
-                _ => {
-                    return Err(ValveError::DataError(format!(
-                        "Table {} not found in {:?}",
-                        table_name, defined_column_orderings
-                    ))
-                    .into());
+                (_, new_fields) => {
+                    let mut change = describe(
+                        "rule",
+                        &item,
+                        match new_fields {
+                            Some(_) => "added",
+                            None => "removed",
+                        },
+                        "",
+                        "",
+                        "",
+                    );
+                    change.revalidates = vec![table.to_string()];
+                    changes.push(change);
                 }
             };
         }
-        tables_config
-            .get_mut(&table_name)
-            .and_then(|t| Some(t.column_order = column_order));
-
-        // Populate the table constraints for this table:
-        let (primaries, uniques, foreigns, trees) = get_table_constraints(
-            &tables_config,
-            &datatypes_config,
-            parser,
-            &table_name,
-            &pool,
-        );
-
-        constraints_config
-            .primary
-            .insert(table_name.to_string(), primaries);
-        constraints_config
-            .unique
-            .insert(table_name.to_string(), uniques);
-        constraints_config
-            .foreign
-            .insert(table_name.to_string(), foreigns);
-        constraints_config
-            .tree
-            .insert(table_name.to_string(), trees);
     }
 
-    // 6. Add implicit unique constraints for trees and foreign keys:
-    for (table, _) in &tables_config {
-        let table_trees = constraints_config
-            .tree
-            .get(table)
-            .expect(&format!("No tree constraints found for table '{}'", table));
-        let table_uniques = constraints_config.unique.get_mut(table).expect(&format!(
-            "No unique constraints found for table '{}'",
-            table
-        ));
-        let table_primaries = constraints_config.primary.get(table).expect(&format!(
-            "No primary constraints found for table '{}'",
-            table
-        ));
-        for tree in table_trees {
-            if !table_uniques.contains(&tree.child) && !table_primaries.contains(&tree.child) {
-                log::warn!(
-                    "Table '{}' has a tree defined on column '{}' which therefore requires \
-                     a UNIQUE constraint. It will be implicitly created.",
-                    table,
-                    tree.child
-                );
-                table_uniques.push(tree.child.to_string());
-            }
+    // 4. Compare the prefixes. A change to a prefix affects the tables whose columns or rules
+    // depend on a datatype with a curie() condition:
+    let prefixes = old
+        .prefix
+        .keys()
+        .chain(new.prefix.keys())
+        .unique()
+        .sorted()
+        .collect::<Vec<_>>();
+    for prefix in prefixes {
+        let old_base = old.prefix.get(prefix);
+        let new_base = new.prefix.get(prefix);
+        if old_base == new_base {
+            continue;
         }
-
-        let table_foreigns = constraints_config.foreign.get(table).expect(&format!(
-            "No foreign constraints found for table '{}'",
-            table
-        ));
-        for foreign in table_foreigns {
-            let ftable = &foreign.ftable;
-            let funiques = constraints_config.unique.get_mut(ftable).expect(&format!(
-                "No unique constraints found for table '{}'",
-                ftable
-            ));
-            let fprimaries = constraints_config.primary.get(ftable).expect(&format!(
-                "No primary constraints found for table '{}'",
-                ftable
-            ));
-            let fcolumn = &foreign.fcolumn;
-            if !funiques.contains(fcolumn) && !fprimaries.contains(fcolumn) {
-                log::warn!(
-                    "Column '{}.{}' is a foreign key for table '{}' and therefore requires \
-                     a UNIQUE constraint. It will be implicitly created.",
-                    ftable,
-                    fcolumn,
-                    table,
-                );
-                funiques.push(fcolumn.to_string());
+        let mut change = match (old_base, new_base) {
+            (Some(old_base), Some(new_base)) => {
+                describe("prefix", prefix, "changed", "base", old_base, new_base)
             }
+            (None, _) => describe("prefix", prefix, "added", "", "", ""),
+            (_, None) => describe("prefix", prefix, "removed", "", "", ""),
+        };
+        let mut revalidates = vec![];
+        for config in [old, new] {
+            let curie_datatypes = config
+                .datatype
+                .values()
+                .filter(|dt| dt.condition.contains("curie("))
+                .flat_map(|dt| get_dependent_datatypes(config, parser, &dt.datatype, true))
+                .collect::<HashSet<_>>();
+            revalidates = merge_names(
+                &revalidates,
+                &get_affected_tables(config, parser, &curie_datatypes),
+            );
         }
+        change.revalidates = revalidates;
+        changes.push(change);
     }
 
-    // 7. Add internal table configuration to the table config:
-    for table in INTERNAL_TABLES.iter() {
-        tables_config.insert(table.to_string(), generate_internal_table_config(table));
-        table_order.push(table.to_string());
+    // A view is never recreated, since it is not a table in the database:
+    for change in changes.iter_mut() {
+        change.rebuilds.retain(|table| {
+            !new.table
+                .get(table)
+                .is_some_and(|t| t.options.contains("db_view"))
+        });
     }
+    changes
+}
 
-    // 8. Sort the tables (other than internal tables) according to their foreign key
-    // dependencies so that tables are always loaded after the tables they depend on.
-    let (sorted_tables, table_dependencies_in, table_dependencies_out) = verify_table_deps_and_sort(
-        &table_order
+/// Given a parsed condition, return the names of the datatypes that are referred to by it, i.e.,
+/// either directly, in the case of a condition that is a datatype label, or in the arguments of
+/// the functions list(), any(), all(), and not().
+fn get_referenced_datatypes(expression: &Expression) -> Vec<String> {
+    match expression {
+        Expression::Label(label) => vec![label.to_string()],
+        Expression::Function(name, args) if name == "list" && !args.is_empty() => match &*args[0] {
+            Expression::Label(label) => vec![label.to_string()],
+            _ => vec![],
+        },
+        Expression::Function(name, args) if ["any", "all", "not"].contains(&name.as_str()) => args
             .iter()
-            .cloned()
-            // Internal tables will be taken account of within verify_table_deps_and_sort() and
-            // manually added to the sorted table list that is returned there.
-            .filter(|m| !INTERNAL_TABLES.contains(&m.to_string().as_str()))
-            .collect::<Vec<_>>(),
-        &constraints_config,
-    );
-
-    // 9. Finally, return all the configs:
-    Ok((
-        specials_config,
-        tables_config,
-        table_order,
-        datatypes_config,
-        rules_config,
-        constraints_config,
-        sorted_tables,
-        table_dependencies_in,
-        table_dependencies_out,
-        startup_table_messages,
-    ))
+            .flat_map(|arg| get_referenced_datatypes(arg))
+            .collect(),
+        _ => vec![],
+    }
 }
 
-/// Given the global configuration struct and a parser, compile all of the datatype conditions,
-/// add them to a hash map whose keys are the text versions of the conditions and whose values
-/// are the compiled conditions, and then finally return the hash map.
+/// Given the global configuration struct, a parser, a database connection pool, the locations of
+/// the configured items in the configuration files, and a list of issues, compile all of the
+/// datatype conditions, add them to a hash map whose keys are the text versions of the conditions
+/// and whose values are the compiled conditions, and then finally return the hash map. Datatypes
+/// whose conditions cannot be compiled are left out of the hash map and the problems found with
+/// them are added to the list of issues.
 pub fn generate_datatype_conditions(
     config: &ValveConfig,
     parser: &StartParser,
-) -> Result<HashMap<String, CompiledCondition>> {
-    // We go through the datatypes one by one, compiling any non-list types first and saving the
-    // list types, which are higher level types that refer to non-list types, for later.
-    let mut saved_for_last = HashMap::new();
-    let mut datatype_conditions = HashMap::new();
-    for (dt_name, dt_config) in config.datatype.iter() {
-        let condition = &dt_config.condition;
+    pool: &AnyPool,
+    locations: &ConfigLocations,
+    issues: &mut Vec<ValveConfigIssue>,
+) -> HashMap<String, CompiledCondition> {
+    // Since a datatype's condition may refer to other datatypes, we go through the datatypes in a
+    // number of passes, compiling, in each pass, those datatypes whose referenced datatypes have
+    // all been compiled in a previous pass, and saving the others for a later pass. A datatype
+    // whose condition refers to a datatype whose condition could not be compiled is skipped, since
+    // the problem has already been reported. Note that parse errors are ignored here, since they
+    // will be reported by compile_condition().
+    let mut datatype_names = config.datatype.keys().collect::<Vec<_>>();
+    datatype_names.sort_by_key(|dt_name| (locations.datatype.get(*dt_name), *dt_name));
+    let mut remaining = vec![];
+    for dt_name in datatype_names {
+        let condition = &config.datatype.get(dt_name).unwrap().condition;
         if condition != "" {
-            if condition.starts_with("list(") {
-                saved_for_last.insert(dt_name, dt_config);
-            } else {
-                let compiled_condition =
-                    compile_condition(condition, parser, &datatype_conditions)?;
-                datatype_conditions.insert(dt_name.to_string(), compiled_condition);
-            }
+            let referenced_datatypes = match parser.parse(condition) {
+                Ok(parsed) => parsed
+                    .iter()
+                    .flat_map(|p| get_referenced_datatypes(p))
+                    .filter(|dt| {
+                        config
+                            .datatype
+                            .get(dt)
+                            .map(|d| !d.condition.is_empty())
+                            .unwrap_or(false)
+                    })
+                    .collect::<Vec<_>>(),
+                Err(_) => vec![],
+            };
+            remaining.push((dt_name, condition, referenced_datatypes));
         }
     }
-    for (dt_name, dt_config) in saved_for_last.iter() {
-        let condition = &dt_config.condition;
-        let compiled_condition = compile_condition(&condition, parser, &datatype_conditions)?;
-        datatype_conditions.insert(dt_name.to_string(), compiled_condition);
+
+    let mut datatype_conditions = HashMap::new();
+    let mut failed_datatypes = HashSet::new();
+    while !remaining.is_empty() {
+        let (ready, not_ready): (Vec<_>, Vec<_>) =
+            remaining.into_iter().partition(|(_, _, referenced)| {
+                referenced
+                    .iter()
+                    .all(|dt| datatype_conditions.contains_key(dt) || failed_datatypes.contains(dt))
+            });
+        if ready.is_empty() {
+            let names = not_ready
+                .iter()
+                .map(|(dt_name, _, _)| format!("'{}'", dt_name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            for (dt_name, _, _) in &not_ready {
+                issues.push(locations.datatype_issue(
+                    dt_name,
+                    "condition",
+                    "error",
+                    &format!(
+                        "Circular references between the conditions of the datatypes: {}",
+                        names
+                    ),
+                ));
+            }
+            break;
+        }
+        for (dt_name, condition, referenced) in ready {
+            if referenced.iter().any(|dt| failed_datatypes.contains(dt)) {
+                failed_datatypes.insert(dt_name.to_string());
+                continue;
+            }
+            let sql_type = get_sql_type(&config.datatype, dt_name, pool);
+            match compile_condition(
+                condition,
+                parser,
+                &datatype_conditions,
+                &sql_type,
+                None,
+                &config.prefix,
+            ) {
+                Ok(compiled_condition) => {
+                    datatype_conditions.insert(dt_name.to_string(), compiled_condition);
+                }
+                Err(e) => {
+                    issues.push(locations.datatype_issue(
+                        dt_name,
+                        "condition",
+                        "error",
+                        &describe_error(&e),
+                    ));
+                    failed_datatypes.insert(dt_name.to_string());
+                }
+            };
+        }
+        remaining = not_ready;
     }
-    Ok(datatype_conditions)
+    datatype_conditions
 }
 
 /// Given the global config struct, a hash map of compiled datatype conditions (indexed by the text
-/// versions of the conditions), and a parser, compile all of the rule conditions, add them to a
-/// hash which has the following structure and return it:
+/// versions of the conditions), a parser, a database connection pool, the locations of the
+/// configured items in the configuration files, and a list of issues, compile all of the rule
+/// conditions, add them to a hash which has the following structure and return it:
 /// ```
 /// {
 ///      table_1: {
@@ -1368,54 +3247,111 @@ pub fn generate_datatype_conditions(
 ///      ...
 /// }
 /// ```
+/// Rules whose conditions cannot be compiled are left out of the hash and the problems found with
+/// them are added to the list of issues.
 pub fn generate_rule_conditions(
     config: &ValveConfig,
     datatype_conditions: &HashMap<String, CompiledCondition>,
     parser: &StartParser,
-) -> Result<HashMap<String, HashMap<String, Vec<ColumnRule>>>> {
+    pool: &AnyPool,
+    locations: &ConfigLocations,
+    issues: &mut Vec<ValveConfigIssue>,
+) -> HashMap<String, HashMap<String, Vec<ColumnRule>>> {
     let mut rule_conditions = HashMap::new();
     let tables_config = &config.table;
-    let rules_config = &config.rule;
-    for (rules_table, table_rules) in rules_config.iter() {
+    let mut rules = vec![];
+    for (rules_table, table_rules) in config.rule.iter() {
         for (column_rule_key, column_rules) in table_rules.iter() {
-            for rule in column_rules {
-                let table_columns = tables_config
-                    .get(rules_table)
-                    .unwrap()
-                    .column
-                    .keys()
-                    .collect::<Vec<_>>();
-                for column in vec![&rule.when_column, &rule.then_column] {
-                    if !table_columns.contains(&column) {
-                        return Err(ValveError::DataError(format!(
-                            "Undefined column '{}.{}' in rules table",
-                            rules_table, column
-                        ))
-                        .into());
-                    }
-                }
-                let when_compiled =
-                    compile_condition(&rule.when_condition, parser, &datatype_conditions)?;
-                let then_compiled =
-                    compile_condition(&rule.then_condition, parser, &datatype_conditions)?;
-
-                if !rule_conditions.contains_key(rules_table) {
-                    let table_rules = HashMap::new();
-                    rule_conditions.insert(rules_table.to_string(), table_rules);
-                }
-                let table_rules = rule_conditions.get_mut(rules_table).unwrap();
-                if !table_rules.contains_key(column_rule_key) {
-                    table_rules.insert(column_rule_key.to_string(), vec![]);
-                }
-                let column_rules = table_rules.get_mut(column_rule_key).unwrap();
-                column_rules.push(ColumnRule {
-                    when: when_compiled,
-                    then: then_compiled,
-                });
+            for (i, rule) in column_rules.iter().enumerate() {
+                rules.push((rules_table, column_rule_key, i, rule));
             }
         }
     }
-    Ok(rule_conditions)
+    // Go through the rules in the order in which they have been configured, so that any problems
+    // with them are reported in that order:
+    rules.sort_by_key(|(table, column, i, _)| {
+        (
+            locations
+                .rule
+                .get(&(table.to_string(), column.to_string(), *i)),
+            *table,
+            *column,
+            *i,
+        )
+    });
+    'rules: for (rules_table, column_rule_key, i, rule) in rules {
+        let table_config = tables_config.get(rules_table).unwrap();
+        let table_columns = table_config.column.keys().collect::<Vec<_>>();
+        let mut to_compile = vec![
+            ("when", &rule.when_column, &rule.when_condition),
+            ("then", &rule.then_column, &rule.then_condition),
+        ];
+        for (column, condition) in &rule.additional_when {
+            to_compile.push(("when", column, condition));
+        }
+        let mut has_undefined_column = false;
+        for (kind, column, _) in &to_compile {
+            if !table_columns.contains(column) {
+                issues.push(locations.rule_issue(
+                    rules_table,
+                    column_rule_key,
+                    i,
+                    &format!("{} column", kind),
+                    "error",
+                    &format!(
+                        "Undefined column '{}.{}' in rules table",
+                        rules_table, column
+                    ),
+                ));
+                has_undefined_column = true;
+            }
+        }
+        if has_undefined_column {
+            continue;
+        }
+
+        let mut compiled = vec![];
+        for (kind, column, condition) in &to_compile {
+            let sql_type = get_sql_type_from_global_config(config, rules_table, column, pool);
+            match compile_condition(
+                condition,
+                parser,
+                datatype_conditions,
+                &sql_type,
+                Some(table_config),
+                &config.prefix,
+            ) {
+                Ok(compiled_condition) => compiled.push(compiled_condition),
+                Err(e) => {
+                    issues.push(locations.rule_issue(
+                        rules_table,
+                        column_rule_key,
+                        i,
+                        &format!("{} condition", kind),
+                        "error",
+                        &describe_error(&e),
+                    ));
+                    continue 'rules;
+                }
+            };
+        }
+        let mut compiled = compiled.into_iter();
+        let when_compiled = compiled.next().unwrap();
+        let then_compiled = compiled.next().unwrap();
+        let additional_when_compiled = compiled.collect::<Vec<_>>();
+
+        rule_conditions
+            .entry(rules_table.to_string())
+            .or_insert(HashMap::new())
+            .entry(column_rule_key.to_string())
+            .or_insert(vec![])
+            .push(ColumnRule {
+                when: when_compiled,
+                additional_when: additional_when_compiled,
+                then: then_compiled,
+            });
+    }
+    rule_conditions
 }
 
 /// Given the global config struct and a parser, parse all of the structure conditions, add them to
@@ -1453,11 +3389,32 @@ pub fn get_parsed_structure_conditions(
     Ok(parsed_structure_conditions)
 }
 
-/// Given the name of a table and a database connection pool, generate SQL for creating a view
-/// based on the table that provides a unified representation of the normal and conflict versions
-/// of the table, plus columns summarising the information associated with the given table that is
-/// contained in the message and history tables.
-pub fn get_sql_for_standard_view(table: &str, pool: &AnyPool) -> String {
+/// Given the tables configuration map, the name of a table and a database connection pool,
+/// generate SQL for creating a view based on the table that provides a unified representation of
+/// the normal and conflict versions of the table, plus columns summarising the information
+/// associated with the given table that is contained in the message and history tables. The
+/// columns of the view are listed in their configured order, which need not be the order in which
+/// they are stored in the database (see [Valve::migrate()](crate::valve::Valve::migrate())).
+pub fn get_sql_for_standard_view(
+    tables_config: &HashMap<String, ValveTableConfig>,
+    table: &str,
+    pool: &AnyPool,
+) -> String {
+    let columns = {
+        let mut columns = vec![r#""row_number""#.to_string(), r#""row_order""#.to_string()];
+        let column_order = &tables_config
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table))
+            .column_order;
+        columns.append(
+            &mut column_order
+                .iter()
+                .map(|c| format!(r#""{}""#, c))
+                .collect::<Vec<_>>(),
+        );
+        columns.join(", ")
+    };
+
     let message_t;
     if pool.any_kind() == AnyKind::Postgres {
         message_t = format!(
@@ -1547,12 +3504,13 @@ pub fn get_sql_for_standard_view(table: &str, pool: &AnyPool) -> String {
               {message_t} AS "message",
               {history_t} AS "history"
             FROM (
-              SELECT * FROM "{t}"
+              SELECT {columns} FROM "{t}"
               UNION ALL
-              SELECT * FROM "{t}_conflict"
+              SELECT {columns} FROM "{t}_conflict"
             ) as union_t;
         "#},
         t = table,
+        columns = columns,
         message_t = message_t,
         history_t = history_t,
     );
@@ -1579,7 +3537,7 @@ pub fn get_sql_for_text_view(
 
     let real_columns = &tables_config
         .get(table)
-        .and_then(|t| Some(t.column.keys().map(|k| k.to_string()).collect::<Vec<_>>()))
+        .and_then(|t| Some(t.column_order.clone()))
         .expect(&format!("Undefined table '{}'", table));
 
     // Add a second "text view" such that the datatypes of all values are TEXT and appear
@@ -1781,9 +3739,61 @@ pub async fn get_affected_rows(
         ),
     );
     sql_params.push(value.to_string());
+    fetch_valve_rows(&sql, &sql_params, tx).await
+}
 
-    let mut query = sqlx_query(&sql);
-    for param in &sql_params {
+/// Given a table name, a tree constraint whose parent column has a list() datatype, a value, a
+/// row number to ignore (optionally), a global config map, a database connection pool, and a
+/// database transaction, return the rows of the table for which one of the items in the tree's
+/// parent column is the given value.
+pub async fn get_affected_rows_by_list_item(
+    table: &str,
+    tree: &ValveTreeConstraint,
+    value: &str,
+    except: Option<&u32>,
+    config: &ValveConfig,
+    pool: &AnyPool,
+    tx: &mut Transaction<'_, sqlx::Any>,
+) -> Result<Vec<ValveRow>> {
+    // We use LIKE to narrow down the rows that could contain the value, and then check the items
+    // of each of those rows explicitly:
+    let (sql, mut sql_params) = generic_select_with_message_values(table, config, pool);
+    let sql = local_sql_syntax(
+        pool,
+        &format!(
+            r#"{sql} WHERE "{column}" LIKE {SQL_PARAM}{except}"#,
+            column = tree.parent,
+            except = match except {
+                None => "".to_string(),
+                Some(row_number) => {
+                    format!(" AND row_number != {}", row_number)
+                }
+            },
+        ),
+    );
+    sql_params.push(format!("%{}%", value));
+    let valve_rows = fetch_valve_rows(&sql, &sql_params, tx)
+        .await?
+        .into_iter()
+        .filter(|row| match row.contents.get(&tree.parent) {
+            Some(cell) => {
+                get_tree_parent_values(tree, &cell.strvalue()).contains(&value.to_string())
+            }
+            None => false,
+        })
+        .collect();
+    Ok(valve_rows)
+}
+
+/// Given a SQL query generated using [generic_select_with_message_values()], its parameters, and a
+/// database transaction, run the query and return the results as [ValveRow]s.
+async fn fetch_valve_rows(
+    sql: &str,
+    sql_params: &Vec<String>,
+    tx: &mut Transaction<'_, sqlx::Any>,
+) -> Result<Vec<ValveRow>> {
+    let mut query = sqlx_query(sql);
+    for param in sql_params {
         query = query.bind(param);
     }
     let mut valve_rows = vec![];
@@ -1957,8 +3967,13 @@ pub async fn get_db_value(
         )
         .into());
     }
+    // A value that is NULL, e.g., because it is empty and has a nulltype, is represented by an
+    // empty string:
     let result_row = &rows[0];
-    let value: &str = result_row.try_get(column).unwrap();
+    let value = result_row
+        .try_get::<&str, &str>(column)
+        .ok()
+        .unwrap_or_default();
     Ok(value.to_string())
 }
 
@@ -2000,7 +4015,23 @@ pub async fn get_rows_to_update(
         for (_, fconstraints) in global_fconstraints {
             for entry in fconstraints {
                 if entry.ftable == *table {
-                    foreign_dependencies.push(entry);
+                    foreign_dependencies.push(entry.clone());
+                }
+            }
+        }
+        // In the case of a foreign key on more than one column, the rows that share a value with
+        // the given row in the first column of the key are a superset of the rows that may be
+        // affected, so we treat the key as a foreign key on its first column:
+        let global_composite_fconstraints = &config.constraint.composite_foreign;
+        for fconstraints in global_composite_fconstraints.values() {
+            for entry in fconstraints {
+                if entry.ftable == *table {
+                    foreign_dependencies.push(ValveForeignConstraint {
+                        table: entry.table.to_string(),
+                        column: entry.columns[0].to_string(),
+                        ftable: entry.ftable.to_string(),
+                        fcolumn: entry.fcolumns[0].to_string(),
+                    });
                 }
             }
         }
@@ -2082,8 +4113,24 @@ pub async fn get_rows_to_update(
                 .await?
             }
         };
-        rows_to_update_before.insert(dependent_table.to_string(), updates_before);
-        rows_to_update_after.insert(dependent_table.to_string(), updates_after);
+        // A dependent table may have more than one foreign key on the given table, so we add
+        // the rows found for this one to those (if any) that have already been found for the others:
+        for (rows_to_update, updates) in [
+            (&mut rows_to_update_before, updates_before),
+            (&mut rows_to_update_after, updates_after),
+        ] {
+            let dependent_rows = rows_to_update
+                .entry(dependent_table.to_string())
+                .or_insert(vec![]);
+            for update in updates {
+                if !dependent_rows
+                    .iter()
+                    .any(|r: &ValveRow| r.row_number == update.row_number)
+                {
+                    dependent_rows.push(update);
+                }
+            }
+        }
     }
 
     let primaries = config
@@ -2096,6 +4143,16 @@ pub async fn get_rows_to_update(
         .unique
         .get(table)
         .expect(&format!("Undefined table '{}'", table));
+    // Rows that share a value with the given row in any column of a multi-column key may also
+    // need to be updated:
+    let composite_columns = config
+        .constraint
+        .composite
+        .get(table)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table))
+        .iter()
+        .flat_map(|c| c.columns.clone())
+        .collect::<Vec<_>>();
     let columns = config
         .table
         .get(table)
@@ -2106,7 +4163,10 @@ pub async fn get_rows_to_update(
         .collect::<Vec<_>>();
 
     for column in &columns {
-        if !uniques.contains(column) && !primaries.contains(column) {
+        if !uniques.contains(column)
+            && !primaries.contains(column)
+            && !composite_columns.contains(column)
+        {
             continue;
         }
 
@@ -2173,16 +4233,34 @@ pub async fn get_rows_to_update(
                 // Fetch the cell corresponding to `column` from `row`, and the value of that cell,
                 // which is the new value for the row.
                 let new_value = get_cell_value(&row, target_column)?;
-                let rows = get_affected_rows(
-                    table,
-                    dependent_column,
-                    &new_value,
-                    Some(&query_as_if.row_number),
-                    config,
-                    pool,
-                    tx,
-                )
-                .await?;
+                let rows = match &tree.separator {
+                    // If the parent column has a list() datatype, the affected rows are those for
+                    // which one of the items in the parent column is the new value:
+                    Some(_) => {
+                        get_affected_rows_by_list_item(
+                            table,
+                            tree,
+                            &new_value,
+                            Some(&query_as_if.row_number),
+                            config,
+                            pool,
+                            tx,
+                        )
+                        .await?
+                    }
+                    None => {
+                        get_affected_rows(
+                            table,
+                            dependent_column,
+                            &new_value,
+                            Some(&query_as_if.row_number),
+                            config,
+                            pool,
+                            tx,
+                        )
+                        .await?
+                    }
+                };
                 rows
             }
         };
@@ -2805,6 +4883,58 @@ pub async fn switch_undone_state(
     Ok(())
 }
 
+/// Given a global config map and a table name, return the table's multi-column primary and unique
+/// keys as they are declared in the database. A table without a conflict table has nowhere to
+/// store rows whose primary key values would be stored as NULL (see [has_null_primary_key()]), so
+/// its multi-column primary keys are declared as unique keys instead.
+pub fn get_declared_composites(
+    config: &ValveConfig,
+    table_name: &str,
+) -> Vec<ValveCompositeConstraint> {
+    let has_conflict_table = config
+        .table
+        .get(table_name)
+        .is_some_and(|t| t.options.contains("conflict"));
+    config
+        .constraint
+        .composite
+        .get(table_name)
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|composite| match has_conflict_table {
+            true => composite,
+            false => ValveCompositeConstraint {
+                key_type: "unique".to_string(),
+                ..composite
+            },
+        })
+        .collect()
+}
+
+/// Given a global config map, a table name, a row, and a database connection pool, return true if
+/// the table has a conflict table and any of the row's values that belong to one of the table's
+/// multi-column primary keys is empty or has the wrong SQL type, and would therefore be stored as
+/// NULL. Since the database does not allow NULL in a primary key column, such a row must be
+/// stored in the conflict table.
+pub fn has_null_primary_key(
+    config: &ValveConfig,
+    table_name: &str,
+    row: &ValveRow,
+    pool: &AnyPool,
+) -> bool {
+    let default_cell = ValveCell::default();
+    get_declared_composites(config, table_name)
+        .iter()
+        .filter(|composite| composite.key_type == "primary")
+        .flat_map(|composite| composite.columns.iter())
+        .any(|column| {
+            let cell = row.contents.get(column).unwrap_or(&default_cell);
+            let sql_type = get_sql_type_from_global_config(config, table_name, column, pool);
+            cell.nulltype.is_some() || is_sql_type_error(&sql_type, &cell.strvalue())
+        })
+}
+
 /// Given a global config map and a table name, return a list of the columns from the table
 /// that may potentially result in database conflicts.
 pub fn get_conflict_columns(config: &ValveConfig, table_name: &str) -> Vec<String> {
@@ -2846,12 +4976,30 @@ pub fn get_conflict_columns(config: &ValveConfig, table_name: &str) -> Vec<Strin
         .filter(|t| t.ftable == *table_name)
         .map(|t| t.fcolumn.to_string())
         .collect::<Vec<_>>();
+    let composite_columns = config
+        .constraint
+        .composite
+        .get(table_name)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table_name))
+        .iter()
+        .flat_map(|c| c.columns.clone())
+        .collect::<Vec<_>>();
+    let composite_foreign_sources = config
+        .constraint
+        .composite_foreign
+        .get(table_name)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table_name))
+        .iter()
+        .flat_map(|t| t.columns.clone())
+        .collect::<Vec<_>>();
 
     for key_columns in vec![
         primaries,
         uniques,
+        &composite_columns,
         &tree_children,
         &foreign_sources,
+        &composite_foreign_sources,
         &foreign_targets,
     ] {
         for column in key_columns {
@@ -2888,11 +5036,41 @@ pub fn is_sql_type_error(sql_type: &str, value: &str) -> bool {
             Ok(_) => false,
             Err(_) => true,
         }
+    } else if sql_type == "date" {
+        parse_datetime(value, SQL_DATE_FORMAT, false).is_none()
+    } else if sql_type == "timestamp" {
+        parse_datetime(value, SQL_TIMESTAMP_FORMAT, true).is_none()
     } else {
         false
     }
 }
 
+/// Given a value, a format (see <https://docs.rs/chrono/latest/chrono/format/strftime/>), and a
+/// flag indicating whether the format represents a date and a time or only a date, parse the value
+/// and return the date and time (midnight in the case of a date) that it represents. If the value
+/// is not a valid calendar date (and time) that is written in exactly the given format, return
+/// None.
+pub fn parse_datetime(value: &str, format: &str, with_time: bool) -> Option<NaiveDateTime> {
+    let datetime = {
+        if with_time {
+            NaiveDateTime::parse_from_str(value, format).ok()?
+        } else {
+            NaiveDate::parse_from_str(value, format)
+                .ok()?
+                .and_hms_opt(0, 0, 0)?
+        }
+    };
+    // The parser is lenient about such things as missing zero-padding and leading whitespace, so
+    // we also require that writing the parsed date back out in the given format yields the value:
+    let mut formatted = String::new();
+    write!(formatted, "{}", datetime.format(format)).ok()?;
+    if formatted == value {
+        Some(datetime)
+    } else {
+        None
+    }
+}
+
 /// Given a table name, a row number, and a database transaction, return the row_order
 /// corresponding to the given row number in the given table. Note that the row order is
 /// represented using the signed type i64 but it will never be negative.
@@ -3124,6 +5302,7 @@ pub async fn insert_new_row_tx(
                 "level": message.level,
                 "rule": message.rule,
                 "message": message.message,
+                "suggestions": message.suggestions,
             }));
         }
 
@@ -3131,7 +5310,7 @@ pub async fn insert_new_row_tx(
         if is_sql_type_error(&sql_type, &cell.strvalue()) {
             insert_values.push(String::from("NULL"));
         } else {
-            insert_values.push(cast_sql_param_from_text(&sql_type));
+            insert_values.push(cast_sql_param_from_text(&sql_type, pool));
             insert_params.push(String::from(cell.strvalue()));
         }
 
@@ -3139,6 +5318,9 @@ pub async fn insert_new_row_tx(
             use_conflict_table = true;
         }
     }
+    if has_null_primary_key(config, table, &row, pool) {
+        use_conflict_table = true;
+    }
 
     // Used to validate the given row, counterfactually, "as if" the version of the row in the
     // database currently were replaced with `row`:
@@ -3193,6 +5375,13 @@ pub async fn insert_new_row_tx(
     }
     query.execute(tx.acquire().await?).await?;
 
+    // If the table's tree hierarchies are materialized, bring its closure table up to date:
+    if get_table_options_from_config(config, table)?.contains("closure") {
+        let child_values =
+            get_tree_child_values_tx(config, pool, tx, table, &new_row_number).await?;
+        update_tree_closure_tx(config, pool, tx, table, Some(&child_values)).await?;
+    }
+
     // Next add any validation messages to the message table:
     for m in all_messages {
         let column = m.get("column").and_then(|c| c.as_str()).unwrap();
@@ -3204,19 +5393,33 @@ pub async fn insert_new_row_tx(
         let level = m.get("level").and_then(|c| c.as_str()).unwrap();
         let rule = m.get("rule").and_then(|c| c.as_str()).unwrap();
         let message = m.get("message").and_then(|c| c.as_str()).unwrap();
+        let suggestions = match m.get("suggestions") {
+            Some(SerdeValue::Array(s)) if !s.is_empty() => format!("{}", json!(s)),
+            _ => "NULL".to_string(),
+        };
         let message_sql = local_sql_syntax(
             pool,
             &format!(
                 r#"INSERT INTO "message"
-                   ("table", "row", "column", "value", "level", "rule", "message")
+                   ("table", "row", "column", "value", "level", "rule", "message", "suggestions")
                    VALUES ({SQL_PARAM}, {new_row_number}, {SQL_PARAM}, {SQL_PARAM},
-                           {SQL_PARAM}, {SQL_PARAM}, {SQL_PARAM})"#,
+                           {SQL_PARAM}, {SQL_PARAM}, {SQL_PARAM}, {suggestions_value})"#,
+                suggestions_value = {
+                    if suggestions == "NULL" {
+                        "NULL"
+                    } else {
+                        SQL_PARAM
+                    }
+                }
             ),
         );
         let mut query = sqlx_query(&message_sql);
         for param in [table, column, &value, level, rule, &message] {
             query = query.bind(param);
         }
+        if suggestions != "NULL" {
+            query = query.bind(suggestions);
+        }
         query.execute(tx.acquire().await?).await?;
     }
 
@@ -3291,6 +5494,14 @@ pub async fn delete_row_tx(
     )
     .await?;
 
+    // If the table's tree hierarchies are materialized, note the row's child values, which will
+    // be needed to bring the table's closure table up to date once the row has been deleted:
+    let closure_values = if get_table_options_from_config(config, table)?.contains("closure") {
+        Some(get_tree_child_values_tx(config, pool, tx, table, row_number).await?)
+    } else {
+        None
+    };
+
     // Now delete the row:
     let sql1 = format!(
         "DELETE FROM \"{}\" WHERE row_number = {}",
@@ -3312,6 +5523,10 @@ pub async fn delete_row_tx(
     let query = sqlx_query(&sql).bind(table);
     query.execute(tx.acquire().await?).await?;
 
+    if let Some(closure_values) = closure_values {
+        update_tree_closure_tx(config, pool, tx, table, Some(&closure_values)).await?;
+    }
+
     // Now process the updates that need to be performed because of an insertion to a tree
     // column:
     process_updates(
@@ -3495,6 +5710,273 @@ pub async fn update_row_tx(
     Ok(())
 }
 
+/// Given a table name and a database connection pool, return the statements needed to create the
+/// table that holds the transitive closure of the table's tree() hierarchies, along with its
+/// indexes. Each row of the closure table records that the given ancestor is an ancestor of the
+/// given descendant, at the given depth, in the tree defined by the given (parent) column.
+pub fn get_tree_closure_ddl(table: &str, pool: &AnyPool) -> Vec<String> {
+    let integer = if pool.any_kind() == AnyKind::Sqlite {
+        "INTEGER"
+    } else {
+        "BIGINT"
+    };
+    vec![
+        format!(
+            r#"CREATE TABLE IF NOT EXISTS "{table}_closure" (
+                 "column" TEXT,
+                 "ancestor" TEXT,
+                 "descendant" TEXT,
+                 "depth" {integer}
+               );"#
+        ),
+        format!(
+            r#"CREATE INDEX IF NOT EXISTS "{table}_closure_ancestor_idx"
+                 ON "{table}_closure"("column", "ancestor");"#
+        ),
+        format!(
+            r#"CREATE INDEX IF NOT EXISTS "{table}_closure_descendant_idx"
+                 ON "{table}_closure"("column", "descendant");"#
+        ),
+    ]
+}
+
+/// Given a global config struct, a database connection pool, a database transaction, a table name,
+/// and one of the table's tree constraints, return the row number, child value, and parent values
+/// of every row of the table whose child value is not NULL, in row order. A row has no parent
+/// values if its parent column is NULL, and may have more than one if the tree is a polyhierarchy.
+/// Values are returned as text.
+pub async fn get_tree_edges(
+    config: &ValveConfig,
+    pool: &AnyPool,
+    tx: &mut Transaction<'_, sqlx::Any>,
+    table: &str,
+    tree: &ValveTreeConstraint,
+) -> Result<Vec<(u32, String, Vec<String>)>> {
+    let query_table = get_tree_query_table(config, table)?;
+    let child_sql_type = get_sql_type_from_global_config(config, table, &tree.child, pool);
+    let parent_sql_type = get_sql_type_from_global_config(config, table, &tree.parent, pool);
+    let sql = format!(
+        r#"SELECT "row_number", {child} AS "child", {parent} AS "parent"
+             FROM "{query_table}"
+            WHERE "{child_column}" IS NOT NULL
+            ORDER BY "row_order""#,
+        child = cast_column_sql_to_text(&tree.child, &child_sql_type),
+        parent = cast_column_sql_to_text(&tree.parent, &parent_sql_type),
+        child_column = tree.child,
+    );
+    let mut edges = vec![];
+    for row in sqlx_query(&sql).fetch_all(tx.acquire().await?).await? {
+        let row_number: i64 = row.get("row_number");
+        let child: String = row.get("child");
+        let raw_parent = row.try_get_raw("parent")?;
+        let parents = if raw_parent.is_null() {
+            vec![]
+        } else {
+            get_tree_parent_values(tree, row.get("parent"))
+        };
+        edges.push((row_number as u32, child, parents));
+    }
+    Ok(edges)
+}
+
+/// Given a global config struct and a table name, return the name of the table or view that should
+/// be used to query the table's tree hierarchies.
+pub fn get_tree_query_table(config: &ValveConfig, table: &str) -> Result<String> {
+    let table_options = get_table_options_from_config(config, table)?;
+    if !table_options.contains("conflict") {
+        Ok(table.to_string())
+    } else {
+        Ok(format!("{}_view", table))
+    }
+}
+
+/// Given a map from values to their adjacent values (i.e., either their parents or their
+/// children) in a tree, and a value, return every value reachable from the given value, other than
+/// the value itself, along with its distance from the given value. Values are returned in order
+/// of increasing distance and each value is returned once, at its shortest distance.
+pub fn get_tree_distances(
+    adjacency: &HashMap<String, Vec<String>>,
+    value: &str,
+) -> Vec<(String, u32)> {
+    let mut distances = vec![];
+    let mut visited = HashSet::from([value.to_string()]);
+    let mut queue = VecDeque::from([(value.to_string(), 0)]);
+    while let Some((current, distance)) = queue.pop_front() {
+        if let Some(adjacent_values) = adjacency.get(&current) {
+            for adjacent in adjacent_values {
+                if visited.insert(adjacent.to_string()) {
+                    distances.push((adjacent.to_string(), distance + 1));
+                    queue.push_back((adjacent.to_string(), distance + 1));
+                }
+            }
+        }
+    }
+    distances
+}
+
+/// Given a global config struct, a database connection pool, a database transaction, a table name,
+/// and a row number, return a map from the parent column of each of the table's tree constraints
+/// to the child value of the given row (if it is not NULL) in the corresponding tree.
+pub async fn get_tree_child_values_tx(
+    config: &ValveConfig,
+    pool: &AnyPool,
+    tx: &mut Transaction<'_, sqlx::Any>,
+    table: &str,
+    row_number: &u32,
+) -> Result<HashMap<String, Vec<String>>> {
+    let query_table = get_tree_query_table(config, table)?;
+    let mut child_values = HashMap::new();
+    for tree in config.constraint.tree.get(table).unwrap_or(&vec![]) {
+        let sql_type = get_sql_type_from_global_config(config, table, &tree.child, pool);
+        let sql = format!(
+            r#"SELECT {child} AS "child" FROM "{query_table}"
+                WHERE "row_number" = {row_number} AND "{child_column}" IS NOT NULL"#,
+            child = cast_column_sql_to_text(&tree.child, &sql_type),
+            child_column = tree.child,
+        );
+        let values = sqlx_query(&sql)
+            .fetch_all(tx.acquire().await?)
+            .await?
+            .iter()
+            .map(|row| row.get::<String, _>("child"))
+            .collect::<Vec<_>>();
+        child_values.insert(tree.parent.to_string(), values);
+    }
+    Ok(child_values)
+}
+
+/// Given a global config struct, a database connection pool, a database transaction, a table name,
+/// and, optionally, a map from the parent column of each of the table's tree constraints to the
+/// child values that have been affected by a change to the table, bring the table's closure table
+/// up to date. Only the closure rows for the affected values and their descendants are recomputed.
+/// If no map is given, the closure table is rebuilt from scratch.
+pub async fn update_tree_closure_tx(
+    config: &ValveConfig,
+    pool: &AnyPool,
+    tx: &mut Transaction<'_, sqlx::Any>,
+    table: &str,
+    affected: Option<&HashMap<String, Vec<String>>>,
+) -> Result<()> {
+    // The maximum number of values to bind to a single DELETE or INSERT statement:
+    let max_params = 800;
+    let closure_table = format!("{}_closure", table);
+    for tree in config.constraint.tree.get(table).unwrap_or(&vec![]) {
+        let edges = get_tree_edges(config, pool, tx, table, tree).await?;
+        let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        for (_, child, child_parents) in &edges {
+            for parent in child_parents {
+                parents
+                    .entry(child.to_string())
+                    .or_default()
+                    .push(parent.to_string());
+                children
+                    .entry(parent.to_string())
+                    .or_default()
+                    .push(child.to_string());
+            }
+        }
+
+        // Determine which descendants' closure rows need to be recomputed and remove their
+        // current closure rows:
+        let stale = match affected {
+            None => {
+                let sql = local_sql_syntax(
+                    pool,
+                    &format!(r#"DELETE FROM "{closure_table}" WHERE "column" = {SQL_PARAM}"#),
+                );
+                sqlx_query(&sql)
+                    .bind(&tree.parent)
+                    .execute(tx.acquire().await?)
+                    .await?;
+                edges
+                    .iter()
+                    .map(|(_, child, _)| child.to_string())
+                    .collect::<IndexSet<_>>()
+            }
+            Some(affected) => {
+                let values = match affected.get(&tree.parent) {
+                    Some(values) if !values.is_empty() => values,
+                    _ => continue,
+                };
+                let mut stale = IndexSet::new();
+                for value in values {
+                    stale.insert(value.to_string());
+                    // The value's current descendants:
+                    for (descendant, _) in get_tree_distances(&children, value) {
+                        stale.insert(descendant);
+                    }
+                    // The value's descendants as they were before the change:
+                    let sql = local_sql_syntax(
+                        pool,
+                        &format!(
+                            r#"SELECT "descendant" FROM "{closure_table}"
+                                WHERE "column" = {SQL_PARAM} AND "ancestor" = {SQL_PARAM}"#
+                        ),
+                    );
+                    for row in sqlx_query(&sql)
+                        .bind(&tree.parent)
+                        .bind(value)
+                        .fetch_all(tx.acquire().await?)
+                        .await?
+                    {
+                        stale.insert(row.get::<String, _>("descendant"));
+                    }
+                }
+                let stale_values = stale.iter().collect::<Vec<_>>();
+                for chunk in stale_values.chunks(max_params) {
+                    let sql = local_sql_syntax(
+                        pool,
+                        &format!(
+                            r#"DELETE FROM "{closure_table}"
+                                WHERE "column" = {SQL_PARAM} AND "descendant" IN ({values})"#,
+                            values = vec![SQL_PARAM; chunk.len()].join(", "),
+                        ),
+                    );
+                    let mut query = sqlx_query(&sql).bind(&tree.parent);
+                    for value in chunk {
+                        query = query.bind(value.as_str());
+                    }
+                    query.execute(tx.acquire().await?).await?;
+                }
+                stale
+            }
+        };
+
+        // Recompute the ancestors of every stale descendant and insert them into the closure table:
+        let mut records = vec![];
+        for descendant in &stale {
+            for (ancestor, depth) in get_tree_distances(&parents, descendant) {
+                records.push((ancestor, descendant.to_string(), depth));
+            }
+        }
+        for chunk in records.chunks(max_params / 4) {
+            let sql = local_sql_syntax(
+                pool,
+                &format!(
+                    r#"INSERT INTO "{closure_table}" ("column", "ancestor", "descendant", "depth")
+                       VALUES {values}"#,
+                    values = vec![
+                        format!("({SQL_PARAM}, {SQL_PARAM}, {SQL_PARAM}, {SQL_PARAM})");
+                        chunk.len()
+                    ]
+                    .join(", "),
+                ),
+            );
+            let mut query = sqlx_query(&sql);
+            for (ancestor, descendant, depth) in chunk {
+                query = query
+                    .bind(&tree.parent)
+                    .bind(ancestor)
+                    .bind(descendant)
+                    .bind(*depth as i64);
+            }
+            query.execute(tx.acquire().await?).await?;
+        }
+    }
+    Ok(())
+}
+
 /// Given a path, read a TSV file and return a vector of rows represented as [SerdeMaps](SerdeMap).
 /// Note: Use this function to read "small" TSVs only. In particular, use this for the special
 /// configuration tables.
@@ -3575,11 +6057,20 @@ pub fn read_db_table_into_vector(pool: &AnyPool, config_table: &str) -> Result<V
 /// Given a condition on a datatype, if the condition is a Function, then parse it using
 /// StartParser, create a corresponding CompiledCondition, and return it. If the condition is a
 /// Label, then look for the CompiledCondition corresponding to it in datatype_conditions
-/// and return it.
+/// and return it. The given SQL type is the SQL type of the values that the condition will be
+/// applied to, and is used to determine how numeric values should be parsed when compiling
+/// conditions, such as `range()`, that compare values numerically. If the condition is a rule
+/// condition, then the configuration of the table that the rule belongs to should also be given,
+/// in which case the arguments of the functions `equals()`, `range()`, `gt()`, `ge()`, `lt()`,
+/// and `le()` may refer to other columns of the same row. The given prefixes, a map from CURIE
+/// prefixes to the base IRIs that they stand for, are used to compile the function `curie()`.
 pub fn compile_condition(
     condition: &str,
     parser: &StartParser,
     datatype_conditions: &HashMap<String, CompiledCondition>,
+    sql_type: &str,
+    table_config: Option<&ValveTableConfig>,
+    prefixes: &HashMap<String, String>,
 ) -> Result<CompiledCondition> {
     if condition == "null" || condition == "not null" {
         // The case of a "null" or "not null" condition will be treated specially later during the
@@ -3591,10 +6082,11 @@ pub fn compile_condition(
             original: String::from(""),
             parsed: Expression::None,
             compiled: Arc::new(|_| true),
+            compiled_with_row: None,
+            subconditions: vec![],
         });
     }
 
-    let unquoted_re = Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#)?;
     let parsed_condition = match parser.parse(condition) {
         Err(_) => {
             return Err(
@@ -3610,16 +6102,91 @@ pub fn compile_condition(
         ))
         .into());
     }
-    let parsed_condition = &parsed_condition[0];
-    match &**parsed_condition {
+    compile_parsed_condition(
+        condition,
+        &parsed_condition[0],
+        datatype_conditions,
+        sql_type,
+        table_config,
+        prefixes,
+    )
+}
+
+/// Given a condition in its original String format, its parsed version, compiled datatype
+/// conditions, the SQL type of the values that the condition will be applied to, and the map of
+/// registered CURIE prefixes, create a corresponding CompiledCondition and return it. If the
+/// condition is a rule condition, the configuration of the table that the rule belongs to should
+/// also be given. This function is called recursively to compile the arguments of the functions
+/// `any()`, `all()`, and `not()`.
+fn compile_parsed_condition(
+    condition: &str,
+    parsed_condition: &Expression,
+    datatype_conditions: &HashMap<String, CompiledCondition>,
+    sql_type: &str,
+    table_config: Option<&ValveTableConfig>,
+    prefixes: &HashMap<String, String>,
+) -> Result<CompiledCondition> {
+    // Given an argument to a function and the configuration of the table that the condition
+    // belongs to, if any, return the name of the column that the argument refers to, or None if
+    // it does not refer to a column. An argument refers to a column only if it is a field of the
+    // form `table.column` that names one of the table's columns. Labels are always literal values,
+    // even when they happen to coincide with the name of a column.
+    fn get_column_reference(
+        arg: &Expression,
+        table_config: Option<&ValveTableConfig>,
+    ) -> Option<String> {
+        let table_config = table_config?;
+        match arg {
+            Expression::Field(table, column)
+                if *table == table_config.table && table_config.column.contains_key(column) =>
+            {
+                Some(column.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    // Given a row and a column name, return the value of the row in that column, or an empty
+    // string if the row has no such column:
+    fn get_row_value(row: &ValveRow, column: &str) -> String {
+        match row.contents.get(column) {
+            Some(cell) => cell.strvalue(),
+            None => String::from(""),
+        }
+    }
+
+    let unquoted_re = Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#)?;
+    match parsed_condition {
+        Expression::Function(name, args) if name == "equals" && args.len() != 1 => {
+            Err(ValveError::InputError(format!(
+                "Function 'equals' in condition: '{}' requires 1 argument",
+                condition
+            ))
+            .into())
+        }
         Expression::Function(name, args) if name == "equals" => match &*args[0] {
+            arg if get_column_reference(arg, table_config).is_some() => {
+                let column = get_column_reference(arg, table_config).unwrap();
+                Ok(CompiledCondition {
+                    value_type: ValueType::Single,
+                    original: condition.to_string(),
+                    parsed: parsed_condition.clone(),
+                    compiled: Arc::new(|_| false),
+                    compiled_with_row: Some(Arc::new(move |x, row| {
+                        x == get_row_value(row, &column)
+                    })),
+                    subconditions: vec![],
+                })
+            }
             Expression::Label(label) => {
                 let label = String::from(unquoted_re.replace(label, "$unquoted"));
                 Ok(CompiledCondition {
                     value_type: ValueType::Single,
                     original: condition.to_string(),
-                    parsed: *parsed_condition.clone(),
+                    parsed: parsed_condition.clone(),
                     compiled: Arc::new(move |x| x == label),
+                    compiled_with_row: None,
+                    subconditions: vec![],
                 })
             }
             _ => Err(
@@ -3642,8 +6209,10 @@ pub fn compile_condition(
                         Ok(CompiledCondition {
                             value_type: ValueType::Single,
                             original: condition.to_string(),
-                            parsed: *parsed_condition.clone(),
+                            parsed: parsed_condition.clone(),
                             compiled: Arc::new(move |x| !re.is_match(x)),
+                            compiled_with_row: None,
+                            subconditions: vec![],
                         })
                     }
                     "match" => {
@@ -3652,8 +6221,10 @@ pub fn compile_condition(
                         Ok(CompiledCondition {
                             value_type: ValueType::Single,
                             original: condition.to_string(),
-                            parsed: *parsed_condition.clone(),
+                            parsed: parsed_condition.clone(),
                             compiled: Arc::new(move |x| re.is_match(x)),
+                            compiled_with_row: None,
+                            subconditions: vec![],
                         })
                     }
                     "search" => {
@@ -3662,8 +6233,10 @@ pub fn compile_condition(
                         Ok(CompiledCondition {
                             value_type: ValueType::Single,
                             original: condition.to_string(),
-                            parsed: *parsed_condition.clone(),
+                            parsed: parsed_condition.clone(),
                             compiled: Arc::new(move |x| re.is_match(x)),
+                            compiled_with_row: None,
+                            subconditions: vec![],
                         })
                     }
                     _ => Err(ValveError::InputError(format!(
@@ -3693,17 +6266,311 @@ pub fn compile_condition(
                     ))
                     .into());
                 }
-            }
+            }
+            Ok(CompiledCondition {
+                value_type: ValueType::Single,
+                original: condition.to_string(),
+                parsed: parsed_condition.clone(),
+                compiled: Arc::new(move |x| alternatives.contains(&x.to_string())),
+                compiled_with_row: None,
+                subconditions: vec![],
+            })
+        }
+        Expression::Function(name, args)
+            if ["range", "gt", "lt", "ge", "le"].contains(&name.as_str()) =>
+        {
+            let num_args = if name == "range" { 2 } else { 1 };
+            if args.len() != num_args {
+                return Err(ValveError::InputError(format!(
+                    "Function '{}' in condition: '{}' requires {} argument(s)",
+                    name, condition, num_args
+                ))
+                .into());
+            }
+
+            // Values are parsed as 64-bit integers when the SQL type is INTEGER or BIGINT, as dates
+            // or timestamps (which are then compared as numbers of milliseconds) when the SQL type
+            // is DATE or TIMESTAMP, and as finite floating point numbers otherwise. Since a given
+            // condition always parses its values in the same way, numbers of different kinds are
+            // never compared with one another:
+            #[derive(Clone, Copy, PartialEq, PartialOrd)]
+            enum Number {
+                Integer(i64),
+                Real(f64),
+            }
+            let sql_type = sql_type.to_lowercase();
+            let parse_number: fn(&str) -> Option<Number> = match sql_type.as_str() {
+                "integer" | "bigint" => |x| x.parse::<i64>().ok().map(Number::Integer),
+                "date" => |x| {
+                    parse_datetime(x, SQL_DATE_FORMAT, false)
+                        .map(|d| Number::Integer(d.and_utc().timestamp_millis()))
+                },
+                "timestamp" => |x| {
+                    parse_datetime(x, SQL_TIMESTAMP_FORMAT, true)
+                        .map(|d| Number::Integer(d.and_utc().timestamp_millis()))
+                },
+                _ => |x| {
+                    x.parse::<f64>()
+                        .ok()
+                        .filter(|n| n.is_finite())
+                        .map(Number::Real)
+                },
+            };
+
+            // Each bound is either a number or, in the case of a rule condition, a reference to
+            // another column in the same row, whose value will be parsed when the condition is
+            // checked:
+            let mut bounds = vec![];
+            for arg in args {
+                if let Some(column) = get_column_reference(arg, table_config) {
+                    bounds.push((None, Some(column)));
+                    continue;
+                }
+                let bound = match &**arg {
+                    Expression::Label(label) => unquoted_re.replace(label, "$unquoted").to_string(),
+                    _ => {
+                        return Err(ValveError::InputError(format!(
+                            "Argument: {:?} to function '{}' is not a label",
+                            arg, name
+                        ))
+                        .into())
+                    }
+                };
+                match parse_number(&bound) {
+                    Some(bound) => bounds.push((Some(bound), None)),
+                    None => {
+                        return Err(ValveError::InputError(format!(
+                            "Argument '{}' to function '{}' is not a valid {} value",
+                            bound, name, sql_type
+                        ))
+                        .into())
+                    }
+                };
+            }
+            if let [(Some(min), _), (Some(max), _)] = bounds.as_slice() {
+                if min > max {
+                    return Err(ValveError::InputError(format!(
+                        "Invalid condition: '{}'. The minimum is greater than the maximum.",
+                        condition
+                    ))
+                    .into());
+                }
+            }
+
+            let has_column_references = bounds.iter().any(|(_, column)| column.is_some());
+            let op = name.to_string();
+            let check = Arc::new(move |x: &str, row: Option<&ValveRow>| {
+                let n = match parse_number(x) {
+                    Some(n) => n,
+                    None => return false,
+                };
+                let mut resolved_bounds = vec![];
+                for (bound, column) in &bounds {
+                    let bound = match (bound, column, row) {
+                        (Some(bound), _, _) => Some(*bound),
+                        (None, Some(column), Some(row)) => {
+                            parse_number(&get_row_value(row, column))
+                        }
+                        _ => None,
+                    };
+                    match bound {
+                        Some(bound) => resolved_bounds.push(bound),
+                        None => return false,
+                    };
+                }
+                match op.as_str() {
+                    "range" => n >= resolved_bounds[0] && n <= resolved_bounds[1],
+                    "gt" => n > resolved_bounds[0],
+                    "lt" => n < resolved_bounds[0],
+                    "ge" => n >= resolved_bounds[0],
+                    _ => n <= resolved_bounds[0],
+                }
+            });
+            let compiled_with_row: Option<RowConditionFn> = {
+                if has_column_references {
+                    let check = check.clone();
+                    Some(Arc::new(move |x, row| check(x, Some(row))))
+                } else {
+                    None
+                }
+            };
+            Ok(CompiledCondition {
+                value_type: ValueType::Single,
+                original: condition.to_string(),
+                parsed: parsed_condition.clone(),
+                compiled: Arc::new(move |x| check(x, None)),
+                compiled_with_row,
+                subconditions: vec![],
+            })
+        }
+        Expression::Function(name, args) if name == "date" || name == "datetime" => {
+            let format = match args.as_slice() {
+                [arg] => match &**arg {
+                    Expression::Label(format) => {
+                        unquoted_re.replace(format, "$unquoted").to_string()
+                    }
+                    _ => {
+                        return Err(ValveError::InputError(format!(
+                            "Argument: {:?} to function '{}' is not a label",
+                            arg, name
+                        ))
+                        .into())
+                    }
+                },
+                _ => {
+                    return Err(ValveError::InputError(format!(
+                        "Function '{}' in condition: '{}' requires 1 argument",
+                        name, condition
+                    ))
+                    .into())
+                }
+            };
+            if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+                return Err(ValveError::InputError(format!(
+                    "Invalid format '{}' in condition: '{}'",
+                    format, condition
+                ))
+                .into());
+            }
+            let with_time = name == "datetime";
+            Ok(CompiledCondition {
+                value_type: ValueType::Single,
+                original: condition.to_string(),
+                parsed: parsed_condition.clone(),
+                compiled: Arc::new(move |x| parse_datetime(x, &format, with_time).is_some()),
+                compiled_with_row: None,
+                subconditions: vec![],
+            })
+        }
+        Expression::Function(name, args) if name == "length" => {
+            if args.len() != 2 {
+                return Err(ValveError::InputError(format!(
+                    "Function 'length' in condition: '{}' requires 2 arguments",
+                    condition
+                ))
+                .into());
+            }
+            let mut bounds = vec![];
+            for arg in args {
+                let bound = match &**arg {
+                    Expression::Label(label) => unquoted_re.replace(label, "$unquoted").to_string(),
+                    _ => {
+                        return Err(ValveError::InputError(format!(
+                            "Argument: {:?} to function 'length' is not a label",
+                            arg
+                        ))
+                        .into())
+                    }
+                };
+                match bound.parse::<usize>() {
+                    Ok(bound) => bounds.push(bound),
+                    Err(_) => {
+                        return Err(ValveError::InputError(format!(
+                            "Argument '{}' to function 'length' is not a natural number",
+                            bound
+                        ))
+                        .into())
+                    }
+                };
+            }
+            let (min, max) = (bounds[0], bounds[1]);
+            if min > max {
+                return Err(ValveError::InputError(format!(
+                    "Invalid condition: '{}'. The minimum is greater than the maximum.",
+                    condition
+                ))
+                .into());
+            }
+            Ok(CompiledCondition {
+                value_type: ValueType::Single,
+                original: condition.to_string(),
+                parsed: parsed_condition.clone(),
+                compiled: Arc::new(move |x| {
+                    let length = x.chars().count();
+                    length >= min && length <= max
+                }),
+                compiled_with_row: None,
+                subconditions: vec![],
+            })
+        }
+        Expression::Function(name, args) if ["any", "all", "not"].contains(&name.as_str()) => {
+            if args.is_empty() || (name == "not" && args.len() != 1) {
+                return Err(ValveError::InputError(format!(
+                    "Invalid number of arguments to function '{}' in condition: '{}'",
+                    name, condition
+                ))
+                .into());
+            }
+
+            // Each argument is either the name of a datatype or a condition in its own right:
+            let mut subconditions = vec![];
+            for arg in args {
+                let subcondition = arg.to_string();
+                let compiled = compile_parsed_condition(
+                    &subcondition,
+                    arg,
+                    datatype_conditions,
+                    sql_type,
+                    table_config,
+                    prefixes,
+                )?;
+                let is_list = match datatype_conditions.get(&subcondition) {
+                    Some(dt_condition) => dt_condition.value_type != ValueType::Single,
+                    None => compiled.value_type != ValueType::Single,
+                };
+                if is_list {
+                    return Err(ValveError::InputError(format!(
+                        "Argument '{}' to function '{}' in condition: '{}' is a list condition",
+                        subcondition, name, condition
+                    ))
+                    .into());
+                }
+                subconditions.push(compiled);
+            }
+
+            let closures = subconditions
+                .iter()
+                .map(|c| c.compiled.clone())
+                .collect::<Vec<_>>();
+            let compiled: Arc<dyn Fn(&str) -> bool + Sync + Send> = match name.as_str() {
+                "any" => Arc::new(move |x| closures.iter().any(|c| c(x))),
+                "all" => Arc::new(move |x| closures.iter().all(|c| c(x))),
+                _ => Arc::new(move |x| !closures[0](x)),
+            };
+
+            // If any of the subconditions refers to other columns in the row, then so does the
+            // combined condition:
+            let compiled_with_row: Option<RowConditionFn> = {
+                if subconditions.iter().any(|c| c.compiled_with_row.is_some()) {
+                    let subconditions = subconditions.clone();
+                    match name.as_str() {
+                        "any" => Some(Arc::new(move |x, row| {
+                            subconditions.iter().any(|c| c.check(x, row))
+                        })),
+                        "all" => Some(Arc::new(move |x, row| {
+                            subconditions.iter().all(|c| c.check(x, row))
+                        })),
+                        _ => Some(Arc::new(move |x, row| !subconditions[0].check(x, row))),
+                    }
+                } else {
+                    None
+                }
+            };
             Ok(CompiledCondition {
                 value_type: ValueType::Single,
                 original: condition.to_string(),
-                parsed: *parsed_condition.clone(),
-                compiled: Arc::new(move |x| alternatives.contains(&x.to_string())),
+                parsed: parsed_condition.clone(),
+                compiled,
+                compiled_with_row,
+                subconditions,
             })
         }
         Expression::Function(name, args) if name == "list" => {
             let syntax_error =
                 ValveError::InputError(format!("Invalid arguments for 'list': {:?}", args));
+            if args.len() != 2 {
+                return Err(syntax_error.into());
+            }
             match &*args[0] {
                 Expression::Label(datatype) => match &*args[1] {
                     Expression::Label(separator) => {
@@ -3719,8 +6586,10 @@ pub fn compile_condition(
                         Ok(CompiledCondition {
                             value_type: ValueType::List(separator.to_string()),
                             original: condition.to_string(),
-                            parsed: *parsed_condition.clone(),
+                            parsed: parsed_condition.clone(),
                             compiled: compiled,
+                            compiled_with_row: None,
+                            subconditions: vec![],
                         })
                     }
                     _ => Err(syntax_error.into()),
@@ -3728,6 +6597,46 @@ pub fn compile_condition(
                 _ => Err(syntax_error.into()),
             }
         }
+        Expression::Function(name, args) if name == "curie" => {
+            // The arguments, if any, restrict the CURIE to the given prefixes. A prefix that has
+            // not been registered is ignored, since no CURIE that uses it can be expanded anyway:
+            let mut allowed_prefixes = vec![];
+            for arg in args {
+                if let Expression::Label(prefix) = &**arg {
+                    let prefix = unquoted_re.replace(prefix, "$unquoted").to_string();
+                    if !prefixes.contains_key(&prefix) {
+                        log::warn!(
+                            "Prefix '{}' in condition: '{}' has not been registered",
+                            prefix,
+                            condition
+                        );
+                    }
+                    allowed_prefixes.push(prefix);
+                } else {
+                    return Err(ValveError::InputError(format!(
+                        "Argument: {:?} to function 'curie' is not a label",
+                        arg
+                    ))
+                    .into());
+                }
+            }
+            let prefixes = prefixes
+                .keys()
+                .filter(|p| allowed_prefixes.is_empty() || allowed_prefixes.contains(p))
+                .cloned()
+                .collect::<HashSet<_>>();
+            Ok(CompiledCondition {
+                value_type: ValueType::Single,
+                original: condition.to_string(),
+                parsed: parsed_condition.clone(),
+                compiled: Arc::new(move |x| match split_curie(x) {
+                    Some((prefix, _)) => prefixes.contains(prefix),
+                    None => false,
+                }),
+                compiled_with_row: None,
+                subconditions: vec![],
+            })
+        }
         Expression::Label(value) if datatype_conditions.contains_key(&value.to_string()) => {
             let condition = datatype_conditions.get(&value.to_string()).unwrap();
             Ok(CompiledCondition {
@@ -3735,39 +6644,60 @@ pub fn compile_condition(
                 original: value.to_string(),
                 parsed: condition.parsed.clone(),
                 compiled: condition.compiled.clone(),
+                compiled_with_row: condition.compiled_with_row.clone(),
+                subconditions: condition.subconditions.clone(),
             })
         }
+        Expression::Function(name, _) => match get_condition_function(name) {
+            Some(function) => function.compile(condition, parsed_condition, sql_type),
+            None => Err(ValveError::ConfigError(format!(
+                "Unrecognized function '{}' in condition: '{}'. It is neither a builtin \
+                 function nor a registered condition function.",
+                name, condition
+            ))
+            .into()),
+        },
         _ => Err(ValveError::InputError(format!("Unrecognized condition: {}", condition)).into()),
     }
 }
 
 /// Given a [SerdeValue] of the type [SerdeValue::String], as well as the SQL type that it will
 /// be converted to when it is inserted to the database (which can be a non-string), return a
-/// [QueryParam] corresponding to the value.
-pub fn get_query_param(value: &SerdeValue, sql_type: &str) -> QueryParam {
-    let param_value = value
-        .as_str()
-        .expect(&format!("'{}' is not a string", value));
+/// [QueryParam] corresponding to the value. If the value cannot be converted to the given SQL
+/// type, return a [ValveError::DataError].
+pub fn get_query_param(value: &SerdeValue, sql_type: &str) -> Result<QueryParam> {
+    let param_value = value.as_str().ok_or(ValveError::DataError(format!(
+        "'{}' is not a string",
+        value
+    )))?;
+    let type_error = |description: &str| {
+        ValveError::DataError(format!("'{}' is not {}", param_value, description))
+    };
     match sql_type {
         "numeric" => {
-            let numeric_value: f64 = param_value
-                .parse()
-                .expect(&format!("{param_value} is not numeric"));
-            QueryParam::Numeric(numeric_value)
+            let numeric_value: f64 = param_value.parse().map_err(|_| type_error("numeric"))?;
+            Ok(QueryParam::Numeric(numeric_value))
         }
         "integer" => {
-            let integer_value: i32 = param_value
-                .parse()
-                .expect(&format!("{param_value} is not an integer"));
-            QueryParam::Integer(integer_value)
+            let integer_value: i32 = param_value.parse().map_err(|_| type_error("an integer"))?;
+            Ok(QueryParam::Integer(integer_value))
         }
         "real" => {
-            let real_value: f64 = param_value
-                .parse()
-                .expect(&format!("{param_value} is not a real"));
-            QueryParam::Real(real_value)
+            let real_value: f64 = param_value.parse().map_err(|_| type_error("a real"))?;
+            Ok(QueryParam::Real(real_value))
         }
-        _ => QueryParam::String(param_value.to_string()),
+        "date" => {
+            let date_value = parse_datetime(param_value, SQL_DATE_FORMAT, false)
+                .ok_or_else(|| type_error("a date"))?
+                .date();
+            Ok(QueryParam::Date(date_value))
+        }
+        "timestamp" => {
+            let timestamp_value = parse_datetime(param_value, SQL_TIMESTAMP_FORMAT, true)
+                .ok_or_else(|| type_error("a timestamp"))?;
+            Ok(QueryParam::Timestamp(timestamp_value))
+        }
+        _ => Ok(QueryParam::String(param_value.to_string())),
     }
 }
 
@@ -3816,9 +6746,11 @@ pub fn get_sql_type_from_global_config(
     get_sql_type(dt_config, &dt, pool)
 }
 
-/// Given a SQL type, return the appropriate CAST(...) statement for casting the SQL_PARAM
-/// from a TEXT column.
-pub fn cast_sql_param_from_text(sql_type: &str) -> String {
+/// Given a SQL type and a database connection pool, return the appropriate CAST(...) statement for
+/// casting the SQL_PARAM from a TEXT column. Note that SQLite has no dedicated storage class for
+/// dates and timestamps, which are therefore stored as text and do not need to be cast (in fact,
+/// casting a date to the DATE type in SQLite would truncate it to its year).
+pub fn cast_sql_param_from_text(sql_type: &str, pool: &AnyPool) -> String {
     let s = sql_type.to_lowercase();
     if s == "numeric" {
         format!("CAST(NULLIF({}, '') AS NUMERIC)", SQL_PARAM)
@@ -3826,6 +6758,10 @@ pub fn cast_sql_param_from_text(sql_type: &str) -> String {
         format!("CAST(NULLIF({}, '') AS INTEGER)", SQL_PARAM)
     } else if s == "real" {
         format!("CAST(NULLIF({}, '') AS REAL)", SQL_PARAM)
+    } else if s == "date" && pool.any_kind() == AnyKind::Postgres {
+        format!("CAST(NULLIF({}, '') AS DATE)", SQL_PARAM)
+    } else if s == "timestamp" && pool.any_kind() == AnyKind::Postgres {
+        format!("CAST(NULLIF({}, '') AS TIMESTAMP)", SQL_PARAM)
     } else {
         String::from(SQL_PARAM)
     }
@@ -3860,6 +6796,12 @@ pub fn get_column_value_as_string(row: &AnyRow, column: &str, sql_type: &str) ->
             .try_get(format!(r#"{}"#, column).as_str())
             .unwrap_or_default();
         value.to_string()
+    } else if s == "date" {
+        let value: Option<NaiveDate> = row.try_get(column.to_string().as_str()).ok();
+        value.map(|v| v.to_string()).unwrap_or_default()
+    } else if s == "timestamp" {
+        let value: Option<NaiveDateTime> = row.try_get(column.to_string().as_str()).ok();
+        value.map(|v| v.to_string()).unwrap_or_default()
     } else {
         let value: &str = row
             .try_get(format!(r#"{}"#, column).as_str())
@@ -3887,6 +6829,12 @@ pub fn get_column_value(row: &AnyRow, column: &str, sql_type: &str) -> SerdeValu
             .try_get(format!(r#"{}"#, column).as_str())
             .unwrap_or_default();
         json!(value)
+    } else if s == "date" {
+        let value: Option<NaiveDate> = row.try_get(column.to_string().as_str()).ok();
+        json!(value.map(|v| v.to_string()).unwrap_or_default())
+    } else if s == "timestamp" {
+        let value: Option<NaiveDateTime> = row.try_get(column.to_string().as_str()).ok();
+        json!(value.map(|v| v.to_string()).unwrap_or_default())
     } else {
         let value: &str = row
             .try_get(format!(r#"{}"#, column).as_str())
@@ -3954,59 +6902,42 @@ pub fn local_sql_syntax(pool: &AnyPool, sql: &String) -> String {
     final_sql
 }
 
-/// Takes as arguments a list of tables and a configuration struct describing all of the constraints
-/// between tables. After validating that there are no cycles amongst the foreign, and tree
-/// dependencies, returns (i) the list of tables sorted according to their foreign key
-/// dependencies, such that if table_a depends on table_b, then table_b comes before table_a in the
-/// list; (ii) A map from table names to the lists of tables that depend on a given table; (iii) a
-/// map from table names to the lists of tables that a given table depends on.
+/// Takes as arguments a list of tables, a configuration struct describing all of the constraints
+/// between tables, the locations of the configured items in the configuration files, and a list of
+/// issues. After checking whether there are any cycles amongst the foreign, and tree dependencies,
+/// and adding each cycle found to the list of issues, returns (i) the list of tables sorted
+/// according to their foreign key dependencies, such that if table_a depends on table_b, then
+/// table_b comes before table_a in the list (if there are cycles between the tables then they are
+/// left in the order in which they have been given); (ii) A map from table names to the lists of
+/// tables that depend on a given table; (iii) a map from table names to the lists of tables that a
+/// given table depends on.
 pub fn verify_table_deps_and_sort(
     table_list: &Vec<String>,
     constraints: &ValveConstraintConfig,
+    locations: &ConfigLocations,
+    issues: &mut Vec<ValveConfigIssue>,
 ) -> (
     Vec<String>,
     HashMap<String, Vec<String>>,
     HashMap<String, Vec<String>>,
 ) {
-    fn get_cycles(g: &DiGraphMap<&str, ()>) -> Result<Vec<String>, Vec<Vec<String>>> {
-        let mut cycles = vec![];
-        match toposort(&g, None) {
-            Err(cycle) => {
-                let problem_node = cycle.node_id();
-                let neighbours = g.neighbors_directed(problem_node, Direction::Outgoing);
-                for neighbour in neighbours {
-                    let ways_to_problem_node =
-                        all_simple_paths::<Vec<_>, _>(&g, neighbour, problem_node, 0, None);
-                    for mut way in ways_to_problem_node {
-                        let mut cycle = vec![problem_node];
-                        cycle.append(&mut way);
-                        let cycle = cycle
-                            .iter()
-                            .map(|&item| item.to_string())
-                            .collect::<Vec<_>>();
-                        cycles.push(cycle);
-                    }
-                }
-                Err(cycles)
-            }
-            Ok(sorted) => {
-                let mut sorted = sorted
-                    .iter()
-                    .map(|&item| item.to_string())
-                    .collect::<Vec<_>>();
-                sorted.reverse();
-                Ok(sorted)
-            }
-        }
+    // Returns the cycles in the given dependency graph, each of which is given by the list of its
+    // nodes.
+    fn get_cycles<'a>(g: &DiGraphMap<&'a str, ()>) -> Vec<Vec<&'a str>> {
+        tarjan_scc(g)
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || g.contains_edge(scc[0], scc[0]))
+            .collect()
     }
 
-    // Check for intra-table cycles:
-    let trees = &constraints.tree;
+    // Check for intra-table cycles. Each cycle is reported in the location of the first of the
+    // dependencies that make it up:
     for table_name in table_list {
         let mut dependency_graph = DiGraphMap::<&str, ()>::new();
-        let table_trees = trees
-            .get(table_name)
-            .expect(&format!("Undefined table '{}'", table_name));
+        let table_trees = match constraints.tree.get(table_name) {
+            None => continue,
+            Some(table_trees) => table_trees,
+        };
         for tree in table_trees {
             let child = &tree.child;
             let parent = &tree.parent;
@@ -4014,118 +6945,142 @@ pub fn verify_table_deps_and_sort(
             let p_index = dependency_graph.add_node(&parent);
             dependency_graph.add_edge(c_index, p_index, ());
         }
-        match get_cycles(&dependency_graph) {
-            Ok(_) => (),
-            Err(cycles) => {
-                let mut message = String::new();
-                for cycle in cycles {
-                    message.push_str(
-                        format!("Cyclic dependency in table '{}': ", table_name).as_str(),
-                    );
-                    let end_index = cycle.len() - 1;
-                    for (i, child) in cycle.iter().enumerate() {
-                        if i < end_index {
-                            let dep = table_trees.iter().find(|d| d.child == *child).unwrap();
-                            let parent = &dep.parent;
-                            message.push_str(
-                                format!("tree({}) references {}", child, parent).as_str(),
-                            );
-                        }
-                        if i < (end_index - 1) {
-                            message.push_str(" and ");
-                        }
-                    }
-                    message.push_str(". ");
-                }
-                panic!("{}", message);
-            }
-        };
+        for cycle in get_cycles(&dependency_graph) {
+            let cycle = table_trees
+                .iter()
+                .filter(|d| cycle.contains(&d.child.as_str()) && cycle.contains(&d.parent.as_str()))
+                .collect::<Vec<_>>();
+            issues.push(locations.column_issue(
+                table_name,
+                &cycle[0].parent,
+                "structure",
+                "error",
+                &format!(
+                    "Cyclic dependency in table '{}': {}",
+                    table_name,
+                    cycle
+                        .iter()
+                        .map(|d| format!("tree({}) references {}", d.child, d.parent))
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                ),
+            ));
+        }
     }
 
     // Check for inter-table cycles:
-    let foreign_keys = &constraints.foreign;
+    let mut dependencies = vec![];
     let mut dependency_graph = DiGraphMap::<&str, ()>::new();
     for table_name in table_list {
         let t_index = dependency_graph.add_node(table_name);
-        let fkeys = foreign_keys
+        for fkey in constraints.foreign.get(table_name).into_iter().flatten() {
+            let ftable = &fkey.ftable;
+            let f_index = dependency_graph.add_node(&ftable);
+            dependency_graph.add_edge(t_index, f_index, ());
+            dependencies.push((
+                table_name,
+                ftable,
+                &fkey.column,
+                format!(
+                    "{}.{} depends on {}.{}",
+                    table_name, fkey.column, ftable, fkey.fcolumn
+                ),
+            ));
+        }
+        for fkey in constraints
+            .composite_foreign
             .get(table_name)
-            .expect(&format!("Undefined table '{}'", table_name));
-        for fkey in fkeys {
+            .into_iter()
+            .flatten()
+        {
             let ftable = &fkey.ftable;
             let f_index = dependency_graph.add_node(&ftable);
             dependency_graph.add_edge(t_index, f_index, ());
+            dependencies.push((
+                table_name,
+                ftable,
+                &fkey.columns[0],
+                format!(
+                    "{}.({}) depends on {}.({})",
+                    table_name,
+                    fkey.columns.join(", "),
+                    ftable,
+                    fkey.fcolumns.join(", ")
+                ),
+            ));
         }
     }
+    dependencies.sort_by_key(|(table, _, column, _)| {
+        locations
+            .column
+            .get(&(table.to_string(), column.to_string()))
+    });
+    for cycle in get_cycles(&dependency_graph) {
+        let cycle = dependencies
+            .iter()
+            .filter(|(table, ftable, _, _)| {
+                cycle.contains(&table.as_str()) && cycle.contains(&ftable.as_str())
+            })
+            .collect::<Vec<_>>();
+        let tables = cycle
+            .iter()
+            .map(|(table, _, _, _)| table.to_string())
+            .unique()
+            .collect::<Vec<_>>();
+        issues.push(locations.column_issue(
+            cycle[0].0,
+            cycle[0].2,
+            "structure",
+            "error",
+            &format!(
+                "Cyclic dependency between tables {}: {}",
+                tables.join(", "),
+                cycle
+                    .iter()
+                    .map(|(_, _, _, description)| description.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            ),
+        ));
+    }
 
-    match get_cycles(&dependency_graph) {
-        Ok(sorted_table_list) => {
-            let mut table_dependencies_in = HashMap::new();
-            for node in dependency_graph.nodes() {
-                let neighbors = dependency_graph
-                    .neighbors_directed(node, petgraph::Direction::Incoming)
-                    .map(|n| n.to_string())
-                    .collect::<Vec<_>>();
-                table_dependencies_in.insert(node.to_string(), neighbors);
-            }
-            let mut table_dependencies_out = HashMap::new();
-            for node in dependency_graph.nodes() {
-                let neighbors = dependency_graph
-                    .neighbors_directed(node, petgraph::Direction::Outgoing)
-                    .map(|n| n.to_string())
-                    .collect::<Vec<_>>();
-                table_dependencies_out.insert(node.to_string(), neighbors);
-            }
-            let mut sorted_table_list = sorted_table_list.clone();
-            let mut with_internals = INTERNAL_TABLES
+    let mut sorted_table_list = match toposort(&dependency_graph, None) {
+        Ok(sorted) => {
+            let mut sorted = sorted
                 .iter()
-                .map(|t| t.to_string())
+                .map(|&item| item.to_string())
                 .collect::<Vec<_>>();
-            with_internals.append(&mut sorted_table_list);
-            return (
-                with_internals,
-                table_dependencies_in,
-                table_dependencies_out,
-            );
-        }
-        Err(cycles) => {
-            let mut message = String::new();
-            for cycle in cycles {
-                message.push_str(
-                    format!("Cyclic dependency between tables {}: ", cycle.join(", ")).as_str(),
-                );
-                let end_index = cycle.len() - 1;
-                for (i, table) in cycle.iter().enumerate() {
-                    if i < end_index {
-                        let dep_name = cycle.get(i + 1).unwrap().as_str();
-                        let fkeys = foreign_keys.get(table).unwrap();
-                        let column;
-                        let ref_table;
-                        let ref_column;
-                        if let Some(dep) = fkeys.iter().find(|d| d.ftable == *dep_name) {
-                            column = &dep.column;
-                            ref_table = &dep.ftable;
-                            ref_column = &dep.fcolumn;
-                        } else {
-                            panic!("{}. Unable to retrieve the details.", message);
-                        }
-
-                        message.push_str(
-                            format!(
-                                "{}.{} depends on {}.{}",
-                                table, column, ref_table, ref_column,
-                            )
-                            .as_str(),
-                        );
-                    }
-                    if i < (end_index - 1) {
-                        message.push_str(" and ");
-                    }
-                }
-                message.push_str(". ");
-            }
-            panic!("{}", message);
+            sorted.reverse();
+            sorted
         }
+        Err(_) => table_list.clone(),
     };
+    let mut table_dependencies_in = HashMap::new();
+    for node in dependency_graph.nodes() {
+        let neighbors = dependency_graph
+            .neighbors_directed(node, petgraph::Direction::Incoming)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>();
+        table_dependencies_in.insert(node.to_string(), neighbors);
+    }
+    let mut table_dependencies_out = HashMap::new();
+    for node in dependency_graph.nodes() {
+        let neighbors = dependency_graph
+            .neighbors_directed(node, petgraph::Direction::Outgoing)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>();
+        table_dependencies_out.insert(node.to_string(), neighbors);
+    }
+    let mut with_internals = INTERNAL_TABLES
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>();
+    with_internals.append(&mut sorted_table_list);
+    (
+        with_internals,
+        table_dependencies_in,
+        table_dependencies_out,
+    )
 }
 
 /// Given a global configuration struct and a table name, returns the options for the table.
@@ -4140,15 +7095,326 @@ pub fn get_table_options_from_config(config: &ValveConfig, table: &str) -> Resul
         .clone())
 }
 
-/// Given a table configuration map and a datatype configuration map, a parser, a table name, and a
-/// database connection pool, return lists of: primary keys, unique constraints, foreign keys,
-/// and trees.
+/// Given a table configuration map, a parser, a table name, the columns of the table in the
+/// order in which they have been defined in the column table, the locations of the configured
+/// items in the configuration files, and a list of issues, return the list of foreign
+/// constraints on more than one column that have been defined for the table. Such a constraint is
+/// declared using a structure of the form `from((ftable.fcolumn_1, ftable.fcolumn_2, ...))`, which
+/// must be given for each of the columns constrained by the key. The constrained columns are
+/// matched with the foreign columns in the order in which they appear in the column table. Invalid
+/// constraints are left out of the list and the problems found with them are added to the list of
+/// issues.
+pub fn get_composite_foreign_constraints(
+    tables_config: &HashMap<String, ValveTableConfig>,
+    parser: &StartParser,
+    table_name: &str,
+    column_ordering: &Vec<String>,
+    locations: &ConfigLocations,
+    issues: &mut Vec<ValveConfigIssue>,
+) -> Vec<ValveCompositeForeignConstraint> {
+    let columns = &tables_config
+        .get(table_name)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table_name))
+        .column;
+
+    // Collect the columns that have been declared for each distinct foreign key:
+    let mut declared_keys: IndexMap<(String, Vec<String>), Vec<String>> = IndexMap::new();
+    for column_name in column_ordering {
+        let structure = match columns.get(column_name) {
+            Some(column_config) if !column_config.structure.is_empty() => &column_config.structure,
+            _ => continue,
+        };
+        let mut add_error = |message: String| {
+            issues.push(locations.column_issue(
+                table_name,
+                column_name,
+                "structure",
+                "error",
+                &message,
+            ))
+        };
+        // Structures that cannot be parsed are reported by get_table_constraints():
+        let parsed_structure = match parser.parse(structure) {
+            Err(_) => continue,
+            Ok(parsed_structure) => parsed_structure,
+        };
+        'expressions: for expression in parsed_structure {
+            let items = match &*expression {
+                Expression::Function(name, args) if name == "from" && args.len() == 1 => {
+                    match &*args[0] {
+                        Expression::Tuple(items) => items,
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            let mut ftables = vec![];
+            let mut fcolumns = vec![];
+            for item in items {
+                match &**item {
+                    Expression::Field(ftable, fcolumn) => {
+                        if !ftables.contains(ftable) {
+                            ftables.push(ftable.to_string());
+                        }
+                        fcolumns.push(fcolumn.to_string());
+                    }
+                    _ => {
+                        add_error(format!("Invalid foreign key: {}", structure));
+                        continue 'expressions;
+                    }
+                };
+            }
+            if ftables.len() != 1 || fcolumns.len() < 2 {
+                add_error(format!(
+                    "Invalid foreign key: {}. A foreign key on more than one column must \
+                     reference two or more columns of a single table.",
+                    structure
+                ));
+                continue;
+            }
+            let ftable = ftables.pop().unwrap();
+            let fcolumns_config = match tables_config.get(&ftable) {
+                None => {
+                    add_error(format!(
+                        "Undefined table '{}' in foreign key: {}",
+                        ftable, structure
+                    ));
+                    continue;
+                }
+                Some(ftable_config) => &ftable_config.column,
+            };
+            let undefined_fcolumns = fcolumns
+                .iter()
+                .filter(|fcolumn| !fcolumns_config.contains_key(*fcolumn))
+                .collect::<Vec<_>>();
+            if !undefined_fcolumns.is_empty() {
+                for fcolumn in undefined_fcolumns {
+                    add_error(format!(
+                        "Undefined column '{}.{}' in foreign key: {}",
+                        ftable, fcolumn, structure
+                    ));
+                }
+                continue;
+            }
+            declared_keys
+                .entry((ftable, fcolumns))
+                .or_insert(vec![])
+                .push(column_name.to_string());
+        }
+    }
+
+    let mut composite_foreigns = vec![];
+    for ((ftable, fcolumns), key_columns) in declared_keys {
+        if key_columns.len() != fcolumns.len() {
+            issues.push(locations.column_issue(
+                table_name,
+                &key_columns[0],
+                "structure",
+                "error",
+                &format!(
+                    "The foreign key referencing ({}) of table '{}' has been declared for {} \
+                     column(s) of table '{}': {}, but it must be declared for exactly {}",
+                    fcolumns.join(", "),
+                    ftable,
+                    key_columns.len(),
+                    table_name,
+                    key_columns.join(", "),
+                    fcolumns.len()
+                ),
+            ));
+            continue;
+        }
+        composite_foreigns.push(ValveCompositeForeignConstraint {
+            table: table_name.to_string(),
+            columns: key_columns,
+            ftable,
+            fcolumns,
+        });
+    }
+    composite_foreigns
+}
+
+/// Given a table configuration map, a parser, a table name, the table-level constraints that have
+/// been declared for the table in the table table, e.g., `primary(subject, visit) unique(a, b)`,
+/// the constraints configuration, the locations of the configured items in the configuration
+/// files, and a list of issues, parse the table-level constraints and return the list of those
+/// that apply to more than one column. Table-level constraints that apply to only one column
+/// are equivalent to the corresponding column-level structures and are added directly to the
+/// primary and unique constraints for the table in the constraints configuration. Invalid
+/// constraints are skipped and the problems found with them are added to the list of issues.
+pub fn get_composite_constraints(
+    tables_config: &HashMap<String, ValveTableConfig>,
+    parser: &StartParser,
+    table_name: &str,
+    constraints: &str,
+    constraints_config: &mut ValveConstraintConfig,
+    locations: &ConfigLocations,
+    issues: &mut Vec<ValveConfigIssue>,
+) -> Vec<ValveCompositeConstraint> {
+    let mut composites = vec![];
+    if constraints.trim() == "" {
+        return composites;
+    }
+
+    let mut add_error = |message: String| {
+        issues.push(locations.table_issue(table_name, "constraints", "error", &message))
+    };
+    let columns = &tables_config
+        .get(table_name)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table_name))
+        .column;
+    let unquoted_re = Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#).unwrap();
+    let parsed_constraints = match parser.parse(constraints) {
+        Err(e) => {
+            add_error(format!(
+                "Could not parse constraints '{}' for table '{}': {}",
+                constraints, table_name, e
+            ));
+            return composites;
+        }
+        Ok(parsed_constraints) => parsed_constraints,
+    };
+    'expressions: for expression in parsed_constraints {
+        let (key_type, args) = match &*expression {
+            Expression::Function(name, args) if name == "primary" || name == "unique" => {
+                (name.to_string(), args)
+            }
+            _ => {
+                add_error(format!(
+                    "Unrecognized constraint {:?} for table '{}'",
+                    expression, table_name
+                ));
+                continue;
+            }
+        };
+        let mut key_columns = vec![];
+        for arg in args {
+            let column = match &**arg {
+                Expression::Label(label) => unquoted_re.replace(label, "$unquoted").to_string(),
+                _ => {
+                    add_error(format!(
+                        "Argument {:?} to '{}' for table '{}' is not a column name",
+                        arg, key_type, table_name
+                    ));
+                    continue 'expressions;
+                }
+            };
+            if !columns.contains_key(&column) {
+                add_error(format!(
+                    "Undefined column '{}.{}' in '{}' constraint",
+                    table_name, column, key_type
+                ));
+                continue 'expressions;
+            }
+            if key_columns.contains(&column) {
+                add_error(format!(
+                    "Column '{}.{}' is repeated in '{}' constraint",
+                    table_name, column, key_type
+                ));
+                continue 'expressions;
+            }
+            key_columns.push(column);
+        }
+
+        let primaries = constraints_config
+            .primary
+            .entry(table_name.to_string())
+            .or_insert(vec![]);
+        let has_primary = !primaries.is_empty()
+            || composites
+                .iter()
+                .any(|c: &ValveCompositeConstraint| c.key_type == "primary");
+        if key_type == "primary" && has_primary {
+            add_error(format!(
+                "More than one primary key has been declared for table '{}'",
+                table_name
+            ));
+            continue;
+        }
+
+        if key_columns.len() == 1 {
+            let column = key_columns.pop().unwrap();
+            if key_type == "primary" {
+                primaries.push(column);
+            } else {
+                let uniques = constraints_config
+                    .unique
+                    .entry(table_name.to_string())
+                    .or_insert(vec![]);
+                if !uniques.contains(&column) {
+                    uniques.push(column);
+                }
+            }
+        } else {
+            composites.push(ValveCompositeConstraint {
+                table: table_name.to_string(),
+                key_type,
+                columns: key_columns,
+            });
+        }
+    }
+    composites
+}
+
+/// Given a datatype configuration map, a parser, and the name of a datatype, return the name of the
+/// item datatype and the separator used by the datatype if its condition is a list() condition,
+/// or None otherwise.
+pub fn get_list_datatype(
+    datatypes_config: &HashMap<String, ValveDatatypeConfig>,
+    parser: &StartParser,
+    datatype: &str,
+) -> Option<(String, String)> {
+    let condition = &datatypes_config.get(datatype)?.condition;
+    if condition.is_empty() {
+        return None;
+    }
+    let parsed_condition = parser.parse(condition).ok()?;
+    if parsed_condition.len() != 1 {
+        return None;
+    }
+    match &*parsed_condition[0] {
+        Expression::Function(name, args) if name == "list" && args.len() == 2 => {
+            match (&*args[0], &*args[1]) {
+                (Expression::Label(item_datatype), Expression::Label(separator)) => {
+                    let unquoted_re = Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#).unwrap();
+                    let separator = unquoted_re.replace(separator, "$unquoted");
+                    Some((item_datatype.to_string(), separator.to_string()))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Given a tree constraint and a value of the tree's parent column, return the parent values
+/// contained in the given value, i.e., the items of the value if the parent column has a list()
+/// datatype, or the value itself otherwise. Empty values are ignored.
+pub fn get_tree_parent_values(tree: &ValveTreeConstraint, value: &str) -> Vec<String> {
+    match &tree.separator {
+        Some(separator) => value
+            .split(separator.as_str())
+            .filter(|item| !item.is_empty())
+            .map(|item| item.to_string())
+            .collect(),
+        None if !value.is_empty() => vec![value.to_string()],
+        None => vec![],
+    }
+}
+
+/// Given a table configuration map and a datatype configuration map, a parser, a table name, a
+/// database connection pool, the locations of the configured items in the configuration files,
+/// and a list of issues, return lists of: primary keys, unique constraints, foreign keys, and
+/// trees. Invalid structures are left out of the lists and the problems found with them are added
+/// to the list of issues.
 pub fn get_table_constraints(
     tables_config: &HashMap<String, ValveTableConfig>,
     datatypes_config: &HashMap<String, ValveDatatypeConfig>,
     parser: &StartParser,
     table_name: &str,
     pool: &AnyPool,
+    locations: &ConfigLocations,
+    issues: &mut Vec<ValveConfigIssue>,
 ) -> (
     Vec<String>,
     Vec<String>,
@@ -4160,101 +7426,121 @@ pub fn get_table_constraints(
     let mut foreigns = vec![];
     let mut trees = vec![];
 
-    let columns = tables_config
+    let table_config = tables_config
         .get(table_name)
-        .and_then(|t| Some(t.column.clone()))
         .expect(&format!("Undefined table '{}'", table_name));
-    let mut colvals = vec![];
-    for (_, column) in columns.iter() {
-        colvals.push(column.clone());
-    }
-
-    for row in colvals {
+    let columns = &table_config.column;
+    for column_name in &table_config.column_order {
+        let row = match columns.get(column_name) {
+            None => continue,
+            Some(row) => row,
+        };
         let datatype = &row.datatype;
-        let sql_type = get_sql_type(datatypes_config, datatype, pool);
-        let column_name = &row.column;
         let structure = &row.structure;
-        if structure != "" {
-            let parsed_structure = parser
-                .parse(&structure)
-                .expect(&format!("Could not parse structure '{}'", structure));
-            for expression in parsed_structure {
-                match *expression {
-                    Expression::Label(value) if value == "primary" => {
-                        primaries.push(column_name.to_string());
-                    }
-                    Expression::Label(value) if value == "unique" => {
-                        uniques.push(column_name.to_string());
-                    }
-                    Expression::Function(name, args) if name == "from" => {
-                        if args.len() != 1 {
-                            panic!("Invalid foreign key: {} for: {}", structure, table_name);
-                        }
-                        match &*args[0] {
-                            Expression::Field(ftable, fcolumn) => {
-                                foreigns.push(ValveForeignConstraint {
-                                    table: table_name.to_string(),
-                                    column: column_name.to_string(),
-                                    ftable: ftable.to_string(),
-                                    fcolumn: fcolumn.to_string(),
-                                });
-                            }
-                            _ => {
-                                panic!("Invalid foreign key: {} for: {}", structure, table_name)
+        if structure.is_empty() {
+            continue;
+        }
+        let mut add_error = |message: String| {
+            issues.push(locations.column_issue(
+                table_name,
+                column_name,
+                "structure",
+                "error",
+                &message,
+            ))
+        };
+        let parsed_structure = match parser.parse(structure) {
+            Err(e) => {
+                add_error(format!("Could not parse structure '{}': {}", structure, e));
+                continue;
+            }
+            Ok(parsed_structure) => parsed_structure,
+        };
+        for expression in parsed_structure {
+            match &*expression {
+                Expression::Label(value) if value == "primary" => {
+                    primaries.push(column_name.to_string());
+                }
+                Expression::Label(value) if value == "unique" => {
+                    uniques.push(column_name.to_string());
+                }
+                Expression::Function(name, args) if name == "from" && args.len() == 1 => {
+                    match &*args[0] {
+                        Expression::Field(ftable, fcolumn) => match tables_config.get(ftable) {
+                            None => add_error(format!(
+                                "Undefined table '{}' in foreign key: {}",
+                                ftable, structure
+                            )),
+                            Some(ftable_config) if !ftable_config.column.contains_key(fcolumn) => {
+                                add_error(format!(
+                                    "Undefined column '{}.{}' in foreign key: {}",
+                                    ftable, fcolumn, structure
+                                ))
                             }
-                        };
-                    }
-                    Expression::Function(name, args) if name == "tree" => {
-                        if args.len() != 1 {
-                            panic!(
-                                "Invalid 'tree' constraint: {} for: {}",
-                                structure, table_name
-                            );
-                        }
-                        match &*args[0] {
-                            Expression::Label(child) => {
-                                let child_datatype = columns
-                                    .get(child)
-                                    .and_then(|c| Some(c.datatype.to_string()));
-                                if let None = child_datatype {
-                                    panic!(
-                                        "Could not determine datatype for {} of tree({})",
-                                        child, child
-                                    );
+                            _ => foreigns.push(ValveForeignConstraint {
+                                table: table_name.to_string(),
+                                column: column_name.to_string(),
+                                ftable: ftable.to_string(),
+                                fcolumn: fcolumn.to_string(),
+                            }),
+                        },
+                        // Foreign keys on more than one column are collected separately by
+                        // get_composite_foreign_constraints():
+                        Expression::Tuple(_) => (),
+                        _ => add_error(format!("Invalid foreign key: {}", structure)),
+                    };
+                }
+                Expression::Function(name, args) if name == "tree" && args.len() == 1 => {
+                    match &*args[0] {
+                        Expression::Label(child) => {
+                            let child_datatype = match columns.get(child) {
+                                None => {
+                                    add_error(format!(
+                                        "Undefined column '{}.{}' in 'tree({})'",
+                                        table_name, child, child
+                                    ));
+                                    continue;
                                 }
-                                let child_datatype = child_datatype.unwrap();
-                                let parent = column_name;
-                                let child_sql_type = get_sql_type(
-                                    datatypes_config,
-                                    &child_datatype.to_string(),
-                                    pool,
-                                );
-                                if sql_type != child_sql_type {
-                                    panic!(
-                                        "SQL type '{}' of '{}' in 'tree({})' for table \
-                                         '{}' doe snot match SQL type: '{}' of parent: '{}'.",
-                                        child_sql_type, child, child, table_name, sql_type, parent
-                                    );
+                                Some(child_config) => &child_config.datatype,
+                            };
+                            let child_sql_type =
+                                get_sql_type(datatypes_config, child_datatype, pool);
+                            // If the parent column has a list() datatype then each item in a
+                            // given cell of the parent column is a parent value, and it is the
+                            // SQL type of the items that must match that of the child:
+                            let list_datatype =
+                                get_list_datatype(datatypes_config, parser, datatype);
+                            let sql_type = match &list_datatype {
+                                Some((item_datatype, _)) => {
+                                    get_sql_type(datatypes_config, item_datatype, pool)
                                 }
-                                trees.push(ValveTreeConstraint {
-                                    child: child.to_string(),
-                                    parent: column_name.to_string(),
-                                });
-                            }
-                            _ => {
-                                panic!(
-                                    "Invalid 'tree' constraint: {} for: {}",
-                                    structure, table_name
-                                );
+                                None => get_sql_type(datatypes_config, datatype, pool),
+                            };
+                            if sql_type != child_sql_type {
+                                add_error(format!(
+                                    "SQL type '{}' of '{}' in 'tree({})' does not match SQL type \
+                                     '{}' of parent '{}'",
+                                    child_sql_type, child, child, sql_type, column_name
+                                ));
+                                continue;
                             }
-                        };
-                    }
-                    _ => panic!(
-                        "Unrecognized structure: {} for {}.{}",
-                        structure, table_name, column_name
-                    ),
-                };
-            }
+                            trees.push(ValveTreeConstraint {
+                                child: child.to_string(),
+                                parent: column_name.to_string(),
+                                separator: list_datatype.map(|(_, separator)| separator),
+                            });
+                        }
+                        _ => add_error(format!("Invalid 'tree' constraint: {}", structure)),
+                    };
+                }
+                Expression::Function(name, _) if name == "from" => {
+                    add_error(format!("Invalid foreign key: {}", structure))
+                }
+                Expression::Function(name, _) if name == "tree" => {
+                    add_error(format!("Invalid 'tree' constraint: {}", structure))
+                }
+                _ => add_error(format!("Unrecognized structure: {}", structure)),
+            };
         }
     }
 
@@ -4416,7 +7702,7 @@ pub fn sort_messages(
             for datatype in sorted_datatypes {
                 let mut messages = datatype_messages
                     .iter()
-                    .filter(|m| m.rule == format!("datatype:{}", datatype))
+                    .filter(|m| m.rule == ValveRuleKind::Datatype.rule_id(datatype))
                     .map(|m| m.clone())
                     .collect::<Vec<_>>();
                 sorted_messages.append(&mut messages);
@@ -4495,7 +7781,8 @@ pub async fn make_inserts(
             let row_number = row.row_number.unwrap();
             // The row order defaults to the row number:
             let row_order = row_number * MOVE_INTERVAL;
-            let use_conflict_table = is_conflict_row(&row, &conflict_columns);
+            let use_conflict_table = is_conflict_row(row, &conflict_columns)
+                || has_null_primary_key(config, main_table, row, pool);
             let mut row_values = vec![format!("{}, {}", row_number, row_order)];
             let mut row_params = vec![];
             // If a table has been configured with more columns than are actually in its
@@ -4513,7 +7800,7 @@ pub async fn make_inserts(
                 if cell.nulltype != None || is_sql_type_error(&sql_type, &cell.strvalue()) {
                     row_values.push(String::from("NULL"));
                 } else {
-                    row_values.push(cast_sql_param_from_text(&sql_type));
+                    row_values.push(cast_sql_param_from_text(&sql_type, pool));
                     row_params.push(cell.strvalue());
                 }
 
@@ -4524,7 +7811,7 @@ pub async fn make_inserts(
 
                 for message in sort_messages(&sorted_datatypes, &cell.messages) {
                     let row = row_number.to_string();
-                    let message_values = vec![
+                    let mut message_values = vec![
                         SQL_PARAM, &row, SQL_PARAM, SQL_PARAM, SQL_PARAM, SQL_PARAM, SQL_PARAM,
                     ];
 
@@ -4534,6 +7821,12 @@ pub async fn make_inserts(
                     message_params.push(message.level);
                     message_params.push(message.rule);
                     message_params.push(message.message);
+                    if message.suggestions.is_empty() {
+                        message_values.push("NULL");
+                    } else {
+                        message_values.push(SQL_PARAM);
+                        message_params.push(json!(message.suggestions).to_string());
+                    }
                     let line = message_values.join(", ");
                     let line = format!("({})", line);
                     message_lines.push(line);
@@ -4581,8 +7874,9 @@ pub async fn make_inserts(
         let mut message_output = String::from("");
         if !message_lines.is_empty() {
             message_output.push_str(r#"INSERT INTO "message" "#);
-            message_output
-                .push_str(r#"("table", "row", "column", "value", "level", "rule", "message") "#);
+            message_output.push_str(
+                r#"("table", "row", "column", "value", "level", "rule", "message", "suggestions") "#,
+            );
             message_output.push_str("VALUES");
             message_output.push_str("\n");
             message_output.push_str(&message_lines.join(",\n"));
@@ -4717,9 +8011,22 @@ pub async fn insert_chunk(
                         "Cannot find foreign constraints for table '{}'",
                         table_name
                     ));
+                    let composite_foreigns = config
+                        .constraint
+                        .composite_foreign
+                        .get(table_name)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Cannot find multi-column foreign constraints for table '{}'",
+                                table_name
+                            )
+                        });
                     foreigns
                         .iter()
                         .any(|foreign| foreign.column == *column_name)
+                        || composite_foreigns
+                            .iter()
+                            .any(|foreign| foreign.columns.contains(column_name))
                 }
                 ValueType::Single => false,
             }
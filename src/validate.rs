@@ -3,22 +3,56 @@
 use crate::{
     ast::Expression,
     toolkit::{
-        cast_sql_param_from_text, get_column_value, get_column_value_as_string,
-        get_datatype_ancestors, get_query_param, get_sql_type_from_global_config,
-        get_table_options_from_config, get_value_type, is_sql_type_error, local_sql_syntax,
-        ColumnRule, CompiledCondition, QueryAsIf, QueryAsIfKind, QueryParam, ValueType,
+        add_transform_messages, cast_sql_param_from_text, get_column_value,
+        get_column_value_as_string, get_conflict_columns, get_datatype_ancestors, get_query_param,
+        get_sql_type_from_global_config, get_suggestions, get_table_options_from_config,
+        get_tree_parent_values, get_value_type, has_null_primary_key, is_sql_type_error,
+        local_sql_syntax, transform_row, ColumnRule, CompiledCondition, QueryAsIf, QueryAsIfKind,
+        QueryParam, ValueType,
     },
     valve::{
-        ValveCell, ValveCellMessage, ValveConfig, ValveRow, ValveRuleConfig, ValveTreeConstraint,
+        ValveCell, ValveCellMessage, ValveCompositeConstraint, ValveCompositeForeignConstraint,
+        ValveConfig, ValveError, ValveRow, ValveRuleKind, ValveTreeConstraint,
     },
     DT_CACHE_SIZE, SQL_PARAM,
 };
 use anyhow::Result;
 use indexmap::IndexMap;
+use lazy_static::lazy_static;
 use lfu_cache::LfuCache;
+use regex::Regex;
+use rhai::{Dynamic, Engine, Map as RhaiMap, Scope, AST};
 use serde_json::{json, Value as SerdeValue};
 use sqlx::{any::AnyPool, query as sqlx_query, Acquire, Row, Transaction, ValueRef};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+/// Alias for a map from the names of foreign tables to the rows of each foreign table and of its
+/// conflict table, used to check multi-column foreign keys without accessing the database.
+pub type CompositeCache = HashMap<String, (Vec<ValveRow>, Vec<ValveRow>)>;
+
+lazy_static! {
+    static ref UNQUOTED_RE: Regex = Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#).unwrap();
+    // The engine used to run script rules. Scripts are sandboxed: They have no access to the file
+    // system or to modules, cannot call eval(), and are subject to limits on their resource usage.
+    static ref SCRIPT_ENGINE: Engine = {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(100_000)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_modules(0)
+            .set_max_string_size(10_000)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000)
+            .disable_symbol("eval")
+            .on_print(|text| log::info!("{}", text))
+            .on_debug(|text, _, _| log::debug!("{}", text));
+        engine
+    };
+}
 
 /// Given a config struct, maps of compiled datatype and rule conditions, a database connection
 /// pool, a table name, a row to validate represented as a [ValveRow], and a row number in the case
@@ -48,8 +82,10 @@ pub async fn validate_row_tx(
     // Store the row number in a separate local variable for convenience:
     let row_number = row.row_number;
 
-    // Initialize the result row with the values from the given row:
+    // Initialize the result row with the values from the given row, and apply any transforms that
+    // have been configured for the row's columns:
     let mut valve_row = row.clone();
+    let rewritten = transform_row(config, table_name, &mut valve_row);
 
     // We check all the cells for nulltype first, since the rules validation requires that we
     // have this information for all cells.
@@ -96,8 +132,10 @@ pub async fn validate_row_tx(
                     &table_name.to_string(),
                     column_name,
                     cell,
+                    &context,
                     query_as_if,
                     &None,
+                    &None,
                 )
                 .await?;
                 validate_cell_unique_constraints(
@@ -107,15 +145,19 @@ pub async fn validate_row_tx(
                     &table_name.to_string(),
                     column_name,
                     cell,
+                    &context,
                     &vec![],
                     row_number,
                     &None,
+                    &None,
                 )
                 .await?;
             }
         }
     }
 
+    validate_row_scripts(config, table_name, &context, &mut valve_row);
+
     // TODO: Possibly propagate `query_as_if` down into this function:
     let mut violations = validate_tree_foreign_keys(
         config,
@@ -125,6 +167,16 @@ pub async fn validate_row_tx(
         Some(&context.clone()),
     )
     .await?;
+    violations.append(
+        &mut validate_tree_cycles(
+            config,
+            pool,
+            Some(tx),
+            &table_name.to_string(),
+            Some(&context.clone()),
+        )
+        .await?,
+    );
     for violation in violations.iter_mut() {
         let vrow_number = violation.get("row_number").unwrap().as_i64().unwrap() as u32;
         if Some(vrow_number) == row_number || (row_number == None && Some(vrow_number) == Some(0)) {
@@ -137,6 +189,7 @@ pub async fn validate_row_tx(
                 level: level.to_string(),
                 rule: rule.to_string(),
                 message: message.to_string(),
+                ..Default::default()
             });
             if result_cell.valid {
                 result_cell.valid = false;
@@ -145,6 +198,7 @@ pub async fn validate_row_tx(
     }
 
     remove_duplicate_messages(&mut valve_row)?;
+    add_transform_messages(&mut valve_row, &rewritten);
     Ok(valve_row)
 }
 
@@ -196,6 +250,63 @@ pub async fn validate_tree_foreign_keys(
             effective_table_name = query_table.clone();
         }
 
+        // If the parent column has a list() datatype, then each of the items in a given cell of
+        // the parent column must be checked individually, which we do here rather than in SQL:
+        if tkey.separator.is_some() {
+            let child_sql_type =
+                get_sql_type_from_global_config(config, table_name, child_col, pool);
+            let sql = local_sql_syntax(
+                pool,
+                &format!(
+                    r#"{with_clause}
+                       SELECT "row_number", "{child_col}", "{parent_col}"
+                       FROM "{effective_table_name}""#,
+                ),
+            );
+            let mut query = sqlx_query(&sql);
+            for param in &params {
+                query = query.bind(param);
+            }
+            let rows = {
+                if let Some(tx) = tx.as_mut() {
+                    query.fetch_all(tx.acquire().await?).await?
+                } else {
+                    query.fetch_all(pool).await?
+                }
+            };
+            let child_vals = rows
+                .iter()
+                .filter(|row| !row.try_get_raw(child_col.as_str()).unwrap().is_null())
+                .map(|row| get_column_value_as_string(row, child_col, &child_sql_type))
+                .collect::<HashSet<_>>();
+            for row in &rows {
+                let raw_row_number = row.try_get_raw("row_number").unwrap();
+                let row_number: i64 = if raw_row_number.is_null() {
+                    0
+                } else {
+                    row.get("row_number")
+                };
+                if row.try_get_raw(parent_col.as_str()).unwrap().is_null() {
+                    continue;
+                }
+                let parent_val = get_column_value_as_string(row, parent_col, &parent_sql_type);
+                for parent_item in get_tree_parent_values(tkey, &parent_val) {
+                    if !child_vals.contains(&parent_item) {
+                        results.push(json!({
+                            "row_number": row_number as u32,
+                            "column": parent_col,
+                            "value": parent_val,
+                            "level": ValveRuleKind::TreeForeign.default_level(),
+                            "rule": ValveRuleKind::TreeForeign.id(),
+                            "message": format!("Value '{}' of column {} is not in column {}",
+                                               parent_item, parent_col, child_col).as_str(),
+                        }));
+                    }
+                }
+            }
+            continue;
+        }
+
         let sql = local_sql_syntax(
             &pool,
             &format!(
@@ -245,8 +356,8 @@ pub async fn validate_tree_foreign_keys(
                     "row_number": row_number as u32,
                     "column": parent_col,
                     "value": parent_val,
-                    "level": "error",
-                    "rule": "tree:foreign",
+                    "level": ValveRuleKind::TreeForeign.default_level(),
+                    "rule": ValveRuleKind::TreeForeign.id(),
                     "message": format!("Value '{}' of column {} is not in column {}",
                                        parent_val, parent_col, child_col).as_str(),
                 });
@@ -258,6 +369,154 @@ pub async fn validate_tree_foreign_keys(
     Ok(results)
 }
 
+/// Given a map from each child value in a tree to its parent values, a child value, and its
+/// parent value, return the path from the child, through the parent, back to the child, or None
+/// if the parent is not a descendant of the child. The path returned is a shortest one.
+fn find_tree_cycle(
+    parents: &HashMap<String, Vec<String>>,
+    child: &str,
+    parent: &str,
+) -> Option<Vec<String>> {
+    let mut predecessors: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([parent]);
+    let mut visited = HashSet::from([parent]);
+    while let Some(node) = queue.pop_front() {
+        if node == child {
+            let mut path = vec![child.to_string()];
+            let mut current = node;
+            while current != parent {
+                current = predecessors.get(current).unwrap();
+                path.push(current.to_string());
+            }
+            path.push(child.to_string());
+            path.reverse();
+            return Some(path);
+        }
+        for next in parents.get(node).into_iter().flatten() {
+            if visited.insert(next.as_str()) {
+                predecessors.insert(next.as_str(), node);
+                queue.push_back(next.as_str());
+            }
+        }
+    }
+    None
+}
+
+/// Given a config map, a db connection pool, and a table name, validate whether any of the table's
+/// trees contains a cycle; i.e., for a given tree: tree(child) which has a given parent column,
+/// validate that no value of the child column is its own ancestor. A 'tree:cycle' violation,
+/// listing the path of the cycle, is returned for each row whose parent value is part of a cycle.
+/// Optionally, if a transaction is given, use that instead of the pool for database access, and
+/// if an extra row is given, validate the table as if it contained that row.
+pub async fn validate_tree_cycles(
+    config: &ValveConfig,
+    pool: &AnyPool,
+    mut tx: Option<&mut Transaction<'_, sqlx::Any>>,
+    table_name: &String,
+    extra_row: Option<&ValveRow>,
+) -> Result<Vec<SerdeValue>> {
+    let tkeys = config
+        .constraint
+        .tree
+        .get(table_name)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table_name));
+
+    let table_options = get_table_options_from_config(config, table_name)?;
+    let query_table = {
+        if !table_options.contains("conflict") {
+            table_name.to_string()
+        } else {
+            format!("{}_view", table_name)
+        }
+    };
+    let mut results = vec![];
+    for tkey in tkeys {
+        let child_col = &tkey.child;
+        let parent_col = &tkey.parent;
+        let child_sql_type = get_sql_type_from_global_config(config, table_name, child_col, pool);
+        let parent_sql_type = get_sql_type_from_global_config(config, table_name, parent_col, pool);
+        let (with_clause, params, effective_table_name) = match extra_row {
+            Some(extra_row) => {
+                let (with_clause, params) =
+                    select_with_extra_row(config, extra_row, table_name, &query_table, pool);
+                (with_clause, params, format!("{}_ext", query_table))
+            }
+            None => (String::new(), vec![], query_table.clone()),
+        };
+
+        let sql = local_sql_syntax(
+            pool,
+            &format!(
+                r#"{with_clause}
+                   SELECT "row_number", "{child_col}", "{parent_col}"
+                   FROM "{effective_table_name}"
+                   WHERE "{child_col}" IS NOT NULL AND "{parent_col}" IS NOT NULL
+                   ORDER BY "row_number""#,
+                with_clause = with_clause,
+                child_col = child_col,
+                parent_col = parent_col,
+                effective_table_name = effective_table_name,
+            ),
+        );
+
+        let mut query = sqlx_query(&sql);
+        for param in &params {
+            query = query.bind(param);
+        }
+        let rows = {
+            if let Some(tx) = tx.as_mut() {
+                query.fetch_all(tx.acquire().await?).await?
+            } else {
+                query.fetch_all(pool).await?
+            }
+        };
+
+        let mut edges = vec![];
+        let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let raw_row_number = row.try_get_raw("row_number").unwrap();
+            let row_number: i64 = if raw_row_number.is_null() {
+                0
+            } else {
+                row.get("row_number")
+            };
+            let child_val = get_column_value_as_string(&row, child_col, &child_sql_type);
+            let parent_val = get_column_value_as_string(&row, parent_col, &parent_sql_type);
+            // If the parent column has a list() datatype, each of the items in the cell is a
+            // parent of the child:
+            for parent_item in get_tree_parent_values(tkey, &parent_val) {
+                parents
+                    .entry(child_val.to_string())
+                    .or_default()
+                    .push(parent_item.to_string());
+                edges.push((
+                    row_number,
+                    child_val.to_string(),
+                    parent_val.to_string(),
+                    parent_item,
+                ));
+            }
+        }
+
+        for (row_number, child_val, parent_val, parent_item) in edges {
+            if let Some(path) = find_tree_cycle(&parents, &child_val, &parent_item) {
+                let message = json!({
+                    "row_number": row_number as u32,
+                    "column": parent_col,
+                    "value": parent_val,
+                    "level": ValveRuleKind::TreeCycle.default_level(),
+                    "rule": ValveRuleKind::TreeCycle.id(),
+                    "message": format!("Value '{}' of column {} results in a cycle: {}",
+                                       parent_item, parent_col, path.join(" -> ")).as_str(),
+                });
+                results.push(message);
+            }
+        }
+    }
+
+    Ok(results)
+}
+
 /// Given a config map, a database connection pool, a hashmap describing datatype conditions, a
 /// table name, and a number of rows to validate, validate foreign and unique constraints, where
 /// the latter include unique and primary constraints and modify the given rows with the validation
@@ -331,7 +590,7 @@ pub async fn validate_rows_constraints(
                 let param_values = values
                     .iter()
                     .map(|value| get_query_param(value, &sql_type))
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>>>()?;
 
                 // Foreign keys always correspond to columns with unique constraints so we do not
                 // need to use the keyword 'DISTINCT' when querying the normal version of the table:
@@ -348,6 +607,8 @@ pub async fn validate_rows_constraints(
                         QueryParam::Integer(p) => query = query.bind(p),
                         QueryParam::Numeric(p) => query = query.bind(p),
                         QueryParam::Real(p) => query = query.bind(p),
+                        QueryParam::Date(p) => query = query.bind(p),
+                        QueryParam::Timestamp(p) => query = query.bind(p),
                         QueryParam::String(p) => query = query.bind(p),
                     }
                 }
@@ -382,6 +643,8 @@ pub async fn validate_rows_constraints(
                                 QueryParam::Integer(p) => query = query.bind(p),
                                 QueryParam::Numeric(p) => query = query.bind(p),
                                 QueryParam::Real(p) => query = query.bind(p),
+                                QueryParam::Date(p) => query = query.bind(p),
+                                QueryParam::Timestamp(p) => query = query.bind(p),
                                 QueryParam::String(p) => query = query.bind(p),
                             }
                         }
@@ -463,7 +726,7 @@ pub async fn validate_rows_constraints(
             let param_values = values
                 .iter()
                 .map(|value| get_query_param(value, &sql_type))
-                .collect::<Vec<_>>();
+                .collect::<Result<Vec<_>>>()?;
 
             let sql = local_sql_syntax(
                 pool,
@@ -478,6 +741,8 @@ pub async fn validate_rows_constraints(
                     QueryParam::Integer(p) => query = query.bind(p),
                     QueryParam::Numeric(p) => query = query.bind(p),
                     QueryParam::Real(p) => query = query.bind(p),
+                    QueryParam::Date(p) => query = query.bind(p),
+                    QueryParam::Timestamp(p) => query = query.bind(p),
                     QueryParam::String(p) => query = query.bind(p),
                 }
             }
@@ -494,6 +759,266 @@ pub async fn validate_rows_constraints(
         }
     }
 
+    // Given a config map, a pool, and a table and column name, then if the column is not the last
+    // column of some multi-column foreign key, return None. Otherwise return a map from the names of
+    // the foreign tables referenced by every such key to two vectors of ValveRows, where the first
+    // contains the (distinct) combinations of values of the key's foreign columns in the foreign
+    // table, and the second contains those in the foreign table's conflict table, or is empty if no
+    // conflict table exists.
+    async fn get_allowed_composites(
+        config: &ValveConfig,
+        pool: &AnyPool,
+        table: &str,
+        column: &str,
+        received_values: &HashMap<&str, Vec<SerdeValue>>,
+    ) -> Result<Option<CompositeCache>> {
+        // Collect, for each foreign table, the foreign columns to fetch and the foreign columns
+        // that correspond to the given column:
+        let mut fcolumns_by_ftable: IndexMap<String, (Vec<String>, Vec<String>)> = IndexMap::new();
+        for fkey in config
+            .constraint
+            .composite_foreign
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table))
+            .iter()
+            .filter(|c| c.columns.last().map(|c| c.as_str()) == Some(column))
+        {
+            let (fcolumns, lookup_fcolumns) = fcolumns_by_ftable
+                .entry(fkey.ftable.to_string())
+                .or_insert((vec![], vec![]));
+            for fcolumn in &fkey.fcolumns {
+                if !fcolumns.contains(fcolumn) {
+                    fcolumns.push(fcolumn.to_string());
+                }
+            }
+            let lookup_fcolumn = fkey.fcolumns.last().unwrap();
+            if !lookup_fcolumns.contains(lookup_fcolumn) {
+                lookup_fcolumns.push(lookup_fcolumn.to_string());
+            }
+        }
+        if fcolumns_by_ftable.is_empty() {
+            return Ok(None);
+        }
+
+        let mut allowed_composites = HashMap::new();
+        for (ftable, (fcolumns, lookup_fcolumns)) in &fcolumns_by_ftable {
+            let mut where_clauses = vec![];
+            let mut param_values = vec![];
+            for lookup_fcolumn in lookup_fcolumns {
+                let sql_type =
+                    get_sql_type_from_global_config(config, ftable, lookup_fcolumn, pool)
+                        .to_lowercase();
+                let values = received_values
+                    .get(column)
+                    .unwrap()
+                    .iter()
+                    .filter(|value| {
+                        !is_sql_type_error(
+                            &sql_type,
+                            value
+                                .as_str()
+                                .unwrap_or_else(|| panic!("'{}' is not a string", value)),
+                        )
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if values.is_empty() {
+                    continue;
+                }
+                where_clauses.push(format!(
+                    r#""{}" IN ({})"#,
+                    lookup_fcolumn,
+                    values
+                        .iter()
+                        .map(|_| SQL_PARAM.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+                for value in &values {
+                    param_values.push(get_query_param(value, &sql_type)?);
+                }
+            }
+            if where_clauses.is_empty() {
+                allowed_composites.insert(ftable.to_string(), (vec![], vec![]));
+                continue;
+            }
+
+            let foptions = &config
+                .table
+                .get(ftable)
+                .unwrap_or_else(|| panic!("No config for table: '{}'", ftable))
+                .options;
+            let mut ftable_rows = vec![];
+            for query_table in [ftable.to_string(), format!("{}_conflict", ftable)] {
+                if query_table.ends_with("_conflict") && !foptions.contains("conflict") {
+                    // If there is no conflict table just use an empty vector.
+                    ftable_rows.push(vec![]);
+                    continue;
+                }
+                let sql = local_sql_syntax(
+                    pool,
+                    &format!(
+                        r#"SELECT DISTINCT {} FROM "{}" WHERE {}"#,
+                        fcolumns
+                            .iter()
+                            .map(|c| format!(r#""{}""#, c))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        query_table,
+                        where_clauses.join(" OR ")
+                    ),
+                );
+                let mut query = sqlx_query(&sql);
+                for param_value in &param_values {
+                    match param_value {
+                        QueryParam::Integer(p) => query = query.bind(p),
+                        QueryParam::Numeric(p) => query = query.bind(p),
+                        QueryParam::Real(p) => query = query.bind(p),
+                        QueryParam::Date(p) => query = query.bind(p),
+                        QueryParam::Timestamp(p) => query = query.bind(p),
+                        QueryParam::String(p) => query = query.bind(p),
+                    }
+                }
+                let mut rows = vec![];
+                for row in query.fetch_all(pool).await? {
+                    let mut allowed_row = ValveRow {
+                        row_number: None,
+                        contents: IndexMap::new(),
+                    };
+                    for fcolumn in fcolumns {
+                        let sql_type =
+                            get_sql_type_from_global_config(config, ftable, fcolumn, pool)
+                                .to_lowercase();
+                        allowed_row.contents.insert(
+                            fcolumn.to_string(),
+                            ValveCell::new(&get_column_value(&row, fcolumn, &sql_type)),
+                        );
+                    }
+                    rows.push(allowed_row);
+                }
+                ftable_rows.push(rows);
+            }
+            let conflict_rows = ftable_rows.pop().unwrap();
+            let rows = ftable_rows.pop().unwrap();
+            allowed_composites.insert(ftable.to_string(), (rows, conflict_rows));
+        }
+        Ok(Some(allowed_composites))
+    }
+
+    // Given a config map, a pool, and a table and column name, then if the column is not the last
+    // column of some multi-column primary or unique key, return None. Otherwise return a vector of
+    // ValveRows containing the (distinct) combinations of values, in the table, of the columns of
+    // every such key, regardless of their validity (except when the invalidity would result in a
+    // SQL error).
+    async fn get_forbidden_composites(
+        config: &ValveConfig,
+        pool: &AnyPool,
+        table: &str,
+        column: &str,
+        received_values: &HashMap<&str, Vec<SerdeValue>>,
+    ) -> Result<Option<Vec<ValveRow>>> {
+        let mut key_columns = vec![];
+        for composite in config
+            .constraint
+            .composite
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table))
+            .iter()
+            .filter(|c| c.columns.last().map(|c| c.as_str()) == Some(column))
+        {
+            for key_column in &composite.columns {
+                if !key_columns.contains(key_column) {
+                    key_columns.push(key_column.to_string());
+                }
+            }
+        }
+        if key_columns.is_empty() {
+            return Ok(None);
+        }
+
+        let options = &config
+            .table
+            .get(table)
+            .unwrap_or_else(|| panic!("No config for table: '{}'", table))
+            .options;
+        let query_table = {
+            if !options.contains("conflict") {
+                table.to_string()
+            } else {
+                format!("{}_view", table)
+            }
+        };
+
+        let sql_type = get_sql_type_from_global_config(config, table, column, pool).to_lowercase();
+        let values = received_values
+            .get(column)
+            .unwrap()
+            .iter()
+            .filter(|value| {
+                !is_sql_type_error(
+                    &sql_type,
+                    value
+                        .as_str()
+                        .unwrap_or_else(|| panic!("'{}' is not a string", value)),
+                )
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let lookup_sql = values
+            .iter()
+            .map(|_| SQL_PARAM.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let param_values = values
+            .iter()
+            .map(|value| get_query_param(value, &sql_type))
+            .collect::<Result<Vec<_>>>()?;
+
+        let sql = local_sql_syntax(
+            pool,
+            &format!(
+                r#"SELECT DISTINCT {} FROM "{}" WHERE "{}" IN ({})"#,
+                key_columns
+                    .iter()
+                    .map(|c| format!(r#""{}""#, c))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                query_table,
+                column,
+                lookup_sql
+            ),
+        );
+        let mut query = sqlx_query(&sql);
+        for param_value in &param_values {
+            match param_value {
+                QueryParam::Integer(p) => query = query.bind(p),
+                QueryParam::Numeric(p) => query = query.bind(p),
+                QueryParam::Real(p) => query = query.bind(p),
+                QueryParam::Date(p) => query = query.bind(p),
+                QueryParam::Timestamp(p) => query = query.bind(p),
+                QueryParam::String(p) => query = query.bind(p),
+            }
+        }
+
+        let mut forbidden_rows = vec![];
+        for row in query.fetch_all(pool).await? {
+            let mut forbidden_row = ValveRow {
+                row_number: None,
+                contents: IndexMap::new(),
+            };
+            for key_column in &key_columns {
+                let sql_type =
+                    get_sql_type_from_global_config(config, table, key_column, pool).to_lowercase();
+                forbidden_row.contents.insert(
+                    key_column.to_string(),
+                    ValveCell::new(&get_column_value(&row, key_column, &sql_type)),
+                );
+            }
+            forbidden_rows.push(forbidden_row);
+        }
+        Ok(Some(forbidden_rows))
+    }
+
     // Begin by getting the table config for this table:
     let table_config = &config
         .table
@@ -563,6 +1088,28 @@ pub async fn validate_rows_constraints(
         }
         forbidden_values
     };
+    let allowed_composites = {
+        let mut allowed_composites = HashMap::new();
+        for column in &table_config.column_order {
+            allowed_composites.insert(
+                column.to_string(),
+                get_allowed_composites(config, pool, table, column, &received_values_unsplit)
+                    .await?,
+            );
+        }
+        allowed_composites
+    };
+    let forbidden_composites = {
+        let mut forbidden_composites = HashMap::new();
+        for column in &table_config.column_order {
+            forbidden_composites.insert(
+                column.to_string(),
+                get_forbidden_composites(config, pool, table, column, &received_values_unsplit)
+                    .await?,
+            );
+        }
+        forbidden_composites
+    };
 
     let mut valve_rows = vec![];
     for row in rows.iter_mut() {
@@ -570,6 +1117,7 @@ pub async fn validate_rows_constraints(
             row_number: None,
             contents: IndexMap::new(),
         };
+        let context = row.clone();
         for column in &table_config.column_order {
             let cell = row.contents.get_mut(column).unwrap();
             // We don't do any further validation on cells that are legitimately empty, or on cells
@@ -583,6 +1131,12 @@ pub async fn validate_rows_constraints(
                     let forbidden_values = forbidden_values.get(column).expect(&error_msg);
                     (allowed_values, forbidden_values)
                 };
+                let allowed_composites = allowed_composites.get(column).unwrap_or_else(|| {
+                    panic!("Could not retrieve key values for column '{}'", column)
+                });
+                let forbidden_composites = forbidden_composites.get(column).unwrap_or_else(|| {
+                    panic!("Could not retrieve key values for column '{}'", column)
+                });
                 validate_cell_foreign_constraints(
                     config,
                     pool,
@@ -591,8 +1145,10 @@ pub async fn validate_rows_constraints(
                     table,
                     &column,
                     cell,
+                    &context,
                     None,
                     &allowed_values,
+                    allowed_composites,
                 )
                 .await
                 .expect(&format!(
@@ -606,9 +1162,11 @@ pub async fn validate_rows_constraints(
                     table,
                     &column,
                     cell,
+                    &context,
                     &valve_rows,
                     None,
                     &forbidden_values,
+                    forbidden_composites,
                 )
                 .await
                 .expect(&format!(
@@ -667,6 +1225,7 @@ pub fn validate_rows_intra(
                     let column = headers.get(i).unwrap();
                     valve_row.contents.insert(column.to_string(), result_cell);
                 }
+                let rewritten = transform_row(config, table_name, &mut valve_row);
 
                 let column_names = &config
                     .table
@@ -774,7 +1333,13 @@ pub fn validate_rows_intra(
                             );
                         }
                     }
+
+                    let context = valve_row.clone();
+                    validate_row_scripts(config, table_name, &context, &mut valve_row);
                 }
+                // Messages about rewritten values are added last so that they do not end up in
+                // the datatype validation cache:
+                add_transform_messages(&mut valve_row, &rewritten);
                 valve_rows.push(valve_row);
             }
         };
@@ -784,43 +1349,517 @@ pub fn validate_rows_intra(
     valve_rows
 }
 
-/// Given a row represented as a [ValveRow], remove any duplicate messages from the row's cells, so
-/// that no cell has messages with the same level, rule, and message text.
-fn remove_duplicate_messages(row: &mut ValveRow) -> Result<()> {
-    for (_column_name, cell) in row.contents.iter_mut() {
-        let mut messages = cell.messages.clone();
-        messages.sort_by(|a, b| {
-            let a = format!("{}{}{}", a.level, a.rule, a.message);
-            let b = format!("{}{}{}", b.level, b.rule, b.message,);
-            a.partial_cmp(&b).unwrap()
-        });
-        messages.dedup_by(|a, b| a.level == b.level && a.rule == b.rule && a.message == b.message);
-        cell.messages = messages;
-    }
-    Ok(())
-}
-
-/// Generate a SQL Select clause that is a union of: (a) the literal values of the given extra row,
-/// and (b) a Select statement over `table_name` of all the fields in the extra row.
-pub fn select_with_extra_row(
+/// Given a config map, a database connection pool, maps of compiled datatype and rule conditions,
+/// and a map, in dependency order, from the names of tables to their headers and the rows to
+/// validate, validate the rows of every table without accessing the database, and return the
+/// validated rows of each table, numbered consecutively beginning with 1. Intra-row validation is
+/// performed as it is by [validate_rows_intra()], after which the foreign, primary, unique, and
+/// tree constraints on each table are checked against in-memory indexes of the rows validated so
+/// far, as if the tables were being loaded into the database in the given order. Note that the
+/// pool is used only to determine the SQL types of columns. Foreign constraints that refer to a
+/// table which does not precede the referring table in the map are not checked.
+pub fn validate_tables_in_memory(
     config: &ValveConfig,
-    extra_row: &ValveRow,
-    table: &str,
-    effective_table: &str,
     pool: &AnyPool,
-) -> (String, Vec<String>) {
-    let extra_row_len = extra_row.contents.keys().len();
-    let mut params = vec![];
-    let mut first_select;
-    match extra_row.row_number {
-        Some(rn) => first_select = format!(r#"SELECT {} AS "row_number", "#, rn),
-        _ => first_select = String::from(r#"SELECT NULL AS "row_number", "#),
-    };
-
+    datatype_conditions: &HashMap<String, CompiledCondition>,
+    rule_conditions: &HashMap<String, HashMap<String, Vec<ColumnRule>>>,
+    tables: &IndexMap<String, (Vec<String>, Vec<csv::StringRecord>)>,
+) -> Result<IndexMap<String, Vec<ValveRow>>> {
+    // Given a config map, a pool, a table name, a row of the table, and a list of columns, return
+    // the values of the given columns of the row as they would be stored in the database, or None
+    // if the value of any of them would be stored as NULL.
+    fn get_stored_values(
+        config: &ValveConfig,
+        pool: &AnyPool,
+        table: &str,
+        row: &ValveRow,
+        columns: &Vec<String>,
+    ) -> Option<Vec<String>> {
+        let mut values = vec![];
+        for column in columns {
+            let cell = row.contents.get(column)?;
+            let sql_type = get_sql_type_from_global_config(config, table, column, pool);
+            if cell.nulltype.is_some() || is_sql_type_error(&sql_type, &cell.strvalue()) {
+                return None;
+            }
+            values.push(cell.strvalue());
+        }
+        Some(values)
+    }
+
+    // The combinations of values of some list of columns of a table, in the table itself and in
+    // its conflict table, respectively:
+    type KeyIndex = (HashSet<Vec<String>>, HashSet<Vec<String>>);
+
+    // Given a config map, a pool, a table name, a row of the table, a flag indicating whether the
+    // row belongs in the table's conflict table, a list of columns, and an index of the values of
+    // those columns in the table, add the values of the columns in the given row to the index.
+    fn add_to_key_index(
+        config: &ValveConfig,
+        pool: &AnyPool,
+        table: &str,
+        row: &ValveRow,
+        conflict: bool,
+        columns: &Vec<String>,
+        index: &mut KeyIndex,
+    ) {
+        if let Some(values) = get_stored_values(config, pool, table, row, columns) {
+            if conflict {
+                index.1.insert(values);
+            } else {
+                index.0.insert(values);
+            }
+        }
+    }
+
+    let mut validated_rows: IndexMap<String, Vec<ValveRow>> = IndexMap::new();
+    let mut conflict_flags: HashMap<&str, Vec<bool>> = HashMap::new();
+    // Indexes of the foreign columns referred to by tables that have already been validated:
+    let mut foreign_indexes: HashMap<(String, Vec<String>), KeyIndex> = HashMap::new();
+    for (table, (headers, records)) in tables {
+        let table_config = config
+            .table
+            .get(table)
+            .ok_or(ValveError::InputError(format!(
+                "Undefined table '{}'",
+                table
+            )))?;
+        let has_conflict_table = table_config.options.contains("conflict");
+        let conflict_columns = get_conflict_columns(config, table);
+        let fkeys = config
+            .constraint
+            .foreign
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table));
+        let composite_fkeys = config
+            .constraint
+            .composite_foreign
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table));
+        let primaries = config
+            .constraint
+            .primary
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table));
+        let uniques = config
+            .constraint
+            .unique
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table));
+        let composites = config
+            .constraint
+            .composite
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table));
+
+        // Index the foreign columns that this table refers to. Foreign keys that refer to the
+        // table itself are indexed incrementally, as the table's rows are validated:
+        let foreign_keys = fkeys
+            .iter()
+            .map(|fkey| (fkey.ftable.to_string(), vec![fkey.fcolumn.to_string()]))
+            .chain(
+                composite_fkeys
+                    .iter()
+                    .map(|fkey| (fkey.ftable.to_string(), fkey.fcolumns.clone())),
+            )
+            .collect::<Vec<_>>();
+        let mut self_indexes: HashMap<Vec<String>, KeyIndex> = HashMap::new();
+        for (ftable, fcolumns) in &foreign_keys {
+            if ftable == table {
+                self_indexes.insert(fcolumns.clone(), (HashSet::new(), HashSet::new()));
+                continue;
+            }
+            if foreign_indexes.contains_key(&(ftable.to_string(), fcolumns.clone())) {
+                continue;
+            }
+            let frows = match validated_rows.get(ftable) {
+                Some(frows) => frows,
+                None => {
+                    log::warn!(
+                        "Not checking the foreign constraints of '{}' on '{}', which has not \
+                         been validated",
+                        table,
+                        ftable
+                    );
+                    continue;
+                }
+            };
+            let mut index = (HashSet::new(), HashSet::new());
+            for (frow, conflict) in frows.iter().zip(&conflict_flags[ftable.as_str()]) {
+                add_to_key_index(config, pool, ftable, frow, *conflict, fcolumns, &mut index);
+            }
+            foreign_indexes.insert((ftable.to_string(), fcolumns.clone()), index);
+        }
+
+        // The values of the table's primary and unique keys in the valid rows validated so far:
+        let mut unique_indexes: HashMap<Vec<String>, HashSet<Vec<String>>> = primaries
+            .iter()
+            .chain(uniques.iter())
+            .map(|column| (vec![column.to_string()], HashSet::new()))
+            .chain(
+                composites
+                    .iter()
+                    .map(|composite| (composite.columns.clone(), HashSet::new())),
+            )
+            .collect();
+
+        let records = records
+            .iter()
+            .map(|record| Ok(record.clone()))
+            .collect::<Vec<_>>();
+        let mut rows = validate_rows_intra(
+            config,
+            datatype_conditions,
+            rule_conditions,
+            table,
+            headers,
+            &records,
+            false,
+        );
+        let mut conflicts = vec![];
+        for (i, row) in rows.iter_mut().enumerate() {
+            // enumerate() begins at 0 by default but we need to begin with 1:
+            row.row_number = Some(i as u32 + 1);
+            let context = row.clone();
+            for column in &table_config.column_order {
+                let cell = match row.contents.get_mut(column) {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                // As when loading, we don't do any further validation on cells that are
+                // legitimately empty, or on cells that have SQL type violations:
+                let sql_type = get_sql_type_from_global_config(config, table, column, pool);
+                if cell.nulltype.is_some() || is_sql_type_error(&sql_type, &cell.strvalue()) {
+                    continue;
+                }
+
+                for fkey in composite_fkeys
+                    .iter()
+                    .filter(|fkey| fkey.columns.last() == Some(column))
+                {
+                    let values =
+                        match get_stored_values(config, pool, table, &context, &fkey.columns) {
+                            Some(values) => values,
+                            None => continue,
+                        };
+                    let (fvalues, conflict_fvalues) = match &fkey.ftable == table {
+                        true => &self_indexes[&fkey.fcolumns],
+                        false => match foreign_indexes
+                            .get(&(fkey.ftable.to_string(), fkey.fcolumns.clone()))
+                        {
+                            Some(index) => index,
+                            None => continue,
+                        },
+                    };
+                    if !fvalues.contains(&values) {
+                        cell.valid = false;
+                        let quoted_values = values
+                            .iter()
+                            .map(|v| format!("'{}'", v))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let get_fcolumns = |ftable: &str| -> String {
+                            fkey.fcolumns
+                                .iter()
+                                .map(|fcolumn| format!("{}.{}", ftable, fcolumn))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        };
+                        let message = match conflict_fvalues.contains(&values) {
+                            false => format!(
+                                "Values ({}) of columns {} are not in ({})",
+                                quoted_values,
+                                fkey.columns.join(", "),
+                                get_fcolumns(&fkey.ftable)
+                            ),
+                            true => format!(
+                                "Values ({}) of columns {} exist only in ({})",
+                                quoted_values,
+                                fkey.columns.join(", "),
+                                get_fcolumns(&format!("{}_conflict", fkey.ftable))
+                            ),
+                        };
+                        cell.messages.push(ValveCellMessage {
+                            rule: ValveRuleKind::KeyForeign.id().to_string(),
+                            level: "error".to_string(),
+                            message,
+                            ..Default::default()
+                        });
+                    }
+                }
+
+                let strvalue = cell.strvalue();
+                let values = match get_value_type(config, datatype_conditions, table, column) {
+                    ValueType::Single => vec![strvalue.to_string()],
+                    ValueType::List(separator) => strvalue
+                        .split(&separator)
+                        .map(|s| s.to_string())
+                        .collect::<Vec<_>>(),
+                };
+                for value in &values {
+                    for fkey in fkeys.iter().filter(|fkey| fkey.column == *column) {
+                        let fcolumns = vec![fkey.fcolumn.to_string()];
+                        let (fvalues, conflict_fvalues) = match &fkey.ftable == table {
+                            true => &self_indexes[&fcolumns],
+                            false => {
+                                match foreign_indexes.get(&(fkey.ftable.to_string(), fcolumns)) {
+                                    Some(index) => index,
+                                    None => continue,
+                                }
+                            }
+                        };
+                        let key = vec![value.to_string()];
+                        if !fvalues.contains(&key) {
+                            cell.valid = false;
+                            let mut allowed_values = fvalues
+                                .iter()
+                                .map(|fvalue| fvalue[0].to_string())
+                                .collect::<Vec<_>>();
+                            allowed_values.sort();
+                            let message = match conflict_fvalues.contains(&key) {
+                                false => format!(
+                                    "Value '{}' of column {} is not in {}.{}",
+                                    value, column, fkey.ftable, fkey.fcolumn
+                                ),
+                                true => format!(
+                                    "Value '{}' of column {} exists only in {}_conflict.{}",
+                                    value, column, fkey.ftable, fkey.fcolumn
+                                ),
+                            };
+                            cell.messages.push(ValveCellMessage {
+                                rule: ValveRuleKind::KeyForeign.id().to_string(),
+                                level: "error".to_string(),
+                                message,
+                                suggestions: get_suggestions(value, &allowed_values),
+                            });
+                        }
+                    }
+                }
+
+                // Check any multi-column keys for which this is the last column, followed by
+                // any single-column key on this column:
+                for composite in composites
+                    .iter()
+                    .filter(|composite| composite.columns.last() == Some(column))
+                {
+                    let values = match get_stored_values(
+                        config,
+                        pool,
+                        table,
+                        &context,
+                        &composite.columns,
+                    ) {
+                        Some(values) => values,
+                        None => continue,
+                    };
+                    if unique_indexes[&composite.columns].contains(&values) {
+                        cell.valid = false;
+                        cell.messages.push(ValveCellMessage {
+                            rule: match composite.key_type.as_str() {
+                                "primary" => ValveRuleKind::KeyPrimary.id().to_string(),
+                                _ => ValveRuleKind::KeyUnique.id().to_string(),
+                            },
+                            level: "error".to_string(),
+                            message: format!(
+                                "Values of {} must be unique",
+                                composite.columns.join(", ")
+                            ),
+                            ..Default::default()
+                        });
+                    }
+                }
+                let key_rule = match (primaries.contains(column), uniques.contains(column)) {
+                    (true, _) => Some(ValveRuleKind::KeyPrimary),
+                    (false, true) => Some(ValveRuleKind::KeyUnique),
+                    _ => None,
+                };
+                if let Some(key_rule) = key_rule {
+                    if unique_indexes[&vec![column.to_string()]].contains(&vec![strvalue]) {
+                        cell.valid = false;
+                        cell.messages.push(ValveCellMessage {
+                            rule: key_rule.id().to_string(),
+                            level: "error".to_string(),
+                            message: format!("Values of {} must be unique", column),
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+
+            // Now that the row has been validated, add it to the indexes of the table's keys:
+            let conflict = has_conflict_table
+                && (row
+                    .contents
+                    .iter()
+                    .any(|(column, cell)| !cell.valid && conflict_columns.contains(column))
+                    || has_null_primary_key(config, table, row, pool));
+            for (fcolumns, index) in self_indexes.iter_mut() {
+                add_to_key_index(config, pool, table, row, conflict, fcolumns, index);
+            }
+            for (columns, index) in unique_indexes.iter_mut() {
+                let valid = columns.iter().all(|column| match row.contents.get(column) {
+                    Some(cell) => cell.valid,
+                    None => false,
+                });
+                if valid {
+                    index.insert(
+                        columns
+                            .iter()
+                            .map(|column| row.contents[column].strvalue())
+                            .collect::<Vec<_>>(),
+                    );
+                }
+            }
+            conflicts.push(conflict);
+        }
+
+        // As when loading, the tree constraints on the table are checked only once all of its
+        // rows have been validated:
+        let tkeys = config
+            .constraint
+            .tree
+            .get(table)
+            .unwrap_or_else(|| panic!("Undefined table '{}'", table));
+        for tkey in tkeys {
+            let child_col = vec![tkey.child.to_string()];
+            let parent_col = vec![tkey.parent.to_string()];
+            let mut child_vals = HashSet::new();
+            let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+            let mut edges = vec![];
+            for (i, row) in rows.iter().enumerate() {
+                let child_val = get_stored_values(config, pool, table, row, &child_col);
+                let parent_val = get_stored_values(config, pool, table, row, &parent_col);
+                if let Some(child_val) = &child_val {
+                    child_vals.insert(child_val[0].to_string());
+                }
+                if let (Some(child_val), Some(parent_val)) = (child_val, parent_val) {
+                    for parent_item in get_tree_parent_values(tkey, &parent_val[0]) {
+                        parents
+                            .entry(child_val[0].to_string())
+                            .or_default()
+                            .push(parent_item.to_string());
+                        edges.push((i, child_val[0].to_string(), parent_item));
+                    }
+                }
+            }
+
+            let mut tree_messages = vec![];
+            for (i, row) in rows.iter().enumerate() {
+                if let Some(parent_val) = get_stored_values(config, pool, table, row, &parent_col) {
+                    for parent_item in get_tree_parent_values(tkey, &parent_val[0]) {
+                        if !child_vals.contains(&parent_item) {
+                            tree_messages.push((
+                                i,
+                                ValveRuleKind::TreeForeign,
+                                format!(
+                                    "Value '{}' of column {} is not in column {}",
+                                    parent_item, tkey.parent, tkey.child
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+            for (i, child_val, parent_item) in edges {
+                if let Some(path) = find_tree_cycle(&parents, &child_val, &parent_item) {
+                    tree_messages.push((
+                        i,
+                        ValveRuleKind::TreeCycle,
+                        format!(
+                            "Value '{}' of column {} results in a cycle: {}",
+                            parent_item,
+                            tkey.parent,
+                            path.join(" -> ")
+                        ),
+                    ));
+                }
+            }
+            for (i, kind, message) in tree_messages {
+                let cell = rows[i].contents.get_mut(&tkey.parent).unwrap();
+                cell.valid = false;
+                cell.messages.push(ValveCellMessage {
+                    rule: kind.id().to_string(),
+                    level: kind.default_level().to_string(),
+                    message,
+                    ..Default::default()
+                });
+            }
+        }
+
+        conflict_flags.insert(table, conflicts);
+        validated_rows.insert(table.to_string(), rows);
+    }
+
+    Ok(validated_rows)
+}
+
+/// Given a row represented as a [ValveRow], remove any duplicate messages from the row's cells, so
+/// that no cell has messages with the same level, rule, and message text.
+fn remove_duplicate_messages(row: &mut ValveRow) -> Result<()> {
+    for (_column_name, cell) in row.contents.iter_mut() {
+        let mut messages = cell.messages.clone();
+        messages.sort_by(|a, b| {
+            let a = format!("{}{}{}", a.level, a.rule, a.message);
+            let b = format!("{}{}{}", b.level, b.rule, b.message,);
+            a.partial_cmp(&b).unwrap()
+        });
+        messages.dedup_by(|a, b| a.level == b.level && a.rule == b.rule && a.message == b.message);
+        cell.messages = messages;
+    }
+    Ok(())
+}
+
+/// Given a validation message, a compiled condition, a value that has failed to satisfy the
+/// condition, and (optionally) the row that the value belongs to, if the condition combines other
+/// conditions (i.e., using `any()`, `all()`, or `not()`), append the sub-conditions responsible for
+/// the failure to the message and return it. Otherwise return the message unchanged.
+fn add_failed_subconditions(
+    message: String,
+    condition: &CompiledCondition,
+    value: &str,
+    row: Option<&ValveRow>,
+) -> String {
+    let failed = match &condition.parsed {
+        Expression::Function(name, _) if name == "any" || name == "all" => condition
+            .subconditions
+            .iter()
+            .filter(|c| match row {
+                Some(row) => !c.check(value, row),
+                None => !(c.compiled)(value),
+            })
+            .map(|c| c.original.to_string())
+            .collect::<Vec<_>>(),
+        Expression::Function(name, _) if name == "not" => vec![condition.original.to_string()],
+        _ => vec![],
+    };
+    if failed.is_empty() {
+        message
+    } else {
+        format!("{} (failed: {})", message, failed.join(", "))
+    }
+}
+
+/// Generate a SQL Select clause that is a union of: (a) the literal values of the given extra row,
+/// and (b) a Select statement over `table_name` of all the fields in the extra row.
+pub fn select_with_extra_row(
+    config: &ValveConfig,
+    extra_row: &ValveRow,
+    table: &str,
+    effective_table: &str,
+    pool: &AnyPool,
+) -> (String, Vec<String>) {
+    let extra_row_len = extra_row.contents.keys().len();
+    let mut params = vec![];
+    let mut first_select;
+    match extra_row.row_number {
+        Some(rn) => first_select = format!(r#"SELECT {} AS "row_number", "#, rn),
+        _ => first_select = String::from(r#"SELECT NULL AS "row_number", "#),
+    };
+
     let mut second_select = String::from(r#"SELECT "row_number", "#);
     for (i, (key, content)) in extra_row.contents.iter().enumerate() {
         let sql_type = get_sql_type_from_global_config(config, &table, &key, pool);
-        let sql_param = cast_sql_param_from_text(&sql_type);
+        let sql_param = cast_sql_param_from_text(&sql_type, pool);
         // enumerate() begins from 0 but we need to begin at 1:
         let i = i + 1;
         first_select.push_str(format!(r#"{} AS "{}""#, sql_param, key).as_str());
@@ -863,7 +1902,7 @@ pub fn with_tree_sql(
            {extra_clause}
                SELECT "{child_col}", "{parent_col}" 
                    FROM "{effective_table_name}" 
-                   UNION ALL 
+                   UNION 
                SELECT "t1"."{child_col}", "t1"."{parent_col}" 
                    FROM "{effective_table_name}" AS "t1" 
                    JOIN "tree" AS "t2" ON "t2"."{parent_col}" = "t1"."{child_col}"
@@ -906,7 +1945,8 @@ pub fn validate_cell_nulltype(
 }
 
 /// Given a config map, compiled datatype conditions, a table name, a column name, and a cell to
-/// validate, validate the cell's datatype and return the validated cell.
+/// validate, validate the cell's datatype and return the validated cell. Messages about violations
+/// of `in()` conditions include the alternatives, if any, that are similar to the cell value.
 pub fn validate_cell_datatype(
     config: &ValveConfig,
     datatype_conditions: &HashMap<String, CompiledCondition>,
@@ -952,6 +1992,27 @@ pub fn validate_cell_datatype(
         }
     }
 
+    // Given a compiled condition and a value that violates it, return the alternatives that the
+    // value may have been intended to be if the condition is an in() condition, or an empty list
+    // otherwise:
+    fn get_in_suggestions(condition: &CompiledCondition, value: &str) -> Vec<String> {
+        match &condition.parsed {
+            Expression::Function(name, args) if name == "in" => {
+                let alternatives = args
+                    .iter()
+                    .filter_map(|arg| match &**arg {
+                        Expression::Label(label) => {
+                            Some(UNQUOTED_RE.replace(label, "$unquoted").to_string())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                get_suggestions(value, &alternatives)
+            }
+            _ => vec![],
+        }
+    }
+
     let column = config
         .table
         .get(table_name)
@@ -986,8 +2047,8 @@ pub fn validate_cell_datatype(
                 let datatype = datatypes_to_check.pop().unwrap();
                 let dt_name = &datatype.datatype;
                 let dt_description = &datatype.description;
-                let dt_condition = &datatype_conditions.get(dt_name).unwrap().compiled;
-                if !dt_condition(&value) {
+                let dt_condition = datatype_conditions.get(dt_name).unwrap();
+                if !(dt_condition.compiled)(value) {
                     let message = construct_message(
                         &value,
                         primary_dt_cond,
@@ -995,10 +2056,12 @@ pub fn validate_cell_datatype(
                         dt_name,
                         dt_description,
                     );
+                    let message = add_failed_subconditions(message, dt_condition, value, None);
                     let message_info = ValveCellMessage {
-                        rule: format!("datatype:{}", dt_name),
+                        rule: ValveRuleKind::Datatype.rule_id(dt_name),
                         level: "error".to_string(),
                         message: message,
+                        suggestions: get_in_suggestions(dt_condition, value),
                     };
                     cell.messages.push(message_info);
                 }
@@ -1011,16 +2074,177 @@ pub fn validate_cell_datatype(
                 primary_dt_name,
                 primary_dt_desc,
             );
+            let message = add_failed_subconditions(message, primary_dt_cond, value, None);
             let message_info = ValveCellMessage {
-                rule: format!("datatype:{}", primary_dt_name),
+                rule: ValveRuleKind::Datatype.rule_id(primary_dt_name),
                 level: "error".to_string(),
                 message: message,
+                suggestions: get_in_suggestions(primary_dt_cond, value),
             };
             cell.messages.push(message_info);
         }
     }
 }
 
+/// Given the text of a script rule, compile the script and return the compiled script, or a
+/// configuration error if it could not be compiled.
+pub fn compile_rule_script(script: &str) -> Result<Arc<AST>> {
+    match SCRIPT_ENGINE.compile(script) {
+        Ok(ast) => Ok(Arc::new(ast)),
+        Err(e) => Err(ValveError::ConfigError(format!(
+            "Could not compile script: '{}': {}",
+            script, e
+        ))
+        .into()),
+    }
+}
+
+/// Given a config map, a table name, the row context, and the row to validate, run each of the
+/// script rules associated with the table, giving it the row (as a map from column names to
+/// values) in the variable `row`. Each script returns a list of findings, each of which is either
+/// an array of the form `[column, level, message]` or a map with the keys `column`, `level`, and
+/// `message`. Each finding is added as a message to the given column's cell, which is marked as
+/// invalid. The level of a finding may be empty, in which case the level of the rule is used. If a
+/// script fails or returns an invalid finding, an error message is added to the row's first cell.
+pub fn validate_row_scripts(
+    config: &ValveConfig,
+    table_name: &str,
+    context: &ValveRow,
+    row: &mut ValveRow,
+) {
+    // Given the result of running a script, and the level to use by default, return the list of
+    // (column, level, message) findings returned by the script, or an error message if the result
+    // is not a valid list of findings:
+    fn get_findings(
+        result: Dynamic,
+        default_level: &str,
+    ) -> std::result::Result<Vec<(String, String, String)>, String> {
+        if result.is_unit() {
+            return Ok(vec![]);
+        }
+        let mut findings = vec![];
+        for finding in result
+            .into_array()
+            .map_err(|t| format!("Expected a list of findings but got a {}", t))?
+        {
+            let (column, level, message) = {
+                if finding.is_map() {
+                    let finding = finding.cast::<RhaiMap>();
+                    let get = |key: &str| -> String {
+                        finding.get(key).map(|v| v.to_string()).unwrap_or_default()
+                    };
+                    (get("column"), get("level"), get("message"))
+                } else if finding.is_array() {
+                    let finding = finding.into_array().unwrap();
+                    if finding.len() != 3 {
+                        return Err(format!(
+                            "Expected a finding of the form [column, level, message] but got: {:?}",
+                            finding
+                        ));
+                    }
+                    (
+                        finding[0].to_string(),
+                        finding[1].to_string(),
+                        finding[2].to_string(),
+                    )
+                } else {
+                    return Err(format!(
+                        "Expected a finding of the form [column, level, message] but got: {}",
+                        finding
+                    ));
+                }
+            };
+            let level = {
+                if level.is_empty() {
+                    default_level.to_string()
+                } else {
+                    level
+                }
+            };
+            if !["error", "warning", "info"].contains(&level.as_str()) {
+                return Err(format!("Invalid level '{}' in finding", level));
+            }
+            findings.push((column, level, message));
+        }
+        Ok(findings)
+    }
+
+    let script_rules = match config.script_rule.get(table_name) {
+        Some(script_rules) => script_rules,
+        None => return,
+    };
+
+    let mut row_map = RhaiMap::new();
+    for (column, cell) in &context.contents {
+        row_map.insert(column.into(), Dynamic::from(cell.strvalue()));
+    }
+
+    for (rule_number, script_rule) in script_rules.iter().enumerate() {
+        // enumerate() begins at 0 by default but we need to begin with 1:
+        let rule_id = match script_rule.id.as_str() {
+            "" => ValveRuleKind::ScriptRule.rule_id(&(rule_number + 1).to_string()),
+            id => ValveRuleKind::Rule.rule_id(id),
+        };
+        // The script is compiled when the configuration is read, unless the configuration has been
+        // deserialized, in which case we compile it here:
+        let ast = match &script_rule.compiled {
+            Some(ast) => Ok(ast.clone()),
+            None => compile_rule_script(&script_rule.script),
+        };
+        let result = match ast {
+            Err(e) => Err(e.to_string()),
+            Ok(ast) => {
+                let mut scope = Scope::new();
+                scope.push_constant("row", row_map.clone());
+                SCRIPT_ENGINE
+                    .eval_ast_with_scope::<Dynamic>(&mut scope, &ast)
+                    .map_err(|e| e.to_string())
+                    .and_then(|result| get_findings(result, &script_rule.level))
+            }
+        };
+        let result = result.and_then(|findings| {
+            match findings
+                .iter()
+                .find(|(column, _, _)| !row.contents.contains_key(column))
+            {
+                Some((column, _, _)) => Err(format!("Unknown column '{}' in finding", column)),
+                None => Ok(findings),
+            }
+        });
+        match result {
+            Ok(findings) => {
+                for (column, level, message) in findings {
+                    let cell = row.contents.get_mut(&column).unwrap();
+                    cell.valid = false;
+                    cell.messages.push(ValveCellMessage {
+                        rule: rule_id.to_string(),
+                        level,
+                        message,
+                        ..Default::default()
+                    });
+                }
+            }
+            Err(e) => {
+                log::warn!(
+                    "Script rule {} of table '{}' failed: {}",
+                    rule_number + 1,
+                    table_name,
+                    e
+                );
+                if let Some((_, cell)) = row.contents.iter_mut().next() {
+                    cell.valid = false;
+                    cell.messages.push(ValveCellMessage {
+                        rule: rule_id.to_string(),
+                        level: "error".to_string(),
+                        message: format!("Script rule failed: {}", e),
+                        ..Default::default()
+                    });
+                }
+            }
+        };
+    }
+}
+
 /// Given a config map, compiled rule conditions, a table name, a column name, the row context,
 /// and the cell to validate, look in the rule table (if it exists) and validate the cell according
 /// to any applicable rules.
@@ -1032,49 +2256,19 @@ pub fn validate_cell_rules(
     context: &ValveRow,
     cell: &mut ValveCell,
 ) {
+    // Given a condition in its original form, its compiled version, a cell, and the row that the
+    // cell belongs to, return true if the cell satisfies the condition and false otherwise.
     fn check_condition(
-        condition_type: &str,
+        condition: &str,
+        compiled_condition: &CompiledCondition,
         cell: &ValveCell,
-        rule: &ValveRuleConfig,
-        table_name: &String,
-        column_name: &String,
-        rules: &HashMap<String, HashMap<String, Vec<ColumnRule>>>,
+        context: &ValveRow,
     ) -> bool {
-        let condition = {
-            if condition_type == "when" {
-                rule.when_condition.as_str()
-            } else if condition_type == "then" {
-                rule.then_condition.as_str()
-            } else {
-                panic!("Invalid condition type: {}", condition_type);
-            }
-        };
-
         if vec!["null", "not null"].contains(&condition) {
             return (condition == "null" && cell.nulltype != None)
                 || (condition == "not null" && cell.nulltype == None);
         } else {
-            let compiled_condition = rules
-                .get(table_name)
-                .and_then(|t| t.get(column_name))
-                .and_then(|v| {
-                    v.iter().find(|c| {
-                        if condition_type == "when" {
-                            c.when.original == condition
-                        } else {
-                            c.then.original == condition
-                        }
-                    })
-                })
-                .and_then(|c| {
-                    if condition_type == "when" {
-                        Some(c.when.compiled.clone())
-                    } else {
-                        Some(c.then.compiled.clone())
-                    }
-                })
-                .unwrap();
-            return compiled_condition(&cell.strvalue());
+            compiled_condition.check(&cell.strvalue(), context)
         }
     }
 
@@ -1082,19 +2276,64 @@ pub fn validate_cell_rules(
         Some(rules) => rules,
         _ => return,
     };
+    // The compiled rules are in the same order as the configured rules:
+    let compiled_rules = rules
+        .get(table_name)
+        .and_then(|t| t.get(column_name))
+        .unwrap_or_else(|| {
+            panic!(
+                "No compiled rules found for column '{}.{}'",
+                table_name, column_name
+            )
+        });
 
-    for (rule_number, rule) in applicable_rules.iter().enumerate() {
+    for (rule_number, (rule, compiled_rule)) in
+        applicable_rules.iter().zip(compiled_rules).enumerate()
+    {
         // enumerate() begins at 0 by default but we need to begin with 1:
         let rule_number = rule_number + 1;
-        // Check the then condition only if the when condition is satisfied:
-        if check_condition("when", cell, rule, table_name, column_name, rules) {
+        // Check the then condition only if all of the when conditions are satisfied:
+        let when_satisfied =
+            check_condition(&rule.when_condition, &compiled_rule.when, cell, context)
+                && rule
+                    .additional_when
+                    .iter()
+                    .zip(&compiled_rule.additional_when)
+                    .all(|((when_column, when_condition), compiled_condition)| {
+                        let when_cell = context.contents.get(when_column).unwrap();
+                        check_condition(when_condition, compiled_condition, when_cell, context)
+                    });
+        if when_satisfied {
             let then_cell = context.contents.get(&rule.then_column).unwrap();
-            if !check_condition("then", then_cell, rule, table_name, column_name, rules) {
+            if !check_condition(
+                &rule.then_condition,
+                &compiled_rule.then,
+                then_cell,
+                context,
+            ) {
                 cell.valid = false;
+                let message = {
+                    if ["null", "not null"].contains(&rule.then_condition.as_str()) {
+                        rule.description.to_string()
+                    } else {
+                        add_failed_subconditions(
+                            rule.description.to_string(),
+                            &compiled_rule.then,
+                            &then_cell.strvalue(),
+                            Some(context),
+                        )
+                    }
+                };
                 cell.messages.push(ValveCellMessage {
-                    rule: format!("rule:{}-{}", column_name, rule_number),
+                    rule: match rule.id.as_str() {
+                        "" => {
+                            ValveRuleKind::Rule.rule_id(&format!("{}-{}", column_name, rule_number))
+                        }
+                        id => ValveRuleKind::Rule.rule_id(id),
+                    },
                     level: rule.level.to_string(),
-                    message: rule.description.to_string(),
+                    message,
+                    ..Default::default()
                 });
             }
         }
@@ -1207,13 +2446,18 @@ pub fn as_if_to_sql(
     sql
 }
 
-/// Given a config map, a db connection pool, a table name, a column name, and a cell to validate,
-/// check the cell value against any foreign keys that have been defined for the column. If there is
-/// a violation, indicate it with an error message attached to the cell. Optionally, if a
-/// transaction is given, use that instead of the pool for database access. Optionally, if
-/// query_as_if is given, use it to query the table counterfactually. Optionally, if a cache is
-/// given, use the cache to determine foreign key violations instead of querying the database. Note
-/// that caching is normally used only in the context of batch processing.
+/// Given a config map, a db connection pool, a table name, a column name, a cell to validate, and
+/// the row to which the cell belongs, check the cell value against any foreign keys that have been
+/// defined for the column. If there is a violation, indicate it with an error message attached to
+/// the cell, along with a list of those values of the foreign column, if any, that are similar to
+/// the cell value (see [get_suggestions()](crate::toolkit::get_suggestions)). Foreign keys on more
+/// than one column are checked only when validating the cell corresponding to the last column of
+/// the key, in which case the values of the key's other columns are taken from the given row.
+/// Optionally, if a transaction is given, use that instead of the pool for database access.
+/// Optionally, if query_as_if is given, use it to query the table counterfactually. Optionally, if
+/// a cache (or, for multi-column keys, a composite cache, which maps foreign table names to their
+/// rows) is given, use the cache to determine foreign key violations instead of querying the
+/// database. Note that caching is normally used only in the context of batch processing.
 pub async fn validate_cell_foreign_constraints(
     config: &ValveConfig,
     pool: &AnyPool,
@@ -1222,8 +2466,10 @@ pub async fn validate_cell_foreign_constraints(
     table_name: &String,
     column_name: &String,
     cell: &mut ValveCell,
+    context: &ValveRow,
     query_as_if: Option<&QueryAsIf>,
     cache: &Option<(Vec<SerdeValue>, Vec<SerdeValue>)>,
+    composite_cache: &Option<CompositeCache>,
 ) -> Result<()> {
     let fkeys = config
         .constraint
@@ -1233,9 +2479,17 @@ pub async fn validate_cell_foreign_constraints(
         .iter()
         .filter(|t| t.column == *column_name)
         .collect::<Vec<_>>();
+    let composite_fkeys = config
+        .constraint
+        .composite_foreign
+        .get(table_name)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table_name))
+        .iter()
+        .filter(|t| t.columns.last() == Some(column_name))
+        .collect::<Vec<_>>();
 
     // If there are no foreign keys, then just return:
-    if fkeys.is_empty() {
+    if fkeys.is_empty() && composite_fkeys.is_empty() {
         return Ok(());
     }
 
@@ -1290,12 +2544,20 @@ pub async fn validate_cell_foreign_constraints(
                         as_if_clause, table, column, SQL_PARAM,
                     ),
                 );
+                // A value that cannot be converted to the SQL type of the foreign column cannot be
+                // one of its values:
+                let param = match get_query_param(&json!(value), sql_type) {
+                    Ok(param) => param,
+                    Err(_) => return Ok(false),
+                };
                 let frows = {
                     let mut query = sqlx_query(&fsql);
-                    match get_query_param(&json!(value), &sql_type) {
+                    match param {
                         QueryParam::Integer(p) => query = query.bind(p),
                         QueryParam::Numeric(p) => query = query.bind(p),
                         QueryParam::Real(p) => query = query.bind(p),
+                        QueryParam::Date(p) => query = query.bind(p),
+                        QueryParam::Timestamp(p) => query = query.bind(p),
                         QueryParam::String(p) => query = query.bind(p),
                     }
                     if let None = tx {
@@ -1311,6 +2573,224 @@ pub async fn validate_cell_foreign_constraints(
         }
     }
 
+    // Given a db pool, optionally a transaction, and an as_if clause, returns the distinct values
+    // of the given column of the given table. Note that, unlike fkey_in_db(), this function
+    // always queries the database, since the caches used for batch processing contain only the
+    // values of the foreign column that are actually referred to by the rows being validated.
+    async fn get_fvalues(
+        pool: &AnyPool,
+        tx: &mut Option<&mut Transaction<'_, sqlx::Any>>,
+        as_if_clause: &str,
+        table: &str,
+        column: &str,
+    ) -> Result<Vec<String>> {
+        let sql = format!(
+            r#"{}SELECT DISTINCT CAST("{}" AS TEXT) AS "value" FROM "{}" WHERE "{}" IS NOT NULL"#,
+            as_if_clause, column, table, column
+        );
+        let query = sqlx_query(&sql);
+        let rows = {
+            if let Some(tx) = tx.as_mut() {
+                query.fetch_all(tx.acquire().await?).await?
+            } else {
+                query.fetch_all(pool).await?
+            }
+        };
+        Ok(rows
+            .iter()
+            .map(|row| row.get::<String, _>("value"))
+            .collect::<Vec<_>>())
+    }
+
+    // Given some rows of the foreign table of the given key, checks whether the given values are
+    // jointly in the key's foreign columns of one of the rows.
+    fn composite_fkey_in_rows(
+        rows: &[ValveRow],
+        fkey: &ValveCompositeForeignConstraint,
+        values: &[String],
+    ) -> bool {
+        rows.iter().any(|row| {
+            fkey.fcolumns
+                .iter()
+                .zip(values)
+                .all(|(column, value)| match row.contents.get(column) {
+                    Some(cell) => cell.strvalue() == *value,
+                    None => false,
+                })
+        })
+    }
+
+    // Given a db pool, optionally a transaction, and an as_if clause, checks whether the given
+    // values are jointly in the foreign columns of the given key in the given table, which is
+    // either the key's foreign table or its conflict table (or an alias of one of them).
+    async fn composite_fkey_in_db(
+        config: &ValveConfig,
+        pool: &AnyPool,
+        tx: &mut Option<&mut Transaction<'_, sqlx::Any>>,
+        as_if_clause: &str,
+        table: &str,
+        fkey: &ValveCompositeForeignConstraint,
+        values: &[String],
+    ) -> Result<bool> {
+        let columns = &fkey.fcolumns;
+        let ftable = &fkey.ftable;
+        let where_clause = columns
+            .iter()
+            .map(|column| format!(r#""{}" = {}"#, column, SQL_PARAM))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let fsql = local_sql_syntax(
+            pool,
+            &format!(
+                r#"{}SELECT 1 FROM "{}" WHERE {} LIMIT 1"#,
+                as_if_clause, table, where_clause
+            ),
+        );
+        let frows = {
+            let mut query = sqlx_query(&fsql);
+            for (column, value) in columns.iter().zip(values) {
+                let sql_type =
+                    get_sql_type_from_global_config(config, ftable, column, pool).to_lowercase();
+                let param = match get_query_param(&json!(value), &sql_type) {
+                    Ok(param) => param,
+                    Err(_) => return Ok(false),
+                };
+                match param {
+                    QueryParam::Integer(p) => query = query.bind(p),
+                    QueryParam::Numeric(p) => query = query.bind(p),
+                    QueryParam::Real(p) => query = query.bind(p),
+                    QueryParam::Date(p) => query = query.bind(p),
+                    QueryParam::Timestamp(p) => query = query.bind(p),
+                    QueryParam::String(p) => query = query.bind(p),
+                }
+            }
+            if let Some(tx) = tx.as_mut() {
+                query.fetch_all(tx.acquire().await?).await?
+            } else {
+                query.fetch_all(pool).await?
+            }
+        };
+        Ok(!frows.is_empty())
+    }
+
+    for fkey in &composite_fkeys {
+        // If any of the key's cells in the row are empty or have SQL type violations, then the key
+        // is not checked:
+        let mut values = vec![];
+        for column in &fkey.columns {
+            match context.contents.get(column) {
+                Some(key_cell) => {
+                    let sql_type =
+                        get_sql_type_from_global_config(config, table_name, column, pool);
+                    if key_cell.nulltype.is_some()
+                        || is_sql_type_error(&sql_type, &key_cell.strvalue())
+                    {
+                        break;
+                    }
+                    values.push(key_cell.strvalue());
+                }
+                None => break,
+            };
+        }
+        if values.len() != fkey.columns.len() {
+            continue;
+        }
+
+        let ftable = &fkey.ftable;
+        let (as_if_clause, ftable_alias) = match query_as_if {
+            Some(query_as_if) if *ftable == query_as_if.table => {
+                (as_if_clause.to_string(), query_as_if.alias.to_string())
+            }
+            _ => ("".to_string(), ftable.to_string()),
+        };
+        // If a composite cache is given, use it to look up the rows of the foreign table (in the
+        // first position) and of its conflict table (in the second position) instead of accessing
+        // the database:
+        let cached_rows = composite_cache.as_ref().and_then(|c| c.get(ftable));
+        let in_ftable = match cached_rows {
+            Some((rows, _)) => composite_fkey_in_rows(rows, fkey, &values),
+            None => {
+                composite_fkey_in_db(
+                    config,
+                    pool,
+                    &mut tx,
+                    &as_if_clause,
+                    &ftable_alias,
+                    fkey,
+                    &values,
+                )
+                .await?
+            }
+        };
+        if !in_ftable {
+            cell.valid = false;
+            let quoted_values = values
+                .iter()
+                .map(|v| format!("'{}'", v))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let get_fcolumns = |ftable: &str| -> String {
+                fkey.fcolumns
+                    .iter()
+                    .map(|fcolumn| format!("{}.{}", ftable, fcolumn))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let mut message = ValveCellMessage {
+                rule: ValveRuleKind::KeyForeign.id().to_string(),
+                level: "error".to_string(),
+                message: format!(
+                    "Values ({}) of columns {} are not in ({})",
+                    quoted_values,
+                    fkey.columns.join(", "),
+                    get_fcolumns(ftable)
+                ),
+                ..Default::default()
+            };
+            let foptions = &config
+                .table
+                .get(ftable)
+                .unwrap_or_else(|| panic!("Foreign table: '{}' is not in table config", ftable))
+                .options;
+
+            if foptions.contains("conflict") {
+                let (as_if_clause_for_conflict, ftable_alias) = match query_as_if {
+                    Some(query_as_if) if *ftable == query_as_if.table => (
+                        as_if_clause_for_conflict.to_string(),
+                        query_as_if.alias.to_string(),
+                    ),
+                    _ => ("".to_string(), ftable.to_string()),
+                };
+                let in_conflict_table = match cached_rows {
+                    Some((_, conflict_rows)) => {
+                        composite_fkey_in_rows(conflict_rows, fkey, &values)
+                    }
+                    None => {
+                        composite_fkey_in_db(
+                            config,
+                            pool,
+                            &mut tx,
+                            &as_if_clause_for_conflict,
+                            &format!("{}_conflict", ftable_alias),
+                            fkey,
+                            &values,
+                        )
+                        .await?
+                    }
+                };
+                if in_conflict_table {
+                    message.message = format!(
+                        "Values ({}) of columns {} exist only in ({})",
+                        quoted_values,
+                        fkey.columns.join(", "),
+                        get_fcolumns(&format!("{}_conflict", ftable))
+                    );
+                }
+            }
+            cell.messages.push(message);
+        }
+    }
+
     // Check if the column has the list() datatype. If so parse the values in the list and
     // iterate over them.
     let strvalue = cell.strvalue();
@@ -1346,13 +2826,16 @@ pub async fn validate_cell_foreign_constraints(
             .await?
             {
                 cell.valid = false;
+                let fvalues =
+                    get_fvalues(pool, &mut tx, &as_if_clause, &ftable_alias, fcolumn).await?;
                 let mut message = ValveCellMessage {
-                    rule: "key:foreign".to_string(),
+                    rule: ValveRuleKind::KeyForeign.id().to_string(),
                     level: "error".to_string(),
                     message: format!(
                         "Value '{}' of column {} is not in {}.{}",
                         value, column_name, ftable, fcolumn
                     ),
+                    suggestions: get_suggestions(value, &fvalues),
                 };
                 let foptions = &config
                     .table
@@ -1398,22 +2881,27 @@ pub async fn validate_cell_foreign_constraints(
 }
 
 /// Given a config map, a db connection pool, a table name, a column name, a cell to validate,
-/// and a list of previously validated rows, check the cell value against any unique-type keys that
-/// have been defined for the column. If there is a violation, indicate it with an error message
-/// attached to the cell. If `row_number` is set to None, then no row corresponding to the given
-/// cell is assumed to exist in the table. Optionally, if a transaction is given, use that
-/// instead of the pool for database access. Optionally, if a cache is given, use that to determine
-/// the validity of the cell rather than accessing the database.
+/// the row to which the cell belongs, and a list of previously validated rows, check the cell
+/// value against any unique-type keys that have been defined for the column. If there is a
+/// violation, indicate it with an error message attached to the cell. Multi-column keys are
+/// checked only when validating the cell corresponding to the last column of the key, in which
+/// case the values of the key's other columns are taken from the given row. If `row_number` is set
+/// to None, then no row corresponding to the given cell is assumed to exist in the table.
+/// Optionally, if a transaction is given, use that instead of the pool for database access.
+/// Optionally, if a cache (or, for multi-column keys, a composite cache) is given, use that to
+/// determine the validity of the cell rather than accessing the database.
 pub async fn validate_cell_unique_constraints(
     config: &ValveConfig,
     pool: &AnyPool,
-    tx: Option<&mut Transaction<'_, sqlx::Any>>,
+    mut tx: Option<&mut Transaction<'_, sqlx::Any>>,
     table_name: &String,
     column_name: &String,
     cell: &mut ValveCell,
+    context: &ValveRow,
     prev_results: &Vec<ValveRow>,
     row_number: Option<u32>,
     cache: &Option<Vec<SerdeValue>>,
+    composite_cache: &Option<Vec<ValveRow>>,
 ) -> Result<()> {
     // If the column has a primary or unique key constraint, or if it is the child associated with
     // a tree, then if the value of the cell is a duplicate either of one of the previously
@@ -1438,6 +2926,7 @@ pub async fn validate_cell_unique_constraints(
             rule: rule.to_string(),
             level: "error".to_string(),
             message: format!("Values of {} must be unique", column_name.to_string()),
+            ..Default::default()
         }
     }
 
@@ -1486,7 +2975,7 @@ pub async fn validate_cell_unique_constraints(
 
                 let sql_type =
                     get_sql_type_from_global_config(&config, &table_name, &column_name, pool);
-                let sql_param = cast_sql_param_from_text(&sql_type);
+                let sql_param = cast_sql_param_from_text(&sql_type, pool);
                 let sql = local_sql_syntax(
                     &pool,
                     &format!(
@@ -1508,6 +2997,160 @@ pub async fn validate_cell_unique_constraints(
         }
     }
 
+    async fn in_db_composite(
+        config: &ValveConfig,
+        pool: &AnyPool,
+        mut tx: Option<&mut Transaction<'_, sqlx::Any>>,
+        table_name: &String,
+        composite: &ValveCompositeConstraint,
+        context: &ValveRow,
+        row_number: Option<u32>,
+    ) -> Result<bool> {
+        let table_options = get_table_options_from_config(config, table_name)?;
+        // If the table does not have a conflict table then there is no view to check, so
+        // we check the table itself.
+        let mut query_table = {
+            if !table_options.contains("conflict") {
+                table_name.to_string()
+            } else {
+                format!("{}_view", table_name)
+            }
+        };
+        let mut with_sql = String::new();
+        let except_table = format!("{}_exc", query_table);
+        if let Some(row_number) = row_number {
+            with_sql = format!(
+                r#"WITH "{}" AS (
+                       SELECT * FROM "{}"
+                       WHERE "row_number" != {}
+                   ) "#,
+                except_table, query_table, row_number
+            );
+        }
+
+        if !with_sql.is_empty() {
+            query_table = except_table;
+        }
+
+        let mut where_clauses = vec![];
+        let mut params = vec![];
+        for column in &composite.columns {
+            let sql_type = get_sql_type_from_global_config(config, table_name, column, pool);
+            let sql_param = cast_sql_param_from_text(&sql_type, pool);
+            where_clauses.push(format!(r#""{}" = {}"#, column, sql_param));
+            params.push(
+                context
+                    .contents
+                    .get(column)
+                    .map(|cell| cell.strvalue())
+                    .ok_or(ValveError::InputError(format!(
+                        "No column '{}' in row {:?}",
+                        column, context
+                    )))?,
+            );
+        }
+        let sql = local_sql_syntax(
+            pool,
+            &format!(
+                r#"{} SELECT 1 FROM "{}" WHERE {} LIMIT 1"#,
+                with_sql,
+                query_table,
+                where_clauses.join(" AND ")
+            ),
+        );
+        let mut query = sqlx_query(&sql);
+        for param in &params {
+            query = query.bind(param);
+        }
+        if let Some(tx) = tx.as_mut() {
+            Ok(!query.fetch_all(tx.acquire().await?).await?.is_empty())
+        } else {
+            Ok(!query.fetch_all(pool).await?.is_empty())
+        }
+    }
+
+    // Check any multi-column keys for which this is the last column. If any of the key's other
+    // cells in the row are empty or have SQL type violations, the key is not checked.
+    let composites = config
+        .constraint
+        .composite
+        .get(table_name)
+        .unwrap_or_else(|| panic!("Undefined table '{}'", table_name))
+        .iter()
+        .filter(|c| c.columns.last() == Some(column_name))
+        .collect::<Vec<_>>();
+    for composite in composites {
+        let checkable = composite
+            .columns
+            .iter()
+            .all(|column| match context.contents.get(column) {
+                Some(key_cell) => {
+                    let sql_type =
+                        get_sql_type_from_global_config(config, table_name, column, pool);
+                    key_cell.nulltype.is_none()
+                        && !is_sql_type_error(&sql_type, &key_cell.strvalue())
+                }
+                None => false,
+            });
+        if !checkable {
+            continue;
+        }
+
+        let in_prev_results = prev_results.iter().any(|p| {
+            composite.columns.iter().all(|column| {
+                match (p.contents.get(column), context.contents.get(column)) {
+                    (Some(prev_cell), Some(key_cell)) => {
+                        prev_cell.valid && prev_cell.strvalue() == key_cell.strvalue()
+                    }
+                    _ => false,
+                }
+            })
+        });
+
+        // If a composite cache is given, just check it, otherwise access the database:
+        let is_duplicate = in_prev_results
+            || match composite_cache {
+                Some(cached_rows) => cached_rows.iter().any(|cached_row| {
+                    composite.columns.iter().all(|column| {
+                        match (
+                            cached_row.contents.get(column),
+                            context.contents.get(column),
+                        ) {
+                            (Some(cached_cell), Some(cell)) => {
+                                cached_cell.strvalue() == cell.strvalue()
+                            }
+                            _ => false,
+                        }
+                    })
+                }),
+                None => {
+                    in_db_composite(
+                        config,
+                        pool,
+                        match tx.as_mut() {
+                            Some(tx) => Some(&mut **tx),
+                            None => None,
+                        },
+                        table_name,
+                        composite,
+                        context,
+                        row_number,
+                    )
+                    .await?
+                }
+            };
+        if is_duplicate {
+            cell.valid = false;
+            cell.messages.push(make_error(
+                match composite.key_type.as_str() {
+                    "primary" => ValveRuleKind::KeyPrimary.id(),
+                    _ => ValveRuleKind::KeyUnique.id(),
+                },
+                &composite.columns.join(", "),
+            ));
+        }
+    }
+
     if is_primary || is_unique {
         let in_prev_results = !prev_results
             .iter()
@@ -1535,9 +3178,9 @@ pub async fn validate_cell_unique_constraints(
             if is_primary || is_unique {
                 let error_message;
                 if is_primary {
-                    error_message = make_error("key:primary", column_name);
+                    error_message = make_error(ValveRuleKind::KeyPrimary.id(), column_name);
                 } else {
-                    error_message = make_error("key:unique", column_name);
+                    error_message = make_error(ValveRuleKind::KeyUnique.id(), column_name);
                 }
                 cell.messages.push(error_message);
             }
@@ -5,18 +5,25 @@ use crate::{
     internal::{generate_internal_table_ddl, INTERNAL_TABLES},
     toolkit,
     toolkit::{
-        add_message_counts, cast_column_sql_to_text, convert_undo_or_redo_record_to_change,
-        delete_row_tx, generate_datatype_conditions, generate_rule_conditions,
-        get_column_for_label, get_column_value_as_string, get_json_array_from_row,
+        add_message_counts, cast_column_sql_to_text, check_config_issues,
+        convert_undo_or_redo_record_to_change, delete_row_tx, diff_configs,
+        generate_datatype_conditions, generate_rule_conditions, get_column_for_label,
+        get_column_value_as_string, get_declared_composites, get_json_array_from_row,
         get_json_object_from_row, get_parsed_structure_conditions, get_pool_from_connection_string,
         get_previous_row_tx, get_record_to_redo, get_record_to_undo, get_row_from_db,
         get_sql_for_standard_view, get_sql_for_text_view, get_sql_type,
-        get_sql_type_from_global_config, insert_chunks, insert_new_row_tx, local_sql_syntax,
-        move_row_tx, normalize_options, read_config_files, record_row_change, record_row_move,
-        switch_undone_state, undo_or_redo_move, update_row_tx, verify_table_deps_and_sort,
-        ColumnRule, CompiledCondition, ParsedStructure, ValueType,
+        get_sql_type_from_global_config, get_tree_closure_ddl, get_tree_distances, get_tree_edges,
+        get_tree_query_table, insert_chunks, insert_new_row_tx, is_sql_type_error,
+        lint_config_files, local_sql_syntax, move_row_tx, normalize_options, propose_fix,
+        read_config_files, record_row_change, record_row_move, register_condition_function,
+        switch_undone_state, transform_row, undo_or_redo_move, update_row_tx,
+        update_tree_closure_tx, verify_table_deps_and_sort, ColumnRule, CompiledCondition,
+        ConditionFunction, ConfigLocations, ParsedStructure, ValueType,
+    },
+    validate::{
+        validate_row_tx, validate_tables_in_memory, validate_tree_cycles,
+        validate_tree_foreign_keys, with_tree_sql,
     },
-    validate::{validate_row_tx, validate_tree_foreign_keys, with_tree_sql},
     valve_grammar::StartParser,
     CHUNK_SIZE, PRINTF_RE, SQL_PARAM, SQL_TYPES,
 };
@@ -27,20 +34,23 @@ use futures::{executor::block_on, TryStreamExt};
 use indexmap::IndexMap;
 use itertools::Itertools;
 use regex::Regex;
+use rhai::AST;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as SerdeValue};
+use sha2::{Digest, Sha256};
 use sprintf::sprintf;
 use sqlx::{
-    any::{AnyKind, AnyPool},
-    query as sqlx_query, Row, ValueRef,
+    any::{AnyKind, AnyPool, AnyPoolOptions},
+    query as sqlx_query, Acquire, Row, Transaction, ValueRef,
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     error::Error,
     fmt,
     fs::File,
     path::Path,
     process::Command,
+    sync::Arc,
 };
 
 /// Alias for [Map](serde_json::map)<[String], [Value](serde_json::value)>.
@@ -92,6 +102,91 @@ pub fn unfold_json_row(json_row: &JsonRow) -> Result<JsonRow> {
     Ok(rich_row)
 }
 
+/// Uses the given (unverified) printf-style format string and the given compiled regular
+/// expression (which is used to verify the given format) to format the given cell.
+fn format_cell(colformat: &str, format_regex: &Regex, cell: &str) -> String {
+    let conversion_spec = match format_regex.captures(colformat) {
+        Some(c) => c[1].to_lowercase(),
+        None => {
+            log::warn!("Illegal format: '{}'", colformat);
+            "s".to_string()
+        }
+    };
+    let generic_error = format!("Error applying format '{}' to '{}':", colformat, cell);
+    match conversion_spec.as_str() {
+        "d" | "i" | "c" => match cell.parse::<isize>() {
+            Ok(cell) => match sprintf!(&colformat, cell) {
+                Ok(cell) => {
+                    // For some reason sprintf converts signed ints to unsigned ints before
+                    // converting them to a string. So we have to workaround this here:
+                    let cell = cell.parse::<usize>().unwrap();
+                    let cell = cell as isize;
+                    cell.to_string()
+                }
+                Err(e) => {
+                    log::warn!("{}: {}", generic_error, e);
+                    cell.to_string()
+                }
+            },
+            Err(e) => {
+                log::warn!("{}: {}", generic_error, e);
+                cell.to_string()
+            }
+        },
+        "o" | "u" | "x" => match cell.parse::<usize>() {
+            Ok(cell) => sprintf!(&colformat, cell).unwrap_or(cell.to_string()),
+            Err(e) => {
+                log::warn!("{}: {}", generic_error, e);
+                cell.to_string()
+            }
+        },
+        "e" | "f" | "g" | "a" => match cell.parse::<f64>() {
+            Ok(cell) => sprintf!(&colformat, cell).unwrap_or(cell.to_string()),
+            Err(e) => {
+                log::warn!("{}: {}", generic_error, e);
+                cell.to_string()
+            }
+        },
+        "s" => sprintf!(&colformat, cell).unwrap_or(cell.to_string()),
+        _ => {
+            log::warn!(
+                "Unsupported conversion specifier '{}' in column format '{}'",
+                conversion_spec,
+                colformat
+            );
+            cell.to_string()
+        }
+    }
+}
+
+/// Given a constraint type (one of 'PRIMARY KEY', 'UNIQUE', or 'FOREIGN KEY'), a list of columns,
+/// and, in the case of a foreign key, the foreign table and columns that it refers to, return the
+/// SQL that is used to declare the constraint in a CREATE TABLE or ALTER TABLE statement.
+fn get_constraint_sql(
+    constraint_type: &str,
+    columns: &Vec<String>,
+    ftable: &str,
+    fcolumns: &Vec<String>,
+) -> String {
+    let quote_columns = |columns: &Vec<String>| -> String {
+        columns
+            .iter()
+            .map(|column| format!(r#""{}""#, column))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if constraint_type == "FOREIGN KEY" {
+        format!(
+            r#"FOREIGN KEY ({}) REFERENCES "{}"({})"#,
+            quote_columns(columns),
+            ftable,
+            quote_columns(fcolumns)
+        )
+    } else {
+        format!("{} ({})", constraint_type, quote_columns(columns))
+    }
+}
+
 /// Represents a particular row of data with validation results.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct ValveRow {
@@ -233,6 +328,11 @@ pub struct ValveCellMessage {
     pub rule: String,
     /// The contents of the message.
     pub message: String,
+    /// Values that the value the message is about may have been intended to be, ranked from most
+    /// to least likely. Only the messages for some rule violations (currently `key:foreign` and
+    /// violations of `in()` datatype conditions) have suggestions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
 }
 
 /// Generic enum representing various error types returned by Valve methods
@@ -281,6 +381,210 @@ pub struct ValveMessage {
     pub message: String,
 }
 
+/// The kinds of rule that a value can violate, as identified by the `rule` field of a
+/// [ValveCellMessage] or [ValveMessage]. Each kind has a stable identifier, a default level, and an
+/// explanation. The identifier of some kinds of rule is followed by a parameter in messages, e.g.,
+/// the name of the datatype in `datatype:integer`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ValveRuleKind {
+    /// `datatype:DATATYPE`: The value does not satisfy the condition of the column's datatype
+    Datatype,
+    /// `key:foreign`: The value is not in the column referenced by the column's from() structure
+    KeyForeign,
+    /// `key:primary`: The value violates the primary key of the table
+    KeyPrimary,
+    /// `key:unique`: The value violates a unique constraint of the table
+    KeyUnique,
+    /// `option:unrecognized`: An option in the table table is not recognized
+    OptionUnrecognized,
+    /// `option:redundant`: An option in the table table is already implied by another
+    OptionRedundant,
+    /// `option:reserved`: An option in the table table is reserved for internal use
+    OptionReserved,
+    /// `option:overrides`: An option in the table table overrides another one
+    OptionOverrides,
+    /// `tree:foreign`: The value is not in the child column of the column's tree() structure
+    TreeForeign,
+    /// `tree:cycle`: The value results in a cycle in the column's tree() structure
+    TreeCycle,
+    /// `rule:ID`: The value violates a rule in the rule table
+    Rule,
+    /// `rule:script-N`: A script rule in the rule table returned a finding about the value
+    ScriptRule,
+    /// `transform:rewrite`: The value was rewritten by a transform before it was validated
+    TransformRewrite,
+}
+
+impl ValveRuleKind {
+    /// All of the kinds of rule, in the order in which they are documented.
+    pub const ALL: [ValveRuleKind; 13] = [
+        ValveRuleKind::Datatype,
+        ValveRuleKind::KeyForeign,
+        ValveRuleKind::KeyPrimary,
+        ValveRuleKind::KeyUnique,
+        ValveRuleKind::OptionUnrecognized,
+        ValveRuleKind::OptionRedundant,
+        ValveRuleKind::OptionReserved,
+        ValveRuleKind::OptionOverrides,
+        ValveRuleKind::TreeForeign,
+        ValveRuleKind::TreeCycle,
+        ValveRuleKind::Rule,
+        ValveRuleKind::ScriptRule,
+        ValveRuleKind::TransformRewrite,
+    ];
+
+    /// Returns the stable identifier of this kind of rule.
+    pub fn id(&self) -> &'static str {
+        match self {
+            ValveRuleKind::Datatype => "datatype",
+            ValveRuleKind::KeyForeign => "key:foreign",
+            ValveRuleKind::KeyPrimary => "key:primary",
+            ValveRuleKind::KeyUnique => "key:unique",
+            ValveRuleKind::OptionUnrecognized => "option:unrecognized",
+            ValveRuleKind::OptionRedundant => "option:redundant",
+            ValveRuleKind::OptionReserved => "option:reserved",
+            ValveRuleKind::OptionOverrides => "option:overrides",
+            ValveRuleKind::TreeForeign => "tree:foreign",
+            ValveRuleKind::TreeCycle => "tree:cycle",
+            ValveRuleKind::Rule => "rule",
+            ValveRuleKind::ScriptRule => "rule:script",
+            ValveRuleKind::TransformRewrite => "transform:rewrite",
+        }
+    }
+
+    /// Returns the level of the messages generated for this kind of rule. Note that the level
+    /// of the messages generated for rules in the rule table is given by the rule table itself.
+    pub fn default_level(&self) -> &'static str {
+        match self {
+            ValveRuleKind::OptionRedundant | ValveRuleKind::OptionOverrides => "warning",
+            ValveRuleKind::TransformRewrite => "info",
+            _ => "error",
+        }
+    }
+
+    /// Returns a human-readable explanation of this kind of rule.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ValveRuleKind::Datatype => {
+                "The value does not satisfy the condition associated with the datatype of its \
+                 column."
+            }
+            ValveRuleKind::KeyForeign => {
+                "The column has a from() structure that references a column in another table, but \
+                 the value is not in that column."
+            }
+            ValveRuleKind::KeyPrimary => {
+                "The column has a primary structure (or is part of a primary key), and the value \
+                 already exists in the column."
+            }
+            ValveRuleKind::KeyUnique => {
+                "The column has a unique structure (or is part of a unique key), and the value \
+                 already exists in the column."
+            }
+            ValveRuleKind::OptionUnrecognized => {
+                "The options column of the table table contains an unrecognized option."
+            }
+            ValveRuleKind::OptionRedundant => {
+                "The options column of the table table contains an option that is already \
+                 implied by one of the other options."
+            }
+            ValveRuleKind::OptionReserved => {
+                "The options column of the table table contains an option that is reserved for \
+                 internal use."
+            }
+            ValveRuleKind::OptionOverrides => {
+                "The options column of the table table contains an option that overrides one of \
+                 the other options."
+            }
+            ValveRuleKind::TreeForeign => {
+                "The column has a tree() structure that references another column of the same \
+                 table, but the value is not in that column."
+            }
+            ValveRuleKind::TreeCycle => {
+                "The column has a tree() structure, and the value is a descendant, in the tree, \
+                 of the value of the tree's child column in the same row, i.e., the tree contains \
+                 a cycle."
+            }
+            ValveRuleKind::Rule => {
+                "The value violates a rule in the rule table: whenever the rule's when conditions \
+                 are satisfied, the value of the rule's then column must satisfy its then \
+                 condition."
+            }
+            ValveRuleKind::ScriptRule => {
+                "A script rule in the rule table has returned a finding about the value."
+            }
+            ValveRuleKind::TransformRewrite => {
+                "The value was rewritten by the transform that applies to its column before it \
+                 was validated. The message records the original value."
+            }
+        }
+    }
+
+    /// Given a parameter (e.g., the name of a datatype, or the identifier of a rule in the rule
+    /// table), returns the rule identifier that is used in messages for this kind of rule. The
+    /// parameter is ignored for kinds of rule that do not take one.
+    pub fn rule_id(&self, parameter: &str) -> String {
+        match self {
+            ValveRuleKind::Datatype | ValveRuleKind::Rule => {
+                format!("{}:{}", self.id(), parameter)
+            }
+            ValveRuleKind::ScriptRule => format!("{}-{}", self.id(), parameter),
+            _ => self.id().to_string(),
+        }
+    }
+
+    /// Given a rule identifier used in a message, returns the kind of rule that it identifies
+    /// along with its parameter, if any. Returns None if the rule identifier is not recognized.
+    pub fn from_rule_id(rule_id: &str) -> Option<(ValveRuleKind, Option<String>)> {
+        if let Some(kind) = ValveRuleKind::ALL
+            .iter()
+            .find(|kind| !kind.takes_parameter() && kind.id() == rule_id)
+        {
+            return Some((*kind, None));
+        }
+        let (prefix, parameter) = rule_id.split_once(':')?;
+        if parameter.is_empty() {
+            return None;
+        }
+        match prefix {
+            "datatype" => Some((ValveRuleKind::Datatype, Some(parameter.to_string()))),
+            "rule" => match parameter.strip_prefix("script-") {
+                Some(n) if n.parse::<usize>().is_ok() => {
+                    Some((ValveRuleKind::ScriptRule, Some(n.to_string())))
+                }
+                _ => Some((ValveRuleKind::Rule, Some(parameter.to_string()))),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns true if rule identifiers of this kind of rule include a parameter.
+    fn takes_parameter(&self) -> bool {
+        matches!(
+            self,
+            ValveRuleKind::Datatype | ValveRuleKind::Rule | ValveRuleKind::ScriptRule
+        )
+    }
+}
+
+/// An explanation of a rule identifier, as returned by [Valve::explain_rule()].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ValveRuleExplanation {
+    /// The rule identifier that has been explained
+    pub rule: String,
+    /// The kind of rule identified
+    pub kind: ValveRuleKind,
+    /// The level of the messages generated for the rule
+    pub level: String,
+    /// An explanation of the kind of rule identified
+    pub explanation: String,
+    /// Details about the particular rule(s) identified, taken from the configuration, e.g., the
+    /// condition of a datatype or the description of a rule in the rule table. Note that a
+    /// positional rule identifier such as `rule:COLUMN-N` may identify a rule in more than one
+    /// table.
+    pub details: Vec<String>,
+}
+
 /// Represents a change to a row in a database table.
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ValveRowChange {
@@ -309,6 +613,181 @@ pub struct ValveChange {
     pub message: String,
 }
 
+/// Represents a set of conditions used to select messages from the message table. Conditions that
+/// are set to None are ignored.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveMessageFilter {
+    /// The name of the column that the message must be about
+    pub column: Option<String>,
+    /// The row number of the row that the message must be about
+    pub row: Option<u32>,
+    /// The rule that the message must be about, which is matched using SQL's LIKE operator, so
+    /// that, e.g., 'datatype:%' matches every datatype violation
+    pub rule: Option<String>,
+    /// The severity that the message must have
+    pub level: Option<String>,
+}
+
+/// Represents a proposed fix for a value in a row of a database table.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveFix {
+    /// The name of the table that the value is from
+    pub table: String,
+    /// The row number of the row that the value is from
+    pub row: u32,
+    /// The name of the column that the value is from
+    pub column: String,
+    /// The current value
+    pub value: String,
+    /// The proposed replacement for the current value
+    pub fixed_value: String,
+    /// The rules, violated by the current value, that the replacement satisfies
+    pub rules: Vec<String>,
+    /// A description of the fix
+    pub message: String,
+}
+
+/// Represents a message in the message table, i.e., a message about a particular value in a
+/// particular row of a particular table.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveTableMessage {
+    /// The name of the table that the value is from
+    pub table: String,
+    /// The row number of the row that the value is from
+    pub row: u32,
+    /// The name of the column that the value is from
+    pub column: String,
+    /// The value
+    pub value: String,
+    /// The severity of the message
+    pub level: String,
+    /// The rule violated by the value
+    pub rule: String,
+    /// A description of the violation
+    pub message: String,
+}
+
+/// Statistics about the loading of a table.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveLoadStats {
+    /// The number of rows loaded into the table itself
+    pub rows: usize,
+    /// The number of rows loaded into the table's conflict table
+    pub conflict_rows: usize,
+    /// The number of error messages generated
+    pub errors: usize,
+    /// The number of warning messages generated
+    pub warnings: usize,
+    /// The number of information messages generated
+    pub infos: usize,
+}
+
+/// The result of a dry run of loading a number of tables (see [Valve::load_tables_dry_run()]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveLoadReport {
+    /// Statistics about the loading of each table, indexed by table name
+    pub tables: IndexMap<String, ValveLoadStats>,
+    /// The messages that were generated for the tables
+    pub messages: Vec<ValveTableMessage>,
+}
+
+/// Statistics about the synchronization of a table with its TSV file (see
+/// [Valve::sync_tables()]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveSyncStats {
+    /// The number of rows that were inserted into the table
+    pub inserted: usize,
+    /// The number of rows in the table that were updated
+    pub updated: usize,
+    /// The number of rows that were deleted from the table
+    pub deleted: usize,
+    /// The number of rows in the table that were left unchanged
+    pub unchanged: usize,
+}
+
+/// A step in the plan to migrate the database to the current configuration (see
+/// [Valve::plan_migration()]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveMigrationStep {
+    /// The table that the step applies to
+    pub table: String,
+    /// A description of the step
+    pub description: String,
+    /// The SQL statements that implement the step
+    pub statements: Vec<String>,
+    /// The values to be bound, in order, to the parameters (given by
+    /// [SQL_PARAM](crate::SQL_PARAM)) of each of the step's statements that has any
+    pub params: Vec<String>,
+    /// Whether the step can only be carried out by dropping the table and recreating it without
+    /// its data, in which case it has no statements. Such a step is carried out by
+    /// [Valve::create_all_tables()], but causes [Valve::migrate()] to fail.
+    pub recreates_table: bool,
+}
+
+/// A problem with the Valve configuration (see [Valve::lint_config()]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveConfigIssue {
+    /// The configuration file in which the problem was found
+    pub file: String,
+    /// The row of the file in which the problem was found, counting the rows after the header row
+    /// from 1. A row of 0 indicates a problem with the header row or with the file as a whole.
+    pub row: u32,
+    /// The column of the file in which the problem was found, if any
+    pub column: String,
+    /// The severity of the problem, either 'error' or 'warning'
+    pub severity: String,
+    /// A description of the problem
+    pub message: String,
+}
+
+/// A change between two versions of the Valve configuration (see [Valve::diff_config()]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveConfigChange {
+    /// The kind of item that was changed: 'table', 'column', 'constraint', 'datatype', 'rule', or
+    /// 'prefix'
+    pub kind: String,
+    /// The item that was changed, e.g., a table, or a column in the form 'table.column'. In the
+    /// case of a constraint, the table that the constraint belongs to
+    pub item: String,
+    /// Whether the item was 'added', 'removed', or 'changed'
+    pub change: String,
+    /// In the case of a changed item, the field of the item that was changed
+    pub field: String,
+    /// The old value of the field, or, in the case of a constraint, the removed constraint
+    pub old: String,
+    /// The new value of the field, or, in the case of a constraint, the added constraint
+    pub new: String,
+    /// The tables whose database structure will need to be migrated as a result of the change
+    /// (see [Valve::table_has_changed()]). Migrating a table keeps its data and history, except
+    /// in the cases described in [Valve::migrate()]
+    pub rebuilds: Vec<String>,
+    /// The tables whose validation results may change as a result of the change
+    pub revalidates: Vec<String>,
+}
+
+/// The differences, for a particular column and rule of a particular table, between the messages
+/// in the message table and those that would be generated by validating the data in the database
+/// under a candidate configuration (see [Valve::validate_with_config()]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveMessageDelta {
+    /// The name of the table
+    pub table: String,
+    /// The name of the column
+    pub column: String,
+    /// The rule that the messages are about
+    pub rule: String,
+    /// The messages that would be generated under the candidate configuration but that are not in
+    /// the message table
+    pub new: Vec<ValveTableMessage>,
+    /// The messages in the message table that would not be generated under the candidate
+    /// configuration
+    pub resolved: Vec<ValveTableMessage>,
+    /// The messages in the message table that are not generated under the current configuration
+    /// either (e.g., messages that were added when the rows were loaded or edited, but that
+    /// validating the rows in memory does not reproduce), and which therefore cannot be compared
+    pub unreproduced: Vec<ValveTableMessage>,
+}
+
 /// Configuration information specific to Valve's special tables
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct ValveSpecialConfig {
@@ -320,6 +799,9 @@ pub struct ValveSpecialConfig {
     pub datatype: String,
     /// The name of the rule table, or an empty string if there isn't any
     pub rule: String,
+    /// The name of the prefix table, or an empty string if there isn't any
+    #[serde(default)]
+    pub prefix: String,
 }
 
 /// Configuration information for a particular table.
@@ -362,6 +844,11 @@ pub struct ValveColumnConfig {
     /// row when the value of that column has not been specified in an INSERT database statement.
     /// An empty string indicates that the column has no default.
     pub default: SerdeValue,
+    /// A list of substitutions, of the form `s/PATTERN/REPLACEMENT/FLAGS`, that are applied to the
+    /// column's values before they are validated. An empty string indicates that the transform, if
+    /// any, of the column's datatype should be used instead.
+    #[serde(default)]
+    pub transform: String,
 }
 
 /// Configuration information for a particular datatype
@@ -377,6 +864,10 @@ pub struct ValveDatatypeConfig {
     pub description: String,
     /// The parent datatype of the datatype
     pub parent: String,
+    /// A list of substitutions, of the form `s/PATTERN/REPLACEMENT/FLAGS`, that are applied to
+    /// values of this datatype before they are validated
+    #[serde(default)]
+    pub transform: String,
 }
 
 /// Configuration information for a particular table rule
@@ -396,6 +887,36 @@ pub struct ValveRuleConfig {
     pub then_column: String,
     /// The condition that the then_column must satisfy whenever the antecedent condition applies
     pub then_condition: String,
+    /// Any further columns on which the antecedent of the rule is based, each paired with the
+    /// condition that it must satisfy for the rule to apply
+    #[serde(default)]
+    pub additional_when: Vec<(String, String)>,
+    /// The stable identifier of the rule, if one has been given in the rule table. When it is
+    /// empty, the rule is identified by its position among the rules for its when column.
+    #[serde(default)]
+    pub id: String,
+}
+
+/// Configuration information for a particular script rule of a table, i.e., a rule whose body is a
+/// script, written in the [Rhai](https://rhai.rs) scripting language, that is given the row being
+/// validated and that returns a list of findings about it.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveScriptRuleConfig {
+    /// The description of the rule
+    pub description: String,
+    /// The level (error, warning, info) to use for findings that do not specify a level
+    pub level: String,
+    /// The table with which the rule is associated
+    pub table: String,
+    /// The text of the script
+    pub script: String,
+    /// The stable identifier of the rule, if one has been given in the rule table. When it is
+    /// empty, the rule is identified by its position among the script rules for its table.
+    #[serde(default)]
+    pub id: String,
+    /// The compiled script, which is generated along with the rest of the configuration
+    #[serde(skip)]
+    pub compiled: Option<Arc<AST>>,
 }
 
 /// Configuration information for a particular 'tree' constraint
@@ -405,6 +926,10 @@ pub struct ValveTreeConstraint {
     pub child: String,
     /// The child's parent
     pub parent: String,
+    /// If the parent column has a list() datatype, the separator between the parent values in a
+    /// given cell of the parent column. In this case the tree is a polyhierarchy.
+    #[serde(default)]
+    pub separator: Option<String>,
 }
 
 /// Configuration information for a particular foreign key constraint
@@ -420,6 +945,31 @@ pub struct ValveForeignConstraint {
     pub fcolumn: String,
 }
 
+/// Configuration information for a particular foreign key constraint on more than one column
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValveCompositeForeignConstraint {
+    /// The table to which the columns constrained by the key belong
+    pub table: String,
+    /// The columns constrained by the key
+    pub columns: Vec<String>,
+    /// The table referenced by the foreign key
+    pub ftable: String,
+    /// The columns referenced by the foreign key, in the same order as the constrained columns
+    pub fcolumns: Vec<String>,
+}
+
+/// Configuration information for a particular primary or unique key constraint on more than one
+/// column
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ValveCompositeConstraint {
+    /// The table to which the columns constrained by the key belong
+    pub table: String,
+    /// The type of the key, either 'primary' or 'unique'
+    pub key_type: String,
+    /// The columns that are jointly constrained by the key
+    pub columns: Vec<String>,
+}
+
 /// Configuration information for the constraints enforced by a particular Valve instance
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct ValveConstraintConfig {
@@ -434,6 +984,13 @@ pub struct ValveConstraintConfig {
     pub foreign: HashMap<String, Vec<ValveForeignConstraint>>,
     /// A map from table names to each given table's tree constraints
     pub tree: HashMap<String, Vec<ValveTreeConstraint>>,
+    /// A map from table names to each given table's multi-column primary and unique key
+    /// constraints
+    #[serde(default)]
+    pub composite: HashMap<String, Vec<ValveCompositeConstraint>>,
+    /// A map from table names to each given table's multi-column foreign key constraints
+    #[serde(default)]
+    pub composite_foreign: HashMap<String, Vec<ValveCompositeForeignConstraint>>,
 }
 
 /// Configuration information for a particular Valve instance
@@ -451,6 +1008,14 @@ pub struct ValveConfig {
     /// conditional 'when-then' rules associated with that column. Note that 'associated with'
     /// means that the given column is the when-column of some rule defined on the table.
     pub rule: HashMap<String, HashMap<String, Vec<ValveRuleConfig>>>,
+    /// A map from table names to the script rules associated with the given table, in the order in
+    /// which they appear in the rule table.
+    #[serde(default)]
+    pub script_rule: HashMap<String, Vec<ValveScriptRuleConfig>>,
+    /// A map from CURIE prefixes to the base IRIs that they stand for, as defined in the prefix
+    /// table (if any).
+    #[serde(default)]
+    pub prefix: HashMap<String, String>,
     /// Configuration specific to Valve's database and tree constraints
     pub constraint: ValveConstraintConfig,
 }
@@ -500,6 +1065,9 @@ pub struct Valve {
     pub pool: AnyPool,
     /// The user associated with this valve instance.
     pub user: String,
+    /// The user to whom changes that are made to the database by
+    /// [sync_tables()](Self::sync_tables) are attributed.
+    pub sync_user: String,
     /// When set to true, the valve instance produces more logging output.
     pub verbose: bool,
     /// When set to true, valve will ask for confirmation before automatically dropping/truncating
@@ -507,6 +1075,9 @@ pub struct Valve {
     pub interactive: bool,
     /// Activates optimizations used for the initial loading of data.
     pub initial_load: bool,
+    /// When set to true, IRIs in columns that are meant to contain CURIEs are contracted to CURIEs
+    /// (see [contract_iri()](Self::contract_iri)) when tables are saved.
+    pub contract_iris: bool,
     /// Private field used to store startup error messages. Note that these are also accessible via
     /// the 'message' database table. Startup messages represent errors and warnings that are
     /// encountered while configuring Valve which cannot be handled at load time. They are always
@@ -528,20 +1099,76 @@ impl Valve {
                 .execute(&pool)
                 .await?;
         }
+        Self::build_with_pool(table_path, database, pool)
+    }
+
+    /// Given a path to a table table, which must end (case-insensitively) in .tsv, configure
+    /// VALVE and return a new Valve struct that is not connected to any database. Such an
+    /// instance may be used to validate data in memory (see [Valve::validate_tables_in_memory()]
+    /// and [Valve::validate_rows_in_memory()]), and to query the configuration, but not to call
+    /// any of the methods that access the database.
+    pub fn build_in_memory(table_path: &str) -> Result<Self> {
+        let _ = env_logger::try_init();
+        if !table_path.to_lowercase().ends_with(".tsv") {
+            return Err(ValveError::InputError(format!(
+                "'{}' is not a TSV file. An in-memory Valve instance must be configured from a \
+                 TSV file",
+                table_path
+            ))
+            .into());
+        }
+        // The pool is used only to determine the SQL types of columns. Since it is lazy, no
+        // connection is ever made through it unless one of the database methods is called:
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect_lazy("sqlite::memory:")?;
+        Self::build_with_pool(table_path, "", pool)
+    }
+
+    /// Given a path to a table table, which must end (case-insensitively) in .tsv, check the
+    /// configuration that it refers to and return every problem found with it, including
+    /// warnings, as a list of [ValveConfigIssue]s. Since a configuration with problems may not
+    /// be usable to build a Valve instance, this function does not require one. The checks are
+    /// the same ones that are made when Valve is built, so a configuration without any
+    /// error-level issues will build unless there are problems with the database. See also
+    /// [lint_config_files()](crate::toolkit::lint_config_files).
+    pub fn lint_config(table_path: &str) -> Result<Vec<ValveConfigIssue>> {
+        if !table_path.to_lowercase().ends_with(".tsv") {
+            return Err(ValveError::InputError(format!(
+                "'{}' is not a TSV file. Only a configuration read from TSV files can be checked",
+                table_path
+            ))
+            .into());
+        }
+        let parser = StartParser::new();
+        // As in build_in_memory(), the pool is used only to determine the SQL types of columns,
+        // and no connection is ever made through it:
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect_lazy("sqlite::memory:")?;
+        Ok(lint_config_files(table_path, &parser, &pool))
+    }
 
+    /// Given a path to a table table, a path to a database, and a connection pool for that
+    /// database, configure VALVE and return a new Valve struct.
+    fn build_with_pool(table_path: &str, database: &str, pool: AnyPool) -> Result<Self> {
         let parser = StartParser::new();
+        let mut config_issues = vec![];
         let (
             specials_config,
             tables_config,
             table_order,
             datatypes_config,
             rules_config,
+            script_rules_config,
+            prefixes_config,
             constraints_config,
             sorted_table_list,
             table_dependencies_in,
             table_dependencies_out,
             startup_table_messages,
-        ) = read_config_files(table_path, &parser, &pool)?;
+            config_locations,
+        ) = read_config_files(table_path, &parser, &pool, &mut config_issues);
 
         let config = ValveConfig {
             special: specials_config,
@@ -549,11 +1176,27 @@ impl Valve {
             table_order: table_order,
             datatype: datatypes_config,
             rule: rules_config,
+            script_rule: script_rules_config,
+            prefix: prefixes_config,
             constraint: constraints_config,
         };
 
-        let datatype_conditions = generate_datatype_conditions(&config, &parser)?;
-        let rule_conditions = generate_rule_conditions(&config, &datatype_conditions, &parser)?;
+        let datatype_conditions = generate_datatype_conditions(
+            &config,
+            &parser,
+            &pool,
+            &config_locations,
+            &mut config_issues,
+        );
+        let rule_conditions = generate_rule_conditions(
+            &config,
+            &datatype_conditions,
+            &parser,
+            &pool,
+            &config_locations,
+            &mut config_issues,
+        );
+        check_config_issues(&config_issues)?;
         let structure_conditions = get_parsed_structure_conditions(&config, &parser)?;
 
         Ok(Self {
@@ -567,13 +1210,27 @@ impl Valve {
             db_path: database.to_string(),
             pool: pool,
             user: String::from("VALVE"),
+            sync_user: String::from(Self::DEFAULT_SYNC_USER),
             verbose: false,
             interactive: false,
             initial_load: false,
+            contract_iris: false,
             startup_table_messages: startup_table_messages,
         })
     }
 
+    /// Given a function name and a [ConditionFunction], register the condition function under the
+    /// given name so that it may be called in the conditions of datatypes and rules. Since
+    /// conditions are compiled when Valve is built, this function must be called before
+    /// [build()](Self::build). See also
+    /// [register_condition_function()](crate::toolkit::register_condition_function).
+    pub fn register_condition_function(
+        name: &str,
+        function: impl ConditionFunction + 'static,
+    ) -> Result<()> {
+        register_condition_function(name, Arc::new(function))
+    }
+
     /// Configures a SQLite database for initial loading by setting a number of unsafe PRAGMAs
     /// that are unsafe in general but suitable when setting up a Valve database for the first
     /// time. Note that if Valve's managed database is not a SQLite database, calling this function
@@ -612,9 +1269,13 @@ impl Valve {
     /// The maximum length of a username.
     pub const USERNAME_MAX_LEN: usize = 20;
 
-    /// Sets the user name, which must be a short (see [USERNAME_MAX_LEN](Self::USERNAME_MAX_LEN)),
-    /// trimmed, string without newlines, for this Valve instance.
-    pub fn set_user(&mut self, user: &str) -> Result<&mut Self> {
+    /// The user to whom changes made by [sync_tables()](Self::sync_tables) are attributed by
+    /// default.
+    pub const DEFAULT_SYNC_USER: &'static str = "file-sync";
+
+    /// (Private function.) Verify that the given user name is a short (see
+    /// [USERNAME_MAX_LEN](Self::USERNAME_MAX_LEN)), trimmed, string without newlines.
+    fn check_user(user: &str) -> Result<()> {
         if user.len() > Self::USERNAME_MAX_LEN {
             return Err(ValveError::InputError(format!(
                 "Username '{}' is longer than {} characters.",
@@ -632,10 +1293,26 @@ impl Valve {
                 .into());
             }
         }
+        Ok(())
+    }
+
+    /// Sets the user name, which must be a short (see [USERNAME_MAX_LEN](Self::USERNAME_MAX_LEN)),
+    /// trimmed, string without newlines, for this Valve instance.
+    pub fn set_user(&mut self, user: &str) -> Result<&mut Self> {
+        Self::check_user(user)?;
         self.user = user.to_string();
         Ok(self)
     }
 
+    /// Sets the name of the user to whom changes made by [sync_tables()](Self::sync_tables) are
+    /// attributed, which must be a short (see [USERNAME_MAX_LEN](Self::USERNAME_MAX_LEN)),
+    /// trimmed, string without newlines.
+    pub fn set_sync_user(&mut self, user: &str) -> Result<&mut Self> {
+        Self::check_user(user)?;
+        self.sync_user = user.to_string();
+        Ok(self)
+    }
+
     /// Configure verbosity
     pub fn set_verbose(&mut self, verbose: bool) -> &mut Self {
         self.verbose = verbose;
@@ -648,6 +1325,12 @@ impl Valve {
         self
     }
 
+    /// Configure whether IRIs are contracted to CURIEs when saving tables
+    pub fn set_contract_iris(&mut self, contract_iris: bool) -> &mut Self {
+        self.contract_iris = contract_iris;
+        self
+    }
+
     /// (Private function.) Given a SQL string, execute it using the connection pool associated
     /// with the Valve instance.
     async fn execute_sql(&self, sql: &str) -> Result<()> {
@@ -756,8 +1439,12 @@ impl Valve {
             .map(|s| s.to_string())
             .collect::<Vec<_>>();
 
-        let (sorted_subset, _, _) =
-            verify_table_deps_and_sort(&filtered_subset, &self.config.constraint);
+        let (sorted_subset, _, _) = verify_table_deps_and_sort(
+            &filtered_subset,
+            &self.config.constraint,
+            &ConfigLocations::default(),
+            &mut vec![],
+        );
 
         // Since the result of verify_table_deps_and_sort() will include dependencies of the tables
         // in its input list, we filter those out here:
@@ -848,6 +1535,16 @@ impl Valve {
         Ok(dependencies)
     }
 
+    /// Given a new configuration, compare it with this Valve instance's configuration and return
+    /// the differences between them as a list of [ValveConfigChange]s, each of which lists the
+    /// tables that would have to be migrated (see [Valve::table_has_changed()]) and the tables
+    /// whose validation results may change if Valve were to be configured using the new
+    /// configuration. See also [diff_configs()](crate::toolkit::diff_configs).
+    pub fn diff_config(&self, new_config: &ValveConfig) -> Vec<ValveConfigChange> {
+        let parser = StartParser::new();
+        diff_configs(&self.config, new_config, &parser)
+    }
+
     /// Given the name of a table, determine whether its current instantiation in the database
     /// differs from the way it has been configured. The answer to this question is yes whenever
     /// (1) the number of columns or any of their names differs from their configured values, or
@@ -855,7 +1552,9 @@ impl Valve {
     /// more columns does not match the configured SQL type for that column; (3) Some column with a
     /// 'unique', 'primary', or 'from(table, column)' in its column configuration fails to be
     /// associated, in the database, with a unique constraint, primary key, or foreign key,
-    /// respectively; or vice versa; (4) The table does not exist in the database.
+    /// respectively; or vice versa; (4) The multi-column primary, unique, and foreign keys
+    /// configured for the table differ from those in the database; (5) The table does not exist in
+    /// the database.
     pub async fn table_has_changed(&self, table: &str) -> Result<bool> {
         // A clojure that, given a parsed structure condition, a table and column name, and an
         // unsigned integer representing whether the given column, in the case of a SQLite database,
@@ -869,7 +1568,8 @@ impl Valve {
                                      sqlite_pk: &u32|
          -> Result<bool> {
             // A clojure to determine whether the given column has the given constraint type, which
-            // can be one of 'UNIQUE', 'PRIMARY KEY', 'FOREIGN KEY':
+            // can be one of 'UNIQUE', 'PRIMARY KEY', 'FOREIGN KEY' (constraints that apply jointly
+            // to more than one column are not considered here):
             let column_has_constraint_type = |constraint_type: &str| -> Result<bool> {
                 if self.pool.any_kind() == AnyKind::Postgres {
                     let sql = format!(
@@ -880,7 +1580,11 @@ impl Valve {
                             AND kcu.constraint_schema = tco.constraint_schema
                             AND kcu.table_name = $1
                        WHERE tco.constraint_type = $2
-                         AND kcu.column_name = $3"#,
+                         AND kcu.column_name = $3
+                         AND (SELECT COUNT(1)
+                                FROM information_schema.key_column_usage kcu2
+                               WHERE kcu2.constraint_name = tco.constraint_name
+                                 AND kcu2.constraint_schema = tco.constraint_schema) = 1"#,
                     );
                     let query = sqlx_query(&sql)
                         .bind(table)
@@ -913,9 +1617,13 @@ impl Valve {
                         Ok(false)
                     } else if constraint_type == "FOREIGN KEY" {
                         let sql = format!(r#"PRAGMA FOREIGN_KEY_LIST("{}")"#, table);
-                        for row in block_on(sqlx_query(&sql).fetch_all(&self.pool))? {
+                        let rows = block_on(sqlx_query(&sql).fetch_all(&self.pool))?;
+                        for row in &rows {
+                            let id = row.get::<i64, _>("id");
                             let cname = row.get::<String, _>("from");
-                            if cname == column {
+                            let num_columns =
+                                rows.iter().filter(|r| r.get::<i64, _>("id") == id).count();
+                            if cname == column && num_columns == 1 {
                                 return Ok(true);
                             }
                         }
@@ -973,9 +1681,14 @@ impl Valve {
                         Expression::Field(cfg_ftable, cfg_fcolumn) => {
                             if self.pool.any_kind() == AnyKind::Sqlite {
                                 let sql = format!(r#"PRAGMA FOREIGN_KEY_LIST("{}")"#, table);
-                                for row in block_on(sqlx_query(&sql).fetch_all(&self.pool))? {
+                                let rows = block_on(sqlx_query(&sql).fetch_all(&self.pool))?;
+                                for row in &rows {
+                                    let id = row.get::<i64, _>("id");
                                     let from = row.get::<String, _>("from");
-                                    if from == column {
+                                    // Foreign keys on more than one column are checked separately:
+                                    let num_columns =
+                                        rows.iter().filter(|r| r.get::<i64, _>("id") == id).count();
+                                    if from == column && num_columns == 1 {
                                         let db_ftable = row.get::<String, _>("table");
                                         let db_fcolumn = row.get::<String, _>("to");
                                         if *cfg_ftable != db_ftable || *cfg_fcolumn != db_fcolumn {
@@ -996,7 +1709,11 @@ impl Valve {
                                      ON ccu.constraint_name = tc.constraint_name
                                    WHERE tc.constraint_type = 'FOREIGN KEY'
                                      AND tc.table_name = $1
-                                     AND kcu.column_name = $2"#,
+                                     AND kcu.column_name = $2
+                                     AND (SELECT COUNT(1)
+                                            FROM information_schema.key_column_usage kcu2
+                                           WHERE kcu2.constraint_name = tc.constraint_name
+                                             AND kcu2.constraint_schema = tc.table_schema) = 1"#,
                                 );
                                 let query = sqlx_query(&sql).bind(table).bind(column);
                                 let rows = block_on(query.fetch_all(&self.pool))?;
@@ -1016,6 +1733,8 @@ impl Valve {
                                 }
                             }
                         }
+                        // Foreign keys on more than one column are checked separately:
+                        Expression::Tuple(_) => (),
                         _ => {
                             return Err(ValveError::InputError(
                                 format!("Unrecognized structure: {:?}", pstruct).into(),
@@ -1040,6 +1759,8 @@ impl Valve {
                         vec!["message_id".to_string()]
                     } else if table == "history" {
                         vec!["history_id".to_string()]
+                    } else if table == "load_state" {
+                        vec![]
                     } else {
                         vec!["row_number".to_string(), "row_order".to_string()]
                     }
@@ -1142,15 +1863,25 @@ impl Valve {
             return Ok(true);
         }
 
+        // In the case of SQLite, every column belonging to a multi-column primary key will have a
+        // nonzero pk value, but we are only interested in single-column primary keys here:
+        let num_pk_columns = db_columns_in_order
+            .iter()
+            .filter(|(_, _, pk)| *pk > 0)
+            .count();
+
         // Check, for all tables, whether their column configuration matches the contents of the
         // database:
         for (cname, ctype, pk) in &db_columns_in_order {
+            let pk = if num_pk_columns > 1 { &0 } else { pk };
             // Do not consider these special columns:
             if (table == "message" && cname == "message_id")
                 || (table == "message" && cname == "row")
                 || (table == "history" && cname == "history_id")
                 || (table == "history" && cname == "timestamp")
                 || (table == "history" && cname == "row")
+                || (table == "load_state" && cname == "size")
+                || (table == "load_state" && cname == "load_time")
                 || cname == "row_number"
                 || cname == "row_order"
             {
@@ -1169,9 +1900,11 @@ impl Valve {
             if sql_type.to_lowercase() != ctype.to_lowercase() {
                 let s = sql_type.to_lowercase();
                 let c = ctype.to_lowercase();
-                // CHARACTER VARYING and VARCHAR are synonyms so we ignore this difference.
+                // CHARACTER VARYING and VARCHAR are synonyms so we ignore this difference. The
+                // same goes for TIMESTAMP and TIMESTAMP WITHOUT TIME ZONE.
                 if !((s.starts_with("varchar") || s.starts_with("character varying"))
                     && (c.starts_with("varchar") || c.starts_with("character varying")))
+                    && !(s == "timestamp" && c == "timestamp without time zone")
                 {
                     if self.verbose || self.interactive {
                         print!(
@@ -1231,6 +1964,188 @@ impl Valve {
             }
         }
 
+        // Check whether the multi-column primary and unique keys in the database match the
+        // configured ones:
+        let mut configured_composites = get_declared_composites(&self.config, table)
+            .iter()
+            .map(|c| (c.key_type.to_string(), c.columns.clone()))
+            .collect::<Vec<_>>();
+        let mut db_composites = {
+            let mut db_composites = vec![];
+            if self.pool.any_kind() == AnyKind::Postgres {
+                let sql = r#"SELECT tco.constraint_name, tco.constraint_type, kcu.column_name
+                       FROM information_schema.table_constraints tco
+                       JOIN information_schema.key_column_usage kcu
+                         ON kcu.constraint_name = tco.constraint_name
+                            AND kcu.constraint_schema = tco.constraint_schema
+                            AND kcu.table_name = $1
+                       WHERE tco.constraint_type IN ('PRIMARY KEY', 'UNIQUE')
+                       ORDER BY tco.constraint_name, kcu.ordinal_position"#
+                    .to_string();
+                let query = sqlx_query(&sql).bind(table);
+                let mut constraints: IndexMap<String, (String, Vec<String>)> = IndexMap::new();
+                for row in query.fetch_all(&self.pool).await? {
+                    let name = row.get::<String, _>("constraint_name");
+                    let key_type = match row.get::<String, _>("constraint_type").as_str() {
+                        "PRIMARY KEY" => "primary",
+                        _ => "unique",
+                    };
+                    let column = row.get::<String, _>("column_name");
+                    constraints
+                        .entry(name)
+                        .or_insert((key_type.to_string(), vec![]))
+                        .1
+                        .push(column);
+                }
+                for (_, (key_type, columns)) in constraints {
+                    if columns.len() > 1 {
+                        db_composites.push((key_type, columns));
+                    }
+                }
+            } else {
+                if num_pk_columns > 1 {
+                    let mut pk_columns = db_columns_in_order
+                        .iter()
+                        .filter(|(_, _, pk)| *pk > 0)
+                        .collect::<Vec<_>>();
+                    pk_columns.sort_by_key(|(_, _, pk)| *pk);
+                    db_composites.push((
+                        "primary".to_string(),
+                        pk_columns
+                            .iter()
+                            .map(|(cname, _, _)| cname.to_string())
+                            .collect::<Vec<_>>(),
+                    ));
+                }
+                let sql = format!(r#"PRAGMA INDEX_LIST("{}")"#, table);
+                for row in sqlx_query(&sql).fetch_all(&self.pool).await? {
+                    let idx_name = row.get::<String, _>("name");
+                    let unique = row.get::<i16, _>("unique") as u8;
+                    let origin = row.get::<String, _>("origin");
+                    // Indexes that originate from a primary key have already been accounted for:
+                    if unique == 1 && origin != "pk" {
+                        let sql = format!(r#"PRAGMA INDEX_INFO("{}")"#, idx_name);
+                        let mut rows = sqlx_query(&sql).fetch_all(&self.pool).await?;
+                        if rows.len() > 1 {
+                            rows.sort_by_key(|r| r.get::<i64, _>("seqno"));
+                            db_composites.push((
+                                "unique".to_string(),
+                                rows.iter()
+                                    .map(|r| r.get::<String, _>("name"))
+                                    .collect::<Vec<_>>(),
+                            ));
+                        }
+                    }
+                }
+            }
+            db_composites
+        };
+        configured_composites.sort();
+        db_composites.sort();
+
+        // Check whether the multi-column foreign keys in the database match the configured ones:
+        let mut configured_composite_foreigns = self
+            .config
+            .constraint
+            .composite_foreign
+            .get(table)
+            .cloned()
+            .unwrap_or(vec![])
+            .iter()
+            .filter(|fkey| {
+                let ftable_options = self
+                    .config
+                    .table
+                    .get(&fkey.ftable)
+                    .map(|t| t.options.clone())
+                    .unwrap_or_default();
+                !ftable_options.contains("db_view")
+                    && fkey
+                        .columns
+                        .iter()
+                        .all(|column| self.get_value_type(table, column) == ValueType::Single)
+            })
+            .map(|fkey| {
+                (
+                    fkey.columns.clone(),
+                    fkey.ftable.to_string(),
+                    fkey.fcolumns.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut db_composite_foreigns = {
+            let mut foreign_keys: IndexMap<String, (Vec<String>, String, Vec<String>)> =
+                IndexMap::new();
+            if self.pool.any_kind() == AnyKind::Postgres {
+                let sql = r#"SELECT con.conname::TEXT AS constraint_name,
+                              col.attname::TEXT AS column_name,
+                              fcl.relname::TEXT AS foreign_table_name,
+                              fcol.attname::TEXT AS foreign_column_name
+                       FROM pg_constraint con
+                       CROSS JOIN LATERAL UNNEST(con.conkey, con.confkey)
+                         WITH ORDINALITY AS k(attnum, fattnum, ord)
+                       JOIN pg_attribute col
+                         ON col.attrelid = con.conrelid AND col.attnum = k.attnum
+                       JOIN pg_attribute fcol
+                         ON fcol.attrelid = con.confrelid AND fcol.attnum = k.fattnum
+                       JOIN pg_class fcl ON fcl.oid = con.confrelid
+                       WHERE con.contype = 'f'
+                         AND con.conrelid = TO_REGCLASS(QUOTE_IDENT($1))
+                       ORDER BY con.conname, k.ord"#
+                    .to_string();
+                let query = sqlx_query(&sql).bind(table);
+                for row in query.fetch_all(&self.pool).await? {
+                    let entry = foreign_keys
+                        .entry(row.get::<String, _>("constraint_name"))
+                        .or_insert((vec![], row.get::<String, _>("foreign_table_name"), vec![]));
+                    entry.0.push(row.get::<String, _>("column_name"));
+                    entry.2.push(row.get::<String, _>("foreign_column_name"));
+                }
+            } else {
+                let sql = format!(r#"PRAGMA FOREIGN_KEY_LIST("{}")"#, table);
+                let mut rows = sqlx_query(&sql).fetch_all(&self.pool).await?;
+                rows.sort_by_key(|r| (r.get::<i64, _>("id"), r.get::<i64, _>("seq")));
+                for row in rows {
+                    let entry = foreign_keys
+                        .entry(row.get::<i64, _>("id").to_string())
+                        .or_insert((vec![], row.get::<String, _>("table"), vec![]));
+                    entry.0.push(row.get::<String, _>("from"));
+                    entry.2.push(row.get::<String, _>("to"));
+                }
+            }
+            foreign_keys
+                .into_values()
+                .filter(|(columns, _, _)| columns.len() > 1)
+                .collect::<Vec<_>>()
+        };
+        configured_composite_foreigns.sort();
+        db_composite_foreigns.sort();
+
+        if configured_composites != db_composites
+            || configured_composite_foreigns != db_composite_foreigns
+        {
+            if self.verbose || self.interactive {
+                print!(
+                    "The table '{}' needs to be recreated because the multi-column keys in the \
+                     database: {:?} do not match the configured multi-column keys: {:?}. ",
+                    table,
+                    (db_composites, db_composite_foreigns),
+                    (configured_composites, configured_composite_foreigns)
+                );
+                if self.interactive {
+                    print!("Do you want to continue? [y/N] ");
+                    if !proceed::proceed() {
+                        return Err(
+                            ValveError::UserError("Execution aborted by user".to_string()).into(),
+                        );
+                    }
+                } else {
+                    println!();
+                }
+            }
+            return Ok(true);
+        }
+
         Ok(false)
     }
 
@@ -1279,17 +2194,30 @@ impl Valve {
                 .collect::<Vec<_>>()
         };
 
-        let (primaries, uniques, foreigns, _trees) = {
+        let (primaries, uniques, foreigns, _trees, composites, composite_foreigns) = {
             // Conflict tables have no database constraints:
             if table_name.ends_with("_conflict") {
-                (vec![], vec![], vec![], vec![])
+                (vec![], vec![], vec![], vec![], vec![], vec![])
             } else {
                 let cons = &self.config.constraint;
                 let primaries = cons.primary.get(table_name).cloned().unwrap_or(vec![]);
                 let uniques = cons.unique.get(table_name).cloned().unwrap_or(vec![]);
                 let foreigns = cons.foreign.get(table_name).cloned().unwrap_or(vec![]);
                 let trees = cons.tree.get(table_name).cloned().unwrap_or(vec![]);
-                (primaries, uniques, foreigns, trees)
+                let composites = get_declared_composites(&self.config, table_name);
+                let composite_foreigns = cons
+                    .composite_foreign
+                    .get(table_name)
+                    .cloned()
+                    .unwrap_or(vec![]);
+                (
+                    primaries,
+                    uniques,
+                    foreigns,
+                    trees,
+                    composites,
+                    composite_foreigns,
+                )
             }
         };
 
@@ -1304,16 +2232,54 @@ impl Valve {
             })
             .collect::<Vec<_>>();
 
-        let c = column_configs.len();
-        let mut r = 0;
-        for column_config in column_configs {
-            r += 1;
-            let sql_type = get_sql_type(&datatypes_config, &column_config.datatype, pool);
-
-            let short_sql_type = {
-                if sql_type.to_lowercase().as_str().starts_with("varchar(") {
-                    "VARCHAR"
-                } else {
+        // Generate the SQL for any multi-column primary, unique, and foreign constraints:
+        let quote_columns = |columns: &Vec<String>| -> String {
+            columns
+                .iter()
+                .map(|column| format!(r#""{}""#, column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut multi_column_lines = vec![];
+        for composite in &composites {
+            multi_column_lines.push(format!(
+                r#"  {} ({})"#,
+                if composite.key_type == "primary" {
+                    "PRIMARY KEY"
+                } else {
+                    "UNIQUE"
+                },
+                quote_columns(&composite.columns)
+            ));
+        }
+        for fkey in &composite_foreigns {
+            let all_single = fkey
+                .columns
+                .iter()
+                .all(|column| self.get_value_type(&normal_table_name, column) == ValueType::Single);
+            let table_config = &tables_config
+                .get(&fkey.ftable)
+                .unwrap_or_else(|| panic!("Undefined table '{}'", fkey.ftable));
+            if all_single && !table_config.options.contains("db_view") {
+                multi_column_lines.push(format!(
+                    r#"  FOREIGN KEY ({}) REFERENCES "{}"({})"#,
+                    quote_columns(&fkey.columns),
+                    fkey.ftable,
+                    quote_columns(&fkey.fcolumns)
+                ));
+            }
+        }
+
+        let c = column_configs.len();
+        let mut r = 0;
+        for column_config in column_configs {
+            r += 1;
+            let sql_type = get_sql_type(&datatypes_config, &column_config.datatype, pool);
+
+            let short_sql_type = {
+                if sql_type.to_lowercase().as_str().starts_with("varchar(") {
+                    "VARCHAR"
+                } else {
                     &sql_type
                 }
             };
@@ -1356,14 +2322,29 @@ impl Valve {
                 line.push_str(&format!(" DEFAULT {}", default_value));
             }
 
-            // If there are foreign constraints add a column to the end of the statement which we will
-            // finish after this for loop is done (but don't do this for views):
-            if !(r >= c && applicable_foreigns.is_empty()) {
+            // If there are multi-column or foreign constraints add a column to the end of the
+            // statement which we will finish after this for loop is done (but don't do this for
+            // views):
+            if !(r >= c && multi_column_lines.is_empty() && applicable_foreigns.is_empty()) {
                 line.push_str(",");
             }
             create_lines.push(line);
         }
 
+        // Add the SQL to indicate any multi-column constraints:
+        let num_multi_column_lines = multi_column_lines.len();
+        for (i, line) in multi_column_lines.iter().enumerate() {
+            create_lines.push(format!(
+                "{}{}",
+                line,
+                if i < (num_multi_column_lines - 1) || !applicable_foreigns.is_empty() {
+                    ","
+                } else {
+                    ""
+                }
+            ));
+        }
+
         // Add the SQL to indicate any foreign constraints:
         let num_fkeys = applicable_foreigns.len();
         let mut foreigns_added = 0;
@@ -1428,23 +2409,29 @@ impl Valve {
                 let mut statements = self.get_table_ddl(&cable, &self.pool)?;
                 table_statements.append(&mut statements);
 
-                let create_view_sql = get_sql_for_standard_view(&table, &self.pool);
+                let create_view_sql = get_sql_for_standard_view(tables_config, table, &self.pool);
                 let create_text_view_sql = get_sql_for_text_view(tables_config, &table, &self.pool);
                 table_statements.push(create_view_sql);
                 table_statements.push(create_text_view_sql);
             }
+            if table_config.options.contains("closure") {
+                table_statements.append(&mut get_tree_closure_ddl(table, &self.pool));
+            }
             setup_statements.insert(table.to_string(), table_statements);
         }
 
         let text_type = get_sql_type(datatypes_config, &"text".to_string(), &self.pool);
 
-        // Generate DDL for the history and message tables:
+        // Generate DDL for the history, message, and load_state tables:
         let history_statements =
             generate_internal_table_ddl("history", &self.pool.any_kind(), &text_type);
         setup_statements.insert("history".to_string(), history_statements);
         let message_statements =
             generate_internal_table_ddl("message", &self.pool.any_kind(), &text_type);
         setup_statements.insert("message".to_string(), message_statements);
+        let load_state_statements =
+            generate_internal_table_ddl("load_state", &self.pool.any_kind(), &text_type);
+        setup_statements.insert("load_state".to_string(), load_state_statements);
 
         return Ok(setup_statements);
     }
@@ -1469,7 +2456,31 @@ impl Valve {
     }
 
     /// Create all configured database tables and views if they do not already exist as configured.
+    /// An existing table whose configuration has changed is migrated, without losing its data,
+    /// using the steps returned by [Valve::plan_migration()]. If that is not possible, e.g.,
+    /// because the values of one of its columns cannot be converted to the column's new SQL type,
+    /// the table is instead dropped and recreated.
     pub async fn create_all_tables(&self) -> Result<&Self> {
+        // The internal tables are not dropped by drop_tables(). Since the views associated with the
+        // other tables refer to them, all of the other tables must be dropped before an internal
+        // table whose structure has changed can be dropped and recreated. In the case of
+        // PostgreSQL, we also drop any remaining views that refer to the internal table, e.g.,
+        // views belonging to tables that are no longer configured:
+        for table in INTERNAL_TABLES.iter() {
+            if self.table_exists(table).await? && self.table_has_changed(table).await? {
+                self.drop_all_tables().await?;
+                let cascade = {
+                    if self.pool.any_kind() == AnyKind::Postgres {
+                        " CASCADE"
+                    } else {
+                        ""
+                    }
+                };
+                self.execute_sql(&format!(r#"DROP TABLE "{}"{}"#, table, cascade))
+                    .await?;
+            }
+        }
+
         let setup_statements = self.get_setup_statements().await?;
         let sorted_table_list = self.get_sorted_table_list(false);
         for table in &sorted_table_list {
@@ -1495,8 +2506,7 @@ impl Valve {
                     ))
                     .into());
                 }
-            } else if self.table_has_changed(*table).await? {
-                self.drop_tables(&vec![table]).await?;
+            } else {
                 let table_statements =
                     setup_statements
                         .get(*table)
@@ -1504,14 +2514,836 @@ impl Valve {
                             "Could not find setup statements for {}",
                             table
                         )))?;
-                for stmt in table_statements {
-                    self.execute_sql(stmt).await?;
+                let recreate = {
+                    if !self.table_exists(table).await? {
+                        true
+                    } else if INTERNAL_TABLES.contains(table) {
+                        // The internal tables that have changed were dropped above:
+                        false
+                    } else {
+                        let plan = self.plan_table_migration(table).await?;
+                        if !plan.is_empty() && (self.verbose || self.interactive) {
+                            print!(
+                                "The table '{}' needs to be migrated: {}. ",
+                                table,
+                                plan.iter().map(|step| &step.description).join("; ")
+                            );
+                            if self.interactive {
+                                print!("Do you want to continue? [y/N] ");
+                                if !proceed::proceed() {
+                                    return Err(ValveError::UserError(
+                                        "Execution aborted by user".to_string(),
+                                    )
+                                    .into());
+                                }
+                            } else {
+                                println!();
+                            }
+                        }
+                        if plan.iter().any(|step| step.recreates_table) {
+                            true
+                        } else if let Err(e) = self.execute_migration_steps(&plan).await {
+                            log::warn!(
+                                "Unable to migrate the table '{}' ({}). Recreating it instead.",
+                                table,
+                                e
+                            );
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                };
+                if recreate {
+                    self.drop_tables(&vec![table]).await?;
+                    for stmt in table_statements {
+                        self.execute_sql(stmt).await?;
+                    }
                 }
             }
+
+            // If the table's tree hierarchies are to be materialized but its closure table does
+            // not yet exist (e.g., because the 'closure' option has only just been added to an
+            // existing table), create and populate it now:
+            if table_config.options.contains("closure")
+                && !self.table_exists(&format!("{}_closure", table)).await?
+            {
+                for stmt in get_tree_closure_ddl(table, &self.pool) {
+                    self.execute_sql(&stmt).await?;
+                }
+                let mut tx = self.pool.begin().await?;
+                update_tree_closure_tx(&self.config, &self.pool, &mut tx, table, None).await?;
+                tx.commit().await?;
+            }
         }
         Ok(self)
     }
 
+    /// Return, in dependency order, the steps needed to migrate the database tables to their
+    /// current configuration without losing the data in them (see [Valve::migrate()]). Tables
+    /// that do not exist yet are created. Wherever possible, an existing table is migrated using
+    /// ALTER TABLE statements that add or drop columns, change the SQL types and default values of
+    /// columns, and add or drop unique, primary key, and foreign key constraints. Columns are
+    /// never physically reordered. Instead, the table's views, which always present its columns
+    /// in their configured order, are recreated. Since SQLite's ALTER TABLE statement is limited,
+    /// in the case of SQLite a table whose SQL types, default values, primary or foreign keys, or
+    /// multi-column constraints have changed is migrated by copying its data to a temporary table,
+    /// recreating it, and copying the data back.
+    pub async fn plan_migration(&self) -> Result<Vec<ValveMigrationStep>> {
+        let setup_statements = self.get_setup_statements().await?;
+        let mut plan = vec![];
+        // The internal tables are only created if they do not exist, since the views associated
+        // with the other tables refer to them:
+        let table_list = INTERNAL_TABLES
+            .iter()
+            .copied()
+            .chain(
+                self.get_sorted_table_list(false)
+                    .into_iter()
+                    .filter(|t| !INTERNAL_TABLES.contains(t)),
+            )
+            .collect::<Vec<_>>();
+        for table in table_list {
+            if self.get_table_config(table)?.options.contains("db_view") {
+                continue;
+            }
+            if !self.table_exists(table).await? {
+                let statements =
+                    setup_statements
+                        .get(table)
+                        .ok_or(ValveError::ConfigError(format!(
+                            "Could not find setup statements for {}",
+                            table
+                        )))?;
+                plan.push(ValveMigrationStep {
+                    table: table.to_string(),
+                    description: "Create the table".to_string(),
+                    statements: statements.clone(),
+                    ..Default::default()
+                });
+            } else if !INTERNAL_TABLES.contains(&table) {
+                plan.append(&mut self.plan_table_migration(table).await?);
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Migrate the database tables to their current configuration by executing, in a single
+    /// transaction, the steps returned by [Valve::plan_migration()], and return those steps.
+    /// This preserves the data in the tables, along with their messages (except for the messages
+    /// about dropped columns) and their history. Note that the data is not revalidated, and that
+    /// if it violates one of the new database constraints, e.g., a new unique constraint, the
+    /// migration fails and the database is left unchanged. The migration also fails, without
+    /// changing the database, if the plan includes a step that would recreate a table without its
+    /// data, e.g., because the values of one of its columns cannot be converted to the column's
+    /// new SQL type. Such a table cannot be migrated at all, and this is the only case in which a
+    /// change to the configuration loses a table's data edits, messages, and history, namely when
+    /// [Valve::create_all_tables()] (which is called whenever tables are loaded) drops the table
+    /// and recreates it.
+    pub async fn migrate(&self) -> Result<Vec<ValveMigrationStep>> {
+        let plan = self.plan_migration().await?;
+        if let Some(step) = plan.iter().find(|step| step.recreates_table) {
+            return Err(ValveError::DataError(format!(
+                "Unable to migrate the table '{}' without losing its data: {}",
+                step.table, step.description
+            ))
+            .into());
+        }
+        self.execute_migration_steps(&plan).await?;
+        Ok(plan)
+    }
+
+    /// (Private function.) Execute the given migration steps in a single transaction.
+    async fn execute_migration_steps(&self, steps: &Vec<ValveMigrationStep>) -> Result<()> {
+        if steps.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+        if self.pool.any_kind() == AnyKind::Sqlite {
+            // A table that is being migrated may be temporarily dropped, so foreign keys referring
+            // to it are only checked once the transaction is committed:
+            sqlx_query("PRAGMA defer_foreign_keys = ON")
+                .execute(tx.acquire().await?)
+                .await?;
+        }
+        for step in steps {
+            for stmt in &step.statements {
+                let sql = local_sql_syntax(&self.pool, stmt);
+                let mut query = sqlx_query(&sql);
+                if stmt.contains(SQL_PARAM) {
+                    for param in &step.params {
+                        query = query.bind(param);
+                    }
+                }
+                query.execute(tx.acquire().await?).await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// (Private function.) Given the name of a table that exists in the database, return the
+    /// steps needed to migrate it to its current configuration (see [Valve::plan_migration()]).
+    async fn plan_table_migration(&self, table: &str) -> Result<Vec<ValveMigrationStep>> {
+        // Given a configured SQL type and a SQL type from the database, determine whether they
+        // are equivalent. CHARACTER VARYING and VARCHAR are synonyms, as are TIMESTAMP and
+        // TIMESTAMP WITHOUT TIME ZONE (see also [Valve::table_has_changed()]).
+        fn types_match(sql_type: &str, db_type: &str) -> bool {
+            let s = sql_type.to_lowercase();
+            let d = db_type.to_lowercase();
+            s == d
+                || ((s.starts_with("varchar") || s.starts_with("character varying"))
+                    && (d.starts_with("varchar") || d.starts_with("character varying")))
+                || (s == "timestamp" && d == "timestamp without time zone")
+        }
+
+        // Given the SQL for a default value, remove any type cast, enclosing parentheses, and
+        // enclosing quotes from it, so that default values from the configuration and from the
+        // database can be compared:
+        fn normalize_default(default: &str) -> String {
+            let mut default = default.trim();
+            if let Some((value, cast)) = default.rsplit_once("::") {
+                if cast
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || " ()".contains(c))
+                {
+                    default = value;
+                }
+            }
+            let default = default
+                .strip_prefix("(")
+                .and_then(|d| d.strip_suffix(")"))
+                .unwrap_or(default);
+            default
+                .strip_prefix("'")
+                .and_then(|d| d.strip_suffix("'"))
+                .unwrap_or(default)
+                .to_string()
+        }
+
+        let is_sqlite = self.pool.any_kind() == AnyKind::Sqlite;
+        let table_config = self.get_table_config(table)?;
+        let with_conflict = table_config.options.contains("conflict");
+        let conflict_table = format!("{}_conflict", table);
+        // If the conflict table does not exist yet (e.g., because the 'conflict' option has only
+        // just been added to the table), it is created below:
+        let conflict_exists = with_conflict && self.table_exists(&conflict_table).await?;
+        let table_names = {
+            let mut table_names = vec![table.to_string()];
+            if conflict_exists {
+                table_names.push(conflict_table.to_string());
+            }
+            table_names
+        };
+
+        // Compare the configured columns with the columns in the database:
+        let db_columns = self.get_db_columns(table).await?;
+        let mut configured_columns = vec![];
+        for column in &table_config.column_order {
+            let column_config = table_config
+                .column
+                .get(column)
+                .ok_or(ValveError::ConfigError(format!(
+                    "Undefined column '{}'",
+                    column
+                )))?;
+            let sql_type = get_sql_type_from_global_config(&self.config, table, column, &self.pool);
+            let default = match &column_config.default {
+                SerdeValue::String(s) if !s.is_empty() => format!("'{}'", s),
+                SerdeValue::Number(n) => n.to_string(),
+                _ => "".to_string(),
+            };
+            configured_columns.push((column.to_string(), sql_type, default));
+        }
+        let added_columns = configured_columns
+            .iter()
+            .filter(|(column, _, _)| !db_columns.iter().any(|(c, _, _)| c == column))
+            .collect::<Vec<_>>();
+        let dropped_columns = db_columns
+            .iter()
+            .filter(|(column, _, _)| {
+                column != "row_number"
+                    && column != "row_order"
+                    && !table_config.column_order.contains(column)
+            })
+            .collect::<Vec<_>>();
+        let mut retyped_columns = vec![];
+        let mut redefaulted_columns = vec![];
+        for (column, sql_type, default) in &configured_columns {
+            if let Some((_, db_type, db_default)) = db_columns.iter().find(|(c, _, _)| c == column)
+            {
+                if !types_match(sql_type, db_type) {
+                    retyped_columns.push((column, db_type, sql_type));
+                }
+                if normalize_default(default) != normalize_default(db_default) {
+                    redefaulted_columns.push((column, default));
+                }
+            }
+        }
+
+        // Compare the configured constraints with the constraints in the database:
+        let db_constraints = self.get_db_constraints(table).await?;
+        let configured_constraints = self.get_configured_constraints(table)?;
+        let added_constraints = configured_constraints
+            .iter()
+            .filter(|(constraint, _)| !db_constraints.contains_key(*constraint))
+            .collect::<Vec<_>>();
+        let dropped_constraints = db_constraints
+            .iter()
+            .filter(|(constraint, _)| !configured_constraints.contains_key(*constraint))
+            .collect::<Vec<_>>();
+
+        let table_has_changed = !added_columns.is_empty()
+            || !dropped_columns.is_empty()
+            || !retyped_columns.is_empty()
+            || !redefaulted_columns.is_empty()
+            || !added_constraints.is_empty()
+            || !dropped_constraints.is_empty();
+        if !(table_has_changed
+            || (with_conflict && !conflict_exists)
+            || (with_conflict && self.views_have_changed(table).await?))
+        {
+            return Ok(vec![]);
+        }
+
+        // A column whose SQL type has changed can only be migrated if all of its values (including
+        // those in the conflict table) can be converted to the new type. In the case of
+        // PostgreSQL, changing the type of such a column fails, and in the case of SQLite, which
+        // does not enforce column types, the unconverted values would be copied into the column
+        // as they are. In either case the table can only be migrated by recreating it without its
+        // data:
+        let mut unconvertible = vec![];
+        for (column, db_type, sql_type) in &retyped_columns {
+            for table_name in &table_names {
+                let sql = format!(
+                    r#"SELECT DISTINCT {} AS "value" FROM "{}" WHERE "{}" IS NOT NULL"#,
+                    cast_column_sql_to_text(column, db_type),
+                    table_name,
+                    column
+                );
+                let values = sqlx_query(&sql).fetch_all(&self.pool).await?;
+                let mut convertible = values.iter().all(|row| {
+                    let value: &str = row.get("value");
+                    !is_sql_type_error(sql_type, value)
+                });
+                if convertible && !is_sqlite {
+                    let sql = format!(
+                        r#"SELECT COUNT("{}"::{}) FROM "{}""#,
+                        column, sql_type, table_name
+                    );
+                    convertible = sqlx_query(&sql).fetch_one(&self.pool).await.is_ok();
+                }
+                if !convertible {
+                    unconvertible.push(format!(
+                        "the values of the column '{}' cannot all be converted from {} to {}",
+                        column, db_type, sql_type
+                    ));
+                    break;
+                }
+            }
+        }
+        if !unconvertible.is_empty() {
+            return Ok(vec![ValveMigrationStep {
+                table: table.to_string(),
+                description: format!(
+                    "Recreate the table without its data, since {}",
+                    unconvertible.join(", and ")
+                ),
+                recreates_table: true,
+                ..Default::default()
+            }]);
+        }
+
+        let mut plan = vec![];
+        let mut add_step = |description: String, statements: Vec<String>| {
+            plan.push(ValveMigrationStep {
+                table: table.to_string(),
+                description,
+                statements,
+                ..Default::default()
+            });
+        };
+        let (drop_view_statements, create_view_statements) = self.get_view_ddl(table);
+        if with_conflict {
+            add_step("Drop the table's views".to_string(), drop_view_statements);
+        }
+
+        // In the case of SQLite, the only changes that we can make to a table using ALTER TABLE are
+        // adding and dropping columns, as well as adding and dropping unique indexes. If anything
+        // else has changed we must recreate the table, copying its data aside in the meantime:
+        let is_simple_unique = |constraint: &str, columns: &Vec<String>| -> bool {
+            constraint.starts_with("UNIQUE") && columns.len() == 1
+        };
+        let copy_and_swap = is_sqlite
+            && (!retyped_columns.is_empty()
+                || !redefaulted_columns.is_empty()
+                || added_constraints
+                    .iter()
+                    .any(|(constraint, (columns, _))| !is_simple_unique(constraint, columns))
+                || dropped_constraints
+                    .iter()
+                    .any(|(constraint, (columns, name))| {
+                        !is_simple_unique(constraint, columns) || name.is_empty()
+                    }));
+
+        if copy_and_swap {
+            let columns = {
+                let mut columns = vec![r#""row_number""#.to_string(), r#""row_order""#.to_string()];
+                for (column, _, _) in &configured_columns {
+                    if db_columns.iter().any(|(c, _, _)| c == column) {
+                        columns.push(format!(r#""{}""#, column));
+                    }
+                }
+                columns.join(", ")
+            };
+            let mut statements = vec![];
+            for table_name in &table_names {
+                let temp_table = format!("{}_migration", table_name);
+                statements.push(format!(
+                    r#"CREATE TABLE "{}" AS SELECT * FROM "{}""#,
+                    temp_table, table_name
+                ));
+                statements.push(format!(r#"DROP TABLE "{}""#, table_name));
+                statements.append(&mut self.get_table_ddl(table_name, &self.pool)?);
+                statements.push(format!(
+                    r#"INSERT INTO "{}" ({}) SELECT {} FROM "{}""#,
+                    table_name, columns, columns, temp_table
+                ));
+                statements.push(format!(r#"DROP TABLE "{}""#, temp_table));
+            }
+            add_step(
+                "Copy the table's data aside, recreate the table as configured, and copy the \
+                 data back"
+                    .to_string(),
+                statements,
+            );
+        } else {
+            // Dropping a primary key or unique constraint in PostgreSQL also drops the foreign
+            // keys that refer to it, which we restore below:
+            let mut restored_foreigns = vec![];
+            for (constraint, (columns, name)) in &dropped_constraints {
+                let statement = {
+                    if is_sqlite {
+                        format!(r#"DROP INDEX "{}""#, name)
+                    } else if constraint.starts_with("FOREIGN KEY") {
+                        format!(r#"ALTER TABLE "{}" DROP CONSTRAINT "{}""#, table, name)
+                    } else {
+                        restored_foreigns
+                            .append(&mut self.get_foreigns_referring_to(table, columns).await?);
+                        format!(
+                            r#"ALTER TABLE "{}" DROP CONSTRAINT "{}" CASCADE"#,
+                            table, name
+                        )
+                    }
+                };
+                add_step(
+                    format!("Drop the constraint {}", constraint),
+                    vec![statement],
+                );
+            }
+            for (column, _, _) in &dropped_columns {
+                let statements = table_names
+                    .iter()
+                    .map(|t| format!(r#"ALTER TABLE "{}" DROP COLUMN "{}""#, t, column))
+                    .collect::<Vec<_>>();
+                add_step(format!("Drop the column '{}'", column), statements);
+            }
+            for (column, sql_type, default) in &added_columns {
+                let default = match default.as_str() {
+                    "" => "".to_string(),
+                    default => format!(" DEFAULT {}", default),
+                };
+                let statements = table_names
+                    .iter()
+                    .map(|t| {
+                        format!(
+                            r#"ALTER TABLE "{}" ADD COLUMN "{}" {}{}"#,
+                            t, column, sql_type, default
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                add_step(format!("Add the column '{}'", column), statements);
+            }
+            for (column, db_type, sql_type) in &retyped_columns {
+                let statements = table_names
+                    .iter()
+                    .map(|t| {
+                        format!(
+                            r#"ALTER TABLE "{}" ALTER COLUMN "{}" TYPE {} USING "{}"::{}"#,
+                            t, column, sql_type, column, sql_type
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                add_step(
+                    format!(
+                        "Change the SQL type of the column '{}' from {} to {}",
+                        column, db_type, sql_type
+                    ),
+                    statements,
+                );
+            }
+            for (column, default) in &redefaulted_columns {
+                let action = match default.as_str() {
+                    "" => "DROP DEFAULT".to_string(),
+                    default => format!("SET DEFAULT {}", default),
+                };
+                let statements = table_names
+                    .iter()
+                    .map(|t| {
+                        format!(
+                            r#"ALTER TABLE "{}" ALTER COLUMN "{}" {}"#,
+                            t, column, action
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                add_step(
+                    format!("Change the default value of the column '{}'", column),
+                    statements,
+                );
+            }
+            for (constraint, (columns, _)) in &added_constraints {
+                let statement = {
+                    if is_sqlite {
+                        format!(
+                            r#"CREATE UNIQUE INDEX "{}_{}_unique_idx" ON "{}"("{}")"#,
+                            table, columns[0], table, columns[0]
+                        )
+                    } else {
+                        format!(r#"ALTER TABLE "{}" ADD {}"#, table, constraint)
+                    }
+                };
+                add_step(
+                    format!("Add the constraint {}", constraint),
+                    vec![statement],
+                );
+            }
+            for (ftable, constraint) in restored_foreigns {
+                add_step(
+                    format!(
+                        "Restore the constraint {} on the table '{}'",
+                        constraint, ftable
+                    ),
+                    vec![format!(r#"ALTER TABLE "{}" ADD {}"#, ftable, constraint)],
+                );
+            }
+        }
+
+        if with_conflict && !conflict_exists {
+            add_step(
+                "Create the conflict table".to_string(),
+                self.get_table_ddl(&conflict_table, &self.pool)?,
+            );
+        }
+        if with_conflict {
+            add_step(
+                "Recreate the table's views".to_string(),
+                create_view_statements,
+            );
+        }
+
+        // The messages about dropped columns are deleted in every case:
+        for (column, _, _) in &dropped_columns {
+            plan.push(ValveMigrationStep {
+                table: table.to_string(),
+                description: format!("Delete the messages about the column '{}'", column),
+                statements: vec![format!(
+                    r#"DELETE FROM "message" WHERE "table" = {} AND "column" = {}"#,
+                    SQL_PARAM, SQL_PARAM
+                )],
+                params: vec![table.to_string(), column.to_string()],
+                ..Default::default()
+            });
+        }
+        Ok(plan)
+    }
+
+    /// (Private function.) Given the name of a table or view, return the name, SQL type, and
+    /// default value of each of its columns in the database, in the order in which they are
+    /// stored. A column without a default value has an empty default.
+    async fn get_db_columns(&self, table: &str) -> Result<Vec<(String, String, String)>> {
+        let mut columns = vec![];
+        if self.pool.any_kind() == AnyKind::Sqlite {
+            let sql = format!(r#"PRAGMA TABLE_INFO("{}")"#, table);
+            for row in sqlx_query(&sql).fetch_all(&self.pool).await? {
+                columns.push((
+                    row.get::<String, _>("name"),
+                    row.get::<String, _>("type"),
+                    row.get::<Option<String>, _>("dflt_value")
+                        .unwrap_or_default(),
+                ));
+            }
+        } else {
+            let sql = r#"SELECT "column_name"::TEXT AS "column_name",
+                                "data_type"::TEXT AS "data_type",
+                                COALESCE("column_default"::TEXT, '') AS "column_default"
+                           FROM "information_schema"."columns"
+                          WHERE "table_name" = $1
+                         ORDER BY "ordinal_position""#;
+            for row in sqlx_query(sql).bind(table).fetch_all(&self.pool).await? {
+                columns.push((
+                    row.get::<String, _>("column_name"),
+                    row.get::<String, _>("data_type"),
+                    row.get::<String, _>("column_default"),
+                ));
+            }
+        }
+        Ok(columns)
+    }
+
+    /// (Private function.) Given the name of a table, return its primary key, unique, and foreign
+    /// key constraints in the database, as a map from the SQL declaring each constraint (see
+    /// [get_constraint_sql()]) to the columns that it applies to and its name. In the case of
+    /// SQLite, the name is empty unless the constraint is a unique index that can be dropped.
+    async fn get_db_constraints(
+        &self,
+        table: &str,
+    ) -> Result<IndexMap<String, (Vec<String>, String)>> {
+        let mut constraints = IndexMap::new();
+        if self.pool.any_kind() == AnyKind::Sqlite {
+            let sql = format!(r#"PRAGMA TABLE_INFO("{}")"#, table);
+            let primaries = sqlx_query(&sql)
+                .fetch_all(&self.pool)
+                .await?
+                .iter()
+                .filter(|row| row.get::<i64, _>("pk") > 0)
+                .map(|row| (row.get::<i64, _>("pk"), row.get::<String, _>("name")))
+                .sorted()
+                .map(|(_, column)| column)
+                .collect::<Vec<_>>();
+            if !primaries.is_empty() {
+                let constraint = get_constraint_sql("PRIMARY KEY", &primaries, "", &vec![]);
+                constraints.insert(constraint, (primaries, "".to_string()));
+            }
+
+            let sql = format!(r#"PRAGMA INDEX_LIST("{}")"#, table);
+            for row in sqlx_query(&sql).fetch_all(&self.pool).await? {
+                let name = row.get::<String, _>("name");
+                let origin = row.get::<String, _>("origin");
+                if row.get::<i64, _>("unique") != 1 || origin == "pk" {
+                    continue;
+                }
+                let sql = format!(r#"PRAGMA INDEX_INFO("{}")"#, name);
+                let columns = sqlx_query(&sql)
+                    .fetch_all(&self.pool)
+                    .await?
+                    .iter()
+                    .map(|row| (row.get::<i64, _>("seqno"), row.get::<String, _>("name")))
+                    .sorted()
+                    .map(|(_, column)| column)
+                    .collect::<Vec<_>>();
+                // The unique indexes on row_number and row_order are not configured constraints:
+                if columns == vec!["row_number"] || columns == vec!["row_order"] {
+                    continue;
+                }
+                // Unique constraints declared in a CREATE TABLE statement cannot be dropped:
+                let name = if origin == "c" { name } else { "".to_string() };
+                let constraint = get_constraint_sql("UNIQUE", &columns, "", &vec![]);
+                constraints.insert(constraint, (columns, name));
+            }
+
+            let sql = format!(r#"PRAGMA FOREIGN_KEY_LIST("{}")"#, table);
+            let mut foreigns: IndexMap<i64, (String, Vec<String>, Vec<String>)> = IndexMap::new();
+            for row in sqlx_query(&sql).fetch_all(&self.pool).await? {
+                let (_, columns, fcolumns) = foreigns.entry(row.get::<i64, _>("id")).or_insert((
+                    row.get::<String, _>("table"),
+                    vec![],
+                    vec![],
+                ));
+                columns.push(row.get::<String, _>("from"));
+                fcolumns.push(row.get::<Option<String>, _>("to").unwrap_or_default());
+            }
+            for (ftable, columns, fcolumns) in foreigns.into_values() {
+                let constraint = get_constraint_sql("FOREIGN KEY", &columns, &ftable, &fcolumns);
+                constraints.insert(constraint, (columns, "".to_string()));
+            }
+        } else {
+            let sql = r#"SELECT c."conname"::TEXT AS "name",
+                                c."contype"::TEXT AS "type",
+                                (SELECT STRING_AGG(a."attname"::TEXT, ',' ORDER BY k."i")
+                                   FROM UNNEST(c."conkey") WITH ORDINALITY AS k("n", "i")
+                                   JOIN "pg_attribute" a
+                                     ON a."attrelid" = c."conrelid" AND a."attnum" = k."n"
+                                ) AS "columns",
+                                COALESCE(f."relname"::TEXT, '') AS "ftable",
+                                COALESCE(
+                                  (SELECT STRING_AGG(a."attname"::TEXT, ',' ORDER BY k."i")
+                                     FROM UNNEST(c."confkey") WITH ORDINALITY AS k("n", "i")
+                                     JOIN "pg_attribute" a
+                                       ON a."attrelid" = c."confrelid" AND a."attnum" = k."n"),
+                                  ''
+                                ) AS "fcolumns"
+                           FROM "pg_constraint" c
+                           JOIN "pg_class" t ON t."oid" = c."conrelid"
+                           LEFT JOIN "pg_class" f ON f."oid" = c."confrelid"
+                          WHERE t."relname" = $1
+                            AND c."contype" IN ('p', 'u', 'f')
+                         ORDER BY c."conname""#;
+            for row in sqlx_query(sql).bind(table).fetch_all(&self.pool).await? {
+                let split = |list: String| -> Vec<String> {
+                    list.split(',')
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.to_string())
+                        .collect()
+                };
+                let columns = split(row.get::<String, _>("columns"));
+                let fcolumns = split(row.get::<String, _>("fcolumns"));
+                let constraint_type = match row.get::<String, _>("type").as_str() {
+                    "p" => "PRIMARY KEY",
+                    "u" => "UNIQUE",
+                    _ => "FOREIGN KEY",
+                };
+                let constraint = get_constraint_sql(
+                    constraint_type,
+                    &columns,
+                    &row.get::<String, _>("ftable"),
+                    &fcolumns,
+                );
+                constraints.insert(constraint, (columns, row.get::<String, _>("name")));
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// (Private function.) Given the name of a table, return the primary key, unique, and foreign
+    /// key constraints that are configured for it, in the same form as
+    /// [Valve::get_db_constraints()] (the names of the configured constraints are empty). These
+    /// are the constraints that are declared by [Valve::get_table_ddl()].
+    fn get_configured_constraints(
+        &self,
+        table: &str,
+    ) -> Result<IndexMap<String, (Vec<String>, String)>> {
+        let cons = &self.config.constraint;
+        let mut constraints = IndexMap::new();
+        let mut add_constraint =
+            |constraint_type: &str, columns: &Vec<String>, ftable: &str, fcolumns: &Vec<String>| {
+                let constraint = get_constraint_sql(constraint_type, columns, ftable, fcolumns);
+                constraints.insert(constraint, (columns.clone(), "".to_string()));
+            };
+        for column in cons.primary.get(table).unwrap_or(&vec![]) {
+            add_constraint("PRIMARY KEY", &vec![column.to_string()], "", &vec![]);
+        }
+        for column in cons.unique.get(table).unwrap_or(&vec![]) {
+            add_constraint("UNIQUE", &vec![column.to_string()], "", &vec![]);
+        }
+        for composite in get_declared_composites(&self.config, table) {
+            let constraint_type = match composite.key_type.as_str() {
+                "primary" => "PRIMARY KEY",
+                _ => "UNIQUE",
+            };
+            add_constraint(constraint_type, &composite.columns, "", &vec![]);
+        }
+        for fkey in cons.foreign.get(table).unwrap_or(&vec![]) {
+            if self.get_value_type(table, &fkey.column) == ValueType::Single
+                && !self
+                    .get_table_config(&fkey.ftable)?
+                    .options
+                    .contains("db_view")
+            {
+                add_constraint(
+                    "FOREIGN KEY",
+                    &vec![fkey.column.to_string()],
+                    &fkey.ftable,
+                    &vec![fkey.fcolumn.to_string()],
+                );
+            }
+        }
+        for fkey in cons.composite_foreign.get(table).unwrap_or(&vec![]) {
+            if fkey
+                .columns
+                .iter()
+                .all(|column| self.get_value_type(table, column) == ValueType::Single)
+                && !self
+                    .get_table_config(&fkey.ftable)?
+                    .options
+                    .contains("db_view")
+            {
+                add_constraint("FOREIGN KEY", &fkey.columns, &fkey.ftable, &fkey.fcolumns);
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// (Private function.) Given the name of a table and a list of its columns, return the
+    /// configured foreign key constraints, on tables that exist in the database, that refer to
+    /// those columns, as pairs consisting of the table that the constraint belongs to and the SQL
+    /// declaring the constraint.
+    async fn get_foreigns_referring_to(
+        &self,
+        table: &str,
+        columns: &Vec<String>,
+    ) -> Result<Vec<(String, String)>> {
+        let reference = get_constraint_sql("FOREIGN KEY", &vec![], table, columns);
+        let reference = reference.split_once(" REFERENCES ").map(|(_, r)| r);
+        let mut foreigns = vec![];
+        for other_table in self.get_sorted_table_list(false) {
+            if INTERNAL_TABLES.contains(&other_table)
+                || self
+                    .get_table_config(other_table)?
+                    .options
+                    .contains("db_view")
+                || !self.table_exists(other_table).await?
+            {
+                continue;
+            }
+            for constraint in self.get_configured_constraints(other_table)?.keys() {
+                if constraint.split_once(" REFERENCES ").map(|(_, r)| r) == reference {
+                    foreigns.push((other_table.to_string(), constraint.to_string()));
+                }
+            }
+        }
+        Ok(foreigns)
+    }
+
+    /// (Private function.) Given the name of a table with the 'conflict' option, determine
+    /// whether its views are missing from the database, or whether their columns differ from the
+    /// table's configured columns or are not in their configured order.
+    async fn views_have_changed(&self, table: &str) -> Result<bool> {
+        let column_order = &self.get_table_config(table)?.column_order;
+        for (view, leading_columns, trailing_columns) in [
+            (
+                format!("{}_view", table),
+                vec!["row_number", "row_order"],
+                vec!["message", "history"],
+            ),
+            (
+                format!("{}_text_view", table),
+                vec!["row_number", "row_order", "message", "history"],
+                vec![],
+            ),
+        ] {
+            let expected_columns = leading_columns
+                .iter()
+                .map(|c| c.to_string())
+                .chain(column_order.iter().cloned())
+                .chain(trailing_columns.iter().map(|c| c.to_string()))
+                .collect::<Vec<_>>();
+            let db_columns = self
+                .get_db_columns(&view)
+                .await?
+                .into_iter()
+                .map(|(column, _, _)| column)
+                .collect::<Vec<_>>();
+            if db_columns != expected_columns {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// (Private function.) Given the name of a table with the 'conflict' option, return the
+    /// statements needed to drop its views and the statements needed to create them.
+    fn get_view_ddl(&self, table: &str) -> (Vec<String>, Vec<String>) {
+        let drop_statements = vec![
+            format!(r#"DROP VIEW IF EXISTS "{}_text_view""#, table),
+            format!(r#"DROP VIEW IF EXISTS "{}_view""#, table),
+        ];
+        let create_statements = vec![
+            get_sql_for_standard_view(&self.config.table, table, &self.pool),
+            get_sql_for_text_view(&self.config.table, table, &self.pool),
+        ];
+        (drop_statements, create_statements)
+    }
+
     /// Checks whether the given table exists in the database.
     pub async fn table_exists(&self, table: &str) -> Result<bool> {
         let sql = {
@@ -1578,6 +3410,7 @@ impl Valve {
     /// that must be dropped implicitly because of a dependency relationship.
     pub async fn drop_tables(&self, tables: &Vec<&str>) -> Result<&Self> {
         let drop_list = self.add_dependencies(tables, true)?;
+        self.forget_load_state(&drop_list).await?;
         for table in &drop_list {
             let table_config = self.get_table_config(table)?;
             if table_config.path != "" {
@@ -1589,6 +3422,10 @@ impl Valve {
                     let sql = format!(r#"DROP TABLE IF EXISTS "{}_conflict""#, table);
                     self.execute_sql(&sql).await?;
                 }
+                if table_config.options.contains("closure") {
+                    let sql = format!(r#"DROP TABLE IF EXISTS "{}_closure""#, table);
+                    self.execute_sql(&sql).await?;
+                }
                 let type_to_drop = match table_config.options.contains("db_view") {
                     true => "VIEW",
                     false => "TABLE",
@@ -1613,6 +3450,7 @@ impl Valve {
     pub async fn truncate_tables(&self, tables: &Vec<&str>) -> Result<&Self> {
         self.create_all_tables().await?;
         let truncate_list = self.add_dependencies(tables, true)?;
+        self.forget_load_state(&truncate_list).await?;
 
         // We must use CASCADE in the case of PostgreSQL since we cannot truncate a table, T, that
         // depends on another table, T', even in the case where we have previously truncated T'.
@@ -1635,6 +3473,10 @@ impl Valve {
                     let sql = truncate_sql(&format!("{}_conflict", table));
                     self.execute_sql(&sql).await?;
                 }
+                if table_options.contains("closure") {
+                    let sql = truncate_sql(&format!("{}_closure", table));
+                    self.execute_sql(&sql).await?;
+                }
             }
         }
 
@@ -1667,8 +3509,13 @@ impl Valve {
         .await?;
 
         // Insert any 'startup' messages into the message table. These are messages generated
-        // during the configuration stage.
-        for (row, messages) in self.startup_table_messages.iter() {
+        // during the configuration stage, about the rows of the table table, and so are only
+        // inserted when the table table is being loaded.
+        let startup_messages = match table_list.contains(&self.config.special.table.as_str()) {
+            true => self.startup_table_messages.iter().collect::<Vec<_>>(),
+            false => vec![],
+        };
+        for (row, messages) in startup_messages {
             for msg in messages {
                 let msg_sql = format!(
                     r#"INSERT INTO "message"
@@ -1770,14 +3617,21 @@ impl Valve {
                 // We need to wait until all of the rows for a table have been loaded before
                 // validating the "foreign" constraints on a table's trees, since this checks if
                 // the values of one column (the tree's parent) are all contained in another column
-                // (the tree's child).
-                let recs_to_update = block_on(validate_tree_foreign_keys(
+                // (the tree's child). The same goes for checking that the trees have no cycles.
+                let mut recs_to_update = block_on(validate_tree_foreign_keys(
                     &self.config,
                     &self.pool,
                     None,
                     &table_name,
                     None,
                 ))?;
+                recs_to_update.append(&mut block_on(validate_tree_cycles(
+                    &self.config,
+                    &self.pool,
+                    None,
+                    &table_name,
+                    None,
+                ))?);
 
                 for record in recs_to_update {
                     let row_number = record.get("row_number").unwrap();
@@ -1811,63 +3665,727 @@ impl Valve {
                     query = query.bind(&message);
                     block_on(query.execute(&self.pool))?;
 
-                    if self.verbose {
-                        // Add the generated message to messages_stats:
-                        let messages = vec![ValveCellMessage {
-                            message: message.to_string(),
-                            level: level.to_string(),
-                            ..Default::default()
-                        }];
-                        add_message_counts(&messages, &mut messages_stats);
-                    }
-                }
+                    if self.verbose {
+                        // Add the generated message to messages_stats:
+                        let messages = vec![ValveCellMessage {
+                            message: message.to_string(),
+                            level: level.to_string(),
+                            ..Default::default()
+                        }];
+                        add_message_counts(&messages, &mut messages_stats);
+                    }
+                }
+            }
+
+            // Now that all of the table's rows have been loaded, populate its closure table if
+            // its tree hierarchies are to be materialized:
+            if self
+                .get_table_options_from_config(&table_name)?
+                .contains("closure")
+            {
+                let mut tx = block_on(self.pool.begin())?;
+                block_on(update_tree_closure_tx(
+                    &self.config,
+                    &self.pool,
+                    &mut tx,
+                    &table_name,
+                    None,
+                ))?;
+                block_on(tx.commit())?;
+            }
+
+            if self.verbose {
+                // Output a report on the messages generated to stderr:
+                let errors = messages_stats.get("error").unwrap();
+                let warnings = messages_stats.get("warning").unwrap();
+                let infos = messages_stats.get("info").unwrap();
+                let status_message = format!(
+                    "{} errors, {} warnings, and {} information messages generated for {}",
+                    errors, warnings, infos, table_name
+                );
+                println!("{}", status_message);
+                total_errors += errors;
+                total_warnings += warnings;
+                total_infos += infos;
+            }
+
+            Ok(())
+        };
+
+        for table in table_list {
+            let table_config = self.get_table_config(&table)?;
+
+            if !table_config.options.contains("load") {
+                continue;
+            }
+            // For all others, how they are loaded depends on the path, such that an empty
+            // path implies that the table in question is not loaded by Valve at all.
+            if table_config.path.to_lowercase().ends_with(".sql") {
+                // SQL files:
+                self.execute_sql_file(&table_config.path).await?;
+            } else if table_config.path.to_lowercase().ends_with(".tsv") {
+                // TSV files:
+                load_normal_table(table)?;
+            } else if table_config.path != "" {
+                // Other types of scripts:
+                self.execute_script(&table_config.path, &vec![&self.db_path, table])?;
+            }
+            self.record_load_state(table).await?;
+        }
+
+        if self.verbose {
+            println!(
+                "Loading complete with {} errors, {} warnings, and {} information messages",
+                total_errors, total_warnings, total_infos
+            );
+        }
+        Ok(self)
+    }
+
+    /// Given a list of tables, perform a dry run of loading them, i.e., read and validate the
+    /// tables and determine which of their rows belong in the tables' conflict tables, exactly as
+    /// [Valve::load_tables()] does, without modifying the database. The tables are loaded, along
+    /// with the tables that they depend on, into a temporary SQLite database that is deleted
+    /// afterwards. Return the statistics for, and the messages generated about, each of the given
+    /// tables. Note that the database is never accessed, so a dry run may be performed using a
+    /// Valve instance that has been created by [Valve::build_in_memory()], and that the temporary
+    /// database is a SQLite database whatever the type of the Valve instance's database.
+    pub async fn load_tables_dry_run(&self, table_list: &Vec<&str>) -> Result<ValveLoadReport> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let db_path = std::env::temp_dir()
+            .join(format!(
+                "valve_dry_run_{}_{}.db",
+                std::process::id(),
+                timestamp
+            ))
+            .to_string_lossy()
+            .to_string();
+
+        let mut dry_run_valve = self.clone();
+        dry_run_valve.pool = get_pool_from_connection_string(&db_path).await?;
+        dry_run_valve.db_path = db_path.to_string();
+        dry_run_valve.interactive = false;
+        dry_run_valve.initial_load = false;
+        let result = dry_run_valve.get_dry_run_report(table_list).await;
+        dry_run_valve.pool.close().await;
+        for suffix in ["", "-journal", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", db_path, suffix));
+        }
+        result
+    }
+
+    /// (Private function.) Given a list of tables, load them, along with the tables that they
+    /// depend on, into the database, and return the statistics for, and the messages generated
+    /// about, each of the given tables. This is used by [Valve::load_tables_dry_run()], and should
+    /// only be called on a Valve instance that is connected to a temporary database.
+    async fn get_dry_run_report(&mut self, table_list: &Vec<&str>) -> Result<ValveLoadReport> {
+        if self.pool.any_kind() == AnyKind::Sqlite {
+            self.execute_sql("PRAGMA foreign_keys = ON").await?;
+        }
+        let mut tables_to_load = vec![];
+        for table in table_list {
+            for dependency in self.get_dependencies(table, false)? {
+                if !tables_to_load.contains(&dependency) {
+                    tables_to_load.push(dependency);
+                }
+            }
+            if !tables_to_load.contains(&table.to_string()) {
+                tables_to_load.push(table.to_string());
+            }
+        }
+        let tables_to_load =
+            self.sort_tables(&tables_to_load.iter().map(|t| t.as_str()).collect(), false)?;
+
+        // Views whose path is empty are set up outside of Valve, and so cannot be recreated in the
+        // temporary database. We stand in for them, as well as for any views that do not need to
+        // be loaded, using empty views with the same columns.
+        for (table, table_config) in self.config.table.iter_mut() {
+            if table_config.options.contains("db_view")
+                && (table_config.path.is_empty() || !tables_to_load.contains(table))
+            {
+                table_config.path = "".to_string();
+                let columns = table_config
+                    .column_order
+                    .iter()
+                    .map(|column| format!(r#"NULL AS "{}""#, column))
+                    .collect::<Vec<_>>();
+                let sql = format!(
+                    r#"CREATE VIEW "{}" AS SELECT {} WHERE 1 = 0"#,
+                    table,
+                    columns.join(", ")
+                );
+                sqlx_query(&sql).execute(&self.pool).await?;
+            }
+        }
+        self.create_all_tables().await?;
+        self.load_tables(&tables_to_load.iter().map(|t| t.as_str()).collect(), true)
+            .await?;
+
+        let mut report = ValveLoadReport::default();
+        for table in self.sort_tables(table_list, false)? {
+            let mut stats = ValveLoadStats::default();
+            let pool = &self.pool;
+            let count_rows = |table: String| async move {
+                let sql = format!(r#"SELECT COUNT(1) AS "count" FROM "{}""#, table);
+                let row = sqlx_query(&sql).fetch_one(pool).await?;
+                let count: i64 = row.get("count");
+                Ok::<usize, anyhow::Error>(count as usize)
+            };
+            let table_config = self.get_table_config(&table)?;
+            if self.table_exists(&table).await? && !table_config.options.contains("db_view") {
+                stats.rows = count_rows(table.to_string()).await?;
+            }
+            if table_config.options.contains("conflict") {
+                stats.conflict_rows = count_rows(format!("{}_conflict", table)).await?;
+            }
+
+            let sql = local_sql_syntax(
+                &self.pool,
+                &format!(
+                    r#"SELECT "row", "column", "value", "level", "rule", "message"
+                       FROM "message"
+                       WHERE "table" = {}
+                       ORDER BY "row", "column", "message_id""#,
+                    SQL_PARAM
+                ),
+            );
+            for row in sqlx_query(&sql).bind(&table).fetch_all(&self.pool).await? {
+                let row_number: Option<i64> = row.get("row");
+                let message = ValveTableMessage {
+                    table: table.to_string(),
+                    row: row_number.unwrap_or_default() as u32,
+                    column: row.get::<Option<String>, _>("column").unwrap_or_default(),
+                    value: row.get::<Option<String>, _>("value").unwrap_or_default(),
+                    level: row.get::<Option<String>, _>("level").unwrap_or_default(),
+                    rule: row.get::<Option<String>, _>("rule").unwrap_or_default(),
+                    message: row.get::<Option<String>, _>("message").unwrap_or_default(),
+                };
+                match message.level.as_str() {
+                    "error" => stats.errors += 1,
+                    "warning" => stats.warnings += 1,
+                    "info" => stats.infos += 1,
+                    _ => (),
+                };
+                report.messages.push(message);
+            }
+            report.tables.insert(table.to_string(), stats);
+        }
+        Ok(report)
+    }
+
+    /// Load only those tables whose source files or configuration have changed since they were
+    /// last loaded (see [Valve::get_changed_tables()]), along with the tables that depend on them,
+    /// in dependency order. The messages about the tables that are reloaded, as well as their
+    /// history, are deleted, just as they are when all of the tables are loaded, since the row
+    /// numbers that they refer to may no longer apply, and the changes recorded in the history
+    /// could otherwise be undone. The other tables, and their messages and history, are left
+    /// untouched. If `validate` is false, just try to insert all rows, as in
+    /// [Valve::load_tables()]. Return the names of the tables that were loaded.
+    pub async fn load_changed_tables(&self, validate: bool) -> Result<Vec<String>> {
+        // Any table whose structure has changed is recreated here (along with the tables that
+        // depend on it), which means that it will be counted among the changed tables below:
+        self.create_all_tables().await?;
+
+        let mut tables_to_load = vec![];
+        for table in self.get_changed_tables().await? {
+            for dependent_table in self.get_dependencies(&table, true)? {
+                if !tables_to_load.contains(&dependent_table) {
+                    tables_to_load.push(dependent_table);
+                }
+            }
+            if !tables_to_load.contains(&table) {
+                tables_to_load.push(table);
+            }
+        }
+        let tables_to_load =
+            self.sort_tables(&tables_to_load.iter().map(|t| t.as_str()).collect(), false)?;
+        if tables_to_load.is_empty() {
+            if self.verbose {
+                println!("No tables have changed since they were last loaded.");
+            }
+            return Ok(tables_to_load);
+        }
+
+        // The messages and history of the tables that are about to be reloaded no longer apply, so
+        // we delete them:
+        for table in &tables_to_load {
+            for internal_table in ["message", "history"] {
+                let sql = local_sql_syntax(
+                    &self.pool,
+                    &format!(
+                        r#"DELETE FROM "{}" WHERE "table" = {}"#,
+                        internal_table, SQL_PARAM
+                    ),
+                );
+                sqlx_query(&sql).bind(table).execute(&self.pool).await?;
+            }
+        }
+        if self.verbose {
+            println!(
+                "Reloading {} changed and dependent tables: {}",
+                tables_to_load.len(),
+                tables_to_load.join(", ")
+            );
+        }
+        self.load_tables(
+            &tables_to_load.iter().map(|t| t.as_str()).collect(),
+            validate,
+        )
+        .await?;
+        Ok(tables_to_load)
+    }
+
+    /// Return the names, in dependency order, of the tables that are loaded from files and for
+    /// which either the checksum or size of the file, or the table's configuration, differs from
+    /// what was recorded in the load_state table when the table was last loaded. A table that has
+    /// never been loaded, or that has been truncated or dropped since it was last loaded, is
+    /// always considered to have changed.
+    pub async fn get_changed_tables(&self) -> Result<Vec<String>> {
+        let mut loaded_states = HashMap::new();
+        if self.table_exists("load_state").await? {
+            let sql = r#"SELECT "table", "path", "checksum", "size", "config_hash"
+                           FROM "load_state""#;
+            for row in sqlx_query(sql).fetch_all(&self.pool).await? {
+                let table: String = row.get("table");
+                let state = (
+                    row.get::<String, _>("path"),
+                    row.get::<String, _>("checksum"),
+                    row.get::<i64, _>("size"),
+                    row.get::<String, _>("config_hash"),
+                );
+                loaded_states.insert(table, state);
+            }
+        }
+
+        let mut changed_tables = vec![];
+        for table in self.get_sorted_table_list_with_option("load", false) {
+            if self.get_table_config(table)?.path.is_empty() {
+                continue;
+            }
+            match self.get_load_state(table)? {
+                Some(state) if loaded_states.get(table) == Some(&state) => (),
+                _ => changed_tables.push(table.to_string()),
+            };
+        }
+        Ok(changed_tables)
+    }
+
+    /// (Private function.) Given a table that is loaded from a file, return the path of the file,
+    /// the file's SHA-256 checksum and size, and a hash of the table's configuration (see
+    /// [Valve::get_table_config_hash()]), or None if the file cannot be read.
+    fn get_load_state(&self, table: &str) -> Result<Option<(String, String, i64, String)>> {
+        let path = &self.get_table_config(table)?.path;
+        let contents = match std::fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::warn!("Unable to read '{}': {}", path, e);
+                return Ok(None);
+            }
+        };
+        Ok(Some((
+            path.to_string(),
+            hex::encode(Sha256::digest(&contents)),
+            contents.len() as i64,
+            self.get_table_config_hash(table)?,
+        )))
+    }
+
+    /// (Private function.) Given a table, return a SHA-256 hash of the parts of the configuration
+    /// that determine how the table is loaded and validated, i.e., the table's own configuration,
+    /// the configuration of its columns and of their datatypes, the rules and constraints on the
+    /// table, and the configured prefixes. The datatypes included are those of the table's
+    /// columns, together with their ancestors and any datatypes that are referred to, directly or
+    /// indirectly, by their conditions (e.g., `list(custom_a, ' ')`) or by the conditions of the
+    /// table's rules. Hash maps and sets are converted to sorted maps and lists, so that the hash
+    /// does not depend on their iteration order.
+    fn get_table_config_hash(&self, table: &str) -> Result<String> {
+        // Adds the names of all of the labels in the given expression, including those nested
+        // within functions such as list(), any(), all(), and not(), to the given list:
+        fn collect_labels(expression: &Expression, labels: &mut Vec<String>) {
+            match expression {
+                Expression::Label(label) => labels.push(label.to_string()),
+                Expression::Function(_, args) | Expression::Tuple(args) => {
+                    for arg in args {
+                        collect_labels(arg, labels);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let table_config = self.get_table_config(table)?;
+        let mut options = table_config.options.iter().collect::<Vec<_>>();
+        options.sort();
+
+        let mut pending = table_config
+            .column
+            .values()
+            .map(|column_config| column_config.datatype.to_string())
+            .collect::<Vec<_>>();
+        for rules in self
+            .rule_conditions
+            .get(table)
+            .into_iter()
+            .flat_map(|r| r.values())
+        {
+            for rule in rules {
+                collect_labels(&rule.when.parsed, &mut pending);
+                collect_labels(&rule.then.parsed, &mut pending);
+                for condition in &rule.additional_when {
+                    collect_labels(&condition.parsed, &mut pending);
+                }
+            }
+        }
+        let mut datatypes = BTreeMap::new();
+        while let Some(datatype) = pending.pop() {
+            if datatypes.contains_key(&datatype) {
+                continue;
+            }
+            if let Some(datatype_config) = self.config.datatype.get(&datatype) {
+                pending.push(datatype_config.parent.to_string());
+                if let Some(condition) = self.datatype_conditions.get(&datatype) {
+                    collect_labels(&condition.parsed, &mut pending);
+                }
+                datatypes.insert(datatype, datatype_config.clone());
+            }
+        }
+        // The lists of constraints on a table are compiled from hash maps, so their order is not
+        // significant:
+        fn sorted<T: Serialize>(constraints: Option<&Vec<T>>) -> Vec<String> {
+            let mut constraints = constraints
+                .unwrap_or(&vec![])
+                .iter()
+                .map(|constraint| json!(constraint).to_string())
+                .collect::<Vec<_>>();
+            constraints.sort();
+            constraints
+        }
+
+        let constraints = &self.config.constraint;
+        let config = json!({
+            "table": table_config.table,
+            "table_type": table_config.table_type,
+            "options": options,
+            "path": table_config.path,
+            "column_order": table_config.column_order,
+            "column": table_config.column.iter().collect::<BTreeMap<_, _>>(),
+            "datatype": datatypes,
+            "rule": self.config.rule.get(table).map(|rules| rules.iter().collect::<BTreeMap<_, _>>()),
+            "script_rule": self.config.script_rule.get(table),
+            "primary": sorted(constraints.primary.get(table)),
+            "unique": sorted(constraints.unique.get(table)),
+            "foreign": sorted(constraints.foreign.get(table)),
+            "tree": sorted(constraints.tree.get(table)),
+            "composite": sorted(constraints.composite.get(table)),
+            "composite_foreign": sorted(constraints.composite_foreign.get(table)),
+            "prefix": self.config.prefix.iter().collect::<BTreeMap<_, _>>(),
+        });
+        Ok(hex::encode(Sha256::digest(config.to_string().as_bytes())))
+    }
+
+    /// (Private function.) Given a table, record the current state of the file that it is loaded
+    /// from, and of its configuration, in the load_state table (see
+    /// [Valve::get_changed_tables()]).
+    async fn record_load_state(&self, table: &str) -> Result<()> {
+        let table_config = self.get_table_config(table)?;
+        if !table_config.options.contains("load") || table_config.path.is_empty() {
+            return Ok(());
+        }
+        self.forget_load_state(&vec![table.to_string()]).await?;
+        if let Some((path, checksum, size, config_hash)) = self.get_load_state(table)? {
+            let sql = local_sql_syntax(
+                &self.pool,
+                &format!(
+                    r#"INSERT INTO "load_state" ("table", "path", "checksum", "size", "config_hash")
+                       VALUES ({}, {}, {}, {}, {})"#,
+                    SQL_PARAM, SQL_PARAM, SQL_PARAM, size, SQL_PARAM
+                ),
+            );
+            sqlx_query(&sql)
+                .bind(table)
+                .bind(path)
+                .bind(checksum)
+                .bind(config_hash)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// (Private function.) Given a list of tables, remove the records of their last loads from the
+    /// load_state table, if it exists, so that they will be considered to have changed (see
+    /// [Valve::get_changed_tables()]).
+    async fn forget_load_state(&self, tables: &Vec<String>) -> Result<()> {
+        if !self.table_exists("load_state").await? {
+            return Ok(());
+        }
+        for table in tables {
+            let sql = local_sql_syntax(
+                &self.pool,
+                &format!(r#"DELETE FROM "load_state" WHERE "table" = {}"#, SQL_PARAM),
+            );
+            sqlx_query(&sql).bind(table).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    /// Synchronize all of the editable tables that are loaded from TSV files with those files (see
+    /// [Valve::sync_tables()]).
+    pub async fn sync_all_tables(&self) -> Result<IndexMap<String, ValveSyncStats>> {
+        let table_list = self
+            .get_sorted_table_list_with_option("load", false)
+            .into_iter()
+            .filter(|table| {
+                let table_config = &self.config.table[*table];
+                table_config.path.to_lowercase().ends_with(".tsv")
+                    && table_config.options.contains("edit")
+            })
+            .collect::<Vec<_>>();
+        self.sync_tables(&table_list).await
+    }
+
+    /// Given a list of tables, each of which must be loaded from a TSV file, synchronize the
+    /// contents of each table in the database with the contents of its TSV file, without
+    /// reloading the table. Each row in the file is matched with the row in the table that has the
+    /// same primary key, if the table has one, and otherwise with the row in the table whose row
+    /// number is the position of the row in the file, i.e., the nth row of the file is matched
+    /// with row n of the table. Values in the file are compared with the values in the table after
+    /// applying any transforms that apply to them. Only those rows that have been added to,
+    /// changed in, or removed from the file are then inserted into, updated in, or deleted from
+    /// the table, in the same way as [Valve::insert_row()], [Valve::update_row()], and
+    /// [Valve::delete_row()] respectively, so that the changes are validated incrementally and
+    /// recorded in the history table, attributed to the [sync_user](Self::sync_user). All of the
+    /// changes to all of the tables are made in a single transaction. Return the number of rows
+    /// inserted, updated, deleted, and left unchanged in each table.
+    pub async fn sync_tables(
+        &self,
+        table_list: &Vec<&str>,
+    ) -> Result<IndexMap<String, ValveSyncStats>> {
+        let mut sync_valve = self.clone();
+        sync_valve.user = self.sync_user.to_string();
+        let sorted_tables = self.sort_tables(table_list, false)?;
+
+        // Determine the changes to be made to each table before making any of them:
+        let mut changes = IndexMap::new();
+        for table in &sorted_tables {
+            let table_config = self.get_table_config(table)?;
+            if !table_config.path.to_lowercase().ends_with(".tsv") {
+                return Err(ValveError::InputError(format!(
+                    "Table '{}' is not loaded from a TSV file",
+                    table
+                ))
+                .into());
             }
+            changes.insert(table.to_string(), self.get_sync_changes(table).await?);
+        }
 
-            if self.verbose {
-                // Output a report on the messages generated to stderr:
-                let errors = messages_stats.get("error").unwrap();
-                let warnings = messages_stats.get("warning").unwrap();
-                let infos = messages_stats.get("info").unwrap();
-                let status_message = format!(
-                    "{} errors, {} warnings, and {} information messages generated for {}",
-                    errors, warnings, infos, table_name
-                );
-                println!("{}", status_message);
-                total_errors += errors;
-                total_warnings += warnings;
-                total_infos += infos;
+        // Delete rows from the tables in reverse dependency order, so that rows are removed before
+        // the rows they depend on, then update and insert rows in dependency order, so that rows
+        // are added after the rows they depend on. All of the changes are made in a single
+        // transaction, so that if any of them fails, none of the tables is changed:
+        let mut tx = self.pool.begin().await?;
+        let mut stats = IndexMap::new();
+        for table in sorted_tables.iter().rev() {
+            let (_, _, to_delete, _) = &changes[table];
+            for row_number in to_delete {
+                sync_valve
+                    .delete_row_with_tx(&mut tx, table, row_number)
+                    .await?;
+            }
+        }
+        for table in &sorted_tables {
+            let (to_insert, to_update, to_delete, unchanged) = &changes[table];
+            for (row_number, row) in to_update {
+                sync_valve
+                    .update_row_with_tx(&mut tx, table, row_number, row)
+                    .await?;
             }
+            for row in to_insert {
+                sync_valve.insert_row_with_tx(&mut tx, table, row).await?;
+            }
+            stats.insert(
+                table.to_string(),
+                ValveSyncStats {
+                    inserted: to_insert.len(),
+                    updated: to_update.len(),
+                    deleted: to_delete.len(),
+                    unchanged: *unchanged,
+                },
+            );
+        }
+        tx.commit().await?;
+        Ok(stats)
+    }
 
-            Ok(())
+    /// (Private function.) Given a table that is loaded from a TSV file, compare the rows in the
+    /// file with the rows in the table (see [Valve::sync_tables()]) and return the rows that need
+    /// to be inserted into the table, the row numbers and new contents of the rows that need to be
+    /// updated, the row numbers of the rows that need to be deleted, and the number of rows that
+    /// are unchanged.
+    async fn get_sync_changes(
+        &self,
+        table: &str,
+    ) -> Result<(Vec<JsonRow>, Vec<(u32, JsonRow)>, Vec<u32>, usize)> {
+        let table_config = self.get_table_config(table)?;
+        let columns = &table_config.column_order;
+        // Rows are matched by primary key or, if the table has none, by row number:
+        let key_columns = match self.config.constraint.primary.get(table) {
+            Some(primary) if !primary.is_empty() => Some(vec![primary[0].to_string()]),
+            _ => self
+                .config
+                .constraint
+                .composite
+                .get(table)
+                .and_then(|constraints| {
+                    constraints
+                        .iter()
+                        .find(|constraint| constraint.key_type == "primary")
+                })
+                .map(|constraint| constraint.columns.clone()),
         };
 
-        for table in table_list {
-            let table_config = self.get_table_config(&table)?;
+        // Read the rows from the file, along with their values after any transforms that apply
+        // to them have been applied, which are the values that would be stored in the table:
+        let (headers, records) = self.read_table_tsv(table, &table_config.path)?;
+        let mut file_rows = vec![];
+        for (position, record) in records.iter().enumerate() {
+            let mut row = JsonRow::new();
+            for column in columns {
+                let value = match headers.iter().position(|header| header == column) {
+                    Some(position) => record.get(position).unwrap_or_default(),
+                    None => "",
+                };
+                row.insert(column.to_string(), json!(value));
+            }
+            let mut valve_row = ValveRow::from_simple_json(&row, None)?;
+            transform_row(&self.config, table, &mut valve_row);
+            let file_values = valve_row
+                .contents
+                .iter()
+                .map(|(column, cell)| (column.to_string(), cell.strvalue()))
+                .collect::<IndexMap<_, _>>();
+            file_rows.push((position as u32 + 1, row, file_values));
+        }
 
-            if !table_config.options.contains("load") {
-                continue;
+        // Read the rows from the database, formatting the values in the same way that
+        // save_table() does:
+        let format_regex = Regex::new(PRINTF_RE)?;
+        let mut formats = HashMap::new();
+        for column in columns {
+            formats.insert(
+                column.to_string(),
+                self.get_column_format(table, column).await?,
+            );
+        }
+        let sql = format!(
+            r#"SELECT "row_number", {} FROM "{}_text_view" ORDER BY "row_number""#,
+            columns
+                .iter()
+                .map(|column| format!(r#""{}""#, column))
+                .collect::<Vec<_>>()
+                .join(", "),
+            table
+        );
+        let mut db_rows = IndexMap::new();
+        let mut formatted_db_rows = IndexMap::new();
+        for row in sqlx_query(&sql).fetch_all(&self.pool).await? {
+            let row_number: i64 = row.get("row_number");
+            let mut values = IndexMap::new();
+            let mut formatted_values = IndexMap::new();
+            for column in columns {
+                let value = row
+                    .try_get::<&str, &str>(column)
+                    .ok()
+                    .unwrap_or_default()
+                    .to_string();
+                let format = &formats[column];
+                let formatted_value = match format.as_str() {
+                    "" => value.to_string(),
+                    _ => format_cell(format, &format_regex, &value),
+                };
+                values.insert(column.to_string(), value);
+                formatted_values.insert(column.to_string(), formatted_value);
             }
-            // For all others, how they are loaded depends on the path, such that an empty
-            // path implies that the table in question is not loaded by Valve at all.
-            if table_config.path.to_lowercase().ends_with(".sql") {
-                // SQL files:
-                self.execute_sql_file(&table_config.path).await?;
-            } else if table_config.path.to_lowercase().ends_with(".tsv") {
-                // TSV files:
-                load_normal_table(table)?;
-            } else if table_config.path != "" {
-                // Other types of scripts:
-                self.execute_script(&table_config.path, &vec![&self.db_path, table])?;
+            db_rows.insert(row_number as u32, values);
+            formatted_db_rows.insert(row_number as u32, formatted_values);
+        }
+
+        // Returns the values of the key columns of the given row or, if there are no key columns,
+        // its row number:
+        fn get_key(
+            key_columns: &Option<Vec<String>>,
+            row_number: u32,
+            row: &IndexMap<String, String>,
+        ) -> String {
+            match key_columns {
+                Some(key_columns) => key_columns
+                    .iter()
+                    .map(|column| row.get(column).map(|v| v.as_str()).unwrap_or_default())
+                    .collect::<Vec<_>>()
+                    .join("\t"),
+                None => row_number.to_string(),
             }
         }
 
-        if self.verbose {
-            println!(
-                "Loading complete with {} errors, {} warnings, and {} information messages",
-                total_errors, total_warnings, total_infos
-            );
+        // Match each row in the file with a row in the database, whose key values may be given
+        // either as they are stored or as they are formatted when the table is saved. There may
+        // be more than one row in the database (e.g., in the conflict table) with a given key, in
+        // which case they are matched in order with the rows in the file:
+        let mut rows_by_key: IndexMap<String, Vec<u32>> = IndexMap::new();
+        for (row_number, values) in &db_rows {
+            let key = get_key(&key_columns, *row_number, values);
+            let formatted_key = get_key(&key_columns, *row_number, &formatted_db_rows[row_number]);
+            rows_by_key
+                .entry(key.to_string())
+                .or_insert(vec![])
+                .push(*row_number);
+            if formatted_key != key {
+                rows_by_key
+                    .entry(formatted_key)
+                    .or_insert(vec![])
+                    .push(*row_number);
+            }
         }
-        Ok(self)
+        let mut to_insert = vec![];
+        let mut to_update = vec![];
+        let mut unchanged = 0;
+        let mut matched = HashSet::new();
+        for (position, row, file_values) in file_rows {
+            let row_number = rows_by_key
+                .get_mut(&get_key(&key_columns, position, &file_values))
+                .and_then(|row_numbers| {
+                    row_numbers.retain(|row_number| !matched.contains(row_number));
+                    match row_numbers.is_empty() {
+                        true => None,
+                        false => Some(row_numbers.remove(0)),
+                    }
+                });
+            match row_number {
+                None => to_insert.push(row),
+                Some(row_number) => {
+                    matched.insert(row_number);
+                    let row_has_changed = columns.iter().any(|column| {
+                        let file_value = &file_values[column];
+                        *file_value != db_rows[&row_number][column]
+                            && *file_value != formatted_db_rows[&row_number][column]
+                    });
+                    if row_has_changed {
+                        to_update.push((row_number, row));
+                    } else {
+                        unchanged += 1;
+                    }
+                }
+            };
+        }
+        let to_delete = db_rows
+            .keys()
+            .filter(|row_number| !matched.contains(*row_number))
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok((to_insert, to_update, to_delete, unchanged))
     }
 
     /// Returns true if the Valve instance has the given optional column enabled,
@@ -2054,63 +4572,6 @@ impl Valve {
 
     /// Save the given table with the given columns at the given path as a TSV file.
     pub async fn save_table(&self, table: &str, save_path: &str) -> Result<&Self> {
-        // Uses the given (unverified) printf-style format string and the given compiled regular
-        // expression (which is used to verify the given format) to format the given cell.
-        fn format_cell(colformat: &str, format_regex: &Regex, cell: &str) -> String {
-            let conversion_spec = match format_regex.captures(colformat) {
-                Some(c) => c[1].to_lowercase(),
-                None => {
-                    log::warn!("Illegal format: '{}'", colformat);
-                    "s".to_string()
-                }
-            };
-            let generic_error = format!("Error applying format '{}' to '{}':", colformat, cell);
-            match conversion_spec.as_str() {
-                "d" | "i" | "c" => match cell.parse::<isize>() {
-                    Ok(cell) => match sprintf!(&colformat, cell) {
-                        Ok(cell) => {
-                            // For some reason sprintf converts signed ints to unsigned ints before
-                            // converting them to a string. So we have to workaround this here:
-                            let cell = cell.parse::<usize>().unwrap();
-                            let cell = cell as isize;
-                            cell.to_string()
-                        }
-                        Err(e) => {
-                            log::warn!("{}: {}", generic_error, e);
-                            cell.to_string()
-                        }
-                    },
-                    Err(e) => {
-                        log::warn!("{}: {}", generic_error, e);
-                        cell.to_string()
-                    }
-                },
-                "o" | "u" | "x" => match cell.parse::<usize>() {
-                    Ok(cell) => sprintf!(&colformat, cell).unwrap_or(cell.to_string()),
-                    Err(e) => {
-                        log::warn!("{}: {}", generic_error, e);
-                        cell.to_string()
-                    }
-                },
-                "e" | "f" | "g" | "a" => match cell.parse::<f64>() {
-                    Ok(cell) => sprintf!(&colformat, cell).unwrap_or(cell.to_string()),
-                    Err(e) => {
-                        log::warn!("{}: {}", generic_error, e);
-                        cell.to_string()
-                    }
-                },
-                "s" => sprintf!(&colformat, cell).unwrap_or(cell.to_string()),
-                _ => {
-                    log::warn!(
-                        "Unsupported conversion specifier '{}' in column format '{}'",
-                        conversion_spec,
-                        colformat
-                    );
-                    cell.to_string()
-                }
-            }
-        }
-
         // Check if we are allowed to save the table:
         if !save_path.to_lowercase().ends_with(".tsv") {
             return Err(ValveError::InputError(format!(
@@ -2194,6 +4655,17 @@ impl Valve {
             }
         }
 
+        // If IRIs are to be contracted, determine which of the columns are meant to contain CURIEs.
+        // The prefix table itself is never contracted since its base IRIs would otherwise be
+        // replaced by the CURIEs of their own prefixes:
+        let curie_columns = {
+            if self.contract_iris && table != self.config.special.prefix {
+                toolkit::get_curie_columns(&self.config, &self.datatype_conditions, table)
+            } else {
+                HashSet::new()
+            }
+        };
+
         // Construct the query to use to retrieve the data:
         let query_table = format!("\"{}_text_view\"", table);
         let sql = format!(
@@ -2223,6 +4695,15 @@ impl Valve {
             let mut record: Vec<String> = vec![];
             for (column, (_, colformat)) in &columns {
                 let cell = row.try_get::<&str, &str>(column).ok().unwrap_or_default();
+                let contracted = {
+                    if curie_columns.contains(column) {
+                        toolkit::contract_iri(&self.config.prefix, cell)
+                            .filter(|curie| !curie.ends_with(':'))
+                    } else {
+                        None
+                    }
+                };
+                let cell = contracted.as_deref().unwrap_or(cell);
                 if *colformat != "" {
                     let formatted_cell = format_cell(&colformat, &format_regex, &cell);
                     record.push(formatted_cell.to_string());
@@ -2242,6 +4723,31 @@ impl Valve {
         toolkit::get_value_type(&self.config, &self.datatype_conditions, table, column)
     }
 
+    /// Given a CURIE, return the IRI that it expands to using the prefixes defined in the prefix
+    /// table. Returns an error if the CURIE is malformed or if its prefix has not been registered.
+    pub fn expand_curie(&self, curie: &str) -> Result<String> {
+        toolkit::expand_curie(&self.config.prefix, curie).ok_or(
+            ValveError::InputError(format!(
+                "Unable to expand '{}': it is not a CURIE with a registered prefix",
+                curie
+            ))
+            .into(),
+        )
+    }
+
+    /// Given an IRI, return the CURIE that it contracts to using the prefixes defined in the prefix
+    /// table. Returns an error if the IRI does not begin with the base IRI of any registered
+    /// prefix.
+    pub fn contract_iri(&self, iri: &str) -> Result<String> {
+        toolkit::contract_iri(&self.config.prefix, iri).ok_or(
+            ValveError::InputError(format!(
+                "Unable to contract '{}': it does not match the base IRI of any registered prefix",
+                iri
+            ))
+            .into(),
+        )
+    }
+
     /// Given the name of a datatype, find (in the database) the value of the optional configuration
     /// parameter called 'format' corresponding to that datatype and return it, or an empty string
     /// if no format parameter has been configured for that datatype or if the format parameter is
@@ -2325,41 +4831,579 @@ impl Valve {
         }
     }
 
-    /// Given a table name, a row number, and a transaction through which to access the database,
-    /// search for and return the row number of the row that is marked as previous to the given row.
-    pub async fn get_previous_row(&self, table: &str, row: &u32) -> Result<u32> {
-        let mut tx = self.pool.begin().await?;
-        get_previous_row_tx(table, row, &mut tx).await
+    /// Given a table name, a row number, and a transaction through which to access the database,
+    /// search for and return the row number of the row that is marked as previous to the given row.
+    pub async fn get_previous_row(&self, table: &str, row: &u32) -> Result<u32> {
+        let mut tx = self.pool.begin().await?;
+        get_previous_row_tx(table, row, &mut tx).await
+    }
+
+    /// Given a table name and a row, represented as a JSON object in the following ('simple')
+    /// format:
+    /// ```
+    /// {
+    ///     "column_1": value1,
+    ///     "column_2": value2,
+    ///     ...
+    /// },
+    /// ```
+    /// validate the row and return the results in the form of a [ValveRow].
+    pub async fn validate_row(
+        &self,
+        table_name: &str,
+        row: &JsonRow,
+        row_number: Option<u32>,
+    ) -> Result<ValveRow> {
+        let row = ValveRow::from_simple_json(row, row_number)?;
+        validate_row_tx(
+            &self.config,
+            &self.datatype_conditions,
+            &self.rule_conditions,
+            &self.pool,
+            None,
+            table_name,
+            &row,
+            None,
+        )
+        .await
+    }
+
+    /// Given a list of table names, validate the rows in the TSV files from which those tables
+    /// are configured to be loaded without accessing the database, and return the validated rows
+    /// of each table, in dependency order. If the list is empty, all of the tables that are
+    /// loaded from TSV files are validated. The tables that the given tables depend on are read
+    /// and validated as well, since their rows are needed to check foreign constraints, but their
+    /// rows are not returned. Any messages generated while reading the configuration are added to
+    /// the rows of the table table.
+    pub fn validate_tables_in_memory(
+        &self,
+        tables: &Vec<&str>,
+    ) -> Result<IndexMap<String, Vec<ValveRow>>> {
+        self.validate_in_memory(tables, IndexMap::new())
+    }
+
+    /// Given a map from table names to rows, represented as simple JSON objects, validate the
+    /// rows without accessing the database, and return the validated rows of each table, in
+    /// dependency order. The rows of each table are validated as if they were the only rows in
+    /// the table, while the tables that the given tables depend on are read from the TSV files
+    /// from which they are configured to be loaded, and are validated as well, though their rows
+    /// are not returned.
+    pub fn validate_rows_in_memory(
+        &self,
+        rows: &IndexMap<String, Vec<JsonRow>>,
+    ) -> Result<IndexMap<String, Vec<ValveRow>>> {
+        let mut given_tables = IndexMap::new();
+        for (table, table_rows) in rows {
+            let headers = self.get_table_config(table)?.column_order.clone();
+            let records = table_rows
+                .iter()
+                .map(|row| {
+                    csv::StringRecord::from(
+                        headers
+                            .iter()
+                            .map(|column| match row.get(column) {
+                                Some(SerdeValue::String(s)) => s.to_string(),
+                                Some(SerdeValue::Null) | None => "".to_string(),
+                                Some(value) => value.to_string(),
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            given_tables.insert(table.to_string(), (headers, records));
+        }
+        let tables = rows.keys().map(|table| table.as_str()).collect::<Vec<_>>();
+        self.validate_in_memory(&tables, given_tables)
+    }
+
+    /// Given another Valve instance, configured using a candidate configuration, and a list of
+    /// table names, validate the rows of the given tables that are currently in this instance's
+    /// database, without changing the database, as they would be validated under the candidate
+    /// configuration, and compare the resulting messages with those in the message table. Return
+    /// the messages that would be generated under the candidate configuration but that are not in
+    /// the message table, and vice versa, grouped by table, column, and rule. Differences that
+    /// would also arise under the current configuration, e.g., because rows have been edited since
+    /// they were loaded, are not reported as new or resolved. Instead, the messages in the message
+    /// table that validating the rows under the current configuration does not reproduce are
+    /// listed separately, since they cannot be compared. If the list is empty, every table in the database that
+    /// is also configured in the candidate configuration is validated, apart from the tables that
+    /// are not validated when they are loaded, whose messages are not in the message table. The
+    /// table, column, datatype, and rule tables, which contain the configuration itself, cannot be
+    /// validated in this way. The rows of the tables that the given tables depend on are read from
+    /// the database as well, or, for tables that are not in the database, from the TSV files from
+    /// which they are configured to be loaded.
+    pub async fn validate_with_config(
+        &self,
+        candidate: &Valve,
+        tables: &Vec<&str>,
+    ) -> Result<Vec<ValveMessageDelta>> {
+        let in_database = |table: &str| -> bool {
+            !INTERNAL_TABLES.contains(&table)
+                && self
+                    .config
+                    .table
+                    .get(table)
+                    .is_some_and(|t| t.options.contains("db_table"))
+        };
+        let special_tables = [
+            &self.config.special.table,
+            &self.config.special.column,
+            &self.config.special.datatype,
+            &self.config.special.rule,
+        ];
+        let requested_tables = match tables.is_empty() {
+            true => candidate
+                .get_sorted_table_list_with_option("db_table", false)
+                .into_iter()
+                .filter(|table| {
+                    in_database(table)
+                        && !special_tables.contains(&&table.to_string())
+                        && self.config.table[*table]
+                            .options
+                            .contains("validate_on_load")
+                })
+                .map(|table| table.to_string())
+                .collect::<Vec<_>>(),
+            false => {
+                let mut requested_tables = vec![];
+                for table in tables {
+                    candidate.get_table_config(table)?;
+                    if !in_database(table) {
+                        return Err(ValveError::InputError(format!(
+                            "Table '{}' is not in the database",
+                            table
+                        ))
+                        .into());
+                    }
+                    if special_tables.contains(&&table.to_string()) {
+                        return Err(ValveError::InputError(format!(
+                            "Table '{}' is a configuration table",
+                            table
+                        ))
+                        .into());
+                    }
+                    requested_tables.push(table.to_string());
+                }
+                requested_tables
+            }
+        };
+
+        // Read the rows of the requested tables, and of the tables that they depend on under the
+        // current and the candidate configurations, from the database:
+        let mut tables_to_read = vec![];
+        for table in &requested_tables {
+            for dependency in candidate
+                .get_dependencies(table, false)?
+                .into_iter()
+                .chain(self.get_dependencies(table, false)?)
+            {
+                if in_database(&dependency) && !tables_to_read.contains(&dependency) {
+                    tables_to_read.push(dependency);
+                }
+            }
+            if !tables_to_read.contains(table) {
+                tables_to_read.push(table.to_string());
+            }
+        }
+        // The row number and the values of each row of each table:
+        type DbRows = IndexMap<String, Vec<(u32, HashMap<String, String>)>>;
+        let mut db_rows: DbRows = IndexMap::new();
+        for table in &tables_to_read {
+            let db_columns = &self.get_table_config(table)?.column_order;
+            // Only a table with a conflict table has a text view:
+            let sql = match self
+                .get_table_options_from_config(table)?
+                .contains("conflict")
+            {
+                true => format!(
+                    r#"SELECT "row_number", {} FROM "{}_text_view" ORDER BY "row_number""#,
+                    db_columns
+                        .iter()
+                        .map(|column| format!(r#""{}""#, column))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    table
+                ),
+                false => format!(
+                    r#"SELECT "row_number", {} FROM "{}" ORDER BY "row_number""#,
+                    db_columns
+                        .iter()
+                        .map(|column| {
+                            let sql_type = get_sql_type_from_global_config(
+                                &self.config,
+                                table,
+                                column,
+                                &self.pool,
+                            );
+                            format!(
+                                r#"{} AS "{}""#,
+                                cast_column_sql_to_text(column, &sql_type),
+                                column
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    table
+                ),
+            };
+            let mut rows = vec![];
+            for row in sqlx_query(&sql).fetch_all(&self.pool).await? {
+                let row_number: i64 = row.get("row_number");
+                let mut values = HashMap::new();
+                for column in db_columns {
+                    let value = row
+                        .try_get::<&str, &str>(column)
+                        .ok()
+                        .unwrap_or_default()
+                        .to_string();
+                    values.insert(column.to_string(), value);
+                }
+                rows.push((row_number as u32, values));
+            }
+            db_rows.insert(table.to_string(), rows);
+        }
+
+        // Given a Valve instance, a list of tables, and the rows, read from the database, of those
+        // tables and of the tables they depend on, validate the rows of the given tables under the
+        // instance's configuration, and return the resulting messages.
+        fn validate_db_rows(
+            valve: &Valve,
+            tables: &[String],
+            db_rows: &DbRows,
+        ) -> Result<Vec<ValveTableMessage>> {
+            let mut given_tables = IndexMap::new();
+            for (table, rows) in db_rows {
+                let headers = match valve.config.table.get(table) {
+                    Some(table_config) => table_config.column_order.clone(),
+                    None => continue,
+                };
+                let records = rows
+                    .iter()
+                    .map(|(_, values)| {
+                        csv::StringRecord::from(
+                            headers
+                                .iter()
+                                .map(|column| values.get(column).cloned().unwrap_or_default())
+                                .collect::<Vec<_>>(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                given_tables.insert(table.to_string(), (headers, records));
+            }
+            let validated_tables = valve
+                .validate_in_memory(&tables.iter().map(|t| t.as_str()).collect(), given_tables)?;
+            let mut messages = vec![];
+            for (table, rows) in &validated_tables {
+                for row in rows {
+                    // The rows are numbered consecutively, in the order in which they were given:
+                    let row_number = match row.row_number {
+                        Some(i) => db_rows[table][i as usize - 1].0,
+                        None => continue,
+                    };
+                    for (column, cell) in &row.contents {
+                        for message in &cell.messages {
+                            messages.push(ValveTableMessage {
+                                table: table.to_string(),
+                                row: row_number,
+                                column: column.to_string(),
+                                value: cell.strvalue(),
+                                level: message.level.to_string(),
+                                rule: message.rule.to_string(),
+                                message: message.message.to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+            Ok(messages)
+        }
+
+        // Validate the rows under the candidate configuration, and also under the current
+        // configuration. Since the rows in the database may have been edited since they were
+        // loaded, the messages generated under the current configuration can differ from those in
+        // the message table (for instance, a duplicate primary key may be attributed to a
+        // different row), and we use them to exclude such differences from the comparison:
+        let candidate_messages = validate_db_rows(candidate, &requested_tables, &db_rows)?;
+        let baseline_messages = validate_db_rows(self, &requested_tables, &db_rows)?;
+
+        // Collect the messages about the requested tables that are in the message table:
+        let sql = local_sql_syntax(
+            &self.pool,
+            &format!(
+                r#"SELECT "table", "row", "column", "value", "level", "rule", "message"
+                     FROM "message"
+                    WHERE "table" IN ({}) AND "row" IS NOT NULL
+                   ORDER BY "table", "row", "column", "message_id""#,
+                requested_tables
+                    .iter()
+                    .map(|_| SQL_PARAM)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        );
+        let mut query = sqlx_query(&sql);
+        for table in &requested_tables {
+            query = query.bind(table);
+        }
+        let mut current_messages = vec![];
+        for row in query.fetch_all(&self.pool).await? {
+            let value = {
+                let raw_value = row.try_get_raw("value")?;
+                if raw_value.is_null() {
+                    "".to_string()
+                } else {
+                    row.get::<String, _>("value")
+                }
+            };
+            current_messages.push(ValveTableMessage {
+                table: row.get::<String, _>("table"),
+                row: row.get::<i64, _>("row") as u32,
+                column: row.get::<String, _>("column"),
+                value,
+                level: row.get::<String, _>("level"),
+                rule: row.get::<String, _>("rule"),
+                message: row.get::<String, _>("message"),
+            });
+        }
+
+        // Given two lists of messages, return the messages in the first list that are not matched
+        // by a message in the second list, where each message in the second list can match only
+        // one message in the first.
+        fn subtract(
+            messages: &[ValveTableMessage],
+            others: &[ValveTableMessage],
+        ) -> Vec<ValveTableMessage> {
+            let get_key = |message: &ValveTableMessage| -> String {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    message.table,
+                    message.row,
+                    message.column,
+                    message.value,
+                    message.level,
+                    message.rule,
+                    message.message
+                )
+            };
+            let mut unmatched_counts: HashMap<String, usize> = HashMap::new();
+            for message in others {
+                *unmatched_counts.entry(get_key(message)).or_insert(0) += 1;
+            }
+            let mut remaining = vec![];
+            for message in messages {
+                match unmatched_counts.get_mut(&get_key(message)) {
+                    Some(count) if *count > 0 => *count -= 1,
+                    _ => remaining.push(message.clone()),
+                };
+            }
+            remaining
+        }
+
+        // A message is new if it is generated under the candidate configuration but is neither in
+        // the message table nor generated under the current configuration, and it is resolved if
+        // it is in the message table and is generated under the current configuration but not
+        // under the candidate configuration:
+        let new_messages = subtract(
+            &subtract(&candidate_messages, &current_messages),
+            &baseline_messages,
+        );
+        let reproduced_messages = subtract(
+            &current_messages,
+            &subtract(&current_messages, &baseline_messages),
+        );
+        let resolved_messages = subtract(&reproduced_messages, &candidate_messages);
+        let unreproduced_messages = subtract(&current_messages, &baseline_messages);
+
+        // Group the messages by table, column, and rule, in the candidate configuration's table
+        // and column order:
+        let mut deltas: IndexMap<(String, String, String), ValveMessageDelta> = IndexMap::new();
+        for (message, kind) in new_messages
+            .into_iter()
+            .map(|message| (message, "new"))
+            .chain(
+                resolved_messages
+                    .into_iter()
+                    .map(|message| (message, "resolved")),
+            )
+            .chain(
+                unreproduced_messages
+                    .into_iter()
+                    .map(|message| (message, "unreproduced")),
+            )
+        {
+            let delta = deltas
+                .entry((
+                    message.table.to_string(),
+                    message.column.to_string(),
+                    message.rule.to_string(),
+                ))
+                .or_insert(ValveMessageDelta {
+                    table: message.table.to_string(),
+                    column: message.column.to_string(),
+                    rule: message.rule.to_string(),
+                    ..Default::default()
+                });
+            match kind {
+                "new" => delta.new.push(message),
+                "resolved" => delta.resolved.push(message),
+                _ => delta.unreproduced.push(message),
+            };
+        }
+        let mut deltas = deltas.into_values().collect::<Vec<_>>();
+        deltas.sort_by_key(|delta| {
+            let table_position = requested_tables
+                .iter()
+                .position(|table| *table == delta.table);
+            let column_position = candidate
+                .config
+                .table
+                .get(&delta.table)
+                .and_then(|t| t.column_order.iter().position(|c| *c == delta.column))
+                .unwrap_or(usize::MAX);
+            (
+                table_position,
+                column_position,
+                delta.column.to_string(),
+                delta.rule.to_string(),
+            )
+        });
+        Ok(deltas)
+    }
+
+    /// (Private function.) Given a table name and the path of the TSV file that the table is
+    /// loaded from, return the names of the columns corresponding to the headers of the file and
+    /// the rows in the file.
+    fn read_table_tsv(
+        &self,
+        table: &str,
+        path: &str,
+    ) -> Result<(Vec<String>, Vec<csv::StringRecord>)> {
+        let table_file = File::open(path)
+            .map_err(|e| ValveError::InputError(format!("Unable to open '{}': {}", path, e)))?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b'\t')
+            .from_reader(table_file);
+        let mut records = rdr.records();
+        let labels = match records.next() {
+            Some(result) => result?,
+            None => return Err(ValveError::DataError(format!("'{}' is empty", path)).into()),
+        };
+        let column_config = &self.get_table_config(table)?.column;
+        let mut headers = vec![];
+        for label in &labels {
+            headers.push(get_column_for_label(column_config, label, table)?);
+        }
+        let mut rows = vec![];
+        for record in records {
+            rows.push(record?);
+        }
+        Ok((headers, rows))
     }
 
-    /// Given a table name and a row, represented as a JSON object in the following ('simple')
-    /// format:
-    /// ```
-    /// {
-    ///     "column_1": value1,
-    ///     "column_2": value2,
-    ///     ...
-    /// },
-    /// ```
-    /// validate the row and return the results in the form of a [ValveRow].
-    pub async fn validate_row(
+    /// Given a list of table names and a map from some of those tables to their headers and the
+    /// rows to validate, validate the given rows of the tables in the map, and the rows in the
+    /// configured TSV files of the other tables and of the tables that the given tables depend
+    /// on, without accessing the database. Return the validated rows of the given tables, in
+    /// dependency order. If the list is empty, all of the tables that are loaded from TSV files
+    /// are validated and returned.
+    fn validate_in_memory(
         &self,
-        table_name: &str,
-        row: &JsonRow,
-        row_number: Option<u32>,
-    ) -> Result<ValveRow> {
-        let row = ValveRow::from_simple_json(row, row_number)?;
-        validate_row_tx(
+        tables: &Vec<&str>,
+        mut given_tables: IndexMap<String, (Vec<String>, Vec<csv::StringRecord>)>,
+    ) -> Result<IndexMap<String, Vec<ValveRow>>> {
+        let requested_tables = match tables.is_empty() {
+            true => self
+                .get_sorted_table_list_with_option("load", false)
+                .into_iter()
+                .filter(|table| {
+                    self.config.table[*table]
+                        .path
+                        .to_lowercase()
+                        .ends_with(".tsv")
+                })
+                .map(|table| table.to_string())
+                .collect::<Vec<_>>(),
+            false => tables
+                .iter()
+                .map(|table| table.to_string())
+                .collect::<Vec<_>>(),
+        };
+        let mut all_tables = vec![];
+        for table in &requested_tables {
+            self.get_table_config(table)?;
+            for dependency in self.get_dependencies(table, false)? {
+                if !all_tables.contains(&dependency) {
+                    all_tables.push(dependency);
+                }
+            }
+            if !all_tables.contains(table) {
+                all_tables.push(table.to_string());
+            }
+        }
+        let sorted_tables =
+            self.sort_tables(&all_tables.iter().map(|t| t.as_str()).collect(), false)?;
+
+        let mut input_tables = IndexMap::new();
+        for table in &sorted_tables {
+            if let Some(given_table) = given_tables.swap_remove(table) {
+                input_tables.insert(table.to_string(), given_table);
+                continue;
+            }
+            let table_config = self.get_table_config(table)?;
+            if !table_config.options.contains("load")
+                || !table_config.path.to_lowercase().ends_with(".tsv")
+            {
+                if requested_tables.contains(table) {
+                    return Err(ValveError::InputError(format!(
+                        "Table '{}' is not loaded from a TSV file",
+                        table
+                    ))
+                    .into());
+                }
+                continue;
+            }
+            input_tables.insert(
+                table.to_string(),
+                self.read_table_tsv(table, &table_config.path)?,
+            );
+        }
+
+        let mut validated_tables = validate_tables_in_memory(
             &self.config,
+            &self.pool,
             &self.datatype_conditions,
             &self.rule_conditions,
-            &self.pool,
-            None,
-            table_name,
-            &row,
-            None,
-        )
-        .await
+            &input_tables,
+        )?;
+
+        // Messages generated while reading the configuration are added to the table table:
+        if let Some(rows) = validated_tables.get_mut("table") {
+            for (row_number, messages) in &self.startup_table_messages {
+                let row = match rows.get_mut(*row_number as usize - 1) {
+                    Some(row) => row,
+                    None => continue,
+                };
+                for message in messages {
+                    if let Some(cell) = row.contents.get_mut(&message.column) {
+                        if message.level == "error" {
+                            cell.valid = false;
+                        }
+                        cell.messages.push(ValveCellMessage {
+                            level: message.level.to_string(),
+                            rule: message.rule.to_string(),
+                            message: message.message.to_string(),
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(validated_tables
+            .into_iter()
+            .filter(|(table, _)| requested_tables.contains(table))
+            .collect())
     }
 
     /// Given a table name and a row, represented as a JSON object in the following ('simple')
@@ -2374,6 +5418,22 @@ impl Valve {
     /// validate and insert the row to the table and return the row number of the inserted row
     /// and the row itself in the form of a [ValveRow].
     pub async fn insert_row(&self, table_name: &str, row: &JsonRow) -> Result<(u32, ValveRow)> {
+        let mut tx = self.pool.begin().await?;
+        let (rn, row) = self.insert_row_with_tx(&mut tx, table_name, row).await?;
+        tx.commit().await?;
+        Ok((rn, row))
+    }
+
+    /// (Private function.) Given a database transaction, a table name, and a row, represented as
+    /// a JSON object in 'simple' format, validate and insert the row to the table using the given
+    /// transaction, and return the row number of the inserted row and the row itself in the form
+    /// of a [ValveRow]. See [Valve::insert_row()].
+    async fn insert_row_with_tx(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Any>,
+        table_name: &str,
+        row: &JsonRow,
+    ) -> Result<(u32, ValveRow)> {
         let table_options = &self.get_table_options_from_config(table_name)?;
         if !table_options.contains("edit") {
             return Err(ValveError::InputError(format!(
@@ -2383,14 +5443,13 @@ impl Valve {
             .into());
         }
 
-        let mut tx = self.pool.begin().await?;
         let row = ValveRow::from_simple_json(row, None)?;
         let mut row = validate_row_tx(
             &self.config,
             &self.datatype_conditions,
             &self.rule_conditions,
             &self.pool,
-            Some(&mut tx),
+            Some(tx),
             table_name,
             &row,
             None,
@@ -2402,7 +5461,7 @@ impl Valve {
             &self.datatype_conditions,
             &self.rule_conditions,
             &self.pool,
-            &mut tx,
+            tx,
             table_name,
             &row,
             true,
@@ -2414,7 +5473,7 @@ impl Valve {
         let serde_row = row.contents_to_rich_json()?;
         record_row_change(
             &self.pool,
-            &mut tx,
+            tx,
             table_name,
             &rn,
             None,
@@ -2422,8 +5481,6 @@ impl Valve {
             &self.user,
         )
         .await?;
-
-        tx.commit().await?;
         Ok((rn, row))
     }
 
@@ -2442,6 +5499,25 @@ impl Valve {
         table_name: &str,
         row_number: &u32,
         row: &JsonRow,
+    ) -> Result<ValveRow> {
+        let mut tx = self.pool.begin().await?;
+        let row = self
+            .update_row_with_tx(&mut tx, table_name, row_number, row)
+            .await?;
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// (Private function.) Given a database transaction, a table name, a row number, and a row,
+    /// represented as a JSON object in 'simple' format, validate and update the row in the
+    /// database using the given transaction, and return it in the form of a [ValveRow]. See
+    /// [Valve::update_row()].
+    async fn update_row_with_tx(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Any>,
+        table_name: &str,
+        row_number: &u32,
+        row: &JsonRow,
     ) -> Result<ValveRow> {
         let table_options = &self.get_table_options_from_config(table_name)?;
         if !table_options.contains("edit") {
@@ -2452,12 +5528,10 @@ impl Valve {
             .into());
         }
 
-        let mut tx = self.pool.begin().await?;
-
         // Get the old version of the row from the database so that we can later record it to the
         // history table:
         let old_row =
-            get_row_from_db(&self.config, &self.pool, &mut tx, table_name, &row_number).await?;
+            get_row_from_db(&self.config, &self.pool, tx, table_name, row_number).await?;
 
         let row = ValveRow::from_simple_json(row, Some(*row_number))?;
         let row = validate_row_tx(
@@ -2465,7 +5539,7 @@ impl Valve {
             &self.datatype_conditions,
             &self.rule_conditions,
             &self.pool,
-            Some(&mut tx),
+            Some(tx),
             table_name,
             &row,
             None,
@@ -2477,7 +5551,7 @@ impl Valve {
             &self.datatype_conditions,
             &self.rule_conditions,
             &self.pool,
-            &mut tx,
+            tx,
             table_name,
             &row,
             true,
@@ -2489,7 +5563,7 @@ impl Valve {
         let serde_row = row.contents_to_rich_json()?;
         record_row_change(
             &self.pool,
-            &mut tx,
+            tx,
             table_name,
             row_number,
             Some(&old_row),
@@ -2497,13 +5571,26 @@ impl Valve {
             &self.user,
         )
         .await?;
-
-        tx.commit().await?;
         Ok(row)
     }
 
     /// Given a table name and a row number, delete that row from the table.
     pub async fn delete_row(&self, table_name: &str, row_number: &u32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        self.delete_row_with_tx(&mut tx, table_name, row_number)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// (Private function.) Given a database transaction, a table name, and a row number, delete
+    /// that row from the table using the given transaction. See [Valve::delete_row()].
+    async fn delete_row_with_tx(
+        &self,
+        tx: &mut Transaction<'_, sqlx::Any>,
+        table_name: &str,
+        row_number: &u32,
+    ) -> Result<()> {
         let table_options = &self.get_table_options_from_config(table_name)?;
         if !table_options.contains("edit") {
             return Err(ValveError::InputError(format!(
@@ -2513,16 +5600,14 @@ impl Valve {
             .into());
         }
 
-        let mut tx = self.pool.begin().await?;
-
         let mut row =
-            get_row_from_db(&self.config, &self.pool, &mut tx, &table_name, row_number).await?;
+            get_row_from_db(&self.config, &self.pool, tx, table_name, row_number).await?;
 
-        let previous_row = get_previous_row_tx(table_name, row_number, &mut tx).await?;
+        let previous_row = get_previous_row_tx(table_name, row_number, tx).await?;
         row.insert("previous_row".into(), json!(previous_row));
         record_row_change(
             &self.pool,
-            &mut tx,
+            tx,
             &table_name,
             row_number,
             Some(&row),
@@ -2536,13 +5621,11 @@ impl Valve {
             &self.datatype_conditions,
             &self.rule_conditions,
             &self.pool,
-            &mut tx,
+            tx,
             table_name,
             row_number,
         )
         .await?;
-
-        tx.commit().await?;
         Ok(())
     }
 
@@ -2868,6 +5951,162 @@ impl Valve {
         }
     }
 
+    /// Given a table name and a filter, look up the messages in the message table that are about
+    /// the given table and that satisfy the filter, and return the fixes, if any, that are proposed
+    /// for the values that the messages are about (see [propose_fix()](toolkit::propose_fix)). At
+    /// most one fix is proposed for each cell.
+    pub async fn propose_fixes(
+        &self,
+        table: &str,
+        filter: &ValveMessageFilter,
+    ) -> Result<Vec<ValveFix>> {
+        if !self.config.table.contains_key(table) {
+            return Err(ValveError::InputError(format!("Undefined table '{}'", table)).into());
+        }
+
+        let mut conditions = vec![format!(r#""table" = {}"#, SQL_PARAM)];
+        let mut params = vec![table.to_string()];
+        if let Some(column) = &filter.column {
+            conditions.push(format!(r#""column" = {}"#, SQL_PARAM));
+            params.push(column.to_string());
+        }
+        if let Some(rule) = &filter.rule {
+            conditions.push(format!(r#""rule" LIKE {}"#, SQL_PARAM));
+            params.push(rule.to_string());
+        }
+        if let Some(level) = &filter.level {
+            conditions.push(format!(r#""level" = {}"#, SQL_PARAM));
+            params.push(level.to_string());
+        }
+        let row_condition = match filter.row {
+            Some(row) => format!(r#""row" = {}"#, row),
+            None => r#""row" IS NOT NULL"#.to_string(),
+        };
+        conditions.push(row_condition);
+
+        let sql = local_sql_syntax(
+            &self.pool,
+            &format!(
+                r#"SELECT "row", "column", "value", "level", "rule", "message", "suggestions"
+                     FROM "message"
+                    WHERE {}
+                   ORDER BY "row", "column", "message_id""#,
+                conditions.join(" AND ")
+            ),
+        );
+        let mut query = sqlx_query(&sql);
+        for param in &params {
+            query = query.bind(param);
+        }
+
+        // Collect the messages for each cell:
+        let mut cells: IndexMap<(u32, String), (String, Vec<ValveCellMessage>)> = IndexMap::new();
+        for row in query.fetch_all(&self.pool).await? {
+            let row_number = row.get::<i64, _>("row") as u32;
+            let column = row.get::<String, _>("column");
+            let value = {
+                let raw_value = row.try_get_raw("value")?;
+                if raw_value.is_null() {
+                    "".to_string()
+                } else {
+                    row.get::<String, _>("value")
+                }
+            };
+            let suggestions = {
+                let raw_suggestions = row.try_get_raw("suggestions")?;
+                if raw_suggestions.is_null() {
+                    vec![]
+                } else {
+                    let suggestions = row.get::<String, _>("suggestions");
+                    serde_json::from_str::<Vec<String>>(&suggestions)?
+                }
+            };
+            let message = ValveCellMessage {
+                level: row.get::<String, _>("level"),
+                rule: row.get::<String, _>("rule"),
+                message: row.get::<String, _>("message"),
+                suggestions,
+            };
+            cells
+                .entry((row_number, column))
+                .or_insert((value, vec![]))
+                .1
+                .push(message);
+        }
+
+        let mut fixes = vec![];
+        for ((row_number, column), (value, messages)) in cells {
+            if let Some((fixed_value, message, rules)) = propose_fix(
+                &self.config,
+                &self.datatype_conditions,
+                table,
+                &column,
+                &value,
+                &messages,
+            ) {
+                fixes.push(ValveFix {
+                    table: table.to_string(),
+                    row: row_number,
+                    column,
+                    value,
+                    fixed_value,
+                    rules,
+                    message,
+                });
+            }
+        }
+        Ok(fixes)
+    }
+
+    /// Given a list of fixes, such as those returned by [Valve::propose_fixes()], apply them to the
+    /// database using [Valve::update_row()], so that they are recorded in the history table and
+    /// can be undone. All of the fixes to a given row are applied in a single update. A fix is
+    /// skipped, with a warning, if the value that it would replace is no longer the current value
+    /// of its cell. Return the rows that have been updated.
+    pub async fn apply_fixes(&self, fixes: &Vec<ValveFix>) -> Result<Vec<ValveRow>> {
+        let mut fixes_by_row: IndexMap<(&str, u32), Vec<&ValveFix>> = IndexMap::new();
+        for fix in fixes {
+            fixes_by_row
+                .entry((&fix.table, fix.row))
+                .or_insert(vec![])
+                .push(fix);
+        }
+
+        let mut updated_rows = vec![];
+        for ((table, row_number), fixes) in fixes_by_row {
+            let current_row = {
+                let mut tx = self.pool.begin().await?;
+                get_row_from_db(&self.config, &self.pool, &mut tx, table, &row_number).await?
+            };
+            let mut row = JsonRow::new();
+            for (column, cell) in current_row.iter() {
+                let value = cell.get("value").cloned().unwrap_or(json!(""));
+                row.insert(column.to_string(), value);
+            }
+
+            let mut row_has_changed = false;
+            for fix in fixes {
+                match row.get(&fix.column) {
+                    Some(SerdeValue::String(value)) if *value == fix.value => {
+                        row.insert(fix.column.to_string(), json!(fix.fixed_value));
+                        row_has_changed = true;
+                    }
+                    _ => log::warn!(
+                        "Skipping the fix to {}.{} for row {} since the value '{}' has changed",
+                        table,
+                        fix.column,
+                        row_number,
+                        fix.value
+                    ),
+                };
+            }
+            if row_has_changed {
+                updated_rows.push(self.update_row(table, &row_number, &row).await?);
+            }
+        }
+        Ok(updated_rows)
+    }
+
     /// Given the name of a datatype, returns the configuration information for all of its
     /// ancestors.
     pub fn get_datatype_ancestors(&self, datatype: &str) -> Vec<ValveDatatypeConfig> {
@@ -2882,6 +6121,317 @@ impl Valve {
             .collect::<Vec<_>>()
     }
 
+    /// Given a rule identifier, as found in the rule column of the message table, return an
+    /// explanation of it that includes the kind of rule identified, the level of the messages
+    /// generated for it, and details about the rule(s) taken from the configuration. Returns an
+    /// error if the rule identifier is not recognized or if it does not correspond to any
+    /// datatype or rule in the configuration.
+    pub fn explain_rule(&self, rule_id: &str) -> Result<ValveRuleExplanation> {
+        let (kind, parameter) = ValveRuleKind::from_rule_id(rule_id).ok_or(
+            ValveError::InputError(format!("Unrecognized rule identifier: '{}'", rule_id)),
+        )?;
+        let parameter = parameter.unwrap_or_default();
+        let mut level = kind.default_level().to_string();
+        let mut details = vec![];
+        match kind {
+            ValveRuleKind::Datatype => {
+                let dt_config =
+                    self.config
+                        .datatype
+                        .get(&parameter)
+                        .ok_or(ValveError::InputError(format!(
+                            "Undefined datatype: '{}'",
+                            parameter
+                        )))?;
+                details.push(format!(
+                    "datatype '{}' ({}) with condition '{}'",
+                    dt_config.datatype, dt_config.description, dt_config.condition
+                ));
+            }
+            ValveRuleKind::Rule => {
+                let mut tables = self.config.rule.keys().collect::<Vec<_>>();
+                tables.sort();
+                for table in tables {
+                    let mut columns = self.config.rule[table].keys().collect::<Vec<_>>();
+                    columns.sort();
+                    for column in columns {
+                        for (i, rule) in self.config.rule[table][column].iter().enumerate() {
+                            let positional_id = format!("{}-{}", column, i + 1);
+                            if (!rule.id.is_empty() && rule.id == parameter)
+                                || (rule.id.is_empty() && positional_id == parameter)
+                            {
+                                level = rule.level.to_string();
+                                details.push(format!(
+                                    "{}: when {} is '{}' then {} must be '{}' ({})",
+                                    table,
+                                    rule.when_column,
+                                    rule.when_condition,
+                                    rule.then_column,
+                                    rule.then_condition,
+                                    rule.description
+                                ));
+                            }
+                        }
+                    }
+                }
+                let mut tables = self.config.script_rule.keys().collect::<Vec<_>>();
+                tables.sort();
+                for table in tables {
+                    for rule in self.config.script_rule[table].iter() {
+                        if !rule.id.is_empty() && rule.id == parameter {
+                            level = rule.level.to_string();
+                            details.push(format!(
+                                "{}: script '{}' ({})",
+                                table, rule.script, rule.description
+                            ));
+                        }
+                    }
+                }
+            }
+            ValveRuleKind::ScriptRule => {
+                let mut tables = self.config.script_rule.keys().collect::<Vec<_>>();
+                tables.sort();
+                for table in tables {
+                    for (i, rule) in self.config.script_rule[table].iter().enumerate() {
+                        if rule.id.is_empty() && (i + 1).to_string() == parameter {
+                            level = rule.level.to_string();
+                            details.push(format!(
+                                "{}: script '{}' ({})",
+                                table, rule.script, rule.description
+                            ));
+                        }
+                    }
+                }
+            }
+            _ => (),
+        };
+
+        if [ValveRuleKind::Rule, ValveRuleKind::ScriptRule].contains(&kind) && details.is_empty() {
+            return Err(ValveError::InputError(format!(
+                "No rule in the rule table corresponds to the rule identifier '{}'",
+                rule_id
+            ))
+            .into());
+        }
+
+        Ok(ValveRuleExplanation {
+            rule: rule_id.to_string(),
+            kind,
+            level,
+            explanation: kind.explanation().to_string(),
+            details,
+        })
+    }
+
+    /// Given a table name and the name of a column of the table that has been given a `tree()`
+    /// structure, return the corresponding tree constraint.
+    fn get_tree_constraint(&self, table: &str, tree_column: &str) -> Result<&ValveTreeConstraint> {
+        self.config
+            .constraint
+            .tree
+            .get(table)
+            .and_then(|trees| trees.iter().find(|tree| tree.parent == tree_column))
+            .ok_or(
+                ValveError::InputError(format!(
+                    "Column '{}' of table '{}' does not have a tree() structure",
+                    tree_column, table
+                ))
+                .into(),
+            )
+    }
+
+    /// Given a table name and a list of row numbers, fetch the given rows from the database and
+    /// return them, in the same order, as [ValveRow]s.
+    async fn get_rows_by_number(
+        &self,
+        table: &str,
+        row_numbers: &Vec<u32>,
+    ) -> Result<Vec<ValveRow>> {
+        let mut tx = self.pool.begin().await?;
+        let mut rows = vec![];
+        for row_number in row_numbers {
+            let row = get_row_from_db(&self.config, &self.pool, &mut tx, table, row_number).await?;
+            rows.push(ValveRow::from_rich_json(Some(*row_number), &row)?);
+        }
+        Ok(rows)
+    }
+
+    /// Given a table name, the name of a column of the table that has been given a `tree()`
+    /// structure, a value, and a flag indicating whether to search upwards (for ancestors) or
+    /// downwards (for descendants), return the row numbers of the rows whose child values are the
+    /// ancestors (or descendants) of the given value, together with their distances from it. Rows
+    /// are returned in order of distance and, for rows at the same distance, in row order. If the
+    /// table has the 'closure' option, the table's closure table is used to answer the query,
+    /// otherwise the tree is traversed in memory.
+    async fn get_tree_relatives(
+        &self,
+        table: &str,
+        tree_column: &str,
+        value: &str,
+        upwards: bool,
+    ) -> Result<Vec<(u32, u32)>> {
+        let tree = self.get_tree_constraint(table, tree_column)?;
+        let mut tx = self.pool.begin().await?;
+        if self
+            .get_table_options_from_config(table)?
+            .contains("closure")
+        {
+            let (given, wanted) = match upwards {
+                true => ("descendant", "ancestor"),
+                false => ("ancestor", "descendant"),
+            };
+            let sql_type =
+                get_sql_type_from_global_config(&self.config, table, &tree.child, &self.pool);
+            let sql = local_sql_syntax(
+                &self.pool,
+                &format!(
+                    r#"SELECT t."row_number", c."depth"
+                         FROM "{table}_closure" c
+                         JOIN (
+                           SELECT "row_number", "row_order", {child} AS "value"
+                             FROM "{query_table}"
+                         ) t ON t."value" = c."{wanted}"
+                        WHERE c."column" = {SQL_PARAM} AND c."{given}" = {SQL_PARAM}
+                        ORDER BY c."depth", t."row_order""#,
+                    child = cast_column_sql_to_text(&tree.child, &sql_type),
+                    query_table = get_tree_query_table(&self.config, table)?,
+                ),
+            );
+            let rows = sqlx_query(&sql)
+                .bind(&tree.parent)
+                .bind(value)
+                .fetch_all(tx.acquire().await?)
+                .await?;
+            Ok(rows
+                .iter()
+                .map(|row| {
+                    let row_number: i64 = row.get("row_number");
+                    let depth: i64 = row.get("depth");
+                    (row_number as u32, depth as u32)
+                })
+                .collect())
+        } else {
+            let edges = get_tree_edges(&self.config, &self.pool, &mut tx, table, tree).await?;
+            let mut adjacency: HashMap<String, Vec<String>> = HashMap::new();
+            for (_, child, parents) in &edges {
+                for parent in parents {
+                    let (from, to) = match upwards {
+                        true => (child, parent),
+                        false => (parent, child),
+                    };
+                    adjacency
+                        .entry(from.to_string())
+                        .or_default()
+                        .push(to.to_string());
+                }
+            }
+            let distances = get_tree_distances(&adjacency, value)
+                .into_iter()
+                .collect::<HashMap<_, _>>();
+            let mut relatives = vec![];
+            for (row_number, child, _) in &edges {
+                if let Some(distance) = distances.get(child) {
+                    relatives.push((*row_number, *distance));
+                }
+            }
+            // The edges are in row order, and sort_by_key() is stable:
+            relatives.sort_by_key(|(_, distance)| *distance);
+            Ok(relatives)
+        }
+    }
+
+    /// Given a table name, the name of a column of the table that has been given a `tree()`
+    /// structure, and a value, return the rows of the table whose child values are ancestors of
+    /// the given value, nearest ancestors first.
+    pub async fn get_ancestors(
+        &self,
+        table: &str,
+        tree_column: &str,
+        value: &str,
+    ) -> Result<Vec<ValveRow>> {
+        let relatives = self
+            .get_tree_relatives(table, tree_column, value, true)
+            .await?;
+        let row_numbers = relatives.iter().map(|(rn, _)| *rn).collect::<Vec<_>>();
+        self.get_rows_by_number(table, &row_numbers).await
+    }
+
+    /// Given a table name, the name of a column of the table that has been given a `tree()`
+    /// structure, and a value, return the rows of the table whose child values are descendants of
+    /// the given value, nearest descendants first.
+    pub async fn get_descendants(
+        &self,
+        table: &str,
+        tree_column: &str,
+        value: &str,
+    ) -> Result<Vec<ValveRow>> {
+        let relatives = self
+            .get_tree_relatives(table, tree_column, value, false)
+            .await?;
+        let row_numbers = relatives.iter().map(|(rn, _)| *rn).collect::<Vec<_>>();
+        self.get_rows_by_number(table, &row_numbers).await
+    }
+
+    /// Given a table name, the name of a column of the table that has been given a `tree()`
+    /// structure, and a value, return the rows of the table whose tree column contains the given
+    /// value, i.e., the rows representing the value's immediate children, in row order.
+    pub async fn get_children(
+        &self,
+        table: &str,
+        tree_column: &str,
+        value: &str,
+    ) -> Result<Vec<ValveRow>> {
+        let tree = self.get_tree_constraint(table, tree_column)?;
+        let mut tx = self.pool.begin().await?;
+        let row_numbers = get_tree_edges(&self.config, &self.pool, &mut tx, table, tree)
+            .await?
+            .into_iter()
+            .filter(|(_, _, parents)| parents.iter().any(|parent| parent == value))
+            .map(|(row_number, _, _)| row_number)
+            .collect::<Vec<_>>();
+        self.get_rows_by_number(table, &row_numbers).await
+    }
+
+    /// Given a table name and the name of a column of the table that has been given a `tree()`
+    /// structure, return the rows of the table that represent the roots of the tree, i.e., the
+    /// rows that have a child value but no value in the tree column, in row order.
+    pub async fn get_roots(&self, table: &str, tree_column: &str) -> Result<Vec<ValveRow>> {
+        let tree = self.get_tree_constraint(table, tree_column)?;
+        let mut tx = self.pool.begin().await?;
+        let row_numbers = get_tree_edges(&self.config, &self.pool, &mut tx, table, tree)
+            .await?
+            .into_iter()
+            .filter(|(_, _, parents)| parents.is_empty())
+            .map(|(row_number, _, _)| row_number)
+            .collect::<Vec<_>>();
+        self.get_rows_by_number(table, &row_numbers).await
+    }
+
+    /// Given a table name, the name of a column of the table that has been given a `tree()`
+    /// structure, and a value, return the depth of the value in the tree, i.e., the length of the
+    /// longest path from the value to one of its ancestors. The depth of a root is 0. Returns an
+    /// error if the value does not appear in the child column of the tree.
+    pub async fn get_depth(&self, table: &str, tree_column: &str, value: &str) -> Result<u32> {
+        let tree = self.get_tree_constraint(table, tree_column)?;
+        let mut tx = self.pool.begin().await?;
+        let edges = get_tree_edges(&self.config, &self.pool, &mut tx, table, tree).await?;
+        if !edges.iter().any(|(_, child, _)| child == value) {
+            return Err(ValveError::InputError(format!(
+                "Value '{}' not found in column '{}' of table '{}'",
+                value, tree.child, table
+            ))
+            .into());
+        }
+        let relatives = self
+            .get_tree_relatives(table, tree_column, value, true)
+            .await?;
+        Ok(relatives
+            .iter()
+            .map(|(_, distance)| *distance)
+            .max()
+            .unwrap_or(0))
+    }
+
     /// Given a table name, a column name, and (optionally) a string to match, return a JSON array
     /// of possible valid values for the given column which contain the matching string as a
     /// substring (or all of them if no matching string is given). The JSON array returned is
@@ -2983,8 +6533,33 @@ impl Valve {
 
                         match parsed {
                             Expression::Function(name, args) if name == "from" => {
-                                let foreign_key = &args[0];
-                                if let Expression::Field(ftable, fcolumn) = &**foreign_key {
+                                let foreign_key = match &*args[0] {
+                                    Expression::Field(ftable, fcolumn) => {
+                                        Some((ftable.to_string(), fcolumn.to_string()))
+                                    }
+                                    // In the case of a foreign key on more than one column, the
+                                    // values are taken from the foreign column that corresponds
+                                    // to this one:
+                                    Expression::Tuple(_) => config
+                                        .constraint
+                                        .composite_foreign
+                                        .get(table_name)
+                                        .and_then(|fkeys| {
+                                            fkeys.iter().find_map(|fkey| {
+                                                fkey.columns
+                                                    .iter()
+                                                    .position(|c| c == column_name)
+                                                    .map(|i| {
+                                                        (
+                                                            fkey.ftable.to_string(),
+                                                            fkey.fcolumns[i].to_string(),
+                                                        )
+                                                    })
+                                            })
+                                        }),
+                                    _ => None,
+                                };
+                                if let Some((ftable, fcolumn)) = foreign_key {
                                     let fcolumn_text = cast_column_sql_to_text(&fcolumn, &sql_type);
                                     let sql = local_sql_syntax(
                                         &pool,
@@ -2998,9 +6573,13 @@ impl Valve {
                                         .fetch_all(pool)
                                         .await?;
                                     for row in rows.iter() {
-                                        values.push(get_column_value_as_string(
-                                            &row, &fcolumn, &sql_type,
-                                        ));
+                                        let value =
+                                            get_column_value_as_string(row, &fcolumn, &sql_type);
+                                        // The foreign column is not necessarily unique in the
+                                        // case of a foreign key on more than one column:
+                                        if !values.contains(&value) {
+                                            values.push(value);
+                                        }
                                     }
                                 }
                             }
@@ -3026,6 +6605,14 @@ impl Valve {
                                         table_name, tree_col
                                     )))?;
                                 let child_column = &tree.child;
+                                // The SQL type of the parent column differs from that of the
+                                // child column when the former has a list() datatype:
+                                let sql_type = get_sql_type_from_global_config(
+                                    config,
+                                    table_name,
+                                    child_column,
+                                    pool,
+                                );
 
                                 let tree_sql = with_tree_sql(&tree, &table_name.to_string(), None);
                                 let child_column_text =
@@ -15,7 +15,9 @@ WINDOW_SIZE = 50
 
 
 def get_special_tables(config):
-    return ["message", "history"] + [k for k, v in config["special"].items() if v is not None]
+    return ["message", "history", "load_state"] + [
+        k for k, v in config["special"].items() if v is not None
+    ]
 
 
 def get_table_columns(config, table):